@@ -0,0 +1,240 @@
+use std::{collections::HashMap, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{http, maven::Coordinate};
+
+/// The OSV endpoint that looks up the vulnerabilities of many packages at once.
+pub const OSV_QUERY_BATCH_URL: &str = "https://api.osv.dev/v1/querybatch";
+
+/// The OSV endpoint that describes a single vulnerability, by ID.
+pub const OSV_VULN_URL: &str = "https://api.osv.dev/v1/vulns";
+
+/// The most packages OSV accepts in a single batch query.
+const BATCH_SIZE: usize = 1000;
+
+/// How severe a vulnerability is, as rated by the advisory that reported it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "low" => Ok(Severity::Low),
+            // GitHub advisories call medium severity "moderate"
+            "medium" | "moderate" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            other => anyhow::bail!(
+                "unknown severity '{}', expected low, medium, high or critical",
+                other
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// A known vulnerability affecting one of the project's dependencies.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Vulnerability {
+    /// The OSV ID of the advisory, such as `GHSA-jfh8-c2jp-5v3q`.
+    pub id: String,
+    /// Other IDs the vulnerability is known by, such as CVE numbers.
+    pub aliases: Vec<String>,
+    pub summary: String,
+    /// The advisory's rating, if it gave one.
+    pub severity: Option<Severity>,
+    /// The `group:artifact:version` of the affected dependency.
+    pub dependency: String,
+}
+
+/// An error raised when `fyg audit --fail-on` finds vulnerabilities at or above its severity.
+#[derive(Debug)]
+pub enum AuditError {
+    Vulnerable {
+        /// How many vulnerabilities are at or above `threshold`.
+        count: usize,
+        threshold: Severity,
+    },
+}
+
+impl std::fmt::Display for AuditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditError::Vulnerable { count, threshold } => write!(
+                f,
+                "found {} {} with {} severity or higher",
+                count,
+                if *count == 1 { "vulnerability" } else { "vulnerabilities" },
+                threshold
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AuditError {}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    #[serde(default)]
+    results: Vec<BatchResult>,
+}
+
+#[derive(Deserialize)]
+struct BatchResult {
+    #[serde(default)]
+    vulns: Vec<VulnId>,
+}
+
+#[derive(Deserialize)]
+struct VulnId {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct Advisory {
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    summary: Option<String>,
+    #[serde(default)]
+    details: Option<String>,
+    #[serde(default)]
+    database_specific: Option<DatabaseSpecific>,
+}
+
+#[derive(Deserialize)]
+struct DatabaseSpecific {
+    #[serde(default)]
+    severity: Option<String>,
+}
+
+/// Looks up the known vulnerabilities of `dependencies` in the OSV database.
+///
+/// Vulnerabilities are sorted with the most severe first.
+pub(crate) fn scan(dependencies: &[Coordinate]) -> anyhow::Result<Vec<Vulnerability>> {
+    // the same advisory often affects several artifacts of a library
+    let mut advisories: HashMap<String, String> = HashMap::new();
+    let mut vulnerabilities = Vec::new();
+
+    for chunk in dependencies.chunks(BATCH_SIZE) {
+        let body = http::post_json(OSV_QUERY_BATCH_URL, &batch_query(chunk).to_string())
+            .map_err(|e| {
+                anyhow::anyhow!("could not query the OSV vulnerability database: {}", e)
+            })?;
+
+        for (coordinate, ids) in chunk.iter().zip(parse_batch(&body)?) {
+            for id in ids {
+                if !advisories.contains_key(&id) {
+                    let url = format!("{}/{}", OSV_VULN_URL, id);
+                    let body = http::get_string(&url).map_err(|e| {
+                        anyhow::anyhow!("could not fetch vulnerability {} from OSV: {}", id, e)
+                    })?;
+                    advisories.insert(id.clone(), body);
+                }
+                vulnerabilities.push(parse_vulnerability(&id, &advisories[&id], coordinate)?);
+            }
+        }
+    }
+
+    vulnerabilities.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.dependency.cmp(&b.dependency))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(vulnerabilities)
+}
+
+/// Fails if any of `vulnerabilities` is at least as severe as `threshold`.
+pub fn check_threshold(
+    vulnerabilities: &[Vulnerability],
+    threshold: Severity,
+) -> Result<(), AuditError> {
+    let count = vulnerabilities
+        .iter()
+        .filter(|v| v.severity.is_some_and(|s| s >= threshold))
+        .count();
+    match count {
+        0 => Ok(()),
+        count => Err(AuditError::Vulnerable { count, threshold }),
+    }
+}
+
+/// The OSV batch query asking about each of `dependencies`, in order.
+pub fn batch_query(dependencies: &[Coordinate]) -> serde_json::Value {
+    let queries: Vec<_> = dependencies
+        .iter()
+        .map(|c| {
+            serde_json::json!({
+                "package": {
+                    "ecosystem": "Maven",
+                    "name": format!("{}:{}", c.group, c.artifact),
+                },
+                "version": c.version,
+            })
+        })
+        .collect();
+    serde_json::json!({ "queries": queries })
+}
+
+/// Reads the IDs of the vulnerabilities found for each query from an OSV batch response, in
+/// the order the queries were made.
+pub fn parse_batch(body: &str) -> anyhow::Result<Vec<Vec<String>>> {
+    let response: BatchResponse = serde_json::from_str(body)
+        .map_err(|e| anyhow::anyhow!("unexpected response from OSV: {}", e))?;
+    Ok(response
+        .results
+        .into_iter()
+        .map(|r| r.vulns.into_iter().map(|v| v.id).collect())
+        .collect())
+}
+
+/// Reads a vulnerability affecting `coordinate` from an OSV vulnerability record.
+pub fn parse_vulnerability(
+    id: &str,
+    body: &str,
+    coordinate: &Coordinate,
+) -> anyhow::Result<Vulnerability> {
+    let advisory: Advisory = serde_json::from_str(body)
+        .map_err(|e| anyhow::anyhow!("unexpected response from OSV: {}", e))?;
+    let summary = advisory
+        .summary
+        .as_deref()
+        .or(advisory.details.as_deref())
+        .and_then(|s| s.lines().next())
+        .unwrap_or_default()
+        .to_string();
+
+    Ok(Vulnerability {
+        id: id.to_string(),
+        aliases: advisory.aliases,
+        summary,
+        severity: advisory
+            .database_specific
+            .and_then(|d| d.severity)
+            .and_then(|s| s.parse().ok()),
+        dependency: format!(
+            "{}:{}:{}",
+            coordinate.group, coordinate.artifact, coordinate.version
+        ),
+    })
+}
@@ -0,0 +1,677 @@
+pub mod report;
+
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Arc,
+};
+
+use crate::{
+    BuildListener, FygBinaryType, ProcessConfig,
+    cancel::{self, CancelFlag},
+    config::{
+        CinteropConfig, DependencyValue, ExplicitApiMode, JsModuleKind, NativeBinaryConfig,
+    },
+    toml::FygToml,
+};
+
+/// Where code generators write the sources they produce, in a directory of their own each,
+/// relative to the project directory.
+pub(crate) const GENERATED_SOURCES_DIR: &str = "build/generated";
+
+/// Options controlling how [`Fyg::build_with_options`](crate::Fyg::build_with_options) behaves.
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    /// Fails the build instead of resolving dependencies if `fyg.lock` is missing or out of date.
+    pub frozen: bool,
+
+    /// Builds without network access, using only artifacts in `~/.fyg/cache/` or the local
+    /// Maven repository. `fyg.lock` has to exist already.
+    pub offline: bool,
+
+    /// Only builds the workspace member with this name, along with the members it depends on.
+    pub module: Option<String>,
+
+    /// Whether to build for development or for production, which picks the profile used.
+    pub mode: BuildMode,
+
+    /// Compiles even if the build cache has the output of an identical build.
+    pub no_cache: bool,
+
+    /// How many workspace members may be built at the same time. Defaults to the number of
+    /// logical CPUs. With `Some(1)`, members are built one after another.
+    pub jobs: Option<usize>,
+
+    /// Writes an HTML report of the build to `build/reports/build/index.html` once it is done,
+    /// whether or not it succeeded, even if `build.generate-report` is off.
+    pub report: bool,
+
+    /// Told about each step of the build as it runs. Builds are silent without one.
+    pub listener: Option<Arc<dyn BuildListener + Send + Sync>>,
+
+    /// How the compilers and tools the build runs are started. Without one, they are started
+    /// as the [`ProcessConfig`] of the current scope says, if there is one.
+    pub process: Option<ProcessConfig>,
+
+    /// Stops a build started with [`Fyg::build_async`](crate::Fyg::build_async) once it is
+    /// cancelled, killing the compiler that is running. Blocking builds don't look at it.
+    #[cfg(feature = "async")]
+    pub cancel: Option<tokio_util::sync::CancellationToken>,
+}
+
+/// Whether a build is for development or for production.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BuildMode {
+    /// Builds with the `debug` profile.
+    #[default]
+    Debug,
+    /// Builds with the `release` profile.
+    Release,
+}
+
+impl BuildMode {
+    /// Returns the name of the `[profile.<name>]` section used in this mode.
+    pub fn profile_name(&self) -> &'static str {
+        match self {
+            BuildMode::Debug => "debug",
+            BuildMode::Release => "release",
+        }
+    }
+}
+
+/// An error raised while compiling a fyg project.
+#[derive(Debug)]
+pub enum BuildError {
+    /// No `kotlinc` executable could be found.
+    CompilerNotFound,
+
+    /// A JavaScript target is enabled but no `kotlinc-js` executable could be found.
+    JsCompilerNotFound,
+
+    /// A WebAssembly target is enabled but no Kotlin/Wasm compiler could be found.
+    WasmCompilerNotFound,
+
+    /// A native target is enabled but the named Kotlin/Native tool could not be found.
+    NativeToolNotFound(&'static str),
+
+    /// An XCFramework is being built but no `xcodebuild` executable could be found.
+    XcodebuildNotFound,
+
+    /// A Windows target has resources to embed but no `windres` executable could be found.
+    WindresNotFound,
+
+    /// The source directory does not contain any source files.
+    NoSources(PathBuf),
+
+    /// The compiler ran but exited unsuccessfully.
+    CompilationFailed {
+        /// The exit code of the compiler, if it exited normally.
+        code: Option<i32>,
+        /// Everything the compiler wrote to stderr.
+        stderr: String,
+    },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::CompilerNotFound => write!(
+                f,
+                "could not find kotlinc, make sure it is on your PATH or set KOTLIN_HOME"
+            ),
+            BuildError::JsCompilerNotFound => write!(
+                f,
+                "could not find kotlinc-js, which is needed for JavaScript targets"
+            ),
+            BuildError::WasmCompilerNotFound => write!(
+                f,
+                "could not find kotlinc-wasm or kotlinc-js, needed for WebAssembly targets"
+            ),
+            BuildError::NativeToolNotFound(tool) => write!(
+                f,
+                "could not find {}, which is needed for native targets, install Kotlin/Native and \
+                 set KOTLIN_NATIVE_HOME",
+                tool
+            ),
+            BuildError::XcodebuildNotFound => write!(
+                f,
+                "could not find xcodebuild, which is needed to bundle an XCFramework, install \
+                 Xcode"
+            ),
+            BuildError::WindresNotFound => write!(
+                f,
+                "could not find windres, which is needed to embed Windows resources, install \
+                 MinGW-w64"
+            ),
+            BuildError::NoSources(dir) => {
+                write!(f, "no source files found in {}", dir.display())
+            }
+            BuildError::CompilationFailed { code, stderr } => {
+                match code {
+                    Some(code) => writeln!(f, "compilation failed (exit code {})", code)?,
+                    None => writeln!(f, "compilation failed")?,
+                }
+                write!(f, "{}", stderr.trim_end())
+            }
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+/// A warning reported by a Kotlin compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompilerWarning {
+    /// The source file the warning is about, if it is about one.
+    pub file: Option<PathBuf>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+impl CompilerWarning {
+    /// Reads the warnings from a compiler's output, which reports each as
+    /// `<file>:<line>:<column>: warning: <message>`, or `warning: <message>` if it isn't about a
+    /// particular place in the sources.
+    pub fn parse(output: &str) -> Vec<CompilerWarning> {
+        output
+            .lines()
+            .filter_map(|line| {
+                if let Some(message) = line.strip_prefix("warning: ") {
+                    return Some(CompilerWarning {
+                        file: None,
+                        line: None,
+                        column: None,
+                        message: message.trim().to_string(),
+                    });
+                }
+
+                let (location, message) = line.split_once(": warning: ")?;
+                // split from the right, since Windows paths have a colon of their own
+                let mut parts = location.rsplitn(3, ':');
+                let column = parts.next()?.trim().parse().ok()?;
+                let line = parts.next()?.trim().parse().ok()?;
+                let file = parts.next().filter(|f| !f.is_empty())?;
+                Some(CompilerWarning {
+                    file: Some(PathBuf::from(file)),
+                    line: Some(line),
+                    column: Some(column),
+                    message: message.trim().to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+impl std::fmt::Display for CompilerWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}", file.display())?;
+            if let (Some(line), Some(column)) = (self.line, self.column) {
+                write!(f, ":{}:{}", line, column)?;
+            }
+            write!(f, ": ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Recursively collects every file under `dir` with the given extension, sorted by path.
+pub(crate) fn collect_sources(dir: &Path, extension: &str) -> std::io::Result<Vec<PathBuf>> {
+    let mut sources = Vec::new();
+    if dir.is_dir() {
+        visit(dir, extension, &mut sources)?;
+    }
+    sources.sort();
+    Ok(sources)
+}
+
+fn visit(dir: &Path, extension: &str, sources: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            visit(&path, extension, sources)?;
+        } else if path.extension().is_some_and(|ext| ext == extension) {
+            sources.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Returns the directories whose contents make up a project's sources JAR: `src/kotlin`,
+/// `src/java` if it exists and, if `include_generated` is set, each tool's directory under
+/// [`GENERATED_SOURCES_DIR`].
+pub(crate) fn source_roots(
+    project_dir: &Path,
+    include_generated: bool,
+) -> std::io::Result<Vec<PathBuf>> {
+    let src_dir = project_dir.join("src");
+    let mut roots = vec![src_dir.join("kotlin")];
+    let java = src_dir.join("java");
+    if java.is_dir() {
+        roots.push(java);
+    }
+
+    let generated = project_dir.join(GENERATED_SOURCES_DIR);
+    if include_generated && generated.is_dir() {
+        let mut tools = Vec::new();
+        for entry in std::fs::read_dir(generated)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                tools.push(path);
+            }
+        }
+        tools.sort();
+        roots.extend(tools);
+    }
+    Ok(roots)
+}
+
+/// Builds the compile classpath for a project.
+///
+/// Local `path` dependencies contribute their own `build/classes` output directory.
+pub(crate) fn classpath(fyg_toml: &FygToml, project_dir: &Path) -> Vec<PathBuf> {
+    let Some(deps) = &fyg_toml.dependencies else {
+        return Vec::new();
+    };
+
+    [&deps.common, &deps.jvm]
+        .into_iter()
+        .flatten()
+        .flat_map(|scope| scope.values())
+        .filter_map(|value| match value {
+            DependencyValue::Complex(dep) => dep.path.as_ref(),
+            DependencyValue::Version(_) => None,
+        })
+        .map(|path| project_dir.join(path).join("build").join("classes"))
+        .collect()
+}
+
+/// Settings from `fyg.toml` that are passed on to `kotlinc`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CompilerOptions {
+    /// The JVM bytecode version to target, from `targets.jvm.target`.
+    pub jvm_target: Option<String>,
+    /// The JDK to run `kotlinc` on and compile against, from `build.java-version`.
+    pub java_home: Option<PathBuf>,
+    /// The Kotlin release whose language features are allowed, from `build.language-version`.
+    pub language_version: Option<String>,
+    /// The Kotlin release whose standard library API is allowed, from `build.api-version`.
+    pub api_version: Option<String>,
+    /// Whether public declarations need explicit visibility, from `build.explicit-api`.
+    pub explicit_api: Option<ExplicitApiMode>,
+    /// Opt-in annotations allowed everywhere, from `build.opt-in`.
+    pub opt_in: Vec<String>,
+    /// Whether warnings fail the build, from `build.all-warnings-as-errors`.
+    pub all_warnings_as_errors: bool,
+    /// Whether warnings are hidden, from `build.suppress-warnings`.
+    pub suppress_warnings: bool,
+    /// Whether to leave out the null checks at Java boundaries, from the build profile.
+    pub no_null_assertions: bool,
+    /// Whether to keep debugging information, from the build profile.
+    pub debug_symbols: bool,
+    /// Arguments passed through as they are, from `build.free-compiler-args` followed by the
+    /// profile's.
+    pub free_args: Vec<String>,
+    /// Compiler plugin JARs, each passed with `-Xplugin`.
+    pub plugins: Vec<PathBuf>,
+    /// Options for the compiler plugins, each `plugin:<plugin id>:<key>=<value>`.
+    pub plugin_options: Vec<String>,
+    /// The name of the module being compiled, which `internal` declarations are mangled with.
+    pub module_name: Option<String>,
+    /// Class directories whose `internal` declarations the sources may use, because they are
+    /// an earlier compilation of the same module.
+    pub friend_paths: Vec<PathBuf>,
+}
+
+/// Invokes `kotlinc` on the given sources, writing class files to `out_dir`, and returns what
+/// it wrote to stderr.
+pub(crate) fn compile_kotlin(
+    kotlinc: &Path,
+    sources: &[PathBuf],
+    classpath: &[PathBuf],
+    out_dir: &Path,
+    options: &CompilerOptions,
+) -> Result<String, BuildError> {
+    let cmd = kotlinc_command(kotlinc, sources, classpath, out_dir, options)?;
+    run_kotlinc(cmd, options.java_home.as_deref())
+}
+
+/// The `kotlinc` command [`compile_kotlin`] runs.
+pub(crate) fn kotlinc_command(
+    kotlinc: &Path,
+    sources: &[PathBuf],
+    classpath: &[PathBuf],
+    out_dir: &Path,
+    options: &CompilerOptions,
+) -> Result<Command, BuildError> {
+    let mut cmd = Command::new(kotlinc);
+    cmd.arg("-d").arg(out_dir);
+
+    if let Some(target) = &options.jvm_target {
+        cmd.arg("-jvm-target").arg(target);
+    }
+
+    if let Some(version) = &options.language_version {
+        cmd.arg("-language-version").arg(version);
+    }
+
+    if let Some(version) = &options.api_version {
+        cmd.arg("-api-version").arg(version);
+    }
+
+    if let Some(mode) = options.explicit_api {
+        cmd.arg(format!("-Xexplicit-api={}", mode.flag_value()));
+    }
+
+    for annotation in &options.opt_in {
+        cmd.arg(format!("-opt-in={}", annotation));
+    }
+
+    if let Some(java_home) = &options.java_home {
+        cmd.env("JAVA_HOME", java_home);
+        cmd.arg("-jdk-home").arg(java_home);
+    }
+
+    if let Some(name) = &options.module_name {
+        cmd.arg("-module-name").arg(name);
+    }
+
+    if !options.friend_paths.is_empty() {
+        let mut arg = std::ffi::OsString::from("-Xfriend-paths=");
+        for (i, path) in options.friend_paths.iter().enumerate() {
+            if i > 0 {
+                arg.push(",");
+            }
+            arg.push(path);
+        }
+        cmd.arg(arg);
+    }
+
+    if !classpath.is_empty() {
+        let cp = std::env::join_paths(classpath).map_err(|e| BuildError::CompilationFailed {
+            code: None,
+            stderr: e.to_string(),
+        })?;
+        cmd.arg("-cp").arg(cp);
+    }
+
+    if options.no_null_assertions {
+        cmd.args([
+            "-Xno-param-assertions",
+            "-Xno-call-assertions",
+            "-Xno-receiver-assertions",
+        ]);
+    }
+
+    if !options.debug_symbols {
+        cmd.arg("-Xno-source-debug-extension");
+    }
+
+    for plugin in &options.plugins {
+        let mut arg = std::ffi::OsString::from("-Xplugin=");
+        arg.push(plugin);
+        cmd.arg(arg);
+    }
+    for option in &options.plugin_options {
+        cmd.arg("-P").arg(option);
+    }
+
+    cmd.args(&options.free_args);
+
+    // the warning flags go last so nothing passed before them can turn them back off
+    if options.all_warnings_as_errors {
+        cmd.arg("-Werror");
+    }
+    if options.suppress_warnings {
+        cmd.arg("-nowarn");
+    }
+
+    cmd.args(sources);
+    Ok(cmd)
+}
+
+/// Runs a `kotlinc` command, returning what it wrote to stderr if it succeeds.
+pub(crate) fn run_kotlinc(cmd: Command, java_home: Option<&Path>) -> Result<String, BuildError> {
+    // a running build daemon saves starting a JVM for the compiler, but what it compiles can't
+    // be cancelled, and it runs in the daemon's environment rather than a ProcessConfig's
+    let kotlinc = Path::new(cmd.get_program());
+    let args: Vec<&std::ffi::OsStr> = cmd.get_args().collect();
+    let compiled = crate::daemon::Daemon::open()
+        .ok()
+        .filter(|_| CancelFlag::current().is_none() && ProcessConfig::current().is_none())
+        .and_then(|daemon| daemon.compile(kotlinc, &args, java_home));
+    match compiled {
+        Some(result) => {
+            log::info!("{} (on the build daemon)", command_line(&cmd));
+            result
+        }
+        None => run_compiler(cmd),
+    }
+}
+
+/// The `kotlinc-js` command that compiles the given sources, writing a JavaScript module named
+/// `module_name` to `out_dir`.
+pub(crate) fn kotlinc_js_command(
+    kotlinc_js: &Path,
+    sources: &[PathBuf],
+    stdlib: Option<&Path>,
+    out_dir: &Path,
+    module_name: &str,
+    module_kind: JsModuleKind,
+    source_maps: bool,
+) -> Command {
+    let mut cmd = Command::new(kotlinc_js);
+    cmd.arg("-Xir-produce-js")
+        .arg("-ir-output-dir")
+        .arg(out_dir)
+        .args(["-ir-output-name", module_name])
+        .args(["-module-kind", &module_kind.to_string()]);
+
+    if let Some(stdlib) = stdlib {
+        cmd.arg("-libraries").arg(stdlib);
+    }
+    if source_maps {
+        cmd.arg("-source-map");
+    }
+
+    cmd.args(sources);
+    cmd
+}
+
+/// The Kotlin/Wasm compiler command that compiles the given sources, writing a WebAssembly
+/// module named `module_name` to `out_dir`.
+///
+/// `wasm_target` is `js` for JavaScript hosts or `wasi` for WASI runtimes.
+pub(crate) fn kotlinc_wasm_command(
+    compiler: &Path,
+    sources: &[PathBuf],
+    stdlib: Option<&Path>,
+    out_dir: &Path,
+    module_name: &str,
+    wasm_target: &str,
+) -> Command {
+    let mut cmd = Command::new(compiler);
+    cmd.args(["-Xwasm", &format!("-Xwasm-target={}", wasm_target)])
+        .arg("-Xir-produce-js")
+        .arg("-ir-output-dir")
+        .arg(out_dir)
+        .args(["-ir-output-name", module_name]);
+
+    if let Some(stdlib) = stdlib {
+        cmd.arg("-libraries").arg(stdlib);
+    }
+
+    cmd.args(sources);
+    cmd
+}
+
+/// The `kotlinc-native` command that compiles the given sources, writing a binary for `target`
+/// to `output`, which gets the extension the binary type calls for. `target` is the key and
+/// Kotlin/Native name of the target, as returned by
+/// [`crate::config::TargetsConfig::native_targets`].
+///
+/// `libraries` are `.klib` files the sources use, such as bindings generated by [`cinterop`].
+/// `exports` are `.klib` files whose APIs a framework exports along with its own. A missing
+/// `binary` builds an executable. `linker_options`, such as the object [`windres_command`]
+/// compiles, are passed to the linker one by one after the `linker-opts` of `binary`.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn kotlinc_native_command(
+    kotlinc_native: &Path,
+    sources: &[PathBuf],
+    libraries: &[PathBuf],
+    exports: &[PathBuf],
+    output: &Path,
+    (key, target): (&str, &str),
+    binary: Option<&NativeBinaryConfig>,
+    linker_options: &[String],
+) -> Command {
+    let binary_type = binary.map_or(&FygBinaryType::Executable, |b| &b.binary_type);
+    let produce = match binary_type {
+        FygBinaryType::Executable | FygBinaryType::Test => "program",
+        FygBinaryType::SharedLib => "dynamic",
+        FygBinaryType::StaticLib => "static",
+        FygBinaryType::Framework | FygBinaryType::XCFramework => "framework",
+    };
+
+    let mut cmd = Command::new(kotlinc_native);
+    cmd.args(["-target", target, "-produce", produce])
+        .arg("-output")
+        .arg(output);
+    if matches!(binary_type, FygBinaryType::Test) {
+        cmd.arg("-generate-test-runner");
+    }
+    for library in libraries {
+        cmd.arg("-library").arg(library);
+    }
+    if let Some(binary) = binary.filter(|b| b.is_framework()) {
+        for export in exports {
+            cmd.arg("-library").arg(export);
+            cmd.arg(format!("-Xexport-library={}", export.display()));
+        }
+        if binary.embed_bitcode == Some(true) {
+            cmd.arg("-Xembed-bitcode");
+        }
+    }
+    let args = binary.map(|b| b.args_for(key)).unwrap_or_default();
+    if let Some(linker_opts) = args.linker_opts {
+        cmd.arg("-linker-options").arg(linker_opts.join(" "));
+    }
+    for option in linker_options {
+        cmd.args(["-linker-option", option]);
+    }
+    if let Some(compiler_opts) = args.compiler_opts {
+        cmd.arg("-compiler-options").arg(compiler_opts.join(" "));
+    }
+
+    cmd.args(sources);
+    cmd
+}
+
+/// Runs `cinterop` on the `.def` file of `config`, writing Kotlin bindings for the Kotlin/Native
+/// `target` to `<name>.klib` in `out_dir`, and returns the path of the `.klib`.
+pub(crate) fn cinterop(
+    cinterop: &Path,
+    config: &CinteropConfig,
+    project_dir: &Path,
+    target: &str,
+    out_dir: &Path,
+) -> Result<PathBuf, BuildError> {
+    let output = out_dir.join(&config.name);
+
+    let mut cmd = Command::new(cinterop);
+    cmd.arg("-def")
+        .arg(project_dir.join(&config.def_file))
+        .args(["-target", target])
+        .arg("-o")
+        .arg(&output);
+    if let Some(package) = &config.package {
+        cmd.args(["-pkg", package]);
+    }
+    for option in config.compiler_opts.iter().flatten() {
+        cmd.args(["-compiler-option", option]);
+    }
+    for option in config.linker_opts.iter().flatten() {
+        cmd.args(["-linker-option", option]);
+    }
+
+    run_compiler(cmd)?;
+    Ok(output.with_extension("klib"))
+}
+
+/// The `windres` command that compiles the resource script `script` into the COFF object
+/// `output`, which is linked into a Windows binary. Files the script refers to by a relative
+/// path, such as icons, are looked up in `include_dir`.
+pub(crate) fn windres_command(
+    windres: &Path,
+    script: &Path,
+    include_dir: &Path,
+    output: &Path,
+) -> Command {
+    let mut cmd = Command::new(windres);
+    cmd.args(["--input-format", "rc", "--output-format", "coff"])
+        .arg("--include-dir")
+        .arg(include_dir)
+        .arg("-i")
+        .arg(script)
+        .arg("-o")
+        .arg(output);
+    cmd
+}
+
+/// Bundles `frameworks`, each built for a different Apple target, into the XCFramework at
+/// `output`, replacing any already there.
+pub(crate) fn create_xcframework(
+    xcodebuild: &Path,
+    frameworks: &[PathBuf],
+    output: &Path,
+) -> Result<(), BuildError> {
+    // xcodebuild refuses to overwrite an existing XCFramework
+    if output.exists() {
+        std::fs::remove_dir_all(output).map_err(|e| BuildError::CompilationFailed {
+            code: None,
+            stderr: e.to_string(),
+        })?;
+    }
+
+    let mut cmd = Command::new(xcodebuild);
+    cmd.arg("-create-xcframework");
+    for framework in frameworks {
+        cmd.arg("-framework").arg(framework);
+    }
+    cmd.arg("-output").arg(output);
+    run_compiler(cmd)?;
+    Ok(())
+}
+
+/// Runs a compiler, returning what it wrote to stderr if it succeeds.
+pub(crate) fn run_compiler(mut cmd: Command) -> Result<String, BuildError> {
+    let output = cancel::output(&mut cmd).map_err(|e| BuildError::CompilationFailed {
+        code: None,
+        stderr: e.to_string(),
+    })?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stderr).into_owned())
+    } else {
+        Err(BuildError::CompilationFailed {
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+/// The command line `cmd` runs, with any argument containing a space quoted.
+pub(crate) fn command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| {
+            let arg = arg.to_string_lossy();
+            match arg.contains(' ') {
+                true => format!("\"{}\"", arg),
+                false => arg.into_owned(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,266 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{BuildListener, BuildResult, build::CompilerWarning, error::FygError};
+
+/// Where `fyg build --report` writes its report, relative to the project.
+pub const REPORT_DIR: &str = "build/reports/build";
+
+/// A step of a build, as it appears on the timeline of the report.
+struct Step {
+    name: String,
+    /// When the step started, from the start of the build.
+    start: Duration,
+    /// How long the step took, or `None` if it never finished.
+    duration: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Recording {
+    steps: Vec<Step>,
+    commands: Vec<String>,
+    output: String,
+}
+
+/// Records what a build does for its report, passing everything on to the listener the build
+/// was given.
+pub(crate) struct BuildRecorder<'a> {
+    inner: &'a dyn BuildListener,
+    started: Instant,
+    recording: Mutex<Recording>,
+}
+
+impl<'a> BuildRecorder<'a> {
+    pub fn new(inner: &'a dyn BuildListener) -> Self {
+        Self {
+            inner,
+            started: Instant::now(),
+            recording: Mutex::default(),
+        }
+    }
+}
+
+impl BuildListener for BuildRecorder<'_> {
+    fn on_step_started(&self, step: &str) {
+        self.recording.lock().unwrap().steps.push(Step {
+            name: step.to_string(),
+            start: self.started.elapsed(),
+            duration: None,
+        });
+        self.inner.on_step_started(step);
+    }
+
+    fn on_step_finished(&self, step: &str, duration: Duration) {
+        let mut recording = self.recording.lock().unwrap();
+        // members of a workspace built in parallel have steps of the same name running at once
+        if let Some(recorded) = recording
+            .steps
+            .iter_mut()
+            .rev()
+            .find(|s| s.name == step && s.duration.is_none())
+        {
+            recorded.duration = Some(duration);
+        }
+        drop(recording);
+        self.inner.on_step_finished(step, duration);
+    }
+
+    fn on_compiler_started(&self, command: &str) {
+        self.recording.lock().unwrap().commands.push(command.to_string());
+        self.inner.on_compiler_started(command);
+    }
+
+    fn on_compiler_output(&self, line: &str) {
+        let mut recording = self.recording.lock().unwrap();
+        recording.output.push_str(line);
+        recording.output.push('\n');
+        drop(recording);
+        self.inner.on_compiler_output(line);
+    }
+
+    fn on_error(&self, err: &FygError) {
+        self.inner.on_error(err);
+    }
+}
+
+/// Writes an HTML page describing the build `recorder` recorded to `index.html` in
+/// [`REPORT_DIR`], returning its path. `result` is what the build returned, and `sources` the
+/// source files of the projects it built.
+pub(crate) fn write_html(
+    recorder: &BuildRecorder,
+    result: Result<&BuildResult, &FygError>,
+    sources: &[PathBuf],
+    project_name: &str,
+    project_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let recording = recorder.recording.lock().unwrap();
+    let total = recorder.started.elapsed().as_secs_f64().max(0.001);
+
+    let mut timeline = String::new();
+    for step in &recording.steps {
+        let start = step.start.as_secs_f64();
+        let (class, seconds, label) = match step.duration {
+            Some(duration) => {
+                let seconds = duration.as_secs_f64();
+                ("finished", seconds, format!("{:.2}s", seconds))
+            }
+            None => ("failed", total - start, "failed".to_string()),
+        };
+        timeline.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td><td class=\"track\"><div class=\"bar\" \
+             style=\"margin-left: {:.2}%; width: {:.2}%\"></div></td><td>{}</td></tr>\n",
+            class,
+            escape(&step.name),
+            start / total * 100.0,
+            (seconds / total * 100.0).max(0.5),
+            label
+        ));
+    }
+
+    let mut source_list = String::new();
+    for source in sources {
+        let relative = source.strip_prefix(project_dir).unwrap_or(source);
+        source_list.push_str(&format!("<li>{}</li>\n", escape(&relative.display().to_string())));
+    }
+
+    let mut commands = String::new();
+    for command in &recording.commands {
+        commands.push_str(&format!("<pre>{}</pre>\n", escape(command)));
+    }
+
+    let parsed = CompilerWarning::parse(&recording.output);
+    let mut warnings = String::new();
+    for warning in &parsed {
+        let location = match &warning.file {
+            Some(file) => {
+                let mut location = file.display().to_string();
+                if let (Some(line), Some(column)) = (warning.line, warning.column) {
+                    location.push_str(&format!(":{}:{}", line, column));
+                }
+                format!(
+                    "<a href=\"file://{}\">{}</a>",
+                    escape(&project_dir.join(file).display().to_string()),
+                    escape(&location)
+                )
+            }
+            None => String::new(),
+        };
+        warnings.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            location,
+            escape(&warning.message)
+        ));
+    }
+
+    let mut artifacts = String::new();
+    for artifact in result.map(|r| r.artifacts.as_slice()).unwrap_or_default() {
+        let relative = artifact.strip_prefix(project_dir).unwrap_or(artifact);
+        artifacts.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td></tr>\n",
+            escape(&relative.display().to_string()),
+            format_size(size(artifact)?)
+        ));
+    }
+
+    let outcome = match result {
+        Ok(result) if result.cached => "Restored from the build cache".to_string(),
+        Ok(_) => "Succeeded".to_string(),
+        Err(error) => format!("Failed<pre class=\"error\">{}</pre>", escape(&error.to_string())),
+    };
+
+    let html = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Build report for {name}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+td, th {{ border-bottom: 1px solid #ddd; padding: 4px 8px; text-align: left; }}
+pre {{ background: #f6f6f6; padding: 8px; white-space: pre-wrap; word-break: break-all; }}
+.track {{ width: 60%; }}
+.bar {{ height: 12px; background: #2a7d2a; }}
+.failed .bar, .error {{ background: #b00020; color: #fff; }}
+</style>
+</head>
+<body>
+<h1>Build report for {name}</h1>
+<p>{outcome}</p>
+<p>{total:.2}s in total</p>
+<h2>Timeline</h2>
+<table>
+<tr><th>Step</th><th></th><th>Time</th></tr>
+{timeline}</table>
+<h2>Warnings ({warning_count})</h2>
+<table>
+{warnings}</table>
+<h2>Artifacts</h2>
+<table>
+<tr><th>Path</th><th>Size</th></tr>
+{artifacts}</table>
+<h2>Compiler commands</h2>
+{commands}<h2>Sources ({source_count})</h2>
+<input id=\"filter\" placeholder=\"Filter sources\">
+<ul id=\"sources\">
+{sources}</ul>
+<script>
+document.getElementById('filter').addEventListener('input', function () {{
+  var text = this.value.toLowerCase();
+  document.querySelectorAll('#sources li').forEach(function (item) {{
+    item.hidden = item.textContent.toLowerCase().indexOf(text) < 0;
+  }});
+}});
+</script>
+</body>
+</html>
+",
+        name = escape(project_name),
+        outcome = outcome,
+        total = total,
+        timeline = timeline,
+        warning_count = parsed.len(),
+        warnings = warnings,
+        artifacts = artifacts,
+        commands = commands,
+        source_count = sources.len(),
+        sources = source_list,
+    );
+
+    let output_dir = project_dir.join(REPORT_DIR);
+    std::fs::create_dir_all(&output_dir)?;
+    let path = output_dir.join("index.html");
+    std::fs::write(&path, html)?;
+    Ok(path)
+}
+
+/// The size of a file, or of everything in a directory, in bytes.
+fn size(path: &Path) -> std::io::Result<u64> {
+    let metadata = std::fs::metadata(path)?;
+    if !metadata.is_dir() {
+        return Ok(metadata.len());
+    }
+    let mut total = 0;
+    for entry in std::fs::read_dir(path)? {
+        total += size(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..1_024 => format!("{} B", bytes),
+        1_024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1_024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,306 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use crate::{build::BuildMode, cancel, hash, lock::FygLock, toml::FygToml, toolchain};
+
+/// How many days `fyg cache clean` keeps an entry for after it was last used, by default.
+pub const DEFAULT_MAX_AGE_DAYS: u64 = 30;
+
+/// The file in each entry whose modification time records when the entry was last used.
+const LAST_USED_FILE: &str = "last-used";
+
+/// The file in each entry holding the [`BuildCache::project_id`] of the project that stored it.
+const PROJECT_FILE: &str = "project";
+
+/// Options controlling what [`Fyg::clean`](crate::Fyg::clean) removes.
+#[derive(Clone, Debug, Default)]
+pub struct CleanOptions {
+    /// Removes the project's `build/` directory.
+    pub build: bool,
+
+    /// Removes the project's entries in the build cache.
+    pub cache: bool,
+
+    /// The build cache to remove entries from, instead of `~/.fyg/build-cache`.
+    pub cache_dir: Option<PathBuf>,
+}
+
+/// What cleaning removed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Cleaned {
+    /// Whether the `build/` directory was removed.
+    pub build_dir: bool,
+    /// How many entries of the build cache were removed.
+    pub entries: usize,
+    /// The size of everything removed, in bytes.
+    pub bytes: u64,
+}
+
+/// A content-addressed store of `build/` directories.
+///
+/// Each entry is keyed by a hash of everything that goes into a build, so a project whose
+/// sources, dependencies and settings haven't changed can restore its last output instead of
+/// compiling again.
+#[derive(Clone, Debug)]
+pub struct BuildCache {
+    dir: PathBuf,
+}
+
+impl BuildCache {
+    /// Opens the cache in `~/.fyg/build-cache`.
+    pub fn open() -> anyhow::Result<Self> {
+        Ok(Self::at(toolchain::fyg_home()?.join("build-cache")))
+    }
+
+    /// Opens a cache kept in `dir`, which is created when the first entry is stored.
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory entries are kept in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The ID the entries a project stores are marked with, a hash of its group and name.
+    pub fn project_id(fyg_toml: &FygToml) -> String {
+        let project = &fyg_toml.project;
+        hash::sha256_hex(format!("{}:{}", project.group, project.name).as_bytes())
+    }
+
+    /// Computes the key of a build of the project in `project_dir`.
+    ///
+    /// The key covers every file under `src/`, the output of any local `path` dependencies,
+    /// the artifacts locked in `fyg.lock`, the settings in `fyg.toml`, the build mode, the
+    /// `kotlinc` and JDK the build uses and the version of fyg.
+    pub fn key(
+        fyg_toml: &FygToml,
+        lock: &FygLock,
+        project_dir: &Path,
+        mode: BuildMode,
+    ) -> anyhow::Result<String> {
+        let mut manifest = format!(
+            "fyg {}\nmode {}\n",
+            env!("CARGO_PKG_VERSION"),
+            mode.profile_name()
+        );
+        // JSON values keep their keys sorted, so maps in fyg.toml always hash the same
+        manifest.push_str(&serde_json::to_value(fyg_toml)?.to_string());
+        manifest.push('\n');
+        manifest.push_str(&lock.to_toml_string()?);
+        manifest.push_str(&toolchain_manifest(fyg_toml));
+
+        let mut roots = vec![project_dir.join("src")];
+        roots.extend(crate::build::classpath(fyg_toml, project_dir));
+        for root in roots {
+            let mut files = Vec::new();
+            if root.is_dir() {
+                collect_files(&root, &mut files)?;
+            }
+            files.sort();
+            for file in files {
+                let relative = file.strip_prefix(project_dir).unwrap_or(&file);
+                manifest.push_str(&format!(
+                    "{} {}\n",
+                    relative.display(),
+                    hash::sha256_hex(&std::fs::read(&file)?)
+                ));
+            }
+        }
+
+        Ok(hash::sha256_hex(manifest.as_bytes()))
+    }
+
+    /// Replaces the `build/` directory of `project_dir` with the entry stored under `key`,
+    /// returning whether there was one.
+    pub fn restore(&self, key: &str, project_dir: &Path) -> anyhow::Result<bool> {
+        let entry = self.dir.join(key);
+        let stored = entry.join("build");
+        if !stored.is_dir() {
+            return Ok(false);
+        }
+
+        let build_dir = project_dir.join("build");
+        if build_dir.exists() {
+            std::fs::remove_dir_all(&build_dir)?;
+        }
+        copy_dir(&stored, &build_dir)?;
+        std::fs::write(entry.join(LAST_USED_FILE), "")?;
+        Ok(true)
+    }
+
+    /// Stores the `build/` directory of `project_dir` under `key`, unless an entry already
+    /// exists, marking it with the [`BuildCache::project_id`] `project`.
+    pub fn store(&self, key: &str, project_dir: &Path, project: &str) -> anyhow::Result<()> {
+        let entry = self.dir.join(key);
+        if entry.is_dir() {
+            return Ok(());
+        }
+
+        // entries are copied to a temporary sibling first, so a build interrupted while storing
+        // never leaves a partial entry to be restored later
+        let partial = self
+            .dir
+            .join(format!("{}.part-{}", key, std::process::id()));
+        if partial.exists() {
+            std::fs::remove_dir_all(&partial)?;
+        }
+        copy_dir(&project_dir.join("build"), &partial.join("build"))?;
+        std::fs::write(partial.join(LAST_USED_FILE), "")?;
+        std::fs::write(partial.join(PROJECT_FILE), project)?;
+        if std::fs::rename(&partial, &entry).is_err() {
+            // another build stored the same entry first
+            std::fs::remove_dir_all(&partial)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the entries that haven't been used for `max_age`.
+    pub fn clean(&self, max_age: Duration) -> anyhow::Result<Cleaned> {
+        let now = SystemTime::now();
+        self.remove_entries(|path| {
+            let last_used = std::fs::metadata(path.join(LAST_USED_FILE))
+                .or_else(|_| std::fs::metadata(path))?
+                .modified()?;
+            Ok(now.duration_since(last_used).unwrap_or_default() > max_age)
+        })
+    }
+
+    /// Removes the entries stored by the project with the [`BuildCache::project_id`] `project`.
+    ///
+    /// Entries stored before fyg marked them with their project are left, as nothing says
+    /// whose they are.
+    pub fn remove_project(&self, project: &str) -> anyhow::Result<Cleaned> {
+        self.remove_entries(|path| {
+            let stored_by = std::fs::read_to_string(path.join(PROJECT_FILE)).unwrap_or_default();
+            Ok(stored_by.trim() == project)
+        })
+    }
+
+    /// Removes every entry, whichever project stored it.
+    pub fn clear(&self) -> anyhow::Result<Cleaned> {
+        self.remove_entries(|_| Ok(true))
+    }
+
+    /// Removes the entries `remove` picks.
+    fn remove_entries(
+        &self,
+        mut remove: impl FnMut(&Path) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<Cleaned> {
+        let mut cleaned = Cleaned::default();
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Ok(cleaned);
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_dir() || !remove(&path)? {
+                continue;
+            }
+            cleaned.bytes += dir_size(&path)?;
+            std::fs::remove_dir_all(&path)?;
+            cleaned.entries += 1;
+        }
+        Ok(cleaned)
+    }
+}
+
+/// Describes the `kotlinc` and JDK a build of `fyg_toml` uses by their paths and what they say
+/// their version is, so a build with another release of either doesn't restore this one.
+fn toolchain_manifest(fyg_toml: &FygToml) -> String {
+    let build = fyg_toml.build.as_ref();
+    // a pinned Kotlin that isn't installed yet is only downloaded once the build misses
+    let kotlinc = match build.and_then(|b| b.kotlin_version.as_deref()) {
+        Some(version) => toolchain::installed_kotlinc(version),
+        None => toolchain::kotlinc(),
+    };
+    let java_home = match build.and_then(|b| b.java_version.as_deref()) {
+        Some(version) => toolchain::jdk(version),
+        None => toolchain::java_home(),
+    };
+    let java = match &java_home {
+        Some(home) => toolchain::java_in(home),
+        None => toolchain::java(),
+    };
+
+    let mut manifest = match &java_home {
+        Some(home) => format!("java-home {}\n", home.display()),
+        None => "java-home none\n".to_string(),
+    };
+    for (name, program) in [("kotlinc", kotlinc), ("java", java)] {
+        match program {
+            Some(program) => manifest.push_str(&format!(
+                "{} {}\n{}\n",
+                name,
+                program.display(),
+                tool_version(&program)
+            )),
+            None => manifest.push_str(&format!("{} none\n", name)),
+        }
+    }
+    manifest
+}
+
+/// What `program -version` prints, remembered for as long as `program` is unchanged, as every
+/// build asks and starting a JVM to answer takes a while.
+pub(crate) fn tool_version(program: &Path) -> String {
+    static VERSIONS: Mutex<BTreeMap<(PathBuf, Option<SystemTime>), String>> =
+        Mutex::new(BTreeMap::new());
+
+    let modified = std::fs::metadata(program).and_then(|m| m.modified()).ok();
+    let key = (program.to_path_buf(), modified);
+    if let Some(version) = VERSIONS.lock().unwrap().get(&key) {
+        return version.clone();
+    }
+
+    let Ok(output) = cancel::output(Command::new(program).arg("-version")) else {
+        return String::new();
+    };
+    // kotlinc and java both print their version to stderr
+    let mut version = String::from_utf8_lossy(&output.stderr).into_owned();
+    version.push_str(&String::from_utf8_lossy(&output.stdout));
+    let version = version.trim().to_string();
+    VERSIONS.lock().unwrap().insert(key, version.clone());
+    version
+}
+
+/// The total size of the files under `dir`, in bytes.
+pub(crate) fn dir_size(dir: &Path) -> std::io::Result<u64> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files
+        .iter()
+        .map(|file| Ok(std::fs::symlink_metadata(file)?.len()))
+        .sum()
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use std::{
+    cell::RefCell,
+    io::{BufRead, BufReader, Read},
+    process::{Child, Command, ExitStatus, Output, Stdio},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{ProcessConfig, build};
+
+/// How often a subprocess is checked on while it runs in a cancellable scope.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancelFlag>> = const { RefCell::new(None) };
+}
+
+/// A flag that kills the compilers and test runs started within [`CancelFlag::scope`] once it
+/// is set, which is how the async API stops a build that is underway.
+///
+/// Outside a scope, subprocesses run to completion like they always have.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    #[cfg_attr(not(feature = "async"), allow(dead_code))]
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// The flag of the scope the current thread is in, if any.
+    pub fn current() -> Option<CancelFlag> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// Runs `f` with `flag` applying to the subprocesses it starts on this thread. Threads `f`
+    /// starts have to enter the scope themselves.
+    pub fn scope<T>(flag: Option<CancelFlag>, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|current| current.replace(flag));
+        let result = f();
+        CURRENT.with(|current| current.replace(previous));
+        result
+    }
+}
+
+/// Waits for `child` to exit, killing it if the current scope is cancelled first, or if it
+/// runs past the timeout of the current [`ProcessConfig`].
+pub(crate) fn wait(child: &mut Child) -> std::io::Result<ExitStatus> {
+    let flag = CancelFlag::current();
+    let timeout = ProcessConfig::current().and_then(|config| config.timeout);
+    if flag.is_none() && timeout.is_none() {
+        return child.wait();
+    }
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(status);
+        }
+        if flag.as_ref().is_some_and(CancelFlag::is_cancelled) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(std::io::ErrorKind::Interrupted, "cancelled"));
+        }
+        if let Some(timeout) = timeout
+            && started.elapsed() >= timeout
+        {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("timed out after {}s", timeout.as_secs_f64()),
+            ));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Runs `cmd` like [`Command::output`], killing it if the current scope is cancelled, and
+/// starting it as the current [`ProcessConfig`] says.
+///
+/// The command line is logged at the info level, and what the command prints is echoed line by
+/// line as it runs at the trace level.
+pub(crate) fn output(cmd: &mut Command) -> std::io::Result<Output> {
+    ProcessConfig::configure(cmd);
+    log::info!("{}", build::command_line(cmd));
+    let echo = log::log_enabled!(log::Level::Trace);
+    let timeout = ProcessConfig::current().and_then(|config| config.timeout);
+    if CancelFlag::current().is_none() && timeout.is_none() && !echo {
+        return cmd.output();
+    }
+
+    let child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    wait_with_output(child, echo)
+}
+
+/// Collects the output of `child` like [`Child::wait_with_output`], waiting for it with
+/// [`wait`] so that it is killed if the current scope is cancelled. With `echo`, what it prints
+/// is logged line by line at the trace level.
+pub(crate) fn wait_with_output(mut child: Child, echo: bool) -> std::io::Result<Output> {
+    // both pipes are drained as the process runs, so it never blocks on a full one
+    let drain = move |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut buffer = Vec::new();
+            if let Some(pipe) = pipe {
+                let mut pipe = BufReader::new(pipe);
+                let mut start = 0;
+                while pipe.read_until(b'\n', &mut buffer).is_ok_and(|read| read > 0) {
+                    if echo {
+                        log::trace!("{}", String::from_utf8_lossy(&buffer[start..]).trim_end());
+                    }
+                    start = buffer.len();
+                }
+            }
+            buffer
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|p| Box::new(p) as _));
+    let stderr = drain(child.stderr.take().map(|p| Box::new(p) as _));
+
+    let status = wait(&mut child)?;
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
@@ -1,11 +1,20 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::FygBinaryType;
+use crate::{
+    FygBinaryType, Version,
+    maven::{self, Coordinate, Credentials, VersionRange},
+    toml::FygToml,
+    toolchain,
+};
 
 /// Project metadata configuration
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct ProjectConfig {
     pub name: String,
     pub group: String,
@@ -14,19 +23,329 @@ pub struct ProjectConfig {
     pub authors: Option<Vec<String>>,
     #[serde(default)]
     pub description: Option<String>,
+    /// The SPDX identifier of the project's license, such as `Apache-2.0`.
+    #[serde(default)]
+    pub license: Option<String>,
+    /// The project's home page.
+    #[serde(default)]
+    pub homepage: Option<String>,
+    /// Where the project's source code is kept.
+    #[serde(default)]
+    pub repository: Option<ScmConfig>,
+    #[serde(default)]
+    pub developers: Option<Vec<DeveloperConfig>>,
+}
+
+/// The source control repository of a project, as published in its POM
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScmConfig {
+    /// A page to browse the sources, such as `https://github.com/example/project`.
+    pub url: String,
+    /// The read-only connection, such as `scm:git:https://github.com/example/project.git`.
+    pub connection: String,
+    /// The read-write connection, such as `scm:git:ssh://git@github.com/example/project.git`.
+    #[serde(rename = "developer-connection")]
+    pub developer_connection: String,
+}
+
+/// Someone who works on a project, as published in its POM
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DeveloperConfig {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+impl ProjectConfig {
+    /// Whether the `[project]` section was left out, as it is in a workspace root.
+    pub fn is_empty(&self) -> bool {
+        self.name.is_empty()
+    }
+}
+
+/// Workspace configuration for a root `fyg.toml` that groups several projects
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WorkspaceConfig {
+    /// Paths to the member projects, relative to the workspace root.
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Shared dependency versions that members can use with `workspace = true`.
+    #[serde(default)]
+    pub dependencies: Option<HashMap<String, DependencyValue>>,
 }
 
 /// Build configuration
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct BuildConfig {
     #[serde(default)]
     pub multiplatform: Option<bool>,
     #[serde(default)]
     pub languages: Option<Vec<String>>,
+    /// The fully qualified class containing the `main` function, such as `com.example.AppKt`.
+    /// It is written to the manifest of the project's JAR and run by `fyg run`.
+    #[serde(default, rename = "main-class")]
+    pub main_class: Option<String>,
+    /// Which version wins when an artifact is required at several versions. Defaults to `newest`.
+    #[serde(default, rename = "conflict-strategy")]
+    pub conflict_strategy: Option<ConflictStrategy>,
+    /// Whether artifacts are looked up in, and copied into, the local Maven repository.
+    /// Defaults to `true`.
+    #[serde(default, rename = "use-local-maven")]
+    pub use_local_maven: Option<bool>,
+    /// The Kotlin compiler version to build with, such as `2.0.21`. It is downloaded into
+    /// `~/.fyg/toolchains` if needed. Defaults to whichever `kotlinc` is installed.
+    #[serde(default, rename = "kotlin-version")]
+    pub kotlin_version: Option<String>,
+    /// The version of an installed JDK to build and run with, such as `17`. Defaults to
+    /// `JAVA_HOME` or whichever `java` is on the `PATH`.
+    #[serde(default, rename = "java-version")]
+    pub java_version: Option<String>,
+    /// Restricts the language features available to those of a Kotlin release, such as `1.9`.
+    #[serde(default, rename = "language-version")]
+    pub language_version: Option<String>,
+    /// Restricts the standard library API available to that of a Kotlin release, such as `1.9`.
+    /// It can't be newer than `language-version`.
+    #[serde(default, rename = "api-version")]
+    pub api_version: Option<String>,
+    /// Whether public declarations must spell out their visibility and return type, which
+    /// keeps a library's API from changing by accident.
+    #[serde(default, rename = "explicit-api")]
+    pub explicit_api: Option<ExplicitApiMode>,
+    /// Fully qualified opt-in annotations, such as `kotlin.RequiresOptIn`, whose APIs may be
+    /// used anywhere in the project without an `@OptIn`.
+    #[serde(default, rename = "opt-in")]
+    pub opt_in: Option<Vec<String>>,
+    /// Whether compiler warnings fail the build.
+    #[serde(default, rename = "all-warnings-as-errors")]
+    pub all_warnings_as_errors: Option<bool>,
+    /// Whether compiler warnings are hidden. Can't be combined with `all-warnings-as-errors`.
+    #[serde(default, rename = "suppress-warnings")]
+    pub suppress_warnings: Option<bool>,
+    /// Extra arguments passed to `kotlinc` as they are, for flags fyg has no setting for.
+    #[serde(default, rename = "free-compiler-args")]
+    pub free_compiler_args: Option<Vec<String>>,
+    /// Whether every build also packs the sources into `build/libs/<name>-<version>-sources.jar`.
+    /// Defaults to `false`.
+    #[serde(default, rename = "sources-jar")]
+    pub sources_jar: Option<bool>,
+    /// Whether the sources JAR includes sources written by code generators. Defaults to `false`.
+    #[serde(default, rename = "include-generated-sources")]
+    pub include_generated_sources: Option<bool>,
+    /// Whether only the sources that changed since the last build, and those that depend on
+    /// them, are recompiled. Defaults to `true`, but builds using KSP or KAPT always compile
+    /// everything.
+    #[serde(default)]
+    pub incremental: Option<bool>,
+    /// Whether every build writes an HTML report of what it did to
+    /// `build/reports/build/index.html`, as `fyg build --report` does. Defaults to `false`.
+    #[serde(default, rename = "generate-report")]
+    pub generate_report: Option<bool>,
+    /// Kotlin Symbol Processing, run on the sources before they are compiled.
+    #[serde(default)]
+    pub ksp: Option<KspConfig>,
+    /// Annotation processing with KAPT, for processors that don't support KSP yet.
+    #[serde(default)]
+    pub kapt: Option<KaptConfig>,
+    /// API documentation generated with Dokka after the project is compiled.
+    #[serde(default)]
+    pub dokka: Option<DokkaConfig>,
+    /// Packaging of the project and all of its dependencies into one runnable JAR.
+    #[serde(default, rename = "fat-jar")]
+    pub fat_jar: Option<FatJarConfig>,
+    /// A Software Bill of Materials listing every dependency, written by each build.
+    #[serde(default)]
+    pub sbom: Option<SbomConfig>,
 }
 
-/// Targets configuration for multiplatform builds
+/// Annotation processing with Kotlin Symbol Processing (KSP)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct KspConfig {
+    /// `group:artifact:version` coordinates of the processors to run.
+    #[serde(default)]
+    pub processors: Vec<String>,
+    /// Options passed to every processor, which each reads the ones it knows.
+    #[serde(default)]
+    pub args: Option<HashMap<String, String>>,
+    /// Whether only the sources affected by a change are reprocessed. Defaults to `false`.
+    #[serde(default)]
+    pub incremental: Option<bool>,
+}
+
+/// Annotation processing with KAPT, which runs Java annotation processors over Kotlin sources
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct KaptConfig {
+    /// `group:artifact:version` coordinates of the processors to run.
+    #[serde(default)]
+    pub processors: Vec<String>,
+    /// Options passed to every processor, which each reads the ones it knows.
+    #[serde(default)]
+    pub arguments: Option<HashMap<String, String>>,
+    /// Whether types that can't be resolved in the generated stubs keep their names instead of
+    /// becoming `error.NonExistentClass`, which Dagger needs. Defaults to `false`.
+    #[serde(default, rename = "correct-error-types")]
+    pub correct_error_types: Option<bool>,
+}
+
+/// API documentation generated from KDoc comments by Dokka
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DokkaConfig {
+    pub enabled: bool,
+    /// The kind of documentation to write. Defaults to `html`.
+    #[serde(default, rename = "output-format")]
+    pub output_format: Option<DokkaFormat>,
+    /// Where the documentation is written, relative to the project. Defaults to `build/docs`.
+    #[serde(default, rename = "output-dir")]
+    pub output_dir: Option<String>,
+}
+
+/// A documentation format Dokka can write
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DokkaFormat {
+    /// A browsable website.
+    #[default]
+    Html,
+    /// GitHub Flavored Markdown.
+    Gfm,
+    /// Javadoc-style HTML, showing Kotlin declarations as Java sees them.
+    Javadoc,
+}
+
+/// A single self-contained JAR holding the project's classes and those of every dependency
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FatJarConfig {
+    pub enabled: bool,
+    /// The class written to the manifest's `Main-Class`, such as `com.example.AppKt`. Defaults
+    /// to `build.main-class`.
+    #[serde(default, rename = "main-class")]
+    pub main_class: Option<String>,
+    /// The file name of the JAR in `build/libs`. Defaults to `<name>-<version>-all.jar`.
+    #[serde(default, rename = "output-name")]
+    pub output_name: Option<String>,
+    /// Globs of entries to leave out, such as `META-INF/*.SF` for signature files. `*` stops at
+    /// a `/` and `**` doesn't.
+    #[serde(default, rename = "exclude-patterns")]
+    pub exclude_patterns: Option<Vec<String>>,
+}
+
+/// A machine-readable inventory of the artifacts the project is built from
 #[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SbomConfig {
+    pub enabled: bool,
+    /// The standard the SBOM is written in. Defaults to `cyclonedx-json`.
+    #[serde(default)]
+    pub format: Option<SbomFormat>,
+    /// Where the SBOM is written, relative to the project. Defaults to `build/sbom.cdx.json`,
+    /// `build/sbom.cdx.xml` or `build/sbom.spdx.json` depending on the format.
+    #[serde(default)]
+    pub output: Option<String>,
+}
+
+/// A format an SBOM can be written in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SbomFormat {
+    /// CycloneDX 1.5 as JSON, the format most Java tooling reads.
+    #[default]
+    #[serde(rename = "cyclonedx-json")]
+    CycloneDxJson,
+    /// CycloneDX 1.5 as XML.
+    #[serde(rename = "cyclonedx-xml")]
+    CycloneDxXml,
+    /// SPDX 2.3 as JSON.
+    #[serde(rename = "spdx-json")]
+    SpdxJson,
+}
+
+/// How strictly Kotlin's explicit API mode is enforced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplicitApiMode {
+    /// Missing visibility modifiers and return types are errors.
+    Strict,
+    /// Missing visibility modifiers and return types are warnings.
+    Warning,
+    /// Explicit API mode is off.
+    Disabled,
+}
+
+impl ExplicitApiMode {
+    /// Returns the value `kotlinc` expects for `-Xexplicit-api`.
+    pub fn flag_value(&self) -> &'static str {
+        match self {
+            ExplicitApiMode::Strict => "strict",
+            ExplicitApiMode::Warning => "warning",
+            ExplicitApiMode::Disabled => "disable",
+        }
+    }
+}
+
+impl std::fmt::Display for ExplicitApiMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExplicitApiMode::Strict => write!(f, "strict"),
+            ExplicitApiMode::Warning => write!(f, "warning"),
+            ExplicitApiMode::Disabled => write!(f, "disabled"),
+        }
+    }
+}
+
+/// How the resolver picks between conflicting versions of the same artifact.
+///
+/// Versions declared directly in `fyg.toml` always win over transitive ones.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// Use the highest version that is asked for.
+    #[default]
+    Newest,
+    /// Use the lowest version that is asked for.
+    Oldest,
+    /// Refuse to resolve until the conflict is settled in `fyg.toml`.
+    Fail,
+}
+
+impl std::fmt::Display for ConflictStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConflictStrategy::Newest => write!(f, "newest"),
+            ConflictStrategy::Oldest => write!(f, "oldest"),
+            ConflictStrategy::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+/// The GNU triplet of 32-bit ARM Linux with hardware floats, which names the cross-compilers
+/// and linkers that build for `linux-arm32`, such as `arm-linux-gnueabihf-gcc`.
+pub const LINUX_ARM32_TRIPLET: &str = "arm-linux-gnueabihf";
+
+/// The key of every target `[targets]` can enable, as `fyg target list` shows them.
+pub const KNOWN_TARGETS: [&str; 21] = [
+    "jvm",
+    "android",
+    "js-browser",
+    "js-node",
+    "wasm-js",
+    "wasm-wasi",
+    "ios-arm64",
+    "ios-x64",
+    "ios-simulator-arm64",
+    "watchos-arm64",
+    "watchos-arm32",
+    "watchos-simulator-arm64",
+    "tvos-arm64",
+    "tvos-simulator-arm64",
+    "linux-x64",
+    "linux-arm64",
+    "linux-arm32",
+    "macos-arm64",
+    "macos-x64",
+    "windows-x64",
+    "windows-arm64",
+];
+
+/// Targets configuration for multiplatform builds
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct TargetsConfig {
     #[serde(default)]
     pub jvm: Option<JvmTarget>,
@@ -36,35 +355,353 @@ pub struct TargetsConfig {
     pub ios_x64: Option<TargetEnabled>,
     #[serde(default, rename = "ios-simulator-arm64")]
     pub ios_simulator_arm64: Option<TargetEnabled>,
+    #[serde(default, rename = "watchos-arm64")]
+    pub watchos_arm64: Option<TargetEnabled>,
+    #[serde(default, rename = "watchos-arm32")]
+    pub watchos_arm32: Option<TargetEnabled>,
+    #[serde(default, rename = "watchos-simulator-arm64")]
+    pub watchos_simulator_arm64: Option<TargetEnabled>,
+    #[serde(default, rename = "tvos-arm64")]
+    pub tvos_arm64: Option<TargetEnabled>,
+    #[serde(default, rename = "tvos-simulator-arm64")]
+    pub tvos_simulator_arm64: Option<TargetEnabled>,
     #[serde(default, rename = "linux-x64")]
     pub linux_x64: Option<TargetEnabled>,
+    #[serde(default, rename = "linux-arm64")]
+    pub linux_arm64: Option<TargetEnabled>,
+    /// 32-bit ARM Linux with hardware floats, such as on a Raspberry Pi, built as
+    /// Kotlin/Native's `linuxArm32Hfp`. Other hosts need an ARM cross-linker to build it.
+    #[serde(default, rename = "linux-arm32")]
+    pub linux_arm32: Option<TargetEnabled>,
     #[serde(default, rename = "macos-arm64")]
     pub macos_arm64: Option<TargetEnabled>,
+    #[serde(default, rename = "macos-x64")]
+    pub macos_x64: Option<TargetEnabled>,
     #[serde(default, rename = "windows-x64")]
-    pub windows_x64: Option<TargetEnabled>,
+    pub windows_x64: Option<MinGwTarget>,
+    /// Windows on ARM64, such as Surface devices, built as Kotlin/Native's `mingwArm64`.
+    #[serde(default, rename = "windows-arm64")]
+    pub windows_arm64: Option<MinGwTarget>,
+    #[serde(default)]
+    pub android: Option<AndroidTarget>,
+    #[serde(default, rename = "js-browser")]
+    pub js_browser: Option<JsTarget>,
+    #[serde(default, rename = "js-node")]
+    pub js_node: Option<JsTarget>,
+    /// Kotlin/Wasm for JavaScript hosts such as browsers. Needs Kotlin 1.9.0 or newer.
+    #[serde(default, rename = "wasm-js")]
+    pub wasm_js: Option<TargetEnabled>,
+    /// Kotlin/Wasm for WASI runtimes. Needs Kotlin 1.9.0 or newer.
+    #[serde(default, rename = "wasm-wasi")]
+    pub wasm_wasi: Option<TargetEnabled>,
     #[serde(default)]
     pub native: Option<NativeConfig>,
 }
 
+impl TargetsConfig {
+    /// Returns the key of every enabled target, such as `jvm` or `ios-arm64`.
+    ///
+    /// Projects without a `[targets.jvm]` section are built for the JVM, so `jvm` counts as
+    /// enabled unless the section turns it off.
+    pub fn enabled_targets(&self) -> Vec<&'static str> {
+        let mut targets = Vec::new();
+        if self.jvm.as_ref().is_none_or(|t| t.enabled) {
+            targets.push("jvm");
+        }
+        let enabled = |target: &Option<TargetEnabled>| target.as_ref().is_some_and(|t| t.enabled);
+        let native = [
+            ("ios-arm64", enabled(&self.ios_arm64)),
+            ("ios-x64", enabled(&self.ios_x64)),
+            ("ios-simulator-arm64", enabled(&self.ios_simulator_arm64)),
+            ("watchos-arm64", enabled(&self.watchos_arm64)),
+            ("watchos-arm32", enabled(&self.watchos_arm32)),
+            ("watchos-simulator-arm64", enabled(&self.watchos_simulator_arm64)),
+            ("tvos-arm64", enabled(&self.tvos_arm64)),
+            ("tvos-simulator-arm64", enabled(&self.tvos_simulator_arm64)),
+            ("linux-x64", enabled(&self.linux_x64)),
+            ("linux-arm64", enabled(&self.linux_arm64)),
+            ("linux-arm32", enabled(&self.linux_arm32)),
+            ("macos-arm64", enabled(&self.macos_arm64)),
+            ("macos-x64", enabled(&self.macos_x64)),
+            ("windows-x64", self.windows_x64.as_ref().is_some_and(|t| t.enabled)),
+            ("windows-arm64", self.windows_arm64.as_ref().is_some_and(|t| t.enabled)),
+            ("wasm-js", enabled(&self.wasm_js)),
+            ("wasm-wasi", enabled(&self.wasm_wasi)),
+        ];
+        targets.extend(native.into_iter().filter(|(_, enabled)| *enabled).map(|(key, _)| key));
+        if self.android.as_ref().is_some_and(|t| t.enabled) {
+            targets.push("android");
+        }
+        for (key, target) in [("js-browser", &self.js_browser), ("js-node", &self.js_node)] {
+            if target.as_ref().is_some_and(|t| t.enabled) {
+                targets.push(key);
+            }
+        }
+        targets
+    }
+
+    /// Returns the key and Kotlin/Native target name of every enabled native target, such as
+    /// `("linux-x64", "linux_x64")`.
+    pub fn native_targets(&self) -> Vec<(&'static str, &'static str)> {
+        let enabled = |target: &Option<TargetEnabled>| target.as_ref().is_some_and(|t| t.enabled);
+        [
+            ("ios-arm64", "ios_arm64", enabled(&self.ios_arm64)),
+            ("ios-x64", "ios_x64", enabled(&self.ios_x64)),
+            ("ios-simulator-arm64", "ios_simulator_arm64", enabled(&self.ios_simulator_arm64)),
+            ("watchos-arm64", "watchos_arm64", enabled(&self.watchos_arm64)),
+            ("watchos-arm32", "watchos_arm32", enabled(&self.watchos_arm32)),
+            (
+                "watchos-simulator-arm64",
+                "watchos_simulator_arm64",
+                enabled(&self.watchos_simulator_arm64),
+            ),
+            ("tvos-arm64", "tvos_arm64", enabled(&self.tvos_arm64)),
+            ("tvos-simulator-arm64", "tvos_simulator_arm64", enabled(&self.tvos_simulator_arm64)),
+            ("linux-x64", "linux_x64", enabled(&self.linux_x64)),
+            ("linux-arm64", "linux_arm64", enabled(&self.linux_arm64)),
+            ("linux-arm32", "linux_arm32_hfp", enabled(&self.linux_arm32)),
+            ("macos-arm64", "macos_arm64", enabled(&self.macos_arm64)),
+            ("macos-x64", "macos_x64", enabled(&self.macos_x64)),
+            ("windows-x64", "mingw_x64", self.windows_x64.as_ref().is_some_and(|t| t.enabled)),
+            (
+                "windows-arm64",
+                "mingw_arm64",
+                self.windows_arm64.as_ref().is_some_and(|t| t.enabled),
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, _, enabled)| *enabled)
+        .map(|(key, konan, _)| (key, konan))
+        .collect()
+    }
+
+    /// The linker option for `linux-arm32` that names an ARM cross-linker, such as
+    /// `-fuse-ld=/usr/bin/arm-linux-gnueabihf-ld`, from `targets.native.binary`.
+    pub fn linux_arm32_linker(&self) -> Option<String> {
+        let binary = self.native.as_ref()?.binary.as_ref()?;
+        binary
+            .args_for("linux-arm32")
+            .linker_opts
+            .into_iter()
+            .flatten()
+            .find(|option| option.contains(LINUX_ARM32_TRIPLET))
+    }
+
+    /// Enables or disables the target with the key `key`, such as `ios-arm64`, returning
+    /// whether it was enabled before, as [`TargetsConfig::enabled_targets`] says. A disabled
+    /// target keeps its other settings, and one without a section only gets one when that
+    /// changes whether it is enabled, such as `jvm` being turned off.
+    pub fn set_enabled(&mut self, key: &str, enabled: bool) -> anyhow::Result<bool> {
+        let Some(key) = KNOWN_TARGETS.into_iter().find(|known| *known == key) else {
+            anyhow::bail!("unknown target '{}', run 'fyg target list' to see them all", key);
+        };
+        let was_enabled = self.enabled_targets().contains(&key);
+        if !enabled && !was_enabled {
+            return Ok(false);
+        }
+
+        let flag = match key {
+            "jvm" => &mut self.jvm.get_or_insert_default().enabled,
+            "android" => &mut self.android.get_or_insert_default().enabled,
+            "js-browser" => &mut self.js_browser.get_or_insert_default().enabled,
+            "js-node" => &mut self.js_node.get_or_insert_default().enabled,
+            "windows-x64" => &mut self.windows_x64.get_or_insert_default().enabled,
+            "windows-arm64" => &mut self.windows_arm64.get_or_insert_default().enabled,
+            _ => {
+                let target = match key {
+                    "wasm-js" => &mut self.wasm_js,
+                    "wasm-wasi" => &mut self.wasm_wasi,
+                    "ios-arm64" => &mut self.ios_arm64,
+                    "ios-x64" => &mut self.ios_x64,
+                    "ios-simulator-arm64" => &mut self.ios_simulator_arm64,
+                    "watchos-arm64" => &mut self.watchos_arm64,
+                    "watchos-arm32" => &mut self.watchos_arm32,
+                    "watchos-simulator-arm64" => &mut self.watchos_simulator_arm64,
+                    "tvos-arm64" => &mut self.tvos_arm64,
+                    "tvos-simulator-arm64" => &mut self.tvos_simulator_arm64,
+                    "linux-x64" => &mut self.linux_x64,
+                    "linux-arm64" => &mut self.linux_arm64,
+                    "linux-arm32" => &mut self.linux_arm32,
+                    "macos-arm64" => &mut self.macos_arm64,
+                    "macos-x64" => &mut self.macos_x64,
+                    _ => unreachable!("every known target is matched"),
+                };
+                &mut target.get_or_insert_default().enabled
+            }
+        };
+        *flag = enabled;
+        Ok(was_enabled)
+    }
+
+    /// The Windows target with the key `key`, such as `windows-x64`, if it is enabled.
+    pub fn mingw_target(&self, key: &str) -> Option<&MinGwTarget> {
+        let target = match key {
+            "windows-x64" => &self.windows_x64,
+            "windows-arm64" => &self.windows_arm64,
+            _ => return None,
+        };
+        target.as_ref().filter(|t| t.enabled)
+    }
+
+    /// Returns the key and display name of every enabled Apple target, which can only be built
+    /// on macOS.
+    pub fn apple_targets(&self) -> Vec<(&'static str, &'static str)> {
+        [
+            ("ios-arm64", "iOS ARM64", &self.ios_arm64),
+            ("ios-x64", "iOS x64", &self.ios_x64),
+            ("ios-simulator-arm64", "iOS Simulator ARM64", &self.ios_simulator_arm64),
+            ("macos-arm64", "macOS ARM64", &self.macos_arm64),
+            ("macos-x64", "macOS x64", &self.macos_x64),
+            ("watchos-arm64", "watchOS ARM64", &self.watchos_arm64),
+            ("watchos-arm32", "watchOS ARM32", &self.watchos_arm32),
+            (
+                "watchos-simulator-arm64",
+                "watchOS Simulator ARM64",
+                &self.watchos_simulator_arm64,
+            ),
+            ("tvos-arm64", "tvOS ARM64", &self.tvos_arm64),
+            ("tvos-simulator-arm64", "tvOS Simulator ARM64", &self.tvos_simulator_arm64),
+        ]
+        .into_iter()
+        .filter(|(_, _, target)| target.as_ref().is_some_and(|t| t.enabled))
+        .map(|(key, name, _)| (key, name))
+        .collect()
+    }
+}
+
 /// JVM target configuration
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct JvmTarget {
     pub enabled: bool,
     #[serde(default)]
     pub target: Option<String>,
+    /// Arguments passed to `java` when the project is run or tested, such as `-Xmx512m` or
+    /// `-Dkey=value`.
+    #[serde(default, rename = "jvm-args")]
+    pub jvm_args: Option<Vec<String>>,
+}
+
+/// Android target configuration
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AndroidTarget {
+    pub enabled: bool,
+    /// The lowest API level the app can be installed on.
+    #[serde(default, rename = "min-sdk")]
+    pub min_sdk: Option<u32>,
+    /// The API level the app is compiled against.
+    #[serde(default, rename = "compile-sdk")]
+    pub compile_sdk: Option<u32>,
+    /// The API level the app is tested against.
+    #[serde(default, rename = "target-sdk")]
+    pub target_sdk: Option<u32>,
+    /// The package used for the generated `R` and `BuildConfig` classes.
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+/// Kotlin/JS target configuration, for the browser or Node.js
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct JsTarget {
+    pub enabled: bool,
+    /// The module system of the generated JavaScript. Defaults to `umd` for the browser and
+    /// `commonjs` for Node.js.
+    #[serde(default, rename = "module-kind")]
+    pub module_kind: Option<JsModuleKind>,
+    /// Whether to generate source maps. Defaults to `true` except in release builds.
+    #[serde(default, rename = "source-maps")]
+    pub source_maps: Option<bool>,
+}
+
+/// The module system used by generated JavaScript
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsModuleKind {
+    CommonJs,
+    Es,
+    Umd,
+    Plain,
+    Amd,
+}
+
+impl std::fmt::Display for JsModuleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsModuleKind::CommonJs => write!(f, "commonjs"),
+            JsModuleKind::Es => write!(f, "es"),
+            JsModuleKind::Umd => write!(f, "umd"),
+            JsModuleKind::Plain => write!(f, "plain"),
+            JsModuleKind::Amd => write!(f, "amd"),
+        }
+    }
 }
 
 /// Simple enabled flag for targets
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct TargetEnabled {
     pub enabled: bool,
 }
 
+/// A Windows target, which Kotlin/Native builds with MinGW.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MinGwTarget {
+    pub enabled: bool,
+    /// A Windows resource file compiled with `windres` and linked into the binary, such as
+    /// `src/windows/app.rc` for its icon and version information, relative to the project.
+    #[serde(default, rename = "resource-file")]
+    pub resource_file: Option<String>,
+    /// An application manifest embedded in the binary, such as `src/windows/app.manifest`,
+    /// relative to the project.
+    #[serde(default)]
+    pub manifest: Option<String>,
+}
+
+impl MinGwTarget {
+    /// The resource script `windres` compiles for the target, which includes the resource file
+    /// and embeds the manifest as the binary's `RT_MANIFEST` resource, with their paths resolved
+    /// against `project_dir`. Returns `None` if neither is set.
+    pub fn resource_script(&self, project_dir: &Path) -> Option<String> {
+        // MinGW's preprocessor and windres both take forward slashes on Windows, where a
+        // backslash would start an escape in a resource string
+        let path = |file: &str| project_dir.join(file).display().to_string().replace('\\', "/");
+        let mut script = String::new();
+        if let Some(file) = &self.resource_file {
+            script.push_str(&format!("#include \"{}\"\n", path(file)));
+        }
+        if let Some(file) = &self.manifest {
+            // CREATEPROCESS_MANIFEST_RESOURCE_ID RT_MANIFEST
+            script.push_str(&format!("1 24 \"{}\"\n", path(file)));
+        }
+        (!script.is_empty()).then_some(script)
+    }
+}
+
 /// Native binary configuration
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NativeConfig {
     #[serde(default)]
     pub binary: Option<NativeBinaryConfig>,
+    /// C libraries to generate Kotlin bindings for with `cinterop` before compiling.
+    #[serde(default)]
+    pub cinterop: Option<Vec<CinteropConfig>>,
+}
+
+/// Kotlin bindings to a C library, generated by `cinterop` from a `.def` file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CinteropConfig {
+    /// The name of the bindings, which is also the name of the `.klib` they are written to.
+    pub name: String,
+    /// The `.def` file listing the headers to bind, relative to the project.
+    #[serde(rename = "def-file")]
+    pub def_file: String,
+    /// The package the bindings are declared in. Defaults to the one in the `.def` file.
+    #[serde(default)]
+    pub package: Option<String>,
+    /// Options passed to the C compiler that reads the headers, such as `-I/usr/include/curl`.
+    #[serde(default, rename = "compiler-opts")]
+    pub compiler_opts: Option<Vec<String>>,
+    /// Options passed to the linker of every binary using the bindings, such as `-lcurl`.
+    #[serde(default, rename = "linker-opts")]
+    pub linker_opts: Option<Vec<String>>,
 }
 
 /// Native binary type and base name
@@ -74,10 +711,66 @@ pub struct NativeBinaryConfig {
     pub binary_type: FygBinaryType,
     #[serde(default, rename = "base-name")]
     pub base_name: Option<String>,
+    /// Dependencies whose APIs a framework exports to Swift and Objective-C, as the
+    /// `group:artifact` of an entry in `[dependencies.common]` or a `group:artifact:version`.
+    #[serde(default, rename = "export-libraries")]
+    pub export_libraries: Option<Vec<String>>,
+    /// Whether a framework embeds LLVM bitcode, which older App Store uploads required.
+    #[serde(default, rename = "embed-bitcode")]
+    pub embed_bitcode: Option<bool>,
+    /// Options passed to the linker, such as `-lcurl`.
+    #[serde(default, rename = "linker-opts")]
+    pub linker_opts: Option<Vec<String>>,
+    /// Options passed to the C compiler `kotlinc-native` runs.
+    #[serde(default, rename = "compiler-opts")]
+    pub compiler_opts: Option<Vec<String>>,
+    /// Options for a single target, keyed by its name such as `linux-x64`, which are passed
+    /// after `linker-opts` and `compiler-opts`.
+    #[serde(default, rename = "native-args")]
+    pub native_args: Option<HashMap<String, NativeArgs>>,
+}
+
+/// Linker and compiler options for a single native target.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct NativeArgs {
+    #[serde(default, rename = "linker-opts")]
+    pub linker_opts: Option<Vec<String>>,
+    #[serde(default, rename = "compiler-opts")]
+    pub compiler_opts: Option<Vec<String>>,
+}
+
+impl NativeBinaryConfig {
+    /// Whether the binary is a framework, on its own or bundled into an XCFramework.
+    pub fn is_framework(&self) -> bool {
+        matches!(
+            self.binary_type,
+            FygBinaryType::Framework | FygBinaryType::XCFramework
+        )
+    }
+
+    /// The linker and compiler options for the target named `target`, such as `linux-x64`,
+    /// with those in `native-args` after the ones shared by every target.
+    pub fn args_for(&self, target: &str) -> NativeArgs {
+        let overrides = self.native_args.as_ref().and_then(|args| args.get(target));
+        let merge = |shared: &Option<Vec<String>>, specific: Option<&Vec<String>>| {
+            let merged: Vec<String> = shared.iter().chain(specific).flatten().cloned().collect();
+            (!merged.is_empty()).then_some(merged)
+        };
+        NativeArgs {
+            linker_opts: merge(
+                &self.linker_opts,
+                overrides.and_then(|o| o.linker_opts.as_ref()),
+            ),
+            compiler_opts: merge(
+                &self.compiler_opts,
+                overrides.and_then(|o| o.compiler_opts.as_ref()),
+            ),
+        }
+    }
 }
 
 /// Dependencies configuration supporting common, jvm, and test scopes
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct DependenciesConfig {
     #[serde(default)]
     pub common: Option<HashMap<String, DependencyValue>>,
@@ -85,6 +778,118 @@ pub struct DependenciesConfig {
     pub jvm: Option<HashMap<String, DependencyValue>>,
     #[serde(default)]
     pub test: Option<HashMap<String, DependencyValue>>,
+    /// Dependencies of every native target, which JVM builds leave out.
+    #[serde(default)]
+    pub native: Option<HashMap<String, DependencyValue>>,
+    /// Dependencies of the iOS targets only, on top of those in `native`.
+    #[serde(default)]
+    pub ios: Option<HashMap<String, DependencyValue>>,
+    /// Dependencies of the Android target only.
+    #[serde(default)]
+    pub android: Option<HashMap<String, DependencyValue>>,
+    /// `group:artifact:version` coordinates of BOMs whose managed versions are used for
+    /// dependencies declared without one. Later BOMs override earlier ones.
+    #[serde(default)]
+    pub bom: Option<Vec<String>>,
+}
+
+impl DependenciesConfig {
+    /// The names of every dependency scope.
+    pub const SCOPES: [&'static str; 6] = ["common", "jvm", "test", "native", "ios", "android"];
+
+    /// Returns every scope paired with its name, in the order of [`Self::SCOPES`].
+    pub fn scopes(&self) -> [(&'static str, Option<&HashMap<String, DependencyValue>>); 6] {
+        [
+            ("common", self.common.as_ref()),
+            ("jvm", self.jvm.as_ref()),
+            ("test", self.test.as_ref()),
+            ("native", self.native.as_ref()),
+            ("ios", self.ios.as_ref()),
+            ("android", self.android.as_ref()),
+        ]
+    }
+
+    /// Returns the scopes whose dependencies a build using `scope` sees, `scope` itself last.
+    pub fn visible_from(scope: &str) -> &'static [&'static str] {
+        match scope {
+            "jvm" => &["common", "jvm"],
+            "test" => &["common", "jvm", "test"],
+            "native" => &["common", "native"],
+            "ios" => &["common", "native", "ios"],
+            "android" => &["common", "android"],
+            _ => &["common"],
+        }
+    }
+
+    /// Returns the scope with the given name, or `None` if there is no such scope.
+    pub fn scope_mut(
+        &mut self,
+        scope: &str,
+    ) -> Option<&mut Option<HashMap<String, DependencyValue>>> {
+        match scope {
+            "common" => Some(&mut self.common),
+            "jvm" => Some(&mut self.jvm),
+            "test" => Some(&mut self.test),
+            "native" => Some(&mut self.native),
+            "ios" => Some(&mut self.ios),
+            "android" => Some(&mut self.android),
+            _ => None,
+        }
+    }
+
+    /// Returns the Maven coordinate of every dependency in `scope`, sorted by key.
+    ///
+    /// Local `path` and `workspace` dependencies, which have none, and malformed entries, which
+    /// [`crate::toml::FygToml::validate`] reports, are skipped.
+    pub fn iter_coordinates(&self, scope: &str) -> impl Iterator<Item = Coordinate> {
+        let entries = self
+            .scopes()
+            .into_iter()
+            .find(|(name, _)| *name == scope)
+            .and_then(|(_, entries)| entries);
+        let mut entries: Vec<(&String, &DependencyValue)> = entries.into_iter().flatten().collect();
+        entries.sort_by_key(|(key, _)| key.as_str());
+        entries
+            .into_iter()
+            .filter_map(|(key, value)| value.coordinate(key)?.ok())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    /// Returns the name of every scope that declares `key`.
+    pub fn find(&self, key: &str) -> Vec<&'static str> {
+        self.scopes()
+            .into_iter()
+            .filter(|(_, entries)| entries.is_some_and(|e| e.contains_key(key)))
+            .map(|(scope, _)| scope)
+            .collect()
+    }
+
+    /// Adds a dependency to the given scope, replacing any existing entry with the same key.
+    pub fn insert(
+        &mut self,
+        scope: &str,
+        key: String,
+        value: DependencyValue,
+    ) -> anyhow::Result<()> {
+        let entries = self
+            .scope_mut(scope)
+            .ok_or_else(|| anyhow::anyhow!("unknown dependency scope '{}'", scope))?;
+        entries.get_or_insert_with(HashMap::new).insert(key, value);
+        Ok(())
+    }
+
+    /// Removes a dependency from the given scope, returning it if it was present.
+    ///
+    /// A scope left with no dependencies is removed entirely.
+    pub fn remove(&mut self, scope: &str, key: &str) -> Option<DependencyValue> {
+        let entries = self.scope_mut(scope)?;
+        let removed = entries.as_mut()?.remove(key);
+        if entries.as_ref().is_some_and(|e| e.is_empty()) {
+            *entries = None;
+        }
+        removed
+    }
 }
 
 /// A dependency can be a version string or a complex object
@@ -95,6 +900,67 @@ pub enum DependencyValue {
     Complex(DependencyConfig),
 }
 
+impl DependencyValue {
+    /// Returns the Maven coordinate of the dependency declared as `key = <self>`, or `None` for a
+    /// local `path` or `workspace` dependency.
+    ///
+    /// The key is usually `group:artifact` with the version as the value, but a value can also
+    /// be a whole `group:artifact:version` coordinate, which makes the key just a name.
+    pub fn coordinate(&self, key: &str) -> Option<anyhow::Result<Coordinate>> {
+        let (version, classifier) = match self {
+            DependencyValue::Version(version) => (Some(version), None),
+            DependencyValue::Complex(dep) if dep.path.is_some() => return None,
+            DependencyValue::Complex(dep) if dep.workspace == Some(true) => return None,
+            DependencyValue::Complex(dep) => (dep.version.as_ref(), dep.classifier.as_deref()),
+        };
+
+        let coordinate = match version {
+            Some(version) if version.contains(':') => version.clone(),
+            version => format!("{}:{}", key, version.map_or("", |v| v.as_str())),
+        };
+        Some(match crate::deps::parse_coordinate(&coordinate) {
+            Ok((group, artifact, Some(version))) => {
+                Ok(Coordinate::new(&group, &artifact, &version).with_classifier(classifier))
+            }
+            _ => Err(anyhow::anyhow!(
+                "'{}' is not a group:artifact:version coordinate",
+                coordinate
+            )),
+        })
+    }
+}
+
+impl DependencyValue {
+    /// Returns the Maven version range of a dependency declared with one, such as
+    /// `"[1.0,2.0)"`, or `None` if it asks for a single version.
+    pub fn version_range(&self) -> Option<anyhow::Result<VersionRange>> {
+        let version = match self {
+            DependencyValue::Version(version) => version,
+            DependencyValue::Complex(dep) => dep.version.as_ref()?,
+        };
+        // a whole `group:artifact:version` coordinate carries its version last
+        let version = version.rsplit(':').next().unwrap_or(version);
+        maven::is_range(version).then(|| version.parse())
+    }
+
+    /// Returns the `group` and `artifact` of a Maven dependency declared without a version,
+    /// which a BOM in `dependencies.bom` is expected to give.
+    pub fn unversioned(&self, key: &str) -> Option<(String, String)> {
+        match self {
+            DependencyValue::Complex(DependencyConfig {
+                path: None,
+                workspace: None | Some(false),
+                version: None,
+                ..
+            }) => match crate::deps::parse_coordinate(key) {
+                Ok((group, artifact, None)) => Some((group, artifact)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
 /// Complex dependency configuration
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DependencyConfig {
@@ -104,6 +970,115 @@ pub struct DependencyConfig {
     pub workspace: Option<bool>,
     #[serde(default)]
     pub version: Option<String>,
+    /// Which of the files published for the artifact to use, such as `natives-linux`.
+    #[serde(default)]
+    pub classifier: Option<String>,
+    /// Transitive dependencies to leave out, along with everything only they depend on.
+    #[serde(default)]
+    pub exclude: Option<Vec<ExcludeRule>>,
+}
+
+/// A transitive dependency to leave out: every artifact of `group`, or only `group:artifact`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExcludeRule {
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub artifact: Option<String>,
+}
+
+impl ExcludeRule {
+    /// Whether the rule excludes `group:artifact`.
+    pub fn matches(&self, group: &str, artifact: &str) -> bool {
+        self.group.as_deref().is_none_or(|g| g == group)
+            && self.artifact.as_deref().is_none_or(|a| a == artifact)
+    }
+}
+
+/// Settings from a `[profile.<name>]` section that apply on top of `[build]` when building in
+/// that mode. `fyg build` uses the `debug` profile and `fyg build --release` uses `release`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildProfile {
+    /// What the compiler optimizes the output for.
+    #[serde(default)]
+    pub optimization: Option<OptimizationLevel>,
+    /// Whether the output keeps debugging information, such as JavaScript source maps.
+    #[serde(default, rename = "debug-symbols")]
+    pub debug_symbols: Option<bool>,
+    /// Whether debugging information is removed from the output, overriding `debug-symbols`.
+    #[serde(default)]
+    pub strip: Option<bool>,
+    /// Whether `kotlinc` leaves out the null checks it inserts where Kotlin and Java code call
+    /// each other, which makes the bytecode smaller and faster but lets a `null` from Java
+    /// travel further before failing. Off unless set, whatever the `optimization`.
+    #[serde(default, rename = "no-null-assertions")]
+    pub no_null_assertions: Option<bool>,
+    /// Extra arguments passed to `kotlinc` after those in `build.free-compiler-args`.
+    #[serde(default, rename = "free-compiler-args")]
+    pub free_compiler_args: Option<Vec<String>>,
+}
+
+impl BuildProfile {
+    /// The built-in `debug` profile: unoptimized, with debugging information.
+    pub fn debug() -> Self {
+        Self {
+            optimization: Some(OptimizationLevel::None),
+            debug_symbols: Some(true),
+            strip: Some(false),
+            no_null_assertions: None,
+            free_compiler_args: None,
+        }
+    }
+
+    /// The built-in `release` profile: optimized for speed, without debugging information.
+    pub fn release() -> Self {
+        Self {
+            optimization: Some(OptimizationLevel::Speed),
+            debug_symbols: Some(false),
+            strip: Some(false),
+            no_null_assertions: None,
+            free_compiler_args: None,
+        }
+    }
+
+    /// Returns this profile with every setting that `overrides` sets replaced.
+    pub fn merge(self, overrides: &BuildProfile) -> Self {
+        Self {
+            optimization: overrides.optimization.or(self.optimization),
+            debug_symbols: overrides.debug_symbols.or(self.debug_symbols),
+            strip: overrides.strip.or(self.strip),
+            no_null_assertions: overrides.no_null_assertions.or(self.no_null_assertions),
+            free_compiler_args: overrides.free_compiler_args.clone().or(self.free_compiler_args),
+        }
+    }
+
+    /// Whether the output should keep debugging information once `strip` is taken into account.
+    pub fn keeps_debug_symbols(&self) -> bool {
+        self.debug_symbols.unwrap_or(true) && !self.strip.unwrap_or(false)
+    }
+}
+
+/// What a [`BuildProfile`] optimizes the output for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptimizationLevel {
+    /// No optimizations, which keeps builds fast and the output easy to debug.
+    #[default]
+    None,
+    /// Optimize for runtime speed.
+    Speed,
+    /// Optimize for a small output.
+    Size,
+}
+
+impl std::fmt::Display for OptimizationLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptimizationLevel::None => write!(f, "none"),
+            OptimizationLevel::Speed => write!(f, "speed"),
+            OptimizationLevel::Size => write!(f, "size"),
+        }
+    }
 }
 
 /// Test configuration
@@ -111,6 +1086,226 @@ pub struct DependencyConfig {
 pub struct TestConfig {
     #[serde(default)]
     pub framework: Option<String>,
+    /// Runs tests concurrently when enabled.
+    #[serde(default)]
+    pub parallel: Option<bool>,
+    /// The maximum heap size of the test JVM, such as `512m` or `2g`.
+    #[serde(default, rename = "max-heap")]
+    pub max_heap: Option<String>,
+    /// Arguments passed to the test JVM after `targets.jvm.jvm-args`, so they win where both
+    /// set the same option.
+    #[serde(default, rename = "jvm-args")]
+    pub jvm_args: Option<Vec<String>>,
+    /// System properties set in the test JVM with `-D<key>=<value>`. Values can use
+    /// `${env.VAR}` to keep secrets out of `fyg.toml`.
+    #[serde(default, rename = "system-properties")]
+    pub system_properties: Option<HashMap<String, String>>,
+    /// Environment variables set for the test JVM, on top of those fyg was started with.
+    #[serde(default)]
+    pub environment: Option<HashMap<String, String>>,
+    /// How many more times a failed test is run before it counts as failed. Defaults to `0`.
+    #[serde(default)]
+    pub retry: Option<u32>,
+    /// How long a single test may run before it fails.
+    #[serde(default, rename = "timeout-seconds")]
+    pub timeout_seconds: Option<u64>,
+    /// Whether JUnit XML reports are written for CI servers to read. Defaults to `false`.
+    #[serde(default, rename = "xml-report")]
+    pub xml_report: Option<bool>,
+    /// Where XML reports are written, relative to the project. Defaults to
+    /// `build/test-results`.
+    #[serde(default, rename = "xml-report-dir")]
+    pub xml_report_dir: Option<String>,
+    /// Measurement of how much of the project's code the tests run.
+    #[serde(default)]
+    pub coverage: Option<CoverageConfig>,
+    /// Slower tests kept apart from the unit tests, run with `fyg test --integration`.
+    #[serde(default, rename = "integration-tests")]
+    pub integration_tests: Option<IntegrationTestConfig>,
+}
+
+impl TestConfig {
+    /// The settings integration tests run with: these, with `integration-tests.system-properties`
+    /// set over `system-properties`.
+    pub fn for_integration_tests(&self) -> TestConfig {
+        let mut config = self.clone();
+        let integration = self.integration_tests.as_ref();
+        if let Some(properties) = integration.and_then(|i| i.system_properties.clone()) {
+            config
+                .system_properties
+                .get_or_insert_with(HashMap::new)
+                .extend(properties);
+        }
+        config
+    }
+}
+
+/// A separate set of tests, such as those needing external services
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IntegrationTestConfig {
+    pub enabled: bool,
+    /// Where the tests are, relative to the project. Defaults to `src/integration-test/kotlin`.
+    #[serde(default, rename = "source-dir")]
+    pub source_dir: Option<String>,
+    /// Whether the integration tests can use the unit tests' classes, such as shared fixtures,
+    /// which are then compiled first. Defaults to `false`.
+    #[serde(default, rename = "depends-on-test")]
+    pub depends_on_test: Option<bool>,
+    /// System properties set only for the integration tests, over `test.system-properties`.
+    #[serde(default, rename = "system-properties")]
+    pub system_properties: Option<HashMap<String, String>>,
+}
+
+/// Code coverage measured while the tests run
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CoverageConfig {
+    pub enabled: bool,
+    /// The agent that records coverage. Defaults to `kover`.
+    #[serde(default)]
+    pub engine: Option<CoverageEngine>,
+    /// The reports written to `build/reports/coverage`. Defaults to `html` and `xml`.
+    #[serde(default, rename = "report-formats")]
+    pub report_formats: Option<Vec<CoverageFormat>>,
+    /// The share of lines, from `0.0` to `1.0`, the tests must cover for `fyg test` to pass.
+    #[serde(default, rename = "minimum-coverage")]
+    pub minimum_coverage: Option<f32>,
+}
+
+/// A tool that records code coverage
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverageEngine {
+    /// JetBrains Kover, which understands Kotlin's inline functions and generated code.
+    #[default]
+    Kover,
+    /// JaCoCo.
+    Jacoco,
+}
+
+/// A format coverage reports can be written in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverageFormat {
+    /// A browsable website, in `build/reports/coverage/html`.
+    Html,
+    /// JaCoCo's XML format, which CI services read, in `build/reports/coverage/coverage.xml`.
+    Xml,
+    /// One line per class, in `build/reports/coverage/coverage.csv`. Only JaCoCo writes it.
+    Csv,
+}
+
+/// Shell commands run around builds and tests, from the `[hooks]` section
+///
+/// Each command is run with `sh -c`, or `cmd /C` on Windows, in the project's directory, and
+/// fails the build or test run if it exits with an error. `${...}` references are expanded in
+/// commands as anywhere else in `fyg.toml`, so a shell variable is written `$VAR` or `$${VAR}`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HooksConfig {
+    /// Run before anything is compiled, such as to generate sources.
+    #[serde(default, rename = "pre-build")]
+    pub pre_build: Option<Vec<String>>,
+    /// Run once everything is compiled, such as to post-process the output.
+    #[serde(default, rename = "post-build")]
+    pub post_build: Option<Vec<String>>,
+    /// Run after the build and before the tests.
+    #[serde(default, rename = "pre-test")]
+    pub pre_test: Option<Vec<String>>,
+    /// Run once the tests have run, whether or not they passed.
+    #[serde(default, rename = "post-test")]
+    pub post_test: Option<Vec<String>>,
+}
+
+/// How `fyg publish` packages the project and where it uploads it
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PublishConfig {
+    /// Names of `[repositories]` entries to upload to.
+    #[serde(default)]
+    pub repositories: Vec<String>,
+    /// Whether to publish a `-sources.jar` alongside the classes. Defaults to `true`.
+    #[serde(default, rename = "sources-jar")]
+    pub sources_jar: Option<bool>,
+    /// Whether to publish a `-javadoc.jar` alongside the classes. Defaults to `true`.
+    #[serde(default, rename = "javadoc-jar")]
+    pub javadoc_jar: Option<bool>,
+    /// Extra information for the published POM, which Maven Central requires.
+    #[serde(default, rename = "pom-metadata")]
+    pub pom_metadata: Option<PomMetadata>,
+    /// GPG signing of every published artifact, which Maven Central also requires.
+    #[serde(default)]
+    pub signing: Option<SigningConfig>,
+}
+
+/// The GPG key `fyg publish` signs artifacts with
+///
+/// Only the names of these are ever printed, so the key ID is redacted from `Debug` output.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SigningConfig {
+    /// The key to sign with, passed to `gpg --local-user`. Defaults to GPG's default key.
+    #[serde(default, rename = "key-id")]
+    pub key_id: Option<String>,
+    /// The environment variable holding the key's passphrase.
+    #[serde(default, rename = "password-env")]
+    pub password_env: Option<String>,
+    /// An exported secret key to sign with instead of one from the user's keyring.
+    #[serde(default, rename = "secret-key-file")]
+    pub secret_key_file: Option<PathBuf>,
+}
+
+impl std::fmt::Debug for SigningConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let redacted = |value: bool| if value { Some("<redacted>") } else { None };
+        f.debug_struct("SigningConfig")
+            .field("key_id", &redacted(self.key_id.is_some()))
+            .field("password_env", &self.password_env)
+            .field("secret_key_file", &redacted(self.secret_key_file.is_some()))
+            .finish()
+    }
+}
+
+/// Project information written to the published POM
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PomMetadata {
+    /// The project's home page.
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub licenses: Vec<PomLicense>,
+    #[serde(default)]
+    pub developers: Vec<PomDeveloper>,
+    #[serde(default)]
+    pub scm: Option<PomScm>,
+}
+
+/// A license the project is distributed under
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PomLicense {
+    /// The license's name, such as `The Apache License, Version 2.0`.
+    pub name: String,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+/// Someone who works on the project
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PomDeveloper {
+    #[serde(default)]
+    pub id: Option<String>,
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+/// Where the project's source code is kept
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PomScm {
+    /// A page to browse the sources, such as `https://github.com/example/project`.
+    pub url: String,
+    /// The read-only connection, such as `scm:git:https://github.com/example/project.git`.
+    #[serde(default)]
+    pub connection: Option<String>,
+    /// The read-write connection, such as `scm:git:ssh://git@github.com/example/project.git`.
+    #[serde(default, rename = "developer-connection")]
+    pub developer_connection: Option<String>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -118,6 +1313,10 @@ pub struct TestConfig {
 pub enum RepositoryConfig {
     Enabled(bool),
     Custom(CustomRepository),
+    /// A local Maven repository, `~/.m2/repository` unless a `path` is given.
+    LocalMaven {
+        path: Option<PathBuf>,
+    },
 }
 
 /// Custom repository configuration
@@ -126,4 +1325,203 @@ pub struct CustomRepository {
     #[serde(rename = "type")]
     pub repo_type: String,
     pub url: String,
-}
\ No newline at end of file
+    /// The username for a private repository. A value starting with `$` names an environment
+    /// variable to read it from, such as `$NEXUS_USER`.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// The password for a private repository, which can also be an environment variable
+    /// reference like `$NEXUS_PASSWORD`.
+    #[serde(default)]
+    pub password: Option<String>,
+}
+
+impl CustomRepository {
+    /// Returns the repository's credentials, reading any `$VARIABLE` references from the
+    /// environment.
+    pub fn credentials(&self) -> anyhow::Result<Option<Credentials>> {
+        self.credentials_with(&|name| std::env::var(name).ok())
+    }
+
+    /// Returns the repository's credentials, looking up any `$VARIABLE` references with
+    /// `lookup` instead of in the environment.
+    pub fn credentials_with(
+        &self,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Option<Credentials>> {
+        let (Some(username), Some(password)) = (&self.username, &self.password) else {
+            if self.username.is_some() || self.password.is_some() {
+                anyhow::bail!(
+                    "the repository {} needs both a username and a password",
+                    self.url
+                );
+            }
+            return Ok(None);
+        };
+
+        Ok(Some(Credentials {
+            username: resolve_env(username, lookup)?,
+            password: resolve_env(password, lookup)?,
+        }))
+    }
+}
+
+/// Reads `$NAME` references with `lookup`, returning any other value unchanged.
+fn resolve_env(value: &str, lookup: &dyn Fn(&str) -> Option<String>) -> anyhow::Result<String> {
+    match value.strip_prefix('$') {
+        Some(name) => lookup(name)
+            .ok_or_else(|| anyhow::anyhow!("the environment variable {} is not set", name)),
+        None => Ok(value.to_string()),
+    }
+}
+/// Personal defaults, read from `~/.fyg/config.toml`, or `$XDG_CONFIG_HOME/fyg/config.toml` if
+/// there is one.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserConfig {
+    /// The `project.group` new projects start with, instead of `com.example`.
+    #[serde(default, rename = "default-group")]
+    pub default_group: Option<String>,
+    /// The `project.version` new projects start with, instead of `1.0.0-SNAPSHOT`.
+    #[serde(default, rename = "default-version")]
+    pub default_version: Option<String>,
+    /// The `project.authors` of new projects.
+    #[serde(default, rename = "default-authors")]
+    pub default_authors: Option<Vec<String>>,
+    /// Where downloaded artifacts are cached, instead of `~/.fyg/cache`.
+    #[serde(default, rename = "cache-dir")]
+    pub cache_dir: Option<PathBuf>,
+    /// The JDK to use when `JAVA_HOME` isn't set.
+    #[serde(default, rename = "java-home")]
+    pub java_home: Option<PathBuf>,
+    /// The `kotlinc` to use when `FYG_KOTLINC` isn't set.
+    #[serde(default, rename = "kotlinc-path")]
+    pub kotlinc_path: Option<PathBuf>,
+}
+
+/// Every setting of the [`UserConfig`], as `fyg config --global` names them.
+pub const USER_SETTINGS: [&str; 6] = [
+    "default-group",
+    "default-version",
+    "default-authors",
+    "cache-dir",
+    "java-home",
+    "kotlinc-path",
+];
+
+static USER_CONFIG: OnceLock<UserConfig> = OnceLock::new();
+
+impl UserConfig {
+    /// Returns where the user config is kept: `$XDG_CONFIG_HOME/fyg/config.toml` if that file
+    /// exists, otherwise `~/.fyg/config.toml`.
+    pub fn path() -> anyhow::Result<PathBuf> {
+        if let Some(config_home) = std::env::var_os("XDG_CONFIG_HOME") {
+            let path = Path::new(&config_home).join("fyg").join("config.toml");
+            if path.is_file() {
+                return Ok(path);
+            }
+        }
+        Ok(toolchain::fyg_home()?.join("config.toml"))
+    }
+
+    /// Reads the user config, which is empty if there is no config file.
+    pub fn load() -> anyhow::Result<UserConfig> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(UserConfig::default());
+        }
+        Self::from_file(path)
+    }
+
+    /// Returns the user config, read the first time it is needed. A config file that can't be
+    /// read counts as empty here, [`UserConfig::load`] reports why.
+    pub fn current() -> &'static UserConfig {
+        USER_CONFIG.get_or_init(|| Self::load().unwrap_or_default())
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<UserConfig> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)?;
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("failed to parse {}: {}", path.display(), e))
+    }
+
+    pub fn write_to_file(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Creates the `fyg.toml` of a new project, filling in what isn't given with the defaults
+    /// from this config.
+    pub fn new_project(&self, name: &str, group: Option<&str>) -> FygToml {
+        let group = group.or(self.default_group.as_deref()).unwrap_or("com.example");
+        let mut fyg_toml = FygToml::new(name, group);
+        if let Some(version) = &self.default_version {
+            fyg_toml.project.version = version.clone();
+        }
+        fyg_toml.project.authors = self.default_authors.clone();
+        fyg_toml
+    }
+
+    /// Returns the setting at `key`, one of [`USER_SETTINGS`], or `None` if it isn't set.
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<toml::Value>> {
+        let key = user_setting(key)?;
+        Ok(toml::Table::try_from(self)?.remove(key))
+    }
+
+    /// Sets the setting at `key`, one of [`USER_SETTINGS`], returning the value it had before.
+    ///
+    /// `value` is taken as a string, or as a TOML value for `default-authors`, where a plain
+    /// string is split on commas.
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<Option<toml::Value>> {
+        let key = user_setting(key)?;
+        let old = self.get(key)?;
+
+        let new = if key == "default-authors" {
+            match format!("value = {}", value).parse::<toml::Table>() {
+                Ok(mut literal) if literal.get("value").is_some_and(toml::Value::is_array) => {
+                    literal.remove("value").expect("the value was just checked")
+                }
+                _ => toml::Value::Array(
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|author| !author.is_empty())
+                        .map(|author| toml::Value::String(author.to_string()))
+                        .collect(),
+                ),
+            }
+        } else {
+            toml::Value::String(value.to_string())
+        };
+
+        if key == "default-version" {
+            value.parse::<Version>()?;
+        }
+
+        let mut table = toml::Table::try_from(&*self)?;
+        table.insert(key.to_string(), new);
+        *self = table
+            .try_into()
+            .map_err(|e| anyhow::anyhow!("'{}' is not a valid value for {}: {}", value, key, e))?;
+        Ok(old)
+    }
+}
+
+/// Looks up `key` in [`USER_SETTINGS`], accepting `_` in place of `-`.
+fn user_setting(key: &str) -> anyhow::Result<&'static str> {
+    let key = key.replace('_', "-");
+    USER_SETTINGS
+        .iter()
+        .find(|setting| **setting == key)
+        .copied()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "unknown user setting '{}', expected one of: {}",
+                key,
+                USER_SETTINGS.join(", ")
+            )
+        })
+}
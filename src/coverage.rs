@@ -0,0 +1,256 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    build, cancel,
+    config::{CoverageConfig, CoverageEngine, CoverageFormat},
+    maven::{self, Coordinate},
+    toolchain,
+    xml::Element,
+};
+
+/// The version of the Kover agent and CLI used to measure coverage.
+const KOVER_VERSION: &str = "0.8.3";
+
+/// The version of the JaCoCo agent and CLI used when `test.coverage.engine = "jacoco"`.
+const JACOCO_VERSION: &str = "0.8.12";
+
+/// Where coverage reports are written, relative to the project.
+pub const REPORT_DIR: &str = "build/reports/coverage";
+
+/// Where the agent records coverage and its settings are kept, relative to the project.
+const WORK_DIR: &str = "build/coverage";
+
+/// An error raised when the tests don't cover enough of the project.
+#[derive(Debug)]
+pub enum CoverageError {
+    /// Fewer lines were covered than `test.coverage.minimum-coverage` asks for.
+    BelowMinimum {
+        /// The share of lines covered, from `0.0` to `1.0`.
+        covered: f32,
+        /// The share `test.coverage.minimum-coverage` asks for.
+        minimum: f32,
+    },
+}
+
+impl std::fmt::Display for CoverageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverageError::BelowMinimum { covered, minimum } => write!(
+                f,
+                "the tests cover {:.1}% of lines, below the {:.1}% test.coverage.minimum-coverage \
+                 asks for",
+                covered * 100.0,
+                minimum * 100.0
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CoverageError {}
+
+/// Prepares to record coverage in `project_dir`, returning the `-javaagent` argument that makes
+/// the test JVM record it.
+///
+/// Anything recorded by an earlier run is removed, and the agent appends to what it records, so
+/// re-runs of failed tests add to the first run. The agent is downloaded into `~/.fyg/cache/`
+/// the first time. `jar_tool` is only needed to unpack the JaCoCo agent.
+pub(crate) fn agent_arg(
+    config: &CoverageConfig,
+    project_dir: &Path,
+    jar_tool: impl FnOnce() -> anyhow::Result<PathBuf>,
+) -> anyhow::Result<String> {
+    let work_dir = project_dir.join(WORK_DIR);
+    std::fs::create_dir_all(&work_dir)?;
+    let recording = recording(config, project_dir);
+    if recording.exists() {
+        std::fs::remove_file(&recording)?;
+    }
+
+    match config.engine.unwrap_or_default() {
+        CoverageEngine::Kover => {
+            let agent = maven::fetch_jar(&kover("kover-jvm-agent"))?;
+            // the agent reads its settings from a file, so paths need no escaping
+            let args = work_dir.join("kover-agent.args");
+            std::fs::write(
+                &args,
+                format!("report.file={}\nreport.append=true\n", recording.display()),
+            )?;
+            Ok(format!("-javaagent:{}=file:{}", agent.display(), args.display()))
+        }
+        CoverageEngine::Jacoco => {
+            let agent = jacoco_agent(&jar_tool()?)?;
+            Ok(format!(
+                "-javaagent:{}=destfile={},append=true",
+                agent.display(),
+                recording.display()
+            ))
+        }
+    }
+}
+
+/// Writes the reports `test.coverage.report-formats` asks for from what the agent recorded,
+/// returning the share of the lines in `build/classes` that were covered.
+pub(crate) fn report(
+    config: &CoverageConfig,
+    java: &Path,
+    project_dir: &Path,
+    title: &str,
+) -> anyhow::Result<f32> {
+    let formats = config
+        .report_formats
+        .clone()
+        .unwrap_or_else(|| vec![CoverageFormat::Html, CoverageFormat::Xml]);
+    let report_dir = project_dir.join(REPORT_DIR);
+    std::fs::create_dir_all(&report_dir)?;
+
+    // the XML report is what coverage is measured from, so it is always written
+    let xml = match formats.contains(&CoverageFormat::Xml) {
+        true => report_dir.join("coverage.xml"),
+        false => project_dir.join(WORK_DIR).join("coverage.xml"),
+    };
+
+    let engine = config.engine.unwrap_or_default();
+    let mut cmd = Command::new(java);
+    match engine {
+        CoverageEngine::Kover => {
+            cmd.arg("-jar").arg(maven::fetch_jar(&kover("kover-cli"))?);
+        }
+        CoverageEngine::Jacoco => {
+            let classpath = jacoco_cli()
+                .iter()
+                .map(maven::fetch_jar)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            cmd.arg("-cp")
+                .arg(std::env::join_paths(classpath)?)
+                .arg("org.jacoco.cli.internal.Main");
+        }
+    }
+    cmd.arg("report")
+        .arg(recording(config, project_dir))
+        .arg("--classfiles")
+        .arg(project_dir.join("build").join("classes"));
+
+    let (source_flag, title_flag) = match engine {
+        CoverageEngine::Kover => ("--src", "--title"),
+        CoverageEngine::Jacoco => ("--sourcefiles", "--name"),
+    };
+    for root in build::source_roots(project_dir, false)? {
+        cmd.arg(source_flag).arg(root);
+    }
+    cmd.arg(title_flag).arg(title).arg("--xml").arg(&xml);
+
+    for format in formats {
+        match (format, engine) {
+            (CoverageFormat::Html, _) => {
+                cmd.arg("--html").arg(report_dir.join("html"));
+            }
+            (CoverageFormat::Xml, _) => {}
+            (CoverageFormat::Csv, CoverageEngine::Jacoco) => {
+                cmd.arg("--csv").arg(report_dir.join("coverage.csv"));
+            }
+            (CoverageFormat::Csv, CoverageEngine::Kover) => anyhow::bail!(
+                "kover can't write csv coverage reports, use engine = \"jacoco\" or another \
+                 format"
+            ),
+        }
+    }
+
+    let result = cancel::output(&mut cmd)?;
+    if !result.status.success() {
+        let stdout = String::from_utf8_lossy(&result.stdout);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        anyhow::bail!(
+            "failed to write coverage reports:\n{}{}",
+            stdout,
+            stderr.trim_end()
+        );
+    }
+
+    let report = Element::parse(&std::fs::read_to_string(&xml)?)?;
+    line_coverage(&report)
+        .ok_or_else(|| anyhow::anyhow!("{} has no line coverage counter", xml.display()))
+}
+
+/// Fails if `covered` is below `test.coverage.minimum-coverage`.
+pub(crate) fn check_minimum(config: &CoverageConfig, covered: f32) -> Result<(), CoverageError> {
+    match config.minimum_coverage {
+        Some(minimum) if covered < minimum => {
+            Err(CoverageError::BelowMinimum { covered, minimum })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Reads the share of lines covered from the totals of a JaCoCo XML report, which Kover writes
+/// too. A project without any lines counts as fully covered.
+pub fn line_coverage(report: &Element) -> Option<f32> {
+    let counter = report
+        .children("counter")
+        .find(|c| c.attribute("type") == Some("LINE"))?;
+    let covered: u64 = counter.attribute("covered")?.parse().ok()?;
+    let missed: u64 = counter.attribute("missed")?.parse().ok()?;
+    match covered + missed {
+        0 => Some(1.0),
+        total => Some(covered as f32 / total as f32),
+    }
+}
+
+/// The file the agent records coverage to.
+fn recording(config: &CoverageConfig, project_dir: &Path) -> PathBuf {
+    let file = match config.engine.unwrap_or_default() {
+        CoverageEngine::Kover => "test.ic",
+        CoverageEngine::Jacoco => "test.exec",
+    };
+    project_dir.join(WORK_DIR).join(file)
+}
+
+fn kover(artifact: &str) -> Coordinate {
+    Coordinate::new("org.jetbrains.kotlinx", artifact, KOVER_VERSION)
+}
+
+/// The JaCoCo CLI and its dependencies.
+fn jacoco_cli() -> Vec<Coordinate> {
+    let jacoco = |artifact| Coordinate::new("org.jacoco", artifact, JACOCO_VERSION);
+    let asm = |artifact| Coordinate::new("org.ow2.asm", artifact, "9.7");
+    vec![
+        jacoco("org.jacoco.cli"),
+        jacoco("org.jacoco.core"),
+        jacoco("org.jacoco.report"),
+        asm("asm"),
+        asm("asm-commons"),
+        asm("asm-tree"),
+        Coordinate::new("args4j", "args4j", "2.0.28"),
+    ]
+}
+
+/// Finds the JaCoCo agent, unpacking it from the `org.jacoco.agent` artifact that wraps it the
+/// first time.
+fn jacoco_agent(jar_tool: &Path) -> anyhow::Result<PathBuf> {
+    let dir = toolchain::cache_dir()?.join(format!("jacoco-{}", JACOCO_VERSION));
+    let agent = dir.join("jacocoagent.jar");
+    if agent.is_file() {
+        return Ok(agent);
+    }
+
+    let wrapper = maven::fetch_jar(&Coordinate::new(
+        "org.jacoco",
+        "org.jacoco.agent",
+        JACOCO_VERSION,
+    ))?;
+    std::fs::create_dir_all(&dir)?;
+    let status = cancel::output(
+        Command::new(jar_tool)
+            .arg("xf")
+            .arg(&wrapper)
+            .arg("jacocoagent.jar")
+            .current_dir(&dir),
+    )?
+    .status;
+    if !status.success() || !agent.is_file() {
+        anyhow::bail!("failed to unpack the JaCoCo agent from {}", wrapper.display());
+    }
+    Ok(agent)
+}
@@ -0,0 +1,467 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{build::BuildError, toolchain};
+
+/// The socket the daemon listens on, in its directory.
+const SOCKET_FILE: &str = "daemon.sock";
+
+/// Where the daemon writes its own output and that of the compilers it runs.
+const LOG_FILE: &str = "daemon.log";
+
+/// A Java program that keeps the Kotlin compiler loaded, compiling once for every request it
+/// reads from stdin.
+///
+/// A request is the number of arguments on one line followed by one argument per line. The
+/// response is the exit code and the length in bytes of the compiler's output on one line,
+/// followed by the output. The compiler is loaded by reflection, so the program runs from source
+/// without being compiled against a particular Kotlin release.
+const COMPILE_SERVER: &str = r#"import java.io.*;
+import java.lang.reflect.Method;
+import java.nio.charset.StandardCharsets;
+
+public class FygCompileServer {
+    public static void main(String[] ignored) throws Exception {
+        Class<?> compiler = Class.forName("org.jetbrains.kotlin.cli.jvm.K2JVMCompiler");
+        Method exec = compiler.getMethod("exec", PrintStream.class, String[].class);
+        BufferedReader in =
+            new BufferedReader(new InputStreamReader(System.in, StandardCharsets.UTF_8));
+        PrintStream out = new PrintStream(new FileOutputStream(FileDescriptor.out), true, "UTF-8");
+        // anything else printed to stdout would corrupt the responses
+        System.setOut(System.err);
+
+        String line;
+        while ((line = in.readLine()) != null) {
+            String[] args = new String[Integer.parseInt(line.trim())];
+            for (int i = 0; i < args.length; i++) {
+                args[i] = in.readLine();
+            }
+
+            ByteArrayOutputStream buffer = new ByteArrayOutputStream();
+            PrintStream err = new PrintStream(buffer, true, "UTF-8");
+            int code;
+            try {
+                Object instance = compiler.getDeclaredConstructor().newInstance();
+                Object exitCode = exec.invoke(instance, err, args);
+                code = (Integer) exitCode.getClass().getMethod("getCode").invoke(exitCode);
+            } catch (Throwable t) {
+                t.printStackTrace(err);
+                code = 3;
+            }
+            err.flush();
+
+            byte[] output = buffer.toByteArray();
+            out.println(code + " " + output.length);
+            out.write(output);
+            out.flush();
+        }
+    }
+}
+"#;
+
+/// A request sent to the daemon over its socket, as a line of JSON.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+enum Request {
+    Status,
+    Stop,
+    Compile {
+        kotlinc: PathBuf,
+        java_home: Option<PathBuf>,
+        /// The directory relative paths in `args` are relative to.
+        dir: PathBuf,
+        args: Vec<String>,
+    },
+}
+
+/// The daemon's answer to a [`Request`], as a line of JSON.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "lowercase")]
+enum Response {
+    Status {
+        pid: u32,
+        /// When the daemon started, in seconds since the Unix epoch.
+        started: u64,
+    },
+    Stopped,
+    Compiled {
+        code: i32,
+        output: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A running build daemon, as reported by `fyg daemon status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub started: SystemTime,
+}
+
+impl DaemonStatus {
+    /// How long the daemon has been running.
+    pub fn uptime(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(self.started)
+            .unwrap_or_default()
+    }
+}
+
+/// A long-lived process that keeps the Kotlin compiler warm between builds.
+///
+/// The daemon listens on a Unix domain socket in its directory. For each `kotlinc` it is asked
+/// to compile with, it starts a JVM with the compiler loaded and reuses it for later builds,
+/// which saves the JVM's startup and warm-up time on every build after the first.
+#[derive(Clone, Debug)]
+pub struct Daemon {
+    dir: PathBuf,
+}
+
+impl Daemon {
+    /// The daemon kept in `~/.fyg/daemon`.
+    pub fn open() -> anyhow::Result<Self> {
+        Ok(Self::at(toolchain::fyg_home()?.join("daemon")))
+    }
+
+    /// A daemon kept in `dir`.
+    pub fn at(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The file the daemon writes its output to.
+    pub fn log_file(&self) -> PathBuf {
+        self.dir.join(LOG_FILE)
+    }
+
+    /// Returns the status of the daemon, or `None` if it isn't running.
+    pub fn status(&self) -> Option<DaemonStatus> {
+        match self.request(&Request::Status).ok()? {
+            Response::Status { pid, started } => Some(DaemonStatus {
+                pid,
+                started: UNIX_EPOCH + Duration::from_secs(started),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Starts the daemon in the background by running `fyg daemon run`, and waits for it to
+    /// answer.
+    pub fn start(&self) -> anyhow::Result<DaemonStatus> {
+        if let Some(status) = self.status() {
+            anyhow::bail!("the build daemon is already running with PID {}", status.pid);
+        }
+        std::fs::create_dir_all(&self.dir)?;
+
+        let log = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_file())?;
+        let mut cmd = std::process::Command::new(std::env::current_exe()?);
+        cmd.args(["daemon", "run", "--dir"])
+            .arg(&self.dir)
+            .stdin(std::process::Stdio::null())
+            .stdout(log.try_clone()?)
+            .stderr(log);
+        #[cfg(unix)]
+        {
+            // a process group of its own keeps Ctrl-C in the terminal from stopping the daemon
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        let mut child = cmd.spawn()?;
+
+        for _ in 0..50 {
+            if let Some(status) = self.status() {
+                return Ok(status);
+            }
+            if let Some(exit) = child.try_wait()? {
+                anyhow::bail!(
+                    "the build daemon exited with {}, see {} for details",
+                    exit,
+                    self.log_file().display()
+                );
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        anyhow::bail!(
+            "the build daemon didn't start answering, see {} for details",
+            self.log_file().display()
+        )
+    }
+
+    /// Asks the daemon to shut down, returning its PID, or `None` if it wasn't running.
+    pub fn stop(&self) -> anyhow::Result<Option<u32>> {
+        let Some(status) = self.status() else {
+            return Ok(None);
+        };
+        match self.request(&Request::Stop)? {
+            Response::Stopped => Ok(Some(status.pid)),
+            response => anyhow::bail!("unexpected response from the build daemon: {:?}", response),
+        }
+    }
+
+    /// Compiles with `kotlinc` and the given arguments in the daemon, returning the compiler's
+    /// output if it succeeds.
+    ///
+    /// Returns `None` if the daemon isn't running or can't run this compiler, in which case the
+    /// caller should run `kotlinc` itself.
+    pub(crate) fn compile(
+        &self,
+        kotlinc: &Path,
+        args: &[&OsStr],
+        java_home: Option<&Path>,
+    ) -> Option<Result<String, BuildError>> {
+        // the compile server reads one argument per line
+        let args = args
+            .iter()
+            .map(|arg| arg.to_str().filter(|a| !a.contains('\n')).map(str::to_string))
+            .collect::<Option<Vec<_>>>()?;
+        let request = Request::Compile {
+            kotlinc: kotlinc.to_path_buf(),
+            java_home: java_home.map(Path::to_path_buf),
+            dir: std::env::current_dir().ok()?,
+            args,
+        };
+
+        match self.request(&request).ok()? {
+            Response::Compiled { code: 0, output } => Some(Ok(output)),
+            Response::Compiled { code, output } => Some(Err(BuildError::CompilationFailed {
+                code: Some(code),
+                stderr: output,
+            })),
+            _ => None,
+        }
+    }
+
+    #[cfg(unix)]
+    fn request(&self, request: &Request) -> anyhow::Result<Response> {
+        use std::{
+            io::{BufRead, BufReader, Write},
+            os::unix::net::UnixStream,
+        };
+
+        let mut stream = UnixStream::connect(self.dir.join(SOCKET_FILE))?;
+        writeln!(stream, "{}", serde_json::to_string(request)?)?;
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line)?;
+        Ok(serde_json::from_str(&line)?)
+    }
+
+    #[cfg(not(unix))]
+    fn request(&self, _request: &Request) -> anyhow::Result<Response> {
+        anyhow::bail!("the build daemon is only supported on Unix")
+    }
+
+    /// Runs the daemon in the current process until it is asked to stop.
+    ///
+    /// This is what `fyg daemon run` does; [`Daemon::start`] runs it in the background.
+    #[cfg(unix)]
+    pub fn run(&self) -> anyhow::Result<()> {
+        use std::{
+            collections::HashMap,
+            io::{BufRead, BufReader},
+            os::unix::net::UnixListener,
+            sync::{Arc, Mutex},
+        };
+
+        if let Some(status) = self.status() {
+            anyhow::bail!("the build daemon is already running with PID {}", status.pid);
+        }
+        std::fs::create_dir_all(&self.dir)?;
+        let socket = self.dir.join(SOCKET_FILE);
+        // a daemon that didn't answer above left this behind when it was killed
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket)?;
+
+        let server = self.dir.join("FygCompileServer.java");
+        std::fs::write(&server, COMPILE_SERVER)?;
+
+        let started = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        type Key = (PathBuf, Option<PathBuf>, PathBuf);
+        type Compilers = HashMap<Key, Arc<Mutex<CompileServer>>>;
+        let compilers: Arc<Mutex<Compilers>> = Arc::default();
+
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            // requests are read one at a time, so a client that never sends one can't hold up
+            // everyone else
+            let _ = stream.set_read_timeout(Some(Duration::from_secs(5)));
+            let mut line = String::new();
+            if BufReader::new(&stream).read_line(&mut line).is_err() {
+                continue;
+            }
+
+            let request = match serde_json::from_str(&line) {
+                Ok(request) => request,
+                Err(e) => {
+                    let message = format!("invalid request: {}", e);
+                    let _ = respond(&mut stream, &Response::Error { message });
+                    continue;
+                }
+            };
+            match request {
+                Request::Status => {
+                    let pid = std::process::id();
+                    let _ = respond(&mut stream, &Response::Status { pid, started });
+                }
+                Request::Stop => {
+                    let _ = respond(&mut stream, &Response::Stopped);
+                    break;
+                }
+                Request::Compile { kotlinc, java_home, dir, args } => {
+                    // compiles run on their own threads so status requests are answered
+                    // while one is going on
+                    let compilers = Arc::clone(&compilers);
+                    let server = server.clone();
+                    std::thread::spawn(move || {
+                        // a JVM can't change its working directory, so each directory the
+                        // daemon is asked to compile in gets a compile server of its own
+                        let key = (kotlinc, java_home, dir);
+                        let compiler = {
+                            let mut compilers = compilers.lock().unwrap();
+                            match compilers.get(&key) {
+                                Some(compiler) => Ok(Arc::clone(compiler)),
+                                None => CompileServer::start(&server, &key).map(|compiler| {
+                                    let compiler = Arc::new(Mutex::new(compiler));
+                                    compilers.insert(key.clone(), Arc::clone(&compiler));
+                                    compiler
+                                }),
+                            }
+                        };
+
+                        let result = compiler.and_then(|c| c.lock().unwrap().compile(&args));
+                        let response = match result {
+                            Ok((code, output)) => Response::Compiled { code, output },
+                            Err(e) => {
+                                // a compiler that failed is started again for the next build
+                                compilers.lock().unwrap().remove(&key);
+                                Response::Error { message: format!("{:#}", e) }
+                            }
+                        };
+                        let _ = respond(&mut stream, &response);
+                    });
+                }
+            }
+        }
+
+        let _ = std::fs::remove_file(&socket);
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn run(&self) -> anyhow::Result<()> {
+        anyhow::bail!("the build daemon is only supported on Unix")
+    }
+}
+
+#[cfg(unix)]
+fn respond(
+    stream: &mut std::os::unix::net::UnixStream,
+    response: &Response,
+) -> anyhow::Result<()> {
+    use std::io::Write;
+    writeln!(stream, "{}", serde_json::to_string(response)?)?;
+    Ok(())
+}
+
+/// A JVM running [`COMPILE_SERVER`] with the compiler of one Kotlin installation loaded.
+struct CompileServer {
+    child: std::process::Child,
+    stdout: std::io::BufReader<std::process::ChildStdout>,
+}
+
+impl CompileServer {
+    fn start(
+        server: &Path,
+        (kotlinc, java_home, dir): &(PathBuf, Option<PathBuf>, PathBuf),
+    ) -> anyhow::Result<Self> {
+        let kotlinc = kotlinc.canonicalize()?;
+        let home = kotlinc.parent().and_then(Path::parent).ok_or_else(|| {
+            anyhow::anyhow!("{} is not in a Kotlin installation", kotlinc.display())
+        })?;
+        let compiler = home.join("lib").join("kotlin-compiler.jar");
+        if !compiler.is_file() {
+            anyhow::bail!("could not find the Kotlin compiler at {}", compiler.display());
+        }
+        let java = match java_home {
+            Some(home) => toolchain::java_in(home),
+            None => toolchain::java(),
+        };
+        let java = java.ok_or_else(|| anyhow::anyhow!("could not find java to run kotlinc on"))?;
+
+        let mut child = std::process::Command::new(java)
+            .arg("-Xss2m")
+            .arg(format!("-Dkotlin.home={}", home.display()))
+            .arg("-cp")
+            .arg(&compiler)
+            .arg(server)
+            .current_dir(dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("could not read from the compile server"))?;
+        Ok(Self {
+            child,
+            stdout: std::io::BufReader::new(stdout),
+        })
+    }
+
+    fn compile(&mut self, args: &[String]) -> anyhow::Result<(i32, String)> {
+        use std::io::{BufRead, Read, Write};
+
+        let stdin = self
+            .child
+            .stdin
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("could not write to the compile server"))?;
+        writeln!(stdin, "{}", args.len())?;
+        for arg in args {
+            writeln!(stdin, "{}", arg)?;
+        }
+        stdin.flush()?;
+
+        let mut header = String::new();
+        if self.stdout.read_line(&mut header)? == 0 {
+            anyhow::bail!("the compile server exited");
+        }
+        let (code, length) = header
+            .trim()
+            .split_once(' ')
+            .and_then(|(code, length)| Some((code.parse().ok()?, length.parse().ok()?)))
+            .ok_or_else(|| anyhow::anyhow!("unexpected response from the compile server"))?;
+        let mut output = vec![0; length];
+        self.stdout.read_exact(&mut output)?;
+        Ok((code, String::from_utf8_lossy(&output).into_owned()))
+    }
+}
+
+impl Drop for CompileServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Formats a duration the way `fyg daemon status` shows uptime, such as `2h 5m 12s`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds % 3600 / 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {}s", m, s),
+        (h, m, s) => format!("{}h {}m {}s", h, m, s),
+    }
+}
@@ -0,0 +1,105 @@
+use serde::Serialize;
+
+use crate::maven;
+
+/// The result of adding a dependency with [`Fyg::add`](crate::Fyg::add).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AddOutcome {
+    /// The dependency was written to `fyg.toml`.
+    Added {
+        key: String,
+        version: String,
+        scope: String,
+    },
+
+    /// The dependency is already declared, so nothing was changed.
+    AlreadyPresent {
+        key: String,
+        scopes: Vec<&'static str>,
+    },
+}
+
+/// The result of removing dependencies with [`Fyg::remove`](crate::Fyg::remove).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemoveOutcome {
+    /// The listed `(scope, key)` entries were removed from `fyg.toml`.
+    Removed(Vec<(&'static str, String)>),
+
+    /// The name matched more than one entry and no scope was given, so nothing was removed.
+    Ambiguous(Vec<(&'static str, String)>),
+}
+
+/// A dependency with a newer version available, found by [`Fyg::outdated`](crate::Fyg::outdated).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Outdated {
+    /// The dependency scope, or `build` for `build.kotlin-version`.
+    pub scope: &'static str,
+    pub key: String,
+    pub current: String,
+    /// The newest stable version, with the same major version as `current` unless major
+    /// updates were asked for.
+    pub stable: String,
+    /// The newest version published, which may be a pre-release.
+    pub latest: String,
+}
+
+/// Picks the newest stable version in `versions` that is newer than `current`, keeping the
+/// major version of `current` unless `major` is set.
+pub fn newest_update<'a>(current: &str, versions: &'a [String], major: bool) -> Option<&'a str> {
+    let major_of = |version: &str| version.split(['.', '-']).next().map(str::to_string);
+    versions
+        .iter()
+        .map(String::as_str)
+        .filter(|v| maven::is_stable(v))
+        .filter(|v| major || major_of(v) == major_of(current))
+        .filter(|v| maven::compare_versions(v, current).is_gt())
+        .max_by(|a, b| maven::compare_versions(a, b))
+}
+
+/// A dependency version bumped by [`Fyg::update`](crate::Fyg::update).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionChange {
+    pub scope: &'static str,
+    pub key: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// Whether the dependency `key` is referred to by `name`.
+///
+/// `name` can be the full key, the artifact ID of a `group:artifact` key, or a
+/// `group:artifact:version` coordinate.
+pub fn matches(key: &str, name: &str) -> bool {
+    if key == name {
+        return true;
+    }
+
+    let Some((group, artifact)) = key.split_once(':') else {
+        return false;
+    };
+    match parse_coordinate(name) {
+        Ok((name_group, name_artifact, _)) => name_group == group && name_artifact == artifact,
+        Err(_) => name == artifact,
+    }
+}
+
+/// Splits a `group:artifact` or `group:artifact:version` coordinate into its parts.
+pub fn parse_coordinate(coordinate: &str) -> anyhow::Result<(String, String, Option<String>)> {
+    let parts: Vec<&str> = coordinate.split(':').collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        anyhow::bail!("invalid coordinate '{}'", coordinate);
+    }
+
+    match parts.as_slice() {
+        [group, artifact] => Ok((group.to_string(), artifact.to_string(), None)),
+        [group, artifact, version] => Ok((
+            group.to_string(),
+            artifact.to_string(),
+            Some(version.to_string()),
+        )),
+        _ => anyhow::bail!(
+            "invalid coordinate '{}', expected group:artifact or group:artifact:version",
+            coordinate
+        ),
+    }
+}
@@ -0,0 +1,93 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    cancel,
+    config::DokkaFormat,
+    maven::{self, Coordinate},
+};
+
+/// The version of the Dokka CLI and plugins used to generate documentation.
+const DOKKA_VERSION: &str = "1.9.20";
+
+/// Where documentation is written when `build.dokka.output-dir` isn't set, relative to the
+/// project.
+pub const DEFAULT_OUTPUT_DIR: &str = "build/docs";
+
+/// The Dokka command line runner, which bundles everything but the plugins.
+fn cli() -> Coordinate {
+    Coordinate::new("org.jetbrains.dokka", "dokka-cli", DOKKA_VERSION)
+}
+
+/// The Dokka plugins, and their dependencies, that write documentation in `format`.
+fn plugins(format: DokkaFormat) -> Vec<Coordinate> {
+    let dokka = |artifact| Coordinate::new("org.jetbrains.dokka", artifact, DOKKA_VERSION);
+    let mut plugins = vec![
+        dokka("dokka-base"),
+        dokka("analysis-kotlin-descriptors"),
+        Coordinate::new("org.jetbrains.kotlinx", "kotlinx-html-jvm", "0.8.0"),
+        Coordinate::new("org.freemarker", "freemarker", "2.3.31"),
+    ];
+    match format {
+        DokkaFormat::Html => {}
+        DokkaFormat::Gfm => plugins.push(dokka("gfm-plugin")),
+        DokkaFormat::Javadoc => plugins.extend([
+            dokka("kotlin-as-java-plugin"),
+            dokka("javadoc-plugin"),
+            Coordinate::new("com.soywiz.korlibs.korte", "korte-jvm", "2.7.0"),
+        ]),
+    }
+    plugins
+}
+
+/// Runs Dokka on `source_roots`, writing documentation for `module_name` to `output_dir`.
+///
+/// The CLI and plugins are downloaded into `~/.fyg/cache/` the first time. Dokka is configured
+/// with a JSON file written to `build_dir`, so no path has to survive its argument splitting.
+/// `classpath` lets it resolve the types the sources refer to.
+pub(crate) fn generate(
+    java: &Path,
+    module_name: &str,
+    source_roots: &[PathBuf],
+    classpath: &[PathBuf],
+    output_dir: &Path,
+    build_dir: &Path,
+    format: DokkaFormat,
+) -> anyhow::Result<()> {
+    let cli = maven::fetch_jar(&cli())?;
+    let plugins = plugins(format)
+        .iter()
+        .map(maven::fetch_jar)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    std::fs::create_dir_all(output_dir)?;
+    std::fs::create_dir_all(build_dir)?;
+    let config = serde_json::json!({
+        "moduleName": module_name,
+        "outputDir": output_dir,
+        "pluginsClasspath": plugins,
+        "sourceSets": [{
+            "displayName": "jvm",
+            "sourceSetID": { "scopeId": module_name, "sourceSetName": "main" },
+            "sourceRoots": source_roots,
+            "classpath": classpath,
+            "analysisPlatform": "jvm",
+        }],
+    });
+    let config_path = build_dir.join("dokka.json");
+    std::fs::write(&config_path, serde_json::to_string_pretty(&config)?)?;
+
+    let result = cancel::output(Command::new(java).arg("-jar").arg(&cli).arg(&config_path))?;
+    if !result.status.success() {
+        let stdout = String::from_utf8_lossy(&result.stdout);
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        anyhow::bail!(
+            "dokka failed to generate documentation:\n{}{}",
+            stdout,
+            stderr.trim_end()
+        );
+    }
+    Ok(())
+}
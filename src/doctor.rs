@@ -0,0 +1,317 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    Fyg,
+    config::LINUX_ARM32_TRIPLET,
+    lock::{FygLock, LOCK_FILE_NAME},
+    toml::FygToml,
+    toolchain,
+};
+
+/// How a [`Check`] turned out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Everything needed is in place.
+    Passed,
+    /// Something is missing or out of date, but builds can still work.
+    Warning,
+    /// Builds will fail until this is fixed.
+    Failed,
+}
+
+/// The result of one of the checks `fyg doctor` runs.
+#[derive(Clone, Debug)]
+pub struct Check {
+    pub status: CheckStatus,
+    /// What was checked, such as `java` or `fyg.lock`.
+    pub name: String,
+    /// What was found, or what to do about it.
+    pub detail: String,
+}
+
+impl Check {
+    fn passed(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Passed,
+            name: name.into(),
+            detail: detail.into(),
+        }
+    }
+
+    fn warning(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Warning,
+            ..Self::passed(name, detail)
+        }
+    }
+
+    fn failed(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            status: CheckStatus::Failed,
+            ..Self::passed(name, detail)
+        }
+    }
+}
+
+/// Runs every check, using the project at `config_path` if there is one.
+///
+/// Without a project, only the tools fyg needs are checked. With one, the Kotlin and Java
+/// versions it pins are checked, along with its JVM target, the cross-linker `linux-arm32`
+/// needs, its repositories and `fyg.lock`.
+pub fn run(config_path: Option<&Path>) -> anyhow::Result<Vec<Check>> {
+    let fyg_toml = match config_path {
+        Some(path) => Some(Fyg::load(path)?),
+        None => None,
+    };
+
+    let (java, java_version) = java(fyg_toml.as_ref());
+    let mut checks = vec![java, kotlinc(fyg_toml.as_ref())];
+    checks.extend(tools(fyg_toml.as_ref()));
+    checks.push(cache());
+
+    if let (Some(fyg_toml), Some(config_path)) = (&fyg_toml, config_path) {
+        checks.extend(jvm_target(fyg_toml, java_version.as_deref()));
+        checks.extend(cross_linker(fyg_toml));
+        checks.extend(repositories(fyg_toml)?);
+        if fyg_toml.workspace.is_none() {
+            checks.push(lock(config_path, fyg_toml));
+        }
+    }
+
+    Ok(checks)
+}
+
+/// Checks for the JDK the project pins, or `java` otherwise, returning its version too.
+fn java(fyg_toml: Option<&FygToml>) -> (Check, Option<String>) {
+    let pinned = fyg_toml
+        .and_then(|f| f.build.as_ref())
+        .and_then(|b| b.java_version.as_deref());
+
+    let java = match pinned {
+        Some(version) => match toolchain::jdk(version) {
+            Some(home) => toolchain::java_in(&home),
+            None => {
+                let check = Check::failed(
+                    format!("JDK {}", version),
+                    "not found, install it or point JAVA_HOME at it",
+                );
+                return (check, None);
+            }
+        },
+        None => toolchain::java(),
+    };
+    let Some(java) = java else {
+        let check = Check::failed("java", "not found, put it on your PATH or set JAVA_HOME");
+        return (check, None);
+    };
+
+    let version = java_version(&java);
+    let check = Check::passed("java", describe(&java, version.as_deref()));
+    (check, version)
+}
+
+/// Checks for the Kotlin compiler the project pins, or `kotlinc` otherwise.
+fn kotlinc(fyg_toml: Option<&FygToml>) -> Check {
+    let pinned = fyg_toml
+        .and_then(|f| f.build.as_ref())
+        .and_then(|b| b.kotlin_version.as_deref());
+
+    let kotlinc = match pinned {
+        Some(version) => match toolchain::installed_kotlinc(version) {
+            Some(kotlinc) => kotlinc,
+            None => {
+                return Check::warning(
+                    format!("Kotlin {}", version),
+                    "not installed yet, it will be downloaded by the next build",
+                );
+            }
+        },
+        None => match toolchain::kotlinc() {
+            Some(kotlinc) => kotlinc,
+            None => {
+                return Check::failed(
+                    "kotlinc",
+                    "not found, put it on your PATH, set KOTLIN_HOME or set \
+                     build.kotlin-version",
+                );
+            }
+        },
+    };
+
+    let version = kotlinc_version(&kotlinc);
+    Check::passed("kotlinc", describe(&kotlinc, version.as_deref()))
+}
+
+/// Checks for the command-line tools fyg runs for downloads.
+fn tools(fyg_toml: Option<&FygToml>) -> Vec<Check> {
+    let mut checks = vec![match toolchain::find_executable("curl") {
+        Some(curl) => Check::passed("curl", curl.display().to_string()),
+        None => Check::failed("curl", "not found, it is needed to download dependencies"),
+    }];
+
+    let pinned = fyg_toml
+        .and_then(|f| f.build.as_ref())
+        .and_then(|b| b.kotlin_version.as_deref());
+    if pinned.is_some_and(|version| toolchain::installed_kotlinc(version).is_none()) {
+        checks.push(match toolchain::find_executable("unzip") {
+            Some(unzip) => Check::passed("unzip", unzip.display().to_string()),
+            None => Check::failed(
+                "unzip",
+                "not found, it is needed to install build.kotlin-version",
+            ),
+        });
+    }
+
+    checks
+}
+
+/// Checks that downloads can be written to `~/.fyg/cache`.
+fn cache() -> Check {
+    let result = toolchain::cache_dir().and_then(|dir| {
+        std::fs::create_dir_all(&dir)?;
+        let probe = dir.join(format!(".doctor-{}", std::process::id()));
+        std::fs::write(&probe, b"")?;
+        std::fs::remove_file(&probe)?;
+        Ok(dir)
+    });
+    match result {
+        Ok(dir) => Check::passed("cache", dir.display().to_string()),
+        Err(e) => Check::failed("cache", format!("not writable: {}", e)),
+    }
+}
+
+/// Checks that the JDK is new enough to compile for `targets.jvm.target`.
+fn jvm_target(fyg_toml: &FygToml, java_version: Option<&str>) -> Option<Check> {
+    let target = fyg_toml
+        .targets
+        .as_ref()
+        .and_then(|t| t.jvm.as_ref())
+        .and_then(|jvm| jvm.target.as_deref())?;
+    let required = major_version(target)?;
+    let installed = major_version(java_version?)?;
+
+    Some(if installed >= required {
+        Check::passed(
+            "targets.jvm.target",
+            format!("{} is supported by Java {}", target, installed),
+        )
+    } else {
+        Check::failed(
+            "targets.jvm.target",
+            format!(
+                "{} needs Java {} or newer, but Java {} is installed",
+                target, required, installed
+            ),
+        )
+    })
+}
+
+/// Checks for the ARM cross-linker `targets.linux-arm32` needs when this machine isn't one.
+fn cross_linker(fyg_toml: &FygToml) -> Option<Check> {
+    let targets = fyg_toml.targets.as_ref()?;
+    if cfg!(target_arch = "arm") || !targets.linux_arm32.as_ref().is_some_and(|t| t.enabled) {
+        return None;
+    }
+
+    // the linker is named by an option such as -fuse-ld=/usr/bin/arm-linux-gnueabihf-ld
+    let linker = match targets.linux_arm32_linker() {
+        Some(option) => {
+            let linker = option.rsplit('=').next().unwrap_or(&option);
+            match Path::new(linker).is_file() {
+                true => Some(PathBuf::from(linker)),
+                false => toolchain::find_executable(linker),
+            }
+        }
+        None => toolchain::find_executable(&format!("{}-ld", LINUX_ARM32_TRIPLET)),
+    };
+    Some(match linker {
+        Some(linker) => Check::passed("targets.linux-arm32", linker.display().to_string()),
+        None => Check::failed(
+            "targets.linux-arm32",
+            format!(
+                "no ARM cross-linker found, install one such as {}-ld and name it in \
+                 targets.native.binary.linker-opts",
+                LINUX_ARM32_TRIPLET
+            ),
+        ),
+    })
+}
+
+/// Checks that every remote repository can be reached.
+fn repositories(fyg_toml: &FygToml) -> anyhow::Result<Vec<Check>> {
+    Ok(fyg_toml
+        .maven_repositories()?
+        .iter()
+        .filter(|repository| !repository.url.starts_with("file://"))
+        .map(|repository| match crate::http::ping(&repository.url) {
+            Ok(()) => Check::passed("repository", &repository.url),
+            Err(e) => Check::warning("repository", e.to_string()),
+        })
+        .collect())
+}
+
+/// Checks that `fyg.lock` exists and matches `fyg.toml`.
+fn lock(config_path: &Path, fyg_toml: &FygToml) -> Check {
+    let dir = config_path.parent().unwrap_or(Path::new("."));
+    let lock_path = dir.join(LOCK_FILE_NAME);
+    if !lock_path.exists() {
+        return Check::warning(
+            LOCK_FILE_NAME,
+            "not created yet, the next build will create it",
+        );
+    }
+
+    match FygLock::from_file(&lock_path) {
+        Ok(lock) if lock.is_up_to_date(fyg_toml) => {
+            Check::passed(LOCK_FILE_NAME, "up to date with fyg.toml")
+        }
+        Ok(_) => Check::warning(
+            LOCK_FILE_NAME,
+            "out of date with fyg.toml, the next build will update it",
+        ),
+        Err(e) => Check::failed(LOCK_FILE_NAME, format!("could not be read: {}", e)),
+    }
+}
+
+/// Asks the `java` launcher at `java` for its version, such as `17.0.2`.
+pub(crate) fn java_version(java: &Path) -> Option<String> {
+    // java -version prints something like: openjdk version "17.0.2" 2022-01-18
+    version_output(java, "-version").and_then(|output| {
+        let start = output.find('"')? + 1;
+        let len = output[start..].find('"')?;
+        Some(output[start..start + len].to_string())
+    })
+}
+
+/// Asks the compiler at `kotlinc` for its version, such as `2.0.21`.
+pub(crate) fn kotlinc_version(kotlinc: &Path) -> Option<String> {
+    // kotlinc -version prints something like: info: kotlinc-jvm 2.0.21 (JRE 17.0.2+8)
+    version_output(kotlinc, "-version").and_then(|output| {
+        let rest = &output[output.find("kotlinc-jvm ")? + "kotlinc-jvm ".len()..];
+        rest.split_whitespace().next().map(str::to_string)
+    })
+}
+
+/// Runs `program` with `flag` and returns what it printed, which may be on either stream.
+fn version_output(program: &Path, flag: &str) -> Option<String> {
+    let output = Command::new(program).arg(flag).output().ok()?;
+    let mut text = String::from_utf8_lossy(&output.stderr).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stdout));
+    Some(text)
+}
+
+fn describe(path: &Path, version: Option<&str>) -> String {
+    match version {
+        Some(version) => format!("{} ({})", version, path.display()),
+        None => path.display().to_string(),
+    }
+}
+
+/// Returns the major Java version of `version`, reading `1.8` and `1.8.0_292` as 8.
+fn major_version(version: &str) -> Option<u32> {
+    let version = version.strip_prefix("1.").unwrap_or(version);
+    version.split(['.', '_', '+', '-']).next()?.parse().ok()
+}
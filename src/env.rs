@@ -0,0 +1,96 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::{
+    Fyg,
+    config::UserConfig,
+    doctor,
+    toml::{self, FygToml},
+    toolchain,
+};
+
+/// The tools and directories a build would use, as `fyg env` shows them.
+///
+/// Unlike `fyg doctor`, nothing is checked, so a tool that can't be found is just `None`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Environment {
+    /// The JDK the project pins with `build.java-version`, or `JAVA_HOME`, or `java-home` in the
+    /// [`UserConfig`].
+    #[serde(rename = "JAVA_HOME")]
+    pub java_home: Option<PathBuf>,
+    /// The compiler the project pins with `build.kotlin-version`, or the one
+    /// [`toolchain::kotlinc`] finds.
+    #[serde(rename = "KOTLINC")]
+    pub kotlinc: Option<PathBuf>,
+    #[serde(rename = "FYG_CACHE_DIR")]
+    pub cache_dir: PathBuf,
+    /// Where the [`UserConfig`] is read from, whether or not there is one.
+    #[serde(rename = "FYG_CONFIG")]
+    pub user_config: PathBuf,
+    /// The version `java -version` reports, such as `17.0.2`.
+    #[serde(rename = "JAVA_VERSION")]
+    pub java_version: Option<String>,
+    /// The version `kotlinc -version` reports, such as `2.0.21`.
+    #[serde(rename = "KOTLIN_VERSION")]
+    pub kotlin_version: Option<String>,
+    /// The environment variables `fyg.toml` reads with `${env.VAR}`.
+    pub variables: Vec<EnvVariable>,
+}
+
+/// An environment variable `fyg.toml` refers to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EnvVariable {
+    pub name: String,
+    /// The variable's value, or `None` if it isn't set.
+    pub value: Option<String>,
+    /// What is used when the variable isn't set, from `${env.VAR:-default}`.
+    pub default: Option<String>,
+}
+
+/// Works out the build environment, using the project at `config_path` if there is one.
+///
+/// A `fyg.toml` that refers to environment variables which aren't set is still read, so the
+/// missing variables can be seen.
+pub fn run(config_path: Option<&Path>) -> anyhow::Result<Environment> {
+    let fyg_toml = match config_path {
+        Some(path) => Some(Fyg::load(path).or_else(|_| FygToml::from_file_uninterpolated(path))?),
+        None => None,
+    };
+    let build = fyg_toml.as_ref().and_then(|f| f.build.as_ref());
+
+    let java_home = match build.and_then(|b| b.java_version.as_deref()) {
+        Some(version) => toolchain::jdk(version),
+        None => toolchain::java_home(),
+    };
+    let java = match &java_home {
+        Some(home) => toolchain::java_in(home),
+        None => toolchain::java(),
+    };
+    let kotlinc = match build.and_then(|b| b.kotlin_version.as_deref()) {
+        Some(version) => toolchain::installed_kotlinc(version),
+        None => toolchain::kotlinc(),
+    };
+
+    let variables = match config_path {
+        Some(path) => toml::env_references(&std::fs::read_to_string(path)?)?
+            .into_iter()
+            .map(|(name, default)| EnvVariable {
+                value: std::env::var(&name).ok(),
+                name,
+                default,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    Ok(Environment {
+        java_version: java.as_deref().and_then(doctor::java_version),
+        kotlin_version: kotlinc.as_deref().and_then(doctor::kotlinc_version),
+        java_home,
+        kotlinc,
+        cache_dir: toolchain::cache_dir()?,
+        user_config: UserConfig::path()?,
+        variables,
+    })
+}
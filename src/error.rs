@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+
+use crate::{
+    build::BuildError, maven::NotCached, release::ReleaseError, run::RunError,
+    testing::TestError, toml::ValidationDiagnostic,
+};
+
+/// An error returned by the public API of fyg, such as [`Fyg::build`](crate::Fyg::build).
+///
+/// Errors without a variant of their own are kept in [`FygError::Other`], and can be told apart
+/// with [`FygError::downcast_ref`].
+#[derive(Debug)]
+pub enum FygError {
+    /// There is no configuration at the path.
+    ConfigNotFound(PathBuf),
+
+    /// The `fyg.toml` isn't valid TOML, or doesn't have the expected shape.
+    ConfigParse(::toml::de::Error),
+
+    /// A compiler ran but exited unsuccessfully.
+    CompilationFailed {
+        /// Everything the compiler wrote to stderr.
+        stderr: String,
+        /// The exit code of the compiler, or -1 if it was killed or couldn't be started.
+        exit_code: i32,
+    },
+
+    /// The dependencies in `fyg.toml` couldn't be resolved.
+    DependencyResolutionFailed(String),
+
+    IoError(std::io::Error),
+
+    /// A tool the build needs isn't installed.
+    ToolNotFound {
+        tool: String,
+        /// What the tool is needed for, or how to install it.
+        hint: String,
+    },
+
+    /// The configuration has errors. Holds every diagnostic found, including warnings and hints.
+    ValidationError(Vec<ValidationDiagnostic>),
+
+    /// The build, run or test was cancelled through its `CancellationToken`.
+    Cancelled,
+
+    /// Any other error, such as a [`TestError`](crate::testing::TestError).
+    Other(anyhow::Error),
+}
+
+impl FygError {
+    /// Returns the error in [`FygError::Other`] if it is a `T`.
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: std::fmt::Display + std::fmt::Debug + Send + Sync + 'static,
+    {
+        match self {
+            FygError::Other(error) => error.downcast_ref(),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for FygError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FygError::ConfigNotFound(path) => {
+                write!(f, "no configuration found at {}", path.display())
+            }
+            FygError::ConfigParse(e) => write!(f, "could not parse fyg.toml: {}", e),
+            FygError::CompilationFailed { stderr, exit_code } => {
+                match exit_code {
+                    -1 => writeln!(f, "compilation failed")?,
+                    code => writeln!(f, "compilation failed (exit code {})", code)?,
+                }
+                write!(f, "{}", stderr.trim_end())
+            }
+            FygError::DependencyResolutionFailed(message) => {
+                write!(f, "could not resolve dependencies: {}", message)
+            }
+            FygError::IoError(e) => write!(f, "{}", e),
+            FygError::ToolNotFound { tool, hint } => {
+                write!(f, "could not find {}, {}", tool, hint)
+            }
+            FygError::ValidationError(diagnostics) => {
+                let errors: Vec<String> = diagnostics
+                    .iter()
+                    .filter(|d| d.is_error())
+                    .map(|d| d.to_string())
+                    .collect();
+                write!(
+                    f,
+                    "fyg.toml has {} error{}: {}",
+                    errors.len(),
+                    if errors.len() == 1 { "" } else { "s" },
+                    errors.join("; ")
+                )
+            }
+            FygError::Cancelled => write!(f, "cancelled"),
+            FygError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FygError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FygError::ConfigParse(e) => Some(e),
+            FygError::IoError(e) => Some(e),
+            FygError::Other(e) => e.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for FygError {
+    fn from(error: std::io::Error) -> Self {
+        FygError::IoError(error)
+    }
+}
+
+impl From<::toml::de::Error> for FygError {
+    fn from(error: ::toml::de::Error) -> Self {
+        FygError::ConfigParse(error)
+    }
+}
+
+impl From<::toml::ser::Error> for FygError {
+    fn from(error: ::toml::ser::Error) -> Self {
+        FygError::Other(error.into())
+    }
+}
+
+impl From<serde_json::Error> for FygError {
+    fn from(error: serde_json::Error) -> Self {
+        FygError::Other(error.into())
+    }
+}
+
+impl From<std::env::JoinPathsError> for FygError {
+    fn from(error: std::env::JoinPathsError) -> Self {
+        FygError::Other(error.into())
+    }
+}
+
+impl From<RunError> for FygError {
+    fn from(error: RunError) -> Self {
+        FygError::Other(error.into())
+    }
+}
+
+impl From<TestError> for FygError {
+    fn from(error: TestError) -> Self {
+        FygError::Other(error.into())
+    }
+}
+
+impl From<ReleaseError> for FygError {
+    fn from(error: ReleaseError) -> Self {
+        FygError::Other(error.into())
+    }
+}
+
+impl From<BuildError> for FygError {
+    fn from(error: BuildError) -> Self {
+        let (tool, hint) = match error {
+            BuildError::CompilationFailed { code, stderr } => {
+                return FygError::CompilationFailed {
+                    stderr,
+                    exit_code: code.unwrap_or(-1),
+                };
+            }
+            BuildError::CompilerNotFound => {
+                ("kotlinc", "make sure it is on your PATH or set KOTLIN_HOME")
+            }
+            BuildError::JsCompilerNotFound => {
+                ("kotlinc-js", "which is needed for JavaScript targets")
+            }
+            BuildError::WasmCompilerNotFound => (
+                "kotlinc-wasm or kotlinc-js",
+                "which are needed for WebAssembly targets",
+            ),
+            BuildError::NativeToolNotFound(tool) => (
+                tool,
+                "which is needed for native targets, install Kotlin/Native and set \
+                 KOTLIN_NATIVE_HOME",
+            ),
+            BuildError::XcodebuildNotFound => (
+                "xcodebuild",
+                "which is needed to bundle an XCFramework, install Xcode",
+            ),
+            BuildError::WindresNotFound => (
+                "windres",
+                "which is needed to embed Windows resources, install MinGW-w64",
+            ),
+            error @ BuildError::NoSources(_) => return FygError::Other(error.into()),
+        };
+        FygError::ToolNotFound {
+            tool: tool.to_string(),
+            hint: hint.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for FygError {
+    /// Picks the variant for an error fyg raised internally. An error with context added to it
+    /// is kept whole in [`FygError::Other`], so none of its message is lost.
+    fn from(error: anyhow::Error) -> Self {
+        let error = match take::<FygError>(error) {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+        let error = match take::<BuildError>(error) {
+            Ok(error) => return error.into(),
+            Err(error) => error,
+        };
+        let error = match take::<std::io::Error>(error) {
+            Ok(error) => return error.into(),
+            Err(error) => error,
+        };
+        let error = match take::<NotCached>(error) {
+            Ok(error) => return FygError::DependencyResolutionFailed(error.to_string()),
+            Err(error) => error,
+        };
+        match take::<::toml::de::Error>(error) {
+            Ok(error) => error.into(),
+            Err(error) => FygError::Other(error),
+        }
+    }
+}
+
+/// Takes the `T` out of `error` if that is all there is to it.
+fn take<T>(error: anyhow::Error) -> Result<T, anyhow::Error>
+where
+    T: std::error::Error + Send + Sync + 'static,
+{
+    match error.chain().next().is_some_and(|top| top.is::<T>()) {
+        true => error.downcast(),
+        false => Err(error),
+    }
+}
@@ -0,0 +1,39 @@
+use std::{path::Path, process::Command};
+
+use crate::cancel;
+
+/// Runs each of `commands`, the `name` hooks such as `pre-build`, in `dir`, stopping at the first
+/// that fails.
+pub(crate) fn run(name: &str, commands: &[String], dir: &Path) -> anyhow::Result<()> {
+    for command in commands {
+        let mut cmd = shell(command);
+        cmd.current_dir(dir);
+        let output = cancel::output(&mut cmd)
+            .map_err(|e| anyhow::anyhow!("the {} hook '{}' failed: {}", name, command, e))?;
+        if !output.status.success() {
+            let status = match output.status.code() {
+                Some(code) => format!("exit code {}", code),
+                None => "a signal".to_string(),
+            };
+            anyhow::bail!(
+                "the {} hook '{}' failed with {}: {}",
+                name,
+                command,
+                status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The command running `command` in the system shell.
+fn shell(command: &str) -> Command {
+    let (shell, flag) = match cfg!(windows) {
+        true => ("cmd", "/C"),
+        false => ("sh", "-c"),
+    };
+    let mut cmd = Command::new(shell);
+    cmd.arg(flag).arg(command);
+    cmd
+}
@@ -0,0 +1,202 @@
+use std::{
+    cell::Cell,
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{ProcessConfig, cancel, maven::Credentials, toolchain};
+
+/// Counts downloads, to give each one's temporary file a unique name.
+static DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static OFFLINE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Whether the current thread is in an offline scope, where only `file://` URLs can be
+/// fetched.
+pub(crate) fn is_offline() -> bool {
+    OFFLINE.with(Cell::get)
+}
+
+/// Runs `f` offline if `offline` is set, refusing every request it makes on this thread, or on
+/// the threads a workspace build starts from it, other than to `file://` URLs. Dependencies then
+/// have to be in `~/.fyg/cache/` or a local repository. A scope within an offline one stays
+/// offline.
+pub fn offline_scope<T>(offline: bool, f: impl FnOnce() -> T) -> T {
+    let previous = OFFLINE.with(|current| current.replace(offline || current.get()));
+    let result = f();
+    OFFLINE.with(|current| current.set(previous));
+    result
+}
+
+/// Fails if the current thread is offline and `url` isn't a `file://` URL.
+fn check_online(url: &str) -> anyhow::Result<()> {
+    if is_offline() && !url.starts_with("file://") {
+        anyhow::bail!("can't reach {} while offline", url);
+    }
+    Ok(())
+}
+
+/// Downloads the file at `url` to `dest`, creating any missing parent directories.
+///
+/// Downloads are performed with `curl`, which must be available on the `PATH`. The file is
+/// written to a temporary sibling first so an interrupted download never leaves a partial file.
+///
+/// Any credentials are sent with HTTP Basic authentication.
+pub(crate) fn download(
+    url: &str,
+    dest: &Path,
+    credentials: Option<&Credentials>,
+) -> anyhow::Result<()> {
+    check_online(url)?;
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    // parallel builds can download the same file at once, so each download gets its own name
+    let partial = dest.with_extension(format!(
+        "part-{}-{}",
+        std::process::id(),
+        DOWNLOADS.fetch_add(1, Ordering::Relaxed)
+    ));
+    if let Err(e) = curl(url, Some(&partial), None, credentials) {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    std::fs::rename(&partial, dest)?;
+    Ok(())
+}
+
+/// Fetches the body of `url` as text.
+pub(crate) fn get_string(url: &str) -> anyhow::Result<String> {
+    let body = curl(url, None, None, None)?;
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Uploads the file at `path` to `url` with an HTTP `PUT`, as Maven repositories expect.
+///
+/// Any credentials are sent with HTTP Basic authentication.
+pub(crate) fn upload(
+    url: &str,
+    path: &Path,
+    credentials: Option<&Credentials>,
+) -> anyhow::Result<()> {
+    curl(url, None, Some(path), credentials)?;
+    Ok(())
+}
+
+/// Sends `body` to `url` as JSON with an HTTP `POST`, returning the body of the response.
+pub(crate) fn post_json(url: &str, body: &str) -> anyhow::Result<String> {
+    check_online(url)?;
+    let curl = toolchain::find_executable("curl")
+        .ok_or_else(|| anyhow::anyhow!("could not find curl, which is needed to reach {}", url))?;
+
+    log::debug!("POST {}", url);
+    // the body is written to stdin, as it can be too long to pass as an argument
+    let mut child = ProcessConfig::configure(&mut Command::new(curl))
+        .args(["--fail", "--silent", "--show-error", "--location", "--max-time", "60"])
+        .args(["--header", "Content-Type: application/json", "--data-binary", "@-"])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(body.as_bytes())?;
+    }
+
+    let result = cancel::wait_with_output(child, false)?;
+    if !result.status.success() {
+        anyhow::bail!(
+            "failed to post to {}: {}",
+            url,
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&result.stdout).into_owned())
+}
+
+/// Checks that the server at `url` answers, whatever the response is.
+pub(crate) fn ping(url: &str) -> anyhow::Result<()> {
+    check_online(url)?;
+    let curl = toolchain::find_executable("curl")
+        .ok_or_else(|| anyhow::anyhow!("could not find curl, which is needed to reach {}", url))?;
+
+    log::debug!("HEAD {}", url);
+    let output = ProcessConfig::configure(&mut Command::new(curl))
+        .args(["--silent", "--show-error", "--head", "--max-time", "10"])
+        .arg("--output")
+        .arg(if cfg!(windows) { "NUL" } else { "/dev/null" })
+        .arg(url)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "could not reach {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+fn curl(
+    url: &str,
+    output: Option<&Path>,
+    upload: Option<&Path>,
+    credentials: Option<&Credentials>,
+) -> anyhow::Result<Vec<u8>> {
+    check_online(url)?;
+
+    let curl = toolchain::find_executable("curl")
+        .ok_or_else(|| anyhow::anyhow!("could not find curl, which is needed to download {}", url))?;
+
+    log::debug!("{} {}", if upload.is_some() { "PUT" } else { "GET" }, url);
+    let mut cmd = Command::new(curl);
+    ProcessConfig::configure(&mut cmd);
+    cmd.args(["--fail", "--silent", "--show-error", "--location"]);
+    if let Some(output) = output {
+        cmd.arg("--output").arg(output);
+    }
+    if let Some(upload) = upload {
+        cmd.arg("--upload-file").arg(upload);
+    }
+
+    // credentials are passed through a config file on stdin rather than as arguments, which
+    // other users on the machine could read from the process list
+    if credentials.is_some() {
+        cmd.args(["--config", "-"]).stdin(Stdio::piped());
+    } else {
+        cmd.stdin(Stdio::null());
+    }
+    let mut child = cmd
+        .arg(url)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let (Some(credentials), Some(mut stdin)) = (credentials, child.stdin.take()) {
+        // curl's config syntax needs quotes and backslashes escaped
+        let user = format!("{}:{}", credentials.username, credentials.password)
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        writeln!(stdin, "user = \"{}\"", user)?;
+    }
+
+    let result = cancel::wait_with_output(child, false)?;
+    if !result.status.success() {
+        anyhow::bail!(
+            "failed to {} {}: {}",
+            if upload.is_some() { "upload to" } else { "download" },
+            url,
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+
+    Ok(result.stdout)
+}
@@ -0,0 +1,407 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{build::CompilerOptions, hash};
+
+/// Where the fingerprints of the last JVM compilation are kept, in the project directory.
+pub const FINGERPRINTS_FILE: &str = "build/.incremental/fingerprints.toml";
+
+/// What a source file looked like when it was last compiled.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// When the file was last modified, in milliseconds since the Unix epoch.
+    pub modified: u64,
+    pub sha256: String,
+}
+
+/// The fingerprints of every source in a compilation, keyed by their path in the project.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprints {
+    /// A hash of the compiler, its settings and the classpath the sources were compiled with.
+    #[serde(default)]
+    pub settings: String,
+    #[serde(default)]
+    pub files: BTreeMap<String, Fingerprint>,
+}
+
+/// Which sources a build has to compile.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Recompile {
+    /// Every source, because there is no earlier compilation to build on, the settings
+    /// changed or a source was removed.
+    All,
+    /// Only these sources, which changed or depend on sources that did.
+    Only(Vec<PathBuf>),
+    /// Nothing, the output of the last compilation is up to date.
+    Nothing,
+}
+
+impl Fingerprints {
+    /// Loads the fingerprints of the last compilation of the project in `project_dir`, if there
+    /// are any that can be read.
+    pub fn load(project_dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(project_dir.join(FINGERPRINTS_FILE)).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    pub fn save(&self, project_dir: &Path) -> anyhow::Result<()> {
+        let path = project_dir.join(FINGERPRINTS_FILE);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Removes the fingerprints of the project in `project_dir`, so the next build compiles
+    /// everything.
+    pub fn remove(project_dir: &Path) -> anyhow::Result<()> {
+        match std::fs::remove_file(project_dir.join(FINGERPRINTS_FILE)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Takes the fingerprints of `sources` as they are now.
+    ///
+    /// A file whose modification time matches its fingerprint in `previous` is assumed not to
+    /// have changed, so only files that were touched are hashed again.
+    pub fn take(
+        project_dir: &Path,
+        sources: &[PathBuf],
+        settings: String,
+        previous: Option<&Fingerprints>,
+    ) -> anyhow::Result<Self> {
+        let mut files = BTreeMap::new();
+        for source in sources {
+            let key = source_key(project_dir, source);
+            let modified = std::fs::metadata(source)?
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64);
+            let unchanged = previous
+                .and_then(|p| p.files.get(&key))
+                .filter(|f| f.modified == modified);
+            let sha256 = match unchanged {
+                Some(fingerprint) => fingerprint.sha256.clone(),
+                None => hash::sha256_hex(&std::fs::read(source)?),
+            };
+            files.insert(key, Fingerprint { modified, sha256 });
+        }
+        Ok(Self { settings, files })
+    }
+
+    /// Works out which sources need compiling since the compilation `previous` was taken of.
+    ///
+    /// Besides the sources that changed, any source in the same package as one of them or
+    /// importing from its package is compiled again, as is anything depending on those in turn.
+    pub fn recompile(
+        &self,
+        project_dir: &Path,
+        previous: Option<&Fingerprints>,
+    ) -> anyhow::Result<Recompile> {
+        let Some(previous) = previous else {
+            return Ok(Recompile::All);
+        };
+        // the classes of a removed source would be left behind in the output
+        if previous.settings != self.settings
+            || previous.files.keys().any(|key| !self.files.contains_key(key))
+        {
+            return Ok(Recompile::All);
+        }
+
+        let changed: BTreeSet<&String> = self
+            .files
+            .iter()
+            .filter(|(key, f)| previous.files.get(*key).is_none_or(|p| p.sha256 != f.sha256))
+            .map(|(key, _)| key)
+            .collect();
+        if changed.is_empty() {
+            return Ok(Recompile::Nothing);
+        }
+
+        let mut headers = BTreeMap::new();
+        for key in self.files.keys() {
+            let source = std::fs::read_to_string(project_dir.join(key))?;
+            headers.insert(key, SourceHeader::parse(&source));
+        }
+
+        let mut stale = changed;
+        let mut packages: Vec<String> = stale.iter().map(|k| headers[k].package.clone()).collect();
+        let mut visited = BTreeSet::new();
+        while let Some(package) = packages.pop() {
+            if !visited.insert(package.clone()) {
+                continue;
+            }
+            for (key, header) in &headers {
+                if !stale.contains(key) && header.depends_on(&package) {
+                    stale.insert(key);
+                    packages.push(header.package.clone());
+                }
+            }
+        }
+
+        Ok(Recompile::Only(stale.into_iter().map(|key| project_dir.join(key)).collect()))
+    }
+}
+
+/// Deletes the class files in `out_dir` that were compiled from `stale`, so the classes that
+/// were removed from those sources or renamed aren't left behind once they are compiled again.
+///
+/// A class file records the name of the source it came from, which along with the package it
+/// is in picks out the source among `sources`. Returns `false`, deleting nothing, if a source in
+/// `stale` can't be told apart that way from one that isn't, and so everything has to be
+/// compiled again.
+pub fn remove_outputs(
+    out_dir: &Path,
+    sources: &[PathBuf],
+    stale: &[PathBuf],
+) -> anyhow::Result<bool> {
+    let origin = |source: &Path| -> anyhow::Result<(String, String)> {
+        let header = SourceHeader::parse(&std::fs::read_to_string(source)?);
+        let name = source.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        Ok((header.package.replace('.', "/"), name))
+    };
+    let stale_origins = stale
+        .iter()
+        .map(|source| origin(source))
+        .collect::<anyhow::Result<BTreeSet<_>>>()?;
+    for source in sources.iter().filter(|source| !stale.contains(source)) {
+        if stale_origins.contains(&origin(source)?) {
+            return Ok(false);
+        }
+    }
+
+    let mut classes = Vec::new();
+    collect_classes(out_dir, &mut classes)?;
+    for class in classes {
+        let package = class
+            .parent()
+            .and_then(|dir| dir.strip_prefix(out_dir).ok())
+            .map(|dir| source_key(Path::new(""), dir))
+            .unwrap_or_default();
+        // a class file that can't be read is left for the compiler to overwrite
+        let Some(source_file) = std::fs::read(&class).ok().and_then(|b| source_file(&b)) else {
+            continue;
+        };
+        if stale_origins.contains(&(package, source_file)) {
+            std::fs::remove_file(&class)?;
+        }
+    }
+    Ok(true)
+}
+
+fn collect_classes(dir: &Path, classes: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_classes(&path, classes)?;
+        } else if path.extension().is_some_and(|ext| ext == "class") {
+            classes.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the `SourceFile` attribute of a class file, the name of the source it was compiled
+/// from, such as `Main.kt`.
+fn source_file(class: &[u8]) -> Option<String> {
+    let mut reader = ClassReader { bytes: class, at: 8 };
+    if class.get(..4)? != [0xCA, 0xFE, 0xBA, 0xBE] {
+        return None;
+    }
+
+    let count = reader.u16()? as usize;
+    let mut constants: Vec<Option<&[u8]>> = vec![None; count];
+    let mut index = 1;
+    while index < count {
+        match reader.u8()? {
+            1 => {
+                let len = reader.u16()? as usize;
+                constants[index] = Some(reader.take(len)?);
+            }
+            7 | 8 | 16 | 19 | 20 => reader.skip(2)?,
+            15 => reader.skip(3)?,
+            3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => reader.skip(4)?,
+            // longs and doubles take up two entries
+            5 | 6 => {
+                reader.skip(8)?;
+                index += 1;
+            }
+            _ => return None,
+        }
+        index += 1;
+    }
+    let utf8 = |index: u16| constants.get(index as usize).copied().flatten();
+
+    // access flags, this class and its superclass
+    reader.skip(6)?;
+    let interfaces = reader.u16()? as usize;
+    reader.skip(interfaces * 2)?;
+    // fields, then methods
+    for _ in 0..2 {
+        for _ in 0..reader.u16()? {
+            reader.skip(6)?;
+            reader.skip_attributes()?;
+        }
+    }
+    for _ in 0..reader.u16()? {
+        let name = reader.u16()?;
+        let len = reader.u32()? as usize;
+        let value = reader.take(len)?;
+        if utf8(name) == Some(b"SourceFile".as_slice()) && len == 2 {
+            let source = utf8(u16::from_be_bytes([value[0], value[1]]))?;
+            return Some(String::from_utf8_lossy(source).into_owned());
+        }
+    }
+    None
+}
+
+/// Reads the big-endian values a class file is made of.
+struct ClassReader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> ClassReader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let taken = self.bytes.get(self.at..self.at.checked_add(len)?)?;
+        self.at += len;
+        Some(taken)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn skip_attributes(&mut self) -> Option<()> {
+        for _ in 0..self.u16()? {
+            self.skip(2)?;
+            let len = self.u32()? as usize;
+            self.skip(len)?;
+        }
+        Some(())
+    }
+}
+
+/// Hashes everything besides the sources that a JVM compilation's output depends on: the
+/// compiler, its settings and what is on the classpath.
+pub(crate) fn settings(
+    kotlinc: &Path,
+    options: &CompilerOptions,
+    classpath: &[PathBuf],
+) -> anyhow::Result<String> {
+    let mut manifest = format!("{}\n{:?}\n", kotlinc.display(), options);
+    for entry in classpath {
+        manifest.push_str(&format!("{} {}\n", entry.display(), last_modified(entry)?));
+    }
+    Ok(hash::sha256_hex(manifest.as_bytes()))
+}
+
+/// The latest modification time of `path` or anything under it, in milliseconds since the Unix
+/// epoch, or 0 if it doesn't exist.
+fn last_modified(path: &Path) -> std::io::Result<u64> {
+    let Ok(metadata) = std::fs::metadata(path) else {
+        return Ok(0);
+    };
+    let mut latest = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64);
+    if metadata.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            latest = latest.max(last_modified(&entry?.path())?);
+        }
+    }
+    Ok(latest)
+}
+
+/// The path of `source` in the project, with forward slashes on every platform.
+fn source_key(project_dir: &Path, source: &Path) -> String {
+    let relative = source.strip_prefix(project_dir).unwrap_or(source);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The package and imports at the top of a Kotlin source file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceHeader {
+    pub package: String,
+    pub imports: Vec<String>,
+}
+
+impl SourceHeader {
+    pub fn parse(source: &str) -> Self {
+        let mut header = SourceHeader::default();
+        let mut in_comment = false;
+        for line in source.lines() {
+            let line = line.trim();
+            if in_comment {
+                in_comment = !line.contains("*/");
+                continue;
+            }
+            if line.starts_with("/*") {
+                in_comment = !line.contains("*/");
+                continue;
+            }
+            if line.is_empty() || line.starts_with("//") || line.starts_with("@file:") {
+                continue;
+            }
+
+            if let Some(package) = line.strip_prefix("package ") {
+                header.package = clean_name(package);
+            } else if let Some(import) = line.strip_prefix("import ") {
+                // `import a.b.C as D` still depends on `a.b.C`
+                let import = import.split(" as ").next().unwrap_or(import);
+                header.imports.push(clean_name(import));
+            } else {
+                // imports can only come before the first declaration
+                break;
+            }
+        }
+        header
+    }
+
+    /// Whether a source with this header can use declarations from `package`.
+    pub fn depends_on(&self, package: &str) -> bool {
+        self.package == package
+            || (!package.is_empty()
+                && self.imports.iter().any(|import| {
+                    import
+                        .strip_prefix(package)
+                        .is_some_and(|rest| rest.starts_with('.'))
+                }))
+    }
+}
+
+fn clean_name(name: &str) -> String {
+    name.split("//")
+        .next()
+        .unwrap_or(name)
+        .trim()
+        .trim_end_matches(';')
+        .replace('`', "")
+}
@@ -0,0 +1,180 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::cancel;
+
+/// Entries that are always left out of a fat JAR: each dependency's manifest would clash with
+/// the one written for the fat JAR, and a module descriptor would describe only one of the
+/// merged JARs.
+const ALWAYS_EXCLUDED: [&str; 3] = [
+    "META-INF/MANIFEST.MF",
+    "module-info.class",
+    "META-INF/versions/*/module-info.class",
+];
+
+/// Packs the contents of each of `dirs` into a JAR at `output`, keeping the paths within them.
+///
+/// With a `main_class`, the manifest names it as `Main-Class` so the JAR can be run with
+/// `java -jar`.
+pub(crate) fn create(
+    jar_tool: &Path,
+    output: &Path,
+    dirs: &[PathBuf],
+    main_class: Option<&str>,
+) -> anyhow::Result<()> {
+    let manifest = main_class
+        .map(|main_class| write_manifest(output, main_class))
+        .transpose()?;
+
+    let mut cmd = Command::new(jar_tool);
+    match &manifest {
+        Some(manifest) => cmd.arg("cfm").arg(output).arg(manifest),
+        None => cmd.arg("cf").arg(output),
+    };
+    for dir in dirs {
+        cmd.arg("-C").arg(dir).arg(".");
+    }
+    let result = run(cmd, &format!("failed to create {}", output.display()));
+
+    if let Some(manifest) = manifest {
+        let _ = std::fs::remove_file(manifest);
+    }
+    result
+}
+
+/// Merges the class directories and JARs in `classpath` into a single JAR at `output` whose
+/// manifest runs `main_class`.
+///
+/// Entries are merged in order, so a later one replaces an earlier one at the same path. Any
+/// entry matching one of `exclude_patterns` is left out; see [`glob_matches`] for the syntax.
+pub(crate) fn fat_jar(
+    jar_tool: &Path,
+    output: &Path,
+    classpath: &[PathBuf],
+    main_class: &str,
+    exclude_patterns: &[String],
+) -> anyhow::Result<()> {
+    let staging = output.with_extension("contents");
+    if staging.exists() {
+        std::fs::remove_dir_all(&staging)?;
+    }
+    std::fs::create_dir_all(&staging)?;
+
+    for entry in classpath {
+        if entry.is_dir() {
+            copy_dir(entry, &staging)?;
+        } else {
+            let mut cmd = Command::new(jar_tool);
+            cmd.arg("xf").arg(entry).current_dir(&staging);
+            run(cmd, &format!("failed to extract {}", entry.display()))?;
+        }
+    }
+
+    let mut files = Vec::new();
+    visit(&staging, &mut files)?;
+    for file in files {
+        let Ok(relative) = file.strip_prefix(&staging) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let excluded = ALWAYS_EXCLUDED
+            .iter()
+            .copied()
+            .chain(exclude_patterns.iter().map(String::as_str))
+            .any(|pattern| glob_matches(pattern, &relative));
+        if excluded {
+            std::fs::remove_file(&file)?;
+        }
+    }
+
+    let manifest = write_manifest(output, main_class)?;
+    let mut cmd = Command::new(jar_tool);
+    cmd.arg("cfm")
+        .arg(output)
+        .arg(&manifest)
+        .arg("-C")
+        .arg(&staging)
+        .arg(".");
+    let result = run(cmd, &format!("failed to create {}", output.display()));
+
+    let _ = std::fs::remove_file(&manifest);
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+/// Writes the manifest for the JAR at `output` next to it, returning its path.
+fn write_manifest(output: &Path, main_class: &str) -> std::io::Result<PathBuf> {
+    let manifest = output.with_extension("mf");
+    std::fs::write(
+        &manifest,
+        format!("Manifest-Version: 1.0\nMain-Class: {}\n", main_class),
+    )?;
+    Ok(manifest)
+}
+
+/// Whether the `/`-separated `path` matches `pattern`.
+///
+/// `*` matches anything but `/`, `**` matches anything including `/`, and `?` matches a single
+/// character other than `/`.
+pub fn glob_matches(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[char], path: &[char]) -> bool {
+        match pattern {
+            [] => path.is_empty(),
+            ['*', '*', rest @ ..] => (0..=path.len()).any(|i| matches(rest, &path[i..])),
+            ['*', rest @ ..] => {
+                let segment = path.iter().position(|&c| c == '/').unwrap_or(path.len());
+                (0..=segment).any(|i| matches(rest, &path[i..]))
+            }
+            ['?', rest @ ..] => {
+                path.first().is_some_and(|&c| c != '/') && matches(rest, &path[1..])
+            }
+            [c, rest @ ..] => path.first() == Some(c) && matches(rest, &path[1..]),
+        }
+    }
+
+    let pattern: Vec<char> = pattern.chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    matches(&pattern, &path)
+}
+
+/// Copies everything under `from` into `to`, replacing files that already exist.
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(from)? {
+        let path = entry?.path();
+        let target = to.join(path.file_name().unwrap_or_default());
+        if path.is_dir() {
+            std::fs::create_dir_all(&target)?;
+            copy_dir(&path, &target)?;
+        } else {
+            std::fs::copy(&path, &target)?;
+        }
+    }
+    Ok(())
+}
+
+fn visit(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            visit(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Runs a `jar` command, failing with `context` and its stderr if it exits unsuccessfully.
+fn run(mut cmd: Command, context: &str) -> anyhow::Result<()> {
+    let result = cancel::output(&mut cmd)?;
+    if !result.status.success() {
+        anyhow::bail!(
+            "{}: {}",
+            context,
+            String::from_utf8_lossy(&result.stderr).trim()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    build::{self, CompilerOptions, GENERATED_SOURCES_DIR},
+    cancel,
+    maven::{self, Coordinate},
+    resolver,
+    toml::FygToml,
+    toolchain,
+};
+
+/// The ID KAPT registers its compiler plugin options under.
+const PLUGIN_ID: &str = "org.jetbrains.kotlin.kapt3";
+
+/// The processor option telling processors that write Kotlin where to put it.
+const KOTLIN_GENERATED_OPTION: &str = "kapt.kotlin.generated";
+
+/// Runs the processors in `build.kapt` over `sources`, returning the Kotlin sources they
+/// generated so they can be compiled along with the project's own.
+///
+/// KAPT generates Java stubs of the sources for the processors to read. Everything the
+/// processors generate goes into `build/generated/kapt`, apart from resources and classes,
+/// which go straight into `build/classes`. Generated Java is compiled afterwards by
+/// [`compile_java`].
+pub(crate) fn process(
+    kotlinc: &Path,
+    fyg_toml: &FygToml,
+    project_dir: &Path,
+    sources: &[PathBuf],
+    classpath: &[PathBuf],
+    options: &CompilerOptions,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let build = fyg_toml.build.as_ref();
+    let Some(config) = build.and_then(|b| b.kapt.as_ref()) else {
+        return Ok(Vec::new());
+    };
+
+    let generated = project_dir.join(GENERATED_SOURCES_DIR).join("kapt");
+    let work_dir = project_dir.join("build").join("kapt");
+    for dir in [&generated, &work_dir] {
+        if dir.exists() {
+            std::fs::remove_dir_all(dir)?;
+        }
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut processor_options = vec![(
+        KOTLIN_GENERATED_OPTION.to_string(),
+        generated.display().to_string(),
+    )];
+    let mut arguments: Vec<_> = config.arguments.clone().into_iter().flatten().collect();
+    arguments.sort();
+    processor_options.extend(arguments);
+
+    let mut plugin_options = vec![
+        ("sources", generated.display().to_string()),
+        (
+            "classes",
+            project_dir
+                .join("build")
+                .join("classes")
+                .display()
+                .to_string(),
+        ),
+        ("stubs", work_dir.join("stubs").display().to_string()),
+        ("aptMode", "stubsAndApt".to_string()),
+        (
+            "correctErrorTypes",
+            config.correct_error_types.unwrap_or(false).to_string(),
+        ),
+        ("apoptions", encode_options(&processor_options)),
+    ];
+    for jar in resolver::processor_jars(fyg_toml, &config.processors)? {
+        plugin_options.push(("apclasspath", jar.display().to_string()));
+    }
+
+    let kotlin_version = build.and_then(|b| b.kotlin_version.as_deref());
+    let mut options = options.clone();
+    options.plugins.push(plugin(kotlinc, kotlin_version)?);
+    options.plugin_options.extend(
+        plugin_options
+            .into_iter()
+            .map(|(key, value)| format!("plugin:{}:{}={}", PLUGIN_ID, key, value)),
+    );
+    // in stubsAndApt mode the compiler stops once processing is done, so nothing is written here
+    build::compile_kotlin(kotlinc, sources, classpath, &work_dir, &options)?;
+
+    Ok(build::collect_sources(&generated, "kt")?)
+}
+
+/// Compiles the Java sources KAPT generated into `build/classes`, against the project's
+/// classes and `classpath`.
+pub(crate) fn compile_java(
+    javac: &Path,
+    project_dir: &Path,
+    classpath: &[PathBuf],
+) -> anyhow::Result<()> {
+    let generated = project_dir.join(GENERATED_SOURCES_DIR).join("kapt");
+    let sources = build::collect_sources(&generated, "java")?;
+    if sources.is_empty() {
+        return Ok(());
+    }
+
+    let class_dir = project_dir.join("build").join("classes");
+    let mut full_classpath = vec![class_dir.clone()];
+    full_classpath.extend(classpath.iter().cloned());
+
+    let result = cancel::output(
+        Command::new(javac)
+            .arg("-d")
+            .arg(&class_dir)
+            .arg("-cp")
+            .arg(std::env::join_paths(&full_classpath)?)
+            // the processors have already run
+            .arg("-proc:none")
+            .args(&sources),
+    )?;
+    if !result.status.success() {
+        anyhow::bail!(
+            "failed to compile the Java sources generated by KAPT:\n{}",
+            String::from_utf8_lossy(&result.stderr).trim_end()
+        );
+    }
+    Ok(())
+}
+
+/// Finds the KAPT compiler plugin: the one shipped with `kotlinc` if it has one, otherwise the
+/// one published for `build.kotlin-version`.
+fn plugin(kotlinc: &Path, kotlin_version: Option<&str>) -> anyhow::Result<PathBuf> {
+    if let Some(plugin) = toolchain::kotlin_annotation_processing(kotlinc) {
+        return Ok(plugin);
+    }
+    match kotlin_version {
+        Some(version) => maven::fetch_jar(&Coordinate::new(
+            "org.jetbrains.kotlin",
+            "kotlin-annotation-processing",
+            version,
+        )),
+        None => anyhow::bail!(
+            "could not find kotlin-annotation-processing.jar next to kotlinc, set \
+             build.kotlin-version so fyg can download it"
+        ),
+    }
+}
+
+/// Encodes processor options the way KAPT's `apoptions` expects: a count followed by each key
+/// and value, written with Java's `ObjectOutputStream` and then Base64-encoded.
+pub(crate) fn encode_options(options: &[(String, String)]) -> String {
+    let mut data = Vec::new();
+    data.extend((options.len() as i32).to_be_bytes());
+    for (key, value) in options {
+        for string in [key, value] {
+            let bytes = modified_utf8(string);
+            data.extend((bytes.len() as u16).to_be_bytes());
+            data.extend(bytes);
+        }
+    }
+
+    // the stream magic and version, then the data in blocks of at most 1024 bytes
+    let mut stream = vec![0xac, 0xed, 0x00, 0x05];
+    for block in data.chunks(1024) {
+        if block.len() <= 0xff {
+            stream.push(0x77);
+            stream.push(block.len() as u8);
+        } else {
+            stream.push(0x7a);
+            stream.extend((block.len() as i32).to_be_bytes());
+        }
+        stream.extend(block);
+    }
+    base64(&stream)
+}
+
+/// Encodes `s` in the modified UTF-8 of Java's `DataOutput::writeUTF`, which writes NUL as two
+/// bytes and characters outside the Basic Multilingual Plane as two surrogates.
+fn modified_utf8(s: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for unit in s.encode_utf16() {
+        match unit {
+            0x0001..=0x007f => bytes.push(unit as u8),
+            0x0000 | 0x0080..=0x07ff => {
+                bytes.push(0xc0 | (unit >> 6) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+            _ => {
+                bytes.push(0xe0 | (unit >> 12) as u8);
+                bytes.push(0x80 | ((unit >> 6) & 0x3f) as u8);
+                bytes.push(0x80 | (unit & 0x3f) as u8);
+            }
+        }
+    }
+    bytes
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
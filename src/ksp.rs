@@ -0,0 +1,85 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    build::{self, CompilerOptions, GENERATED_SOURCES_DIR},
+    config::KspConfig,
+    maven::{self, Coordinate},
+};
+
+/// The version of KSP run as a `kotlinc` plugin. Its first half is the Kotlin version it was
+/// built against, which processing works best with.
+const KSP_VERSION: &str = "1.9.22-1.0.17";
+
+/// The ID KSP registers its compiler plugin options under.
+const PLUGIN_ID: &str = "com.google.devtools.ksp.symbol-processing";
+
+/// The KSP compiler plugin and the API processors are compiled against.
+fn plugins() -> [Coordinate; 2] {
+    let ksp = |artifact| Coordinate::new("com.google.devtools.ksp", artifact, KSP_VERSION);
+    [
+        ksp("symbol-processing-cmdline"),
+        ksp("symbol-processing-api"),
+    ]
+}
+
+/// Runs the processors in `processor_jars` over `sources`, returning the Kotlin sources they
+/// generated so they can be compiled along with the project's own.
+///
+/// Sources are generated into `build/generated/ksp`, and any resources or classes the
+/// processors write go straight into `build/classes`. Unless `build.ksp.incremental` is set, the
+/// output of the previous run is deleted first.
+pub(crate) fn process(
+    kotlinc: &Path,
+    project_dir: &Path,
+    sources: &[PathBuf],
+    classpath: &[PathBuf],
+    config: &KspConfig,
+    processor_jars: &[PathBuf],
+    options: &CompilerOptions,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let generated = project_dir.join(GENERATED_SOURCES_DIR).join("ksp");
+    let class_dir = project_dir.join("build").join("classes");
+    let work_dir = project_dir.join("build").join("ksp");
+    let incremental = config.incremental.unwrap_or(false);
+    if !incremental {
+        for dir in [&generated, &work_dir] {
+            if dir.exists() {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+    }
+    std::fs::create_dir_all(&generated)?;
+    std::fs::create_dir_all(&work_dir)?;
+
+    let apclasspath = std::env::join_paths(processor_jars)?;
+    let mut plugin_options = vec![
+        ("apclasspath", apclasspath.to_string_lossy().into_owned()),
+        ("projectBaseDir", project_dir.display().to_string()),
+        ("kotlinOutputDir", generated.display().to_string()),
+        ("javaOutputDir", generated.display().to_string()),
+        ("resourceOutputDir", class_dir.display().to_string()),
+        ("classOutputDir", class_dir.display().to_string()),
+        ("kspOutputDir", work_dir.display().to_string()),
+        ("cachesDir", work_dir.join("caches").display().to_string()),
+        ("incremental", incremental.to_string()),
+    ];
+    let mut args: Vec<_> = config.args.iter().flatten().collect();
+    args.sort();
+    for (key, value) in args {
+        plugin_options.push(("apoption", format!("{}={}", key, value)));
+    }
+
+    let mut options = options.clone();
+    for plugin in plugins() {
+        options.plugins.push(maven::fetch_jar(&plugin)?);
+    }
+    options.plugin_options.extend(
+        plugin_options
+            .into_iter()
+            .map(|(key, value)| format!("plugin:{}:{}={}", PLUGIN_ID, key, value)),
+    );
+    // KSP stops the compiler once processing is done, so nothing is written here
+    build::compile_kotlin(kotlinc, sources, classpath, &work_dir, &options)?;
+
+    Ok(build::collect_sources(&generated, "kt")?)
+}
@@ -1,11 +1,300 @@
 pub mod toml;
 pub mod config;
+pub mod audit;
+pub mod build;
+pub mod cache;
+mod cancel;
+pub mod coverage;
+pub mod daemon;
+pub mod deps;
+pub mod doc;
+pub mod doctor;
+pub mod env;
+pub mod error;
+pub mod hash;
+mod hooks;
+pub mod http;
+pub mod incremental;
+pub mod jar;
+pub mod kapt;
+pub mod ksp;
+pub mod licenses;
+pub mod lint;
+pub mod lock;
+pub mod maven;
+pub mod migrate;
+#[cfg(feature = "async")]
+mod nonblocking;
+pub mod output;
+pub mod plan;
+pub mod publish;
+pub mod release;
+pub mod resolver;
+pub mod run;
+pub mod sbom;
+pub mod scaffold;
+pub mod testing;
+pub mod toolchain;
+pub mod watch;
+pub mod workspace;
+pub mod xml;
 
-use std::path::Path;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::toml::FygToml;
+use crate::{
+    audit::Vulnerability,
+    build::{
+        BuildError, BuildMode, BuildOptions, CompilerOptions, CompilerWarning,
+        report::{self, BuildRecorder},
+    },
+    cache::{BuildCache, CleanOptions, Cleaned},
+    cancel::CancelFlag,
+    config::{
+        CinteropConfig, DependenciesConfig, DependencyValue, JsModuleKind, MinGwTarget, UserConfig,
+    },
+    deps::{AddOutcome, Outdated, RemoveOutcome, VersionChange},
+    error::FygError,
+    incremental::{Fingerprints, Recompile},
+    licenses::DependencyLicense,
+    lint::LintFinding,
+    lock::{FygLock, LOCK_FILE_NAME},
+    plan::PlannedAction,
+    release::{Release, ReleaseError, ReleaseOptions, ReleaseStep},
+    resolver::DependencyGraph,
+    run::RunError,
+    scaffold::{
+        InitOptions,
+        source::{self, SourceTemplate},
+        template, vcs,
+    },
+    testing::{TestFramework, TestOptions, TestSummary},
+    toml::{FygToml, ValidationDiagnostic},
+    workspace::Workspace,
+};
+
+/// The file in `build/` listing the artifacts of the last build.
+const ARTIFACTS_FILE: &str = ".artifacts";
+
+/// What a successful build produced.
+#[derive(Clone, Debug, Default)]
+pub struct BuildResult {
+    /// Every JAR, binary, module and class directory the build wrote.
+    pub artifacts: Vec<PathBuf>,
+    /// The warnings the compilers reported.
+    pub warnings: Vec<CompilerWarning>,
+    /// How long the build took.
+    pub duration: Duration,
+    /// Whether the output was restored from the build cache instead of being compiled.
+    pub cached: bool,
+}
+
+impl BuildResult {
+    /// Adds the artifacts and warnings of a workspace member's build to this one.
+    fn merge(&mut self, member: BuildResult) {
+        self.artifacts.extend(member.artifacts);
+        self.warnings.extend(member.warnings);
+        self.cached &= member.cached;
+    }
+
+    /// Runs the compiler command `cmd` with `run` as a step of the build, telling `listener`
+    /// what it ran and wrote, and keeps the warnings it reported.
+    fn compile(
+        &mut self,
+        listener: &dyn BuildListener,
+        step_name: &str,
+        cmd: Command,
+        run: impl FnOnce(Command) -> Result<String, BuildError>,
+    ) -> anyhow::Result<()> {
+        let output = step(listener, step_name, || {
+            listener.on_compiler_started(&build::command_line(&cmd));
+            Ok(run(cmd)?)
+        })?;
+        for line in output.lines() {
+            listener.on_compiler_output(line);
+        }
+        self.warnings.extend(CompilerWarning::parse(&output));
+        Ok(())
+    }
+}
+
+/// Receives the progress of a build, to show it in a UI of its own. Set one with
+/// [`BuildOptions::listener`].
+///
+/// A build is made up of steps, such as resolving dependencies or compiling a target, which
+/// are named after what they do. Members of a workspace built in parallel report their steps
+/// from several threads at once. Every method does nothing by default.
+pub trait BuildListener: Send + Sync {
+    fn on_step_started(&self, _step: &str) {}
+
+    fn on_step_finished(&self, _step: &str, _duration: Duration) {}
+
+    /// Called with the command line of each compiler before it runs.
+    fn on_compiler_started(&self, _command: &str) {}
+
+    /// Called with each line a compiler wrote once it has finished.
+    fn on_compiler_output(&self, _line: &str) {}
+
+    /// Called once if the build fails, before the error is returned.
+    fn on_error(&self, _err: &FygError) {}
+}
+
+impl std::fmt::Debug for dyn BuildListener + Send + Sync {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BuildListener")
+    }
+}
+
+/// Prints each step of a build to stdout as it finishes, as `fyg build` does.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConsoleBuildListener;
+
+impl BuildListener for ConsoleBuildListener {
+    fn on_step_finished(&self, step: &str, duration: Duration) {
+        println!(
+            "   {} {} {}",
+            console::style("✓").green().bold(),
+            step,
+            console::style(format!("({:.1}s)", duration.as_secs_f64())).dim()
+        );
+    }
+
+    fn on_error(&self, _err: &FygError) {
+        println!("   {} Build failed", console::style("✗").red().bold());
+    }
+}
+
+/// Ignores the progress of a build, which is what builds without a listener do.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SilentBuildListener;
+
+impl BuildListener for SilentBuildListener {}
+
+thread_local! {
+    static PROCESS_CONFIG: RefCell<Option<ProcessConfig>> = const { RefCell::new(None) };
+}
+
+/// How fyg starts the compilers, tools, tests and programs it runs, such as to build in a
+/// sandbox that mustn't see the environment fyg itself runs in.
+///
+/// It applies to what is started within [`ProcessConfig::scope`] on the same thread or on the
+/// threads a workspace build starts from it, and to builds and test runs given one in
+/// [`BuildOptions::process`] or [`TestOptions::process`].
+/// Compilers don't run on the build daemon under it, as the daemon has a process of its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessConfig {
+    /// The directory subprocesses run in, except those fyg runs in a particular one, such as
+    /// git in the project.
+    pub working_dir: Option<PathBuf>,
+    /// Whether subprocesses inherit fyg's environment. Without it, they only get the variables
+    /// fyg sets for them, such as `JAVA_HOME`, and `extra_env`.
+    pub env_passthrough: bool,
+    /// Variables set for every subprocess, over any others.
+    pub extra_env: HashMap<String, String>,
+    /// How long a compiler, tool or test run may take before it is killed and fails.
+    pub timeout: Option<Duration>,
+    /// Starts subprocesses with nothing to read on stdin, rather than fyg's own.
+    pub stdin_null: bool,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        Self {
+            working_dir: None,
+            env_passthrough: true,
+            extra_env: HashMap::new(),
+            timeout: None,
+            stdin_null: false,
+        }
+    }
+}
+
+impl ProcessConfig {
+    /// Runs `f` with this config applying to the subprocesses it starts on this thread.
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = PROCESS_CONFIG.with(|current| current.replace(Some(self.clone())));
+        let result = f();
+        PROCESS_CONFIG.with(|current| current.replace(previous));
+        result
+    }
+
+    /// The config of the scope the current thread is in, if any.
+    pub(crate) fn current() -> Option<ProcessConfig> {
+        PROCESS_CONFIG.with(|current| current.borrow().clone())
+    }
+
+    /// Applies the config of the current scope, if any, to `cmd`. Whatever `cmd` sets up for
+    /// stdin afterwards wins over [`ProcessConfig::stdin_null`].
+    pub(crate) fn configure(cmd: &mut Command) -> &mut Command {
+        let Some(config) = Self::current() else {
+            return cmd;
+        };
+        if !config.env_passthrough {
+            let set: Vec<(OsString, Option<OsString>)> = cmd
+                .get_envs()
+                .map(|(key, value)| (key.to_owned(), value.map(ToOwned::to_owned)))
+                .collect();
+            cmd.env_clear();
+            for (key, value) in set {
+                if let Some(value) = value {
+                    cmd.env(key, value);
+                }
+            }
+        }
+        cmd.envs(&config.extra_env);
+        if let Some(dir) = &config.working_dir
+            && cmd.get_current_dir().is_none()
+        {
+            cmd.current_dir(dir);
+        }
+        if config.stdin_null {
+            cmd.stdin(Stdio::null());
+        }
+        cmd
+    }
+}
+
+/// `config_file_path`, made absolute if the current [`ProcessConfig`] has a `working_dir`, so the
+/// paths into the project that subprocesses are given still lead there.
+fn process_config_path(config_file_path: &Path) -> std::io::Result<PathBuf> {
+    match ProcessConfig::current().is_some_and(|config| config.working_dir.is_some()) {
+        true => std::path::absolute(config_file_path),
+        false => Ok(config_file_path.to_path_buf()),
+    }
+}
+
+/// Runs `f` in the scope of `config` if there is one, or in the current scope otherwise.
+fn process_scope<T>(config: Option<&ProcessConfig>, f: impl FnOnce() -> T) -> T {
+    match config {
+        Some(config) => config.scope(f),
+        None => f(),
+    }
+}
+
+/// Runs one step of a build, telling `listener` when it starts and when it finishes.
+fn step<T>(
+    listener: &dyn BuildListener,
+    name: &str,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    listener.on_step_started(name);
+    let started = Instant::now();
+    let value = f()?;
+    listener.on_step_finished(name, started.elapsed());
+    Ok(value)
+}
 
 /// Fyg is the build tool used for building JVM based apps. It serves as a simpler replacement 
 /// to other build tools such as Gradle and Maven. 
@@ -16,21 +305,60 @@ impl Fyg {
     /// 
     /// This will create a new directory with the project name inside the given folder path,
     /// then initialise it with the fyg project structure.
-    pub fn new<P: AsRef<Path>>(folder_path: P, fyg_toml: FygToml) -> anyhow::Result<()> {
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<P: AsRef<Path>>(
+        folder_path: P,
+        fyg_toml: FygToml,
+        options: &InitOptions,
+    ) -> Result<(), FygError> {
         let new_path = folder_path.as_ref().join(&fyg_toml.project.name);
         std::fs::create_dir_all(&new_path)?;
-        Self::init(&new_path, fyg_toml)?;
+        Self::init(&new_path, fyg_toml, options)?;
+        Ok(())
+    }
+
+    /// Creates a new folder containing a workspace root `fyg.toml` with no members.
+    pub fn new_workspace<P: AsRef<Path>>(
+        folder_path: P,
+        name: &str,
+        options: &InitOptions,
+    ) -> Result<(), FygError> {
+        let new_path = folder_path.as_ref().join(name);
+        std::fs::create_dir_all(&new_path)?;
+        let fyg_toml = FygToml::new_workspace();
+        fyg_toml.write_to_file(new_path.join("fyg.toml"))?;
+        scaffold::write_extras(&new_path, &fyg_toml, options)?;
+        vcs::init_repository(&new_path, options.vcs)?;
         Ok(())
     }
 
     /// Initialises an existing folder to be ready for Fyg compilation. 
     /// 
     /// This requires you to pass in the [`Path`] of the folder and a [`FygToml`] configuration.
-    /// It will populate the folder with the necessary project structure.
-    pub fn init<P: AsRef<Path>>(folder_path: P, fyg_toml: FygToml) -> anyhow::Result<()> {
+    /// It will populate the folder with the necessary project structure, a `.gitignore` and an
+    /// `.editorconfig` unless [`InitOptions::gitignore`] and [`InitOptions::editorconfig`] are
+    /// turned off, and a CI configuration if [`InitOptions::ci`] names a provider. With
+    /// [`InitOptions::template`], the `fyg.toml` and sources come from the template, filled in
+    /// with the name, group and version of `fyg_toml`. With [`InitOptions::vcs`], the folder is
+    /// made a repository and all of it is committed as the initial commit.
+    pub fn init<P: AsRef<Path>>(
+        folder_path: P,
+        fyg_toml: FygToml,
+        options: &InitOptions,
+    ) -> Result<(), FygError> {
         let path = folder_path.as_ref().to_path_buf();
 
-        fyg_toml.write_to_file(path.join("fyg.toml"))?;
+        let fyg_toml = match options.template {
+            Some(template) => {
+                template::write_template(&path, template, &fyg_toml)?;
+                FygToml::from_file_uninterpolated(path.join("fyg.toml"))?
+            }
+            None => {
+                fyg_toml.write_to_file(path.join("fyg.toml"))?;
+                fyg_toml
+            }
+        };
+        scaffold::write_extras(&path, &fyg_toml, options)?;
 
         let src_path = path
             .join("src")
@@ -38,87 +366,5985 @@ impl Fyg {
             .join(fyg_toml.project.group.replace('.', "/"));
         std::fs::create_dir_all(&src_path)?;
 
+        vcs::init_repository(&path, options.vcs)?;
         Ok(())
     }
 
-    pub fn build(_config_file_path: impl AsRef<Path>) -> anyhow::Result<()> {
-        todo!("Not implemented yet :(")
+    /// Generates a Kotlin source file from `template` in the project described by the
+    /// `fyg.toml` at the given path, returning the path of the new file.
+    ///
+    /// The file is declared in the `project.group` package and written to its directory under
+    /// `src/kotlin/`, unless `output` gives another path relative to the project.
+    pub fn scaffold(
+        config_file_path: impl AsRef<Path>,
+        template: &SourceTemplate,
+        output: Option<&Path>,
+    ) -> Result<PathBuf, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+        if fyg_toml.workspace.is_some() {
+            return Err(anyhow::anyhow!(
+                "a workspace root has no sources, run fyg scaffold in a member instead"
+            )
+            .into());
+        }
+        Ok(source::write_source(project_dir(config_file_path), &fyg_toml, template, output)?)
     }
-}
 
-/// The type of project that will be exported when the project is built. 
-/// 
-/// This is shown at https://kotlinlang.org/docs/multiplatform/multiplatform-build-native-binaries.html#declare-binaries
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum FygBinaryType {
-    /// Factory method: `executable`
-    Executable,
+    /// Compiles the project described by the `fyg.toml` at the given path.
+    ///
+    /// All `.kt` files under `src/kotlin/` are passed to `kotlinc`, and the resulting class files
+    /// are written to `build/classes/`. If the compiler fails, a [`BuildError`] is returned
+    /// containing its output, otherwise a [`BuildResult`] listing what was produced.
+    pub fn build(config_file_path: impl AsRef<Path>) -> Result<BuildResult, FygError> {
+        Self::build_with_options(config_file_path, &BuildOptions::default())
+    }
 
-    /// Factory method: `test`
-    Test,
+    /// Compiles the project like [`Fyg::build`], with the given [`BuildOptions`].
+    ///
+    /// Dependencies are taken from `fyg.lock` if it is up to date with `fyg.toml`. Otherwise they
+    /// are resolved from the repositories and the lock file is rewritten, unless
+    /// [`BuildOptions::frozen`] is set, in which case the build fails. With
+    /// [`BuildOptions::offline`], nothing is downloaded: `fyg.lock` has to exist, and every
+    /// artifact has to be in `~/.fyg/cache/` or the local Maven repository.
+    ///
+    /// If the `fyg.toml` is a workspace root, every member is built in dependency order, or only
+    /// [`BuildOptions::module`] and the members it depends on if one is given.
+    ///
+    /// With [`BuildOptions::report`] or `build.generate-report`, an HTML report of the build is
+    /// written to [`build::report::REPORT_DIR`], whether or not the build succeeds.
+    pub fn build_with_options(
+        config_file_path: impl AsRef<Path>,
+        options: &BuildOptions,
+    ) -> Result<BuildResult, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let listener = options.listener.as_deref().unwrap_or(&SilentBuildListener);
+        let fyg_toml = FygToml::from_file(config_file_path).ok();
+        let reported = options.report
+            || fyg_toml
+                .as_ref()
+                .and_then(|t| t.build.as_ref())
+                .and_then(|b| b.generate_report)
+                .unwrap_or(false);
+        if !reported {
+            let result = Self::build_listened(config_file_path, options, listener);
+            if let Err(e) = &result {
+                listener.on_error(e);
+            }
+            return result;
+        }
 
-    /// Factory method: `sharedLib`
-    SharedLib,
+        let recorder = BuildRecorder::new(listener);
+        let result = Self::build_listened(config_file_path, options, &recorder);
+        if let Err(e) = &result {
+            recorder.on_error(e);
+        }
+        let report = report_sources(config_file_path).and_then(|sources| {
+            let name = fyg_toml.as_ref().map_or("", |t| t.project.name.as_str());
+            let project_dir = project_dir(config_file_path);
+            report::write_html(&recorder, result.as_ref(), &sources, name, project_dir)
+        });
+        // a failed build is reported as such, even if the report couldn't be written
+        let result = result?;
+        report?;
+        Ok(result)
+    }
 
-    /// Factory method: `staticLib`
-    StaticLib,
+    /// Works out what [`Fyg::build_with_options`] would do with the same options, without
+    /// writing any files or downloading anything.
+    ///
+    /// The compiler command lines are given for the JVM, JS and Wasm targets, compiling every
+    /// source file, but leave out any sources KSP or KAPT would generate. JARs are only listed
+    /// for download if `fyg.lock` is up to date, since resolving the dependencies is what picks
+    /// them otherwise.
+    pub fn plan_build(
+        config_file_path: impl AsRef<Path>,
+        options: &BuildOptions,
+    ) -> Result<Vec<PlannedAction>, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = FygToml::from_file(config_file_path)?;
+        let root = match (&fyg_toml.workspace, &options.module) {
+            (Some(_), _) => Some(config_file_path.to_path_buf()),
+            (None, Some(_)) => Some(
+                Workspace::find_root(project_dir(config_file_path)).ok_or_else(|| {
+                    anyhow::anyhow!("--module can only be used inside a workspace")
+                })?,
+            ),
+            (None, None) => None,
+        };
+        let Some(root) = root else {
+            return Ok(Self::plan_project(config_file_path, options)?);
+        };
 
-    /// Factory method: `framework`
-    Framework,
-}
+        let workspace = Workspace::load(root)?;
+        let only = match &options.module {
+            Some(name) => Some(
+                workspace
+                    .member(name)
+                    .ok_or_else(|| anyhow::anyhow!("no workspace member named '{}'", name))?,
+            ),
+            None => None,
+        };
+        let mut actions = Vec::new();
+        for member in workspace.build_order(only)? {
+            let name = format!("build {}", member.fyg_toml.project.name);
+            actions.push(PlannedAction::Step(name));
+            actions.extend(Self::plan_project(&member.config_path, options)?);
+        }
+        Ok(actions)
+    }
 
-impl std::fmt::Display for FygBinaryType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let s = match self {
-            FygBinaryType::Executable => "executable",
-            FygBinaryType::Test => "test",
-            FygBinaryType::SharedLib => "sharedLib",
-            FygBinaryType::StaticLib => "staticLib",
-            FygBinaryType::Framework => "framework",
+    /// Works out what [`Fyg::build_project`] would do, for [`Fyg::plan_build`].
+    fn plan_project(
+        config_file_path: &Path,
+        options: &BuildOptions,
+    ) -> anyhow::Result<Vec<PlannedAction>> {
+        let fyg_toml = Self::load(config_file_path)?;
+        let project_dir = project_dir(config_file_path);
+
+        let src_dir = project_dir.join("src").join("kotlin");
+        let sources = build::collect_sources(&src_dir, "kt")?;
+        if sources.is_empty() {
+            return Err(BuildError::NoSources(src_dir).into());
+        }
+
+        let mut actions = Vec::new();
+        let hooks = fyg_toml.hooks.clone().unwrap_or_default();
+        if hooks.pre_build.is_some() {
+            actions.push(PlannedAction::Step("run pre-build hooks".to_string()));
+        }
+        let lock_path = project_dir.join(LOCK_FILE_NAME);
+        let lock = match lock_path.exists() {
+            true => Some(FygLock::from_file(&lock_path)?).filter(|l| l.is_up_to_date(&fyg_toml)),
+            false => None,
         };
-        write!(f, "{}", s)
+        match &lock {
+            Some(_) => actions.push(PlannedAction::UseLock(lock_path)),
+            // without an up to date lock, this fails the way the build would
+            None if options.frozen || options.offline || http::is_offline() => {
+                Self::lock(project_dir, &fyg_toml, true)?;
+            }
+            None => actions.push(PlannedAction::ResolveDependencies(lock_path)),
+        }
+
+        let jvm = fyg_toml.targets.as_ref().and_then(|t| t.jvm.as_ref());
+        if jvm.is_none_or(|jvm| jvm.enabled) {
+            let build = fyg_toml.build.as_ref();
+            let kotlinc = match build.and_then(|b| b.kotlin_version.as_deref()) {
+                Some(version) => match toolchain::installed_kotlinc(version) {
+                    Some(kotlinc) => kotlinc,
+                    None => {
+                        actions.push(PlannedAction::Download {
+                            what: format!("Kotlin {}", version),
+                            url: toolchain::kotlin_release_url(version),
+                        });
+                        toolchain::toolchains_dir()?
+                            .join(format!("kotlin-{}", version))
+                            .join("kotlinc")
+                            .join("bin")
+                            .join("kotlinc")
+                    }
+                },
+                None => kotlinc(&fyg_toml)?,
+            };
+            let compiler_options = compiler_options(&fyg_toml, options.mode)?;
+
+            let mut classpath = build::classpath(&fyg_toml, project_dir);
+            let locked = lock.iter().flat_map(|l| &l.artifacts);
+            for locked in locked.filter(|a| ["common", "jvm"].contains(&a.scope.as_str())) {
+                let coordinate = locked.coordinate();
+                let relative = coordinate.repository_path("jar");
+                let jar = toolchain::cache_dir()?.join(&relative);
+                if !jar.is_file() {
+                    actions.push(PlannedAction::Download {
+                        what: coordinate.to_string(),
+                        url: format!("{}/{}", locked.repository.trim_end_matches('/'), relative),
+                    });
+                }
+                classpath.push(jar);
+            }
+
+            if build.is_some_and(|b| b.ksp.is_some()) {
+                actions.push(PlannedAction::Step("run ksp".to_string()));
+            }
+            let kapt = build.is_some_and(|b| b.kapt.is_some());
+            if kapt {
+                actions.push(PlannedAction::Step("run kapt".to_string()));
+            }
+            let out_dir = project_dir.join("build").join("classes");
+            let cmd = build::kotlinc_command(
+                &kotlinc,
+                &sources,
+                &classpath,
+                &out_dir,
+                &compiler_options,
+            )?;
+            actions.push(PlannedAction::Compile {
+                target: "jvm".to_string(),
+                command: build::command_line(&cmd),
+            });
+            if kapt {
+                actions.push(PlannedAction::Step("compile java".to_string()));
+            }
+            if build.and_then(|b| b.dokka.as_ref()).is_some_and(|d| d.enabled) {
+                actions.push(PlannedAction::Step("generate documentation".to_string()));
+            }
+            if build.and_then(|b| b.fat_jar.as_ref()).is_some_and(|f| f.enabled) {
+                actions.push(PlannedAction::Step("package fat jar".to_string()));
+            }
+        }
+
+        let profile = fyg_toml.profile(options.mode);
+        let js_targets = fyg_toml.targets.as_ref().map(|t| {
+            [
+                ("browser", &t.js_browser, JsModuleKind::Umd),
+                ("node", &t.js_node, JsModuleKind::CommonJs),
+            ]
+        });
+        for (name, target, default_kind) in js_targets.into_iter().flatten() {
+            let Some(target) = target.as_ref().filter(|t| t.enabled) else {
+                continue;
+            };
+            let kotlinc_js = toolchain::kotlinc_js().ok_or(BuildError::JsCompilerNotFound)?;
+            let cmd = build::kotlinc_js_command(
+                &kotlinc_js,
+                &sources,
+                toolchain::kotlin_stdlib_js().as_deref(),
+                &project_dir.join("build").join("js").join(name),
+                &fyg_toml.project.name,
+                target.module_kind.unwrap_or(default_kind),
+                target.source_maps.unwrap_or(profile.keeps_debug_symbols()),
+            );
+            actions.push(PlannedAction::Compile {
+                target: format!("js-{}", name),
+                command: build::command_line(&cmd),
+            });
+        }
+
+        let wasm_targets = fyg_toml
+            .targets
+            .as_ref()
+            .map(|t| [("js", &t.wasm_js), ("wasi", &t.wasm_wasi)]);
+        for (name, target) in wasm_targets.into_iter().flatten() {
+            if !target.as_ref().is_some_and(|t| t.enabled) {
+                continue;
+            }
+            let compiler = toolchain::kotlinc_wasm().ok_or(BuildError::WasmCompilerNotFound)?;
+            let cmd = build::kotlinc_wasm_command(
+                &compiler,
+                &sources,
+                toolchain::kotlin_stdlib_wasm(name).as_deref(),
+                &project_dir.join("build").join("wasm").join(name),
+                &fyg_toml.project.name,
+                name,
+            );
+            actions.push(PlannedAction::Compile {
+                target: format!("wasm-{}", name),
+                command: build::command_line(&cmd),
+            });
+        }
+
+        // the libraries Kotlin/Native links are only known once cinterop has run and the
+        // .klib files are downloaded, so native targets are listed without a command line
+        let targets = fyg_toml.targets.as_ref();
+        let binary = targets
+            .and_then(|t| t.native.as_ref())
+            .and_then(|n| n.binary.as_ref());
+        let xcframework =
+            binary.is_some_and(|b| matches!(b.binary_type, FygBinaryType::XCFramework));
+        let apple_targets = targets.map(|t| t.apple_targets()).unwrap_or_default();
+        let mut bundled = false;
+        for (name, _) in targets.map(|t| t.native_targets()).unwrap_or_default() {
+            if xcframework && !apple_targets.iter().any(|(key, _)| *key == name) {
+                continue;
+            }
+            actions.push(PlannedAction::Step(format!("compile {}", name)));
+            bundled = xcframework;
+        }
+        if bundled {
+            actions.push(PlannedAction::Step("bundle xcframework".to_string()));
+        }
+        if hooks.post_build.is_some() {
+            actions.push(PlannedAction::Step("run post-build hooks".to_string()));
+        }
+        Ok(actions)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::toml::FygToml;
+    /// Builds the project like [`Fyg::build_with_options`], reporting to `listener`.
+    fn build_listened(
+        config_file_path: &Path,
+        options: &BuildOptions,
+        listener: &dyn BuildListener,
+    ) -> Result<BuildResult, FygError> {
+        process_scope(options.process.as_ref(), || {
+            let config_file_path = &process_config_path(config_file_path)?;
+            Self::build_scoped(config_file_path, options, listener)
+        })
+    }
 
-    #[test]
-    fn test_parse_fyg_toml() {
-        let config = FygToml::from_file("fyg.toml").expect("Failed to parse fyg.toml");
-        
-        assert_eq!(config.project.name, "dropbear");
-        assert_eq!(config.project.group, "com.dropbear");
-        assert_eq!(config.project.version, "1.0.0-SNAPSHOT");
-        
-        let build = config.build.expect("build section should exist");
-        assert_eq!(build.multiplatform, Some(true));
-        assert_eq!(build.languages, Some(vec!["kotlin".to_string(), "java".to_string()]));
-        
-        let targets = config.targets.expect("targets section should exist");
-        let jvm = targets.jvm.expect("jvm target should exist");
-        assert!(jvm.enabled);
-        assert_eq!(jvm.target, Some("17".to_string()));
+    /// Builds the project like [`Fyg::build_listened`], in the process scope the build runs in.
+    fn build_scoped(
+        config_file_path: &Path,
+        options: &BuildOptions,
+        listener: &dyn BuildListener,
+    ) -> Result<BuildResult, FygError> {
+        let fyg_toml = FygToml::from_file(config_file_path)?;
+        let started = Instant::now();
+
+        let mut result = if fyg_toml.workspace.is_some() {
+            Self::build_workspace(config_file_path, options, listener)?
+        } else if options.module.is_some() {
+            match Workspace::find_root(project_dir(config_file_path)) {
+                Some(root) => Self::build_workspace(root, options, listener)?,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "--module can only be used inside a workspace"
+                    )
+                    .into());
+                }
+            }
+        } else {
+            http::offline_scope(options.offline, || {
+                Self::build_project(config_file_path, options, listener)
+            })?
+        };
+        result.duration = started.elapsed();
+        Ok(result)
     }
 
-    #[test]
-    fn test_create_new_fyg_toml() {
-        let config = FygToml::new("my-app", "com.example");
-        
-        assert_eq!(config.project.name, "my-app");
-        assert_eq!(config.project.group, "com.example");
-        assert_eq!(config.project.version, "1.0.0-SNAPSHOT");
+    /// Builds every member of the workspace rooted at the given `fyg.toml`.
+    fn build_workspace(
+        root_config_path: impl AsRef<Path>,
+        options: &BuildOptions,
+        listener: &dyn BuildListener,
+    ) -> anyhow::Result<BuildResult> {
+        let workspace = Workspace::load(root_config_path)?;
+
+        let only = match &options.module {
+            Some(name) => Some(
+                workspace
+                    .member(name)
+                    .ok_or_else(|| anyhow::anyhow!("no workspace member named '{}'", name))?,
+            ),
+            None => None,
+        };
+
+        let jobs = options
+            .jobs
+            .or_else(|| std::thread::available_parallelism().ok().map(|n| n.get()))
+            .unwrap_or(1)
+            .max(1);
+        // the workspace only counts as cached if every member was
+        let mut result = BuildResult {
+            cached: true,
+            ..BuildResult::default()
+        };
+        if jobs == 1 {
+            for member in workspace.build_order(only)? {
+                result.merge(Self::build_member(member, options, listener)?);
+            }
+            return Ok(result);
+        }
+
+        for batch in workspace.task_graph(only)?.batches()? {
+            let next = AtomicUsize::new(0);
+            let results = Mutex::new(Vec::new());
+            let failures = Mutex::new(Vec::new());
+            let cancel = CancelFlag::current();
+            let process = ProcessConfig::current();
+            let offline = http::is_offline();
+            std::thread::scope(|scope| {
+                for _ in 0..jobs.min(batch.len()) {
+                    scope.spawn(|| process_scope(process.as_ref(), || {
+                        http::offline_scope(offline, || CancelFlag::scope(cancel.clone(), || {
+                            while let Some(member) =
+                                batch.get(next.fetch_add(1, Ordering::Relaxed))
+                            {
+                                let name = &member.fyg_toml.project.name;
+                                match Self::build_member(member, options, listener) {
+                                    Ok(built) => {
+                                        results.lock().unwrap().push((name.clone(), built))
+                                    }
+                                    Err(e) => failures.lock().unwrap().push((name.clone(), e)),
+                                }
+                            }
+                        }))
+                    }));
+                }
+            });
+
+            // members finish in any order, but the result lists them in a stable one
+            let mut results = results.into_inner().unwrap();
+            results.sort_by(|a, b| a.0.cmp(&b.0));
+            for (_, built) in results {
+                result.merge(built);
+            }
+
+            let mut failures = failures.into_inner().unwrap();
+            match failures.len() {
+                0 => {}
+                1 => {
+                    let (name, e) = failures.remove(0);
+                    return Err(e.context(format!("failed to build workspace member {}", name)));
+                }
+                count => {
+                    failures.sort_by(|a, b| a.0.cmp(&b.0));
+                    let messages: Vec<String> = failures
+                        .iter()
+                        .map(|(name, e)| prefix_lines(name, &format!("{:#}", e)))
+                        .collect();
+                    anyhow::bail!(
+                        "{} workspace members failed to build:\n{}",
+                        count,
+                        messages.join("\n")
+                    );
+                }
+            }
+        }
+
+        Ok(result)
     }
 
-    #[test]
-    fn test_roundtrip_serialization() {
-        let config = FygToml::new("test-app", "org.test");
-        let toml_str = config.to_toml_string().expect("Failed to serialize");
-        let parsed = FygToml::from_str(&toml_str).expect("Failed to parse back");
-        
-        assert_eq!(parsed.project.name, config.project.name);
-        assert_eq!(parsed.project.group, config.project.group);
+    /// Builds a member of a workspace, as a step of the workspace's build.
+    fn build_member(
+        member: &workspace::Member,
+        options: &BuildOptions,
+        listener: &dyn BuildListener,
+    ) -> anyhow::Result<BuildResult> {
+        let name = format!("build {}", member.fyg_toml.project.name);
+        step(listener, &name, || {
+            http::offline_scope(options.offline, || {
+                Self::build_project(&member.config_path, options, listener)
+            })
+        })
+    }
+
+    /// Builds a single, non-workspace project. It has to be called in an offline scope if
+    /// [`BuildOptions::offline`] is set.
+    fn build_project(
+        config_file_path: &Path,
+        options: &BuildOptions,
+        listener: &dyn BuildListener,
+    ) -> anyhow::Result<BuildResult> {
+        let lock_path = project_dir(config_file_path).join(LOCK_FILE_NAME);
+        if http::is_offline() && !lock_path.exists() {
+            return Err(FygError::DependencyResolutionFailed(format!(
+                "{} is missing, which building offline needs, run 'fyg build' without --offline \
+                 first",
+                lock_path.display()
+            ))
+            .into());
+        }
+
+        let started = Instant::now();
+        let fyg_toml = Self::load(config_file_path)?;
+        let project_dir = project_dir(config_file_path);
+
+        // pre-build hooks may generate sources, so they run before anything is looked at
+        if let Some(commands) = fyg_toml.hooks.as_ref().and_then(|h| h.pre_build.as_deref()) {
+            step(listener, "run pre-build hooks", || {
+                hooks::run("pre-build", commands, project_dir)
+            })?;
+        }
+
+        let src_dir = project_dir.join("src").join("kotlin");
+        let sources = build::collect_sources(&src_dir, "kt")?;
+        if sources.is_empty() {
+            return Err(BuildError::NoSources(src_dir).into());
+        }
+
+        let cached = match options.no_cache {
+            true => None,
+            false => {
+                let lock = step(listener, "resolve dependencies", || {
+                    Self::lock(project_dir, &fyg_toml, options.frozen)
+                })?;
+                let key = BuildCache::key(&fyg_toml, &lock, project_dir, options.mode)?;
+                Some((BuildCache::open()?, key))
+            }
+        };
+        if let Some((cache, key)) = &cached
+            && step(listener, "restore from cache", || cache.restore(key, project_dir))?
+        {
+            let artifacts = read_artifacts(project_dir)?;
+            post_build_hooks(&fyg_toml, project_dir, listener)?;
+            return Ok(BuildResult {
+                artifacts,
+                warnings: Vec::new(),
+                duration: started.elapsed(),
+                cached: true,
+            });
+        }
+
+        let mut result = BuildResult::default();
+
+        let jvm = fyg_toml.targets.as_ref().and_then(|t| t.jvm.as_ref());
+        // projects without a [targets.jvm] section are JVM projects
+        if jvm.is_none_or(|jvm| jvm.enabled) {
+            let kotlinc = kotlinc(&fyg_toml)?;
+            let compiler_options = compiler_options(&fyg_toml, options.mode)?;
+            let lock = match &cached {
+                // the cache key was computed from an up to date lock
+                Some(_) => Self::lock(project_dir, &fyg_toml, options.frozen)?,
+                None => step(listener, "resolve dependencies", || {
+                    Self::lock(project_dir, &fyg_toml, options.frozen)
+                })?,
+            };
+
+            let out_dir = project_dir.join("build").join("classes");
+            std::fs::create_dir_all(&out_dir)?;
+
+            let mut classpath = build::classpath(&fyg_toml, project_dir);
+            classpath.extend(lock.jars(&["common", "jvm"], &fyg_toml.maven_repositories()?)?);
+
+            let mut jvm_sources = sources.clone();
+            if let Some(ksp) = fyg_toml.build.as_ref().and_then(|b| b.ksp.as_ref()) {
+                jvm_sources.extend(step(listener, "run ksp", || {
+                    let processors = resolver::processor_jars(&fyg_toml, &ksp.processors)?;
+                    ksp::process(
+                        &kotlinc,
+                        project_dir,
+                        &sources,
+                        &classpath,
+                        ksp,
+                        &processors,
+                        &compiler_options,
+                    )
+                })?);
+            }
+            if fyg_toml.build.as_ref().is_some_and(|b| b.kapt.is_some()) {
+                jvm_sources.extend(step(listener, "run kapt", || {
+                    kapt::process(
+                        &kotlinc,
+                        &fyg_toml,
+                        project_dir,
+                        &sources,
+                        &classpath,
+                        &compiler_options,
+                    )
+                })?);
+            }
+
+            // processors generate sources from all the others, so builds that run them always
+            // compile everything
+            let build = fyg_toml.build.as_ref();
+            let incremental = build.and_then(|b| b.incremental).unwrap_or(true)
+                && build.is_none_or(|b| b.ksp.is_none() && b.kapt.is_none());
+            let fingerprints = match incremental {
+                true => {
+                    let settings = incremental::settings(&kotlinc, &compiler_options, &classpath)?;
+                    let previous = Fingerprints::load(project_dir);
+                    let current =
+                        Fingerprints::take(project_dir, &sources, settings, previous.as_ref())?;
+                    Some((current.recompile(project_dir, previous.as_ref())?, current))
+                }
+                false => None,
+            };
+
+            let mut recompile = fingerprints.as_ref().map(|(recompile, _)| recompile.clone());
+            if let Some(Recompile::Only(stale)) = &recompile {
+                // a failed compilation leaves the output half updated, so the next build
+                // starts over
+                Fingerprints::remove(project_dir)?;
+                // the classes of stale sources go, so those renamed or removed from them don't
+                // linger in the output
+                if !incremental::remove_outputs(&out_dir, &sources, stale)? {
+                    recompile = Some(Recompile::All);
+                }
+            }
+            match &recompile {
+                Some(Recompile::Nothing) => {}
+                Some(Recompile::Only(stale)) => {
+                    let mut classpath = classpath.clone();
+                    classpath.insert(0, out_dir.clone());
+                    let mut compiler_options = compiler_options.clone();
+                    compiler_options.friend_paths.push(out_dir.clone());
+                    let cmd = build::kotlinc_command(
+                        &kotlinc,
+                        stale,
+                        &classpath,
+                        &out_dir,
+                        &compiler_options,
+                    )?;
+                    result.compile(listener, "compile jvm", cmd, |cmd| {
+                        build::run_kotlinc(cmd, compiler_options.java_home.as_deref())
+                    })?;
+                }
+                recompile => {
+                    if recompile.is_some() {
+                        Fingerprints::remove(project_dir)?;
+                        // classes of removed sources would otherwise be left behind
+                        std::fs::remove_dir_all(&out_dir)?;
+                        std::fs::create_dir_all(&out_dir)?;
+                    }
+                    let cmd = build::kotlinc_command(
+                        &kotlinc,
+                        &jvm_sources,
+                        &classpath,
+                        &out_dir,
+                        &compiler_options,
+                    )?;
+                    result.compile(listener, "compile jvm", cmd, |cmd| {
+                        build::run_kotlinc(cmd, compiler_options.java_home.as_deref())
+                    })?;
+                }
+            }
+            if let Some((_, fingerprints)) = &fingerprints {
+                fingerprints.save(project_dir)?;
+            }
+            if fyg_toml.build.as_ref().is_some_and(|b| b.kapt.is_some()) {
+                step(listener, "compile java", || {
+                    kapt::compile_java(&javac(&fyg_toml)?, project_dir, &classpath)
+                })?;
+            }
+            result.artifacts.push(out_dir.clone());
+            let stdlib = toolchain::kotlin_stdlib(&kotlinc);
+
+            let dokka = fyg_toml.build.as_ref().and_then(|b| b.dokka.as_ref());
+            if dokka.is_some_and(|d| d.enabled) {
+                let mut classpath = classpath.clone();
+                classpath.extend(stdlib.clone());
+                step(listener, "generate documentation", || {
+                    Self::document(&fyg_toml, project_dir, &classpath)
+                })?;
+            }
+
+            let fat_jar = fyg_toml.build.as_ref().and_then(|b| b.fat_jar.as_ref());
+            if let Some(fat_jar) = fat_jar.filter(|f| f.enabled) {
+                let main_class = fat_jar
+                    .main_class
+                    .as_deref()
+                    .or(fyg_toml.build.as_ref().and_then(|b| b.main_class.as_deref()))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "a fat JAR needs a main class, set build.main-class in fyg.toml"
+                        )
+                    })?;
+                let project = &fyg_toml.project;
+                let libs = project_dir.join("build").join("libs");
+                std::fs::create_dir_all(&libs)?;
+                let output = libs.join(fat_jar.output_name.clone().unwrap_or_else(|| {
+                    format!("{}-{}-all.jar", project.name, project.version)
+                }));
+
+                // the project's own classes go last so they win over any dependency's
+                let mut entries: Vec<PathBuf> = stdlib.into_iter().collect();
+                entries.extend(classpath);
+                entries.push(out_dir);
+                step(listener, "package fat jar", || {
+                    jar::fat_jar(
+                        &jar_tool(&fyg_toml)?,
+                        &output,
+                        &entries,
+                        main_class,
+                        fat_jar.exclude_patterns.as_deref().unwrap_or_default(),
+                    )
+                })?;
+                result.artifacts.push(output);
+            }
+        }
+
+        let profile = fyg_toml.profile(options.mode);
+        let js_targets = fyg_toml.targets.as_ref().map(|t| {
+            [
+                ("browser", &t.js_browser, JsModuleKind::Umd),
+                ("node", &t.js_node, JsModuleKind::CommonJs),
+            ]
+        });
+        for (name, target, default_kind) in js_targets.into_iter().flatten() {
+            let Some(target) = target.as_ref().filter(|t| t.enabled) else {
+                continue;
+            };
+            if options.mode == BuildMode::Release
+                && !profile.keeps_debug_symbols()
+                && target.source_maps == Some(true)
+            {
+                anyhow::bail!(
+                    "source maps are enabled for the js-{} target, which would ship them in a \
+                     release build; remove source-maps = true, build without --release or set \
+                     debug-symbols = true in [profile.release]",
+                    name
+                );
+            }
+
+            let kotlinc_js = toolchain::kotlinc_js().ok_or(BuildError::JsCompilerNotFound)?;
+            let out_dir = project_dir.join("build").join("js").join(name);
+            std::fs::create_dir_all(&out_dir)?;
+
+            let cmd = build::kotlinc_js_command(
+                &kotlinc_js,
+                &sources,
+                toolchain::kotlin_stdlib_js().as_deref(),
+                &out_dir,
+                &fyg_toml.project.name,
+                target.module_kind.unwrap_or(default_kind),
+                target.source_maps.unwrap_or(profile.keeps_debug_symbols()),
+            );
+            let step_name = format!("compile js-{}", name);
+            result.compile(listener, &step_name, cmd, build::run_compiler)?;
+            result.artifacts.push(out_dir);
+        }
+
+        let wasm_targets = fyg_toml
+            .targets
+            .as_ref()
+            .map(|t| [("js", &t.wasm_js), ("wasi", &t.wasm_wasi)]);
+        for (name, target) in wasm_targets.into_iter().flatten() {
+            if !target.as_ref().is_some_and(|t| t.enabled) {
+                continue;
+            }
+
+            let compiler = toolchain::kotlinc_wasm().ok_or(BuildError::WasmCompilerNotFound)?;
+            let out_dir = project_dir.join("build").join("wasm").join(name);
+            std::fs::create_dir_all(&out_dir)?;
+
+            let cmd = build::kotlinc_wasm_command(
+                &compiler,
+                &sources,
+                toolchain::kotlin_stdlib_wasm(name).as_deref(),
+                &out_dir,
+                &fyg_toml.project.name,
+                name,
+            );
+            let step_name = format!("compile wasm-{}", name);
+            result.compile(listener, &step_name, cmd, build::run_compiler)?;
+            result.artifacts.push(out_dir);
+        }
+
+        let native = fyg_toml.targets.as_ref().and_then(|t| t.native.as_ref());
+        let binary = native.and_then(|n| n.binary.as_ref());
+        let base_name = binary
+            .and_then(|b| b.base_name.as_deref())
+            .unwrap_or(&fyg_toml.project.name);
+        let xcframework =
+            binary.is_some_and(|b| matches!(b.binary_type, FygBinaryType::XCFramework));
+        let exported = binary
+            .filter(|b| b.is_framework())
+            .and_then(|b| b.export_libraries.as_deref())
+            .unwrap_or_default();
+
+        let targets = fyg_toml.targets.as_ref();
+        let apple_targets = targets.map(|t| t.apple_targets()).unwrap_or_default();
+        let mut frameworks = Vec::new();
+        for (name, target) in targets.map(|t| t.native_targets()).unwrap_or_default() {
+            // only Apple targets can be bundled into an XCFramework
+            if xcframework && !apple_targets.iter().any(|(key, _)| *key == name) {
+                continue;
+            }
+
+            let compiler = toolchain::kotlin_native_tool("kotlinc-native")
+                .ok_or(BuildError::NativeToolNotFound("kotlinc-native"))?;
+            let out_dir = project_dir.join("build").join("native").join(name);
+            std::fs::create_dir_all(&out_dir)?;
+
+            let cinterops = native.and_then(|n| n.cinterop.as_deref()).unwrap_or_default();
+            let mut libraries = cinterop(project_dir, cinterops, target, &out_dir)?;
+            libraries.extend(native_dependencies(&fyg_toml, (name, target))?);
+            let exports = exported
+                .iter()
+                .map(|coordinate| native_library(&fyg_toml, coordinate, target))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            let mut linker_options = Vec::new();
+            if let Some(mingw) = targets.and_then(|t| t.mingw_target(name)) {
+                let resources = windows_resources(mingw, project_dir, (name, target), &out_dir);
+                if let Some((step_name, cmd, object)) = resources? {
+                    result.compile(listener, &step_name, cmd, build::run_compiler)?;
+                    linker_options.push(object.display().to_string());
+                }
+            }
+
+            let output = out_dir.join(base_name);
+            let cmd = build::kotlinc_native_command(
+                &compiler,
+                &sources,
+                &libraries,
+                &exports,
+                &output,
+                (name, target),
+                binary,
+                &linker_options,
+            );
+            let step_name = format!("compile {}", name);
+            result.compile(listener, &step_name, cmd, build::run_compiler)?;
+            result.artifacts.extend(native_artifacts(&out_dir, base_name)?);
+            frameworks.push(output.with_extension("framework"));
+        }
+
+        if xcframework && !frameworks.is_empty() {
+            let xcodebuild = toolchain::xcodebuild().ok_or(BuildError::XcodebuildNotFound)?;
+            let out_dir = project_dir.join("build").join("xcframeworks");
+            std::fs::create_dir_all(&out_dir)?;
+            let output = out_dir.join(format!("{}.xcframework", base_name));
+            step(listener, "bundle xcframework", || {
+                Ok(build::create_xcframework(&xcodebuild, &frameworks, &output)?)
+            })?;
+            result.artifacts.push(output);
+        }
+
+        let build = fyg_toml.build.as_ref();
+        if build.and_then(|b| b.sources_jar).unwrap_or(false) {
+            let project = &fyg_toml.project;
+            let libs = project_dir.join("build").join("libs");
+            std::fs::create_dir_all(&libs)?;
+            let include_generated = build.and_then(|b| b.include_generated_sources);
+            let output = libs.join(format!("{}-{}-sources.jar", project.name, project.version));
+            step(listener, "package sources jar", || {
+                jar::create(
+                    &jar_tool(&fyg_toml)?,
+                    &output,
+                    &build::source_roots(project_dir, include_generated.unwrap_or(false))?,
+                    None,
+                )
+            })?;
+            result.artifacts.push(output);
+        }
+
+        if let Some(sbom) = build.and_then(|b| b.sbom.as_ref()).filter(|s| s.enabled) {
+            let lock = Self::lock(project_dir, &fyg_toml, options.frozen)?;
+            result.artifacts.push(step(listener, "write sbom", || {
+                sbom::write(sbom, &fyg_toml, &lock, project_dir)
+            })?);
+        }
+
+        write_artifacts(project_dir, &result.artifacts)?;
+        if let Some((cache, key)) = &cached {
+            cache.store(key, project_dir, &BuildCache::project_id(&fyg_toml))?;
+        }
+        // the cache holds the build as it was before the hooks, which run again on a cache hit
+        post_build_hooks(&fyg_toml, project_dir, listener)?;
+
+        result.duration = started.elapsed();
+        Ok(result)
+    }
+
+    /// Cleans the project described by the `fyg.toml` at the given path, removing what
+    /// [`CleanOptions`] picks.
+    ///
+    /// The build cache only knows which project stored an entry, so cleaning the cache of a
+    /// workspace root removes the entries of every member.
+    pub fn clean(
+        config_file_path: impl AsRef<Path>,
+        options: &CleanOptions,
+    ) -> Result<Cleaned, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let mut cleaned = Cleaned::default();
+
+        if options.cache {
+            let cache = match &options.cache_dir {
+                Some(dir) => BuildCache::at(dir),
+                None => BuildCache::open()?,
+            };
+            let fyg_toml = Self::load(config_file_path)?;
+            let projects = match fyg_toml.workspace {
+                Some(_) => Workspace::load(config_file_path)?
+                    .members
+                    .iter()
+                    .map(|member| BuildCache::project_id(&member.fyg_toml))
+                    .collect(),
+                None => vec![BuildCache::project_id(&fyg_toml)],
+            };
+            for project in projects {
+                let removed = cache.remove_project(&project)?;
+                cleaned.entries += removed.entries;
+                cleaned.bytes += removed.bytes;
+            }
+        }
+
+        let build_dir = project_dir(config_file_path).join("build");
+        if options.build && build_dir.exists() {
+            cleaned.bytes += cache::dir_size(&build_dir)?;
+            std::fs::remove_dir_all(&build_dir)?;
+            cleaned.build_dir = true;
+        }
+        Ok(cleaned)
+    }
+
+    /// Generates API documentation for the project described by the `fyg.toml` at the given
+    /// path with Dokka, returning the directory it was written to.
+    ///
+    /// Settings are taken from `build.dokka`, but documentation is generated even if it isn't
+    /// enabled there. The project doesn't need to be built first.
+    pub fn doc(config_file_path: impl AsRef<Path>) -> Result<PathBuf, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+        let project_dir = project_dir(config_file_path);
+
+        if fyg_toml.workspace.is_some() {
+            return Err(anyhow::anyhow!(
+                "a workspace root has no sources, run fyg doc in a member instead"
+            )
+            .into());
+        }
+
+        let lock = Self::lock(project_dir, &fyg_toml, false)?;
+        let mut classpath = build::classpath(&fyg_toml, project_dir);
+        classpath.extend(lock.jars(&["common", "jvm"], &fyg_toml.maven_repositories()?)?);
+        classpath.extend(kotlinc(&fyg_toml).ok().and_then(|k| toolchain::kotlin_stdlib(&k)));
+        Ok(Self::document(&fyg_toml, project_dir, &classpath)?)
+    }
+
+    /// Runs Dokka with the project's `build.dokka` settings, returning the output directory.
+    fn document(
+        fyg_toml: &FygToml,
+        project_dir: &Path,
+        classpath: &[PathBuf],
+    ) -> anyhow::Result<PathBuf> {
+        let build = fyg_toml.build.as_ref();
+        let dokka = build.and_then(|b| b.dokka.as_ref());
+        let output_dir = project_dir.join(
+            dokka
+                .and_then(|d| d.output_dir.as_deref())
+                .unwrap_or(doc::DEFAULT_OUTPUT_DIR),
+        );
+        let include_generated = build.and_then(|b| b.include_generated_sources);
+
+        doc::generate(
+            &java(fyg_toml)?,
+            &fyg_toml.project.name,
+            &build::source_roots(project_dir, include_generated.unwrap_or(false))?,
+            classpath,
+            &output_dir,
+            &project_dir.join("build"),
+            dokka.and_then(|d| d.output_format).unwrap_or_default(),
+        )?;
+        Ok(output_dir)
+    }
+
+    /// Adds a `group:artifact[:version]` dependency to the given scope of the `fyg.toml` at the
+    /// given path.
+    ///
+    /// The artifact is looked up on Maven Central to make sure it exists, along with the version
+    /// if one was given, and its latest version is used if none was. If the dependency is already
+    /// declared in any scope, the file is left untouched and [`AddOutcome::AlreadyPresent`] is
+    /// returned.
+    pub fn add(
+        config_file_path: impl AsRef<Path>,
+        coordinate: &str,
+        scope: &str,
+    ) -> Result<AddOutcome, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let (group, artifact, version) = deps::parse_coordinate(coordinate)?;
+        if !DependenciesConfig::SCOPES.contains(&scope) {
+            return Err(anyhow::anyhow!("unknown dependency scope '{}'", scope).into());
+        }
+
+        let mut fyg_toml = FygToml::from_file_uninterpolated(config_file_path)?;
+        let key = format!("{}:{}", group, artifact);
+
+        let scopes = fyg_toml
+            .dependencies
+            .as_ref()
+            .map(|deps| deps.find(&key))
+            .unwrap_or_default();
+        if !scopes.is_empty() {
+            return Ok(AddOutcome::AlreadyPresent { key, scopes });
+        }
+
+        let version = maven::search(&group, &artifact, version.as_deref())?.ok_or_else(|| {
+            let wanted = match &version {
+                Some(version) => format!("{}:{}", key, version),
+                None => key.clone(),
+            };
+            anyhow::anyhow!("could not find {} on Maven Central", wanted)
+        })?;
+
+        fyg_toml
+            .dependencies
+            .get_or_insert_with(DependenciesConfig::default)
+            .insert(scope, key.clone(), DependencyValue::Version(version.clone()))?;
+        fyg_toml.write_to_file(config_file_path)?;
+
+        Ok(AddOutcome::Added {
+            key,
+            version,
+            scope: scope.to_string(),
+        })
+    }
+
+    /// Removes every dependency matching `name` from the `fyg.toml` at the given path.
+    ///
+    /// `name` can be an artifact ID or a full coordinate, and the search can be limited to one
+    /// `scope`. If more than one entry matches, nothing is removed and
+    /// [`RemoveOutcome::Ambiguous`] lists the matches so a scope or full coordinate can be given.
+    pub fn remove(
+        config_file_path: impl AsRef<Path>,
+        name: &str,
+        scope: Option<&str>,
+    ) -> Result<RemoveOutcome, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        if let Some(scope) = scope
+            && !DependenciesConfig::SCOPES.contains(&scope)
+        {
+            return Err(anyhow::anyhow!("unknown dependency scope '{}'", scope).into());
+        }
+
+        let mut fyg_toml = FygToml::from_file_uninterpolated(config_file_path)?;
+        let Some(dependencies) = &mut fyg_toml.dependencies else {
+            return Err(anyhow::anyhow!("no dependency matching '{}'", name).into());
+        };
+
+        let mut matches: Vec<(&'static str, String)> = dependencies
+            .scopes()
+            .into_iter()
+            .filter(|(s, _)| scope.is_none_or(|scope| scope == *s))
+            .flat_map(|(s, entries)| {
+                entries
+                    .into_iter()
+                    .flat_map(|e| e.keys())
+                    .filter(|key| deps::matches(key, name))
+                    .map(move |key| (s, key.clone()))
+            })
+            .collect();
+        matches.sort();
+
+        match matches.len() {
+            0 => return Err(anyhow::anyhow!("no dependency matching '{}'", name).into()),
+            1 => {}
+            _ => return Ok(RemoveOutcome::Ambiguous(matches)),
+        }
+
+        for (scope, key) in &matches {
+            dependencies.remove(scope, key);
+        }
+        if dependencies.scopes().iter().all(|(_, entries)| entries.is_none()) {
+            fyg_toml.dependencies = None;
+        }
+        fyg_toml.write_to_file(config_file_path)?;
+
+        Ok(RemoveOutcome::Removed(matches))
+    }
+
+    /// Bumps `project.version` in the `fyg.toml` at the given path, returning the old and new
+    /// versions. With `dry_run`, the file is left as it is.
+    pub fn bump_version(
+        config_file_path: impl AsRef<Path>,
+        kind: BumpKind,
+        dry_run: bool,
+    ) -> Result<(Version, Version), FygError> {
+        Self::change_version(config_file_path.as_ref(), |old| old.bump(kind), dry_run)
+    }
+
+    /// Sets `project.version` in the `fyg.toml` at the given path, returning the old and new
+    /// versions. With `dry_run`, the file is left as it is.
+    pub fn set_version(
+        config_file_path: impl AsRef<Path>,
+        version: Version,
+        dry_run: bool,
+    ) -> Result<(Version, Version), FygError> {
+        Self::change_version(config_file_path.as_ref(), |_| version, dry_run)
+    }
+
+    fn change_version(
+        config_file_path: &Path,
+        change: impl FnOnce(&Version) -> Version,
+        dry_run: bool,
+    ) -> Result<(Version, Version), FygError> {
+        let mut fyg_toml = FygToml::from_file_uninterpolated(config_file_path)?;
+        if fyg_toml.project.is_empty() {
+            return Err(anyhow::anyhow!(
+                "a workspace root has no version, change it in a member instead"
+            )
+            .into());
+        }
+
+        let old: Version = fyg_toml.project.version.parse()?;
+        let new = change(&old);
+        if !dry_run {
+            fyg_toml.project.version = new.to_string();
+            fyg_toml.write_to_file(config_file_path)?;
+        }
+        Ok((old, new))
+    }
+
+    /// Returns every target in [`config::KNOWN_TARGETS`], with whether the `fyg.toml` at the given
+    /// path enables it.
+    pub fn targets(
+        config_file_path: impl AsRef<Path>,
+    ) -> Result<Vec<(&'static str, bool)>, FygError> {
+        let fyg_toml = FygToml::from_file_uninterpolated(config_file_path)?;
+        let enabled = fyg_toml.targets.unwrap_or_default().enabled_targets();
+        Ok(config::KNOWN_TARGETS
+            .into_iter()
+            .map(|key| (key, enabled.contains(&key)))
+            .collect())
+    }
+
+    /// Enables the target `key`, such as `ios-arm64`, in the `fyg.toml` at the given path,
+    /// returning `false` if it already was.
+    pub fn add_target(config_file_path: impl AsRef<Path>, key: &str) -> Result<bool, FygError> {
+        Self::set_target_enabled(config_file_path.as_ref(), key, true).map(|was| !was)
+    }
+
+    /// Disables the target `key` in the `fyg.toml` at the given path, keeping its other
+    /// settings, and returns `false` if it wasn't enabled.
+    pub fn remove_target(
+        config_file_path: impl AsRef<Path>,
+        key: &str,
+    ) -> Result<bool, FygError> {
+        Self::set_target_enabled(config_file_path.as_ref(), key, false)
+    }
+
+    /// Enables or disables a target like [`config::TargetsConfig::set_enabled`], writing
+    /// `fyg.toml` if that changes it.
+    fn set_target_enabled(
+        config_file_path: &Path,
+        key: &str,
+        enabled: bool,
+    ) -> Result<bool, FygError> {
+        let mut fyg_toml = FygToml::from_file_uninterpolated(config_file_path)?;
+        let was_enabled = fyg_toml.targets.get_or_insert_default().set_enabled(key, enabled)?;
+        if was_enabled != enabled {
+            fyg_toml.write_to_file(config_file_path)?;
+        }
+        Ok(was_enabled)
+    }
+
+    /// Returns the value of a setting, such as `project.version`, in the `fyg.toml` at the given
+    /// path, or `None` if it isn't set. See [`toml::SETTINGS`] for the settings that can be read.
+    pub fn get_setting(
+        config_file_path: impl AsRef<Path>,
+        key: &str,
+    ) -> Result<Option<::toml::Value>, FygError> {
+        let fyg_toml = Self::load(config_file_path.as_ref())?;
+        Ok(fyg_toml.get(key)?)
+    }
+
+    /// Changes a setting, such as `project.version`, in the `fyg.toml` at the given path,
+    /// returning its old and new values. See [`FygToml::set`] for how `value` is read.
+    pub fn set_setting(
+        config_file_path: impl AsRef<Path>,
+        key: &str,
+        value: &str,
+    ) -> Result<(Option<::toml::Value>, ::toml::Value), FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let mut fyg_toml = FygToml::from_file_uninterpolated(config_file_path)?;
+        let old = fyg_toml.set(key, value)?;
+        fyg_toml.write_to_file(config_file_path)?;
+        let new = fyg_toml.get(key)?.expect("the setting was just set");
+        Ok((old, new))
+    }
+
+    /// Returns the value of a setting, such as `default-group`, in the [`UserConfig`], or `None`
+    /// if it isn't set. See [`config::USER_SETTINGS`] for the settings that can be read.
+    pub fn get_user_setting(key: &str) -> Result<Option<::toml::Value>, FygError> {
+        Ok(UserConfig::load()?.get(key)?)
+    }
+
+    /// Changes a setting, such as `default-group`, in the [`UserConfig`], returning its old and
+    /// new values. The config file is created if there isn't one yet.
+    pub fn set_user_setting(
+        key: &str,
+        value: &str,
+    ) -> Result<(Option<::toml::Value>, ::toml::Value), FygError> {
+        let mut user_config = UserConfig::load()?;
+        let old = user_config.set(key, value)?;
+        user_config.write_to_file(UserConfig::path()?)?;
+        let new = user_config.get(key)?.expect("the setting was just set");
+        Ok((old, new))
+    }
+
+    /// Checks every dependency in the `fyg.toml` at the given path, and `build.kotlin-version`,
+    /// for newer stable versions, without changing anything.
+    ///
+    /// Only minor and patch updates are reported unless `major` is set. Dependencies using
+    /// `LATEST` or a version range, a local `path` or `workspace = true` are skipped.
+    pub fn outdated(
+        config_file_path: impl AsRef<Path>,
+        major: bool,
+    ) -> Result<Vec<Outdated>, FygError> {
+        let fyg_toml = Self::load(config_file_path.as_ref())?;
+        let repositories = fyg_toml.maven_repositories()?;
+
+        let mut checked: Vec<(&'static str, String, maven::Coordinate)> = Vec::new();
+        if let Some(version) = fyg_toml.build.as_ref().and_then(|b| b.kotlin_version.as_ref()) {
+            let stdlib = maven::Coordinate::new("org.jetbrains.kotlin", "kotlin-stdlib", version);
+            checked.push(("build", "kotlin-version".to_string(), stdlib));
+        }
+        for (scope, entries) in fyg_toml.dependencies.iter().flat_map(|d| d.scopes()) {
+            let mut entries: Vec<_> = entries.into_iter().flatten().collect();
+            entries.sort_by_key(|(key, _)| key.as_str());
+            for (key, value) in entries {
+                if let Some(Ok(coordinate)) = value.coordinate(key) {
+                    checked.push((scope, key.clone(), coordinate));
+                }
+            }
+        }
+
+        let mut outdated = Vec::new();
+        for (scope, key, coordinate) in checked {
+            let current = coordinate.version;
+            if lock::is_dynamic(&current) || maven::is_range(&current) {
+                continue;
+            }
+
+            let metadata = maven::metadata(&repositories, &coordinate.group, &coordinate.artifact)?;
+            let Some(stable) = deps::newest_update(&current, &metadata.versions, major) else {
+                continue;
+            };
+            let latest = metadata.newest().unwrap_or(stable);
+            let latest = match maven::compare_versions(latest, stable).is_gt() {
+                true => latest,
+                false => stable,
+            };
+            outdated.push(Outdated {
+                scope,
+                key,
+                stable: stable.to_string(),
+                latest: latest.to_string(),
+                current,
+            });
+        }
+        Ok(outdated)
+    }
+
+    /// Looks up known security vulnerabilities in the resolved dependencies of the project
+    /// described by the `fyg.toml` at the given path, using the OSV database.
+    ///
+    /// The versions in `fyg.lock` are checked if it is up to date, otherwise the dependencies are
+    /// resolved without writing a new lock file. Vulnerabilities are returned most severe first.
+    pub fn audit(config_file_path: impl AsRef<Path>) -> Result<Vec<Vulnerability>, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+
+        let lock_path = project_dir(config_file_path).join(LOCK_FILE_NAME);
+        let lock = match lock_path.exists() {
+            true => Some(FygLock::from_file(&lock_path)?),
+            false => None,
+        };
+        let lock = match lock {
+            Some(lock) if lock.is_up_to_date(&fyg_toml) => lock,
+            _ => FygLock::resolve(&fyg_toml)
+                .map_err(|e| FygError::DependencyResolutionFailed(format!("{:#}", e)))?,
+        };
+
+        // OSV knows packages by group and artifact, so classified variants are checked once
+        let mut dependencies: Vec<maven::Coordinate> = lock
+            .artifacts
+            .iter()
+            .map(|a| maven::Coordinate::new(&a.group, &a.artifact, &a.version))
+            .collect();
+        dependencies.sort();
+        dependencies.dedup();
+
+        Ok(audit::scan(&dependencies)?)
+    }
+
+    /// Bumps dependencies in the `fyg.toml` at the given path to their latest released versions,
+    /// then regenerates `fyg.lock`.
+    ///
+    /// Only dependencies matching `name` are checked if one is given. With `dry_run` set, the
+    /// changes are returned without writing anything to disk. Dependencies using `LATEST`, a
+    /// `${...}` property, a local `path` or `workspace = true` are left alone.
+    pub fn update(
+        config_file_path: impl AsRef<Path>,
+        name: Option<&str>,
+        dry_run: bool,
+    ) -> Result<Vec<VersionChange>, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let repositories = FygToml::from_file(config_file_path)?.maven_repositories()?;
+        let mut fyg_toml = FygToml::from_file_uninterpolated(config_file_path)?;
+
+        if let Some(name) = name {
+            let found = fyg_toml.dependencies.as_ref().is_some_and(|deps| {
+                deps.scopes()
+                    .into_iter()
+                    .flat_map(|(_, entries)| entries.into_iter().flat_map(|e| e.keys()))
+                    .any(|key| deps::matches(key, name))
+            });
+            if !found {
+                return Err(anyhow::anyhow!("no dependency matching '{}'", name).into());
+            }
+        }
+
+        let mut changes = Vec::new();
+        if let Some(dependencies) = &mut fyg_toml.dependencies {
+            for scope in DependenciesConfig::SCOPES {
+                let Some(entries) = dependencies.scope_mut(scope).and_then(|e| e.as_mut()) else {
+                    continue;
+                };
+
+                let mut keys: Vec<String> = entries.keys().cloned().collect();
+                keys.sort();
+                for key in keys {
+                    if name.is_some_and(|name| !deps::matches(&key, name)) {
+                        continue;
+                    }
+                    let Some((group, artifact)) = key.split_once(':') else {
+                        continue;
+                    };
+
+                    let version = match entries.get_mut(&key) {
+                        Some(DependencyValue::Version(version)) => version,
+                        Some(DependencyValue::Complex(dep)) if dep.path.is_none() => {
+                            match &mut dep.version {
+                                Some(version) => version,
+                                None => continue,
+                            }
+                        }
+                        _ => continue,
+                    };
+                    // versions taken from a ${...} property are managed elsewhere
+                    if lock::is_dynamic(version) || version.contains("${") {
+                        continue;
+                    }
+
+                    let latest = maven::latest_version(&repositories, group, artifact)?;
+                    if maven::compare_versions(&latest, version).is_gt() {
+                        changes.push(VersionChange {
+                            scope,
+                            key: key.clone(),
+                            old: std::mem::replace(version, latest.clone()),
+                            new: latest,
+                        });
+                    }
+                }
+            }
+        }
+
+        if dry_run {
+            return Ok(changes);
+        }
+
+        if !changes.is_empty() {
+            fyg_toml.write_to_file(config_file_path)?;
+        }
+
+        let lock_path = project_dir(config_file_path).join(LOCK_FILE_NAME);
+        let mut lock = if lock_path.exists() {
+            FygLock::from_file(&lock_path)?
+        } else {
+            FygLock::default()
+        };
+        lock.update(&Self::load(config_file_path)?)?;
+        lock.write_to_file(&lock_path)?;
+
+        Ok(changes)
+    }
+
+    /// Resolves the full graph of direct and transitive dependencies of the project described
+    /// by the `fyg.toml` at the given path.
+    pub fn dependency_graph(
+        config_file_path: impl AsRef<Path>,
+    ) -> Result<DependencyGraph, FygError> {
+        let fyg_toml = Self::load(config_file_path)?;
+        DependencyGraph::resolve(&fyg_toml)
+            .map_err(|e| FygError::DependencyResolutionFailed(format!("{:#}", e)))
+    }
+
+    /// Reads the license of every direct and transitive dependency of the project described by
+    /// the `fyg.toml` at the given path from the dependencies' POMs, sorted by coordinate.
+    pub fn licenses(
+        config_file_path: impl AsRef<Path>,
+    ) -> Result<Vec<DependencyLicense>, FygError> {
+        let graph = Self::dependency_graph(config_file_path)?;
+        let mut nodes = graph.reachable(None);
+        nodes.sort_by(|a, b| a.coordinate.cmp(&b.coordinate));
+        Ok(nodes.into_iter().map(DependencyLicense::from_node).collect())
+    }
+
+    /// Reads the `fyg.toml` at the given path.
+    ///
+    /// If the project is a member of a workspace, any `workspace = true` dependencies are filled
+    /// in from the workspace root.
+    pub fn load(config_file_path: impl AsRef<Path>) -> Result<FygToml, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let mut fyg_toml = FygToml::from_file(config_file_path)?;
+
+        if fyg_toml.workspace.is_none()
+            && let Some(root_path) = Workspace::find_root(project_dir(config_file_path))
+        {
+            fyg_toml.inherit_workspace(&FygToml::from_file(root_path)?)?;
+        }
+
+        Ok(fyg_toml)
     }
-}
\ No newline at end of file
+
+    /// Validates the `fyg.toml` at the given path, returning any warnings and hints about it.
+    ///
+    /// If any of the problems found is an error, [`FygError::ValidationError`] is returned with
+    /// all of them instead.
+    pub fn check(
+        config_file_path: impl AsRef<Path>,
+    ) -> Result<Vec<ValidationDiagnostic>, FygError> {
+        let diagnostics = FygToml::from_file(config_file_path)?.validate();
+        match diagnostics.iter().any(ValidationDiagnostic::is_error) {
+            true => Err(FygError::ValidationError(diagnostics)),
+            false => Ok(diagnostics),
+        }
+    }
+
+    /// Checks the project described by the `fyg.toml` at the given path against the best
+    /// practices in [`lint::LINTS`], or only the lints with an ID in `only`. A workspace root
+    /// has each of its members checked.
+    pub fn lint(
+        config_file_path: impl AsRef<Path>,
+        only: Option<&[&str]>,
+    ) -> Result<Vec<LintFinding>, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+        if fyg_toml.workspace.is_none() {
+            return Ok(lint::run(&fyg_toml, project_dir(config_file_path), only)?);
+        }
+
+        let workspace = Workspace::load(config_file_path)?;
+        let mut findings = Vec::new();
+        for member in &workspace.members {
+            for finding in lint::run(&member.fyg_toml, &member.dir, only)? {
+                findings.push(LintFinding {
+                    member: Some(member.fyg_toml.project.name.clone()),
+                    ..finding
+                });
+            }
+        }
+        Ok(findings)
+    }
+
+    /// Loads `fyg.lock` from the project directory, resolving and writing a new one if it is
+    /// missing or out of date. With `frozen` set, a missing or outdated lock file is an error.
+    fn lock(project_dir: &Path, fyg_toml: &FygToml, frozen: bool) -> anyhow::Result<FygLock> {
+        let lock_path = project_dir.join(LOCK_FILE_NAME);
+
+        if lock_path.exists() {
+            let lock = FygLock::from_file(&lock_path)?;
+            if lock.is_up_to_date(fyg_toml) {
+                return Ok(lock);
+            }
+            if frozen {
+                anyhow::bail!("{} is out of date with fyg.toml", LOCK_FILE_NAME);
+            }
+        } else if frozen {
+            anyhow::bail!(
+                "{} is missing, run 'fyg build' without --frozen first",
+                LOCK_FILE_NAME
+            );
+        }
+
+        let lock = FygLock::resolve(fyg_toml)
+            .map_err(|e| FygError::DependencyResolutionFailed(format!("{:#}", e)))?;
+        lock.write_to_file(&lock_path)?;
+        Ok(lock)
+    }
+
+    /// Builds the project in release mode and uploads it to Maven repositories.
+    ///
+    /// The JAR and a POM generated from `fyg.toml` are uploaded, along with sources and Javadoc
+    /// JARs unless `[publish]` turns them off, and `.md5` and `.sha1` checksums of each.
+    /// `repository` names the `[repositories]` entry to upload to; without it, every entry in
+    /// `publish.repositories` is used. Returns the URL of every uploaded file.
+    pub fn publish(
+        config_file_path: impl AsRef<Path>,
+        repository: Option<&str>,
+    ) -> Result<Vec<String>, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+        let project_dir = project_dir(config_file_path);
+
+        check_publishable(&fyg_toml)?;
+        // fail before building if the repositories are misconfigured
+        let repositories = publish::repositories(&fyg_toml, repository)?;
+
+        let options = BuildOptions {
+            mode: BuildMode::Release,
+            ..Default::default()
+        };
+        Self::build_with_options(config_file_path, &options)?;
+        Self::publish_built(&fyg_toml, project_dir, &repositories)
+    }
+
+    /// Packages the release build of the project in `project_dir`, which has already been
+    /// made, and uploads it to `repositories`.
+    fn publish_built(
+        fyg_toml: &FygToml,
+        project_dir: &Path,
+        repositories: &[maven::Repository],
+    ) -> Result<Vec<String>, FygError> {
+        // the build has just written fyg.lock
+        let lock = Self::lock(project_dir, fyg_toml, true)?;
+        let files = publish::package(fyg_toml, project_dir, &lock, &jar_tool(fyg_toml)?)?;
+
+        let mut uploaded = Vec::new();
+        for repository in repositories {
+            uploaded.extend(publish::upload(fyg_toml, &files, repository)?);
+        }
+        Ok(uploaded)
+    }
+
+    /// Works out what [`Fyg::publish`] would do, as [`Fyg::plan_build`] does for the release
+    /// build, followed by the files that would be packaged and the URL each one would be
+    /// uploaded to.
+    pub fn plan_publish(
+        config_file_path: impl AsRef<Path>,
+        repository: Option<&str>,
+    ) -> Result<Vec<PlannedAction>, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+        check_publishable(&fyg_toml)?;
+        let repositories = publish::repositories(&fyg_toml, repository)?;
+
+        let options = BuildOptions {
+            mode: BuildMode::Release,
+            ..Default::default()
+        };
+        let mut actions = Self::plan_build(config_file_path, &options)?;
+        let (artifacts, files) = publish::planned_files(&fyg_toml, project_dir(config_file_path));
+        actions.extend(artifacts.into_iter().map(PlannedAction::Package));
+        for repository in &repositories {
+            actions.extend(files.iter().map(|file| PlannedAction::Upload {
+                file: file.clone(),
+                url: publish::upload_url(&fyg_toml, file, repository),
+            }));
+        }
+        Ok(actions)
+    }
+
+    /// Releases the project described by the `fyg.toml` at the given path: drops `-SNAPSHOT`
+    /// from its version, builds it in release mode, tests it, commits and tags the release in
+    /// git, publishes the build and moves on to the next `-SNAPSHOT` version, which is committed
+    /// too. Only `fyg.toml` is committed.
+    ///
+    /// The release stops at the first step that fails with a
+    /// [`ReleaseError`](crate::release::ReleaseError) saying which it was. With
+    /// [`ReleaseOptions::dry_run`], the steps are returned without running any of them.
+    pub fn release(
+        config_file_path: impl AsRef<Path>,
+        options: &ReleaseOptions,
+    ) -> Result<Release, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+        if fyg_toml.workspace.is_some() {
+            return Err(anyhow::anyhow!(
+                "a workspace root can't be released, release each member instead"
+            )
+            .into());
+        }
+        // fail before changing anything if the project can't be published
+        check_publishable(&fyg_toml)?;
+        let repositories = publish::repositories(&fyg_toml, options.repository.as_deref())?;
+
+        let release = Release::plan(fyg_toml.project.version.parse()?, options);
+        if options.dry_run {
+            return Ok(release);
+        }
+
+        let listener = options.listener.as_deref().unwrap_or(&SilentBuildListener);
+        for release_step in &release.steps {
+            let name = release_step.to_string();
+            listener.on_step_started(&name);
+            let started = Instant::now();
+            let result = match release_step {
+                ReleaseStep::SetVersion(version) | ReleaseStep::NextSnapshot(version) => {
+                    Self::set_version(config_file_path, *version, false).map(|_| ())
+                }
+                ReleaseStep::Build => {
+                    let options = BuildOptions {
+                        mode: BuildMode::Release,
+                        ..Default::default()
+                    };
+                    Self::build_with_options(config_file_path, &options).map(|_| ())
+                }
+                ReleaseStep::Test => Self::test(config_file_path, None).map(|_| ()),
+                ReleaseStep::Commit(message) => release::commit(config_file_path, message),
+                ReleaseStep::Tag(tag) => release::tag(project_dir(config_file_path), tag),
+                // the release build has just been made, with the version being published
+                ReleaseStep::Publish => Self::load(config_file_path).and_then(|fyg_toml| {
+                    Self::publish_built(&fyg_toml, project_dir(config_file_path), &repositories)
+                        .map(|_| ())
+                }),
+            };
+            if let Err(error) = result {
+                return Err(ReleaseError {
+                    step: release_step.clone(),
+                    error,
+                }
+                .into());
+            }
+            listener.on_step_finished(&name, started.elapsed());
+        }
+        Ok(release)
+    }
+
+    /// Generates the Kotlin bindings of the `targets.native.cinterop` entry called `name`, for
+    /// every enabled native target, returning the `.klib` files they were written to.
+    ///
+    /// This is the step [`Fyg::build`] runs before compiling for native targets, on its own so
+    /// a `.def` file can be debugged without compiling anything else.
+    pub fn cinterop(
+        config_file_path: impl AsRef<Path>,
+        name: &str,
+    ) -> Result<Vec<PathBuf>, FygError> {
+        let config_file_path = config_file_path.as_ref();
+        let fyg_toml = Self::load(config_file_path)?;
+        let project_dir = project_dir(config_file_path);
+
+        let targets = fyg_toml.targets.as_ref();
+        let cinterops = targets
+            .and_then(|t| t.native.as_ref())
+            .and_then(|n| n.cinterop.as_deref())
+            .unwrap_or_default();
+        let Some(config) = cinterops.iter().find(|c| c.name == name) else {
+            let names: Vec<&str> = cinterops.iter().map(|c| c.name.as_str()).collect();
+            match names.is_empty() {
+                true => {
+                    return Err(anyhow::anyhow!(
+                        "no cinterop named '{}', fyg.toml has none", name
+                    )
+                    .into());
+                }
+                false => return Err(anyhow::anyhow!(
+                    "no cinterop named '{}', expected one of: {}",
+                    name,
+                    names.join(", ")
+                ).into()),
+            }
+        };
+
+        let native_targets = targets.map(|t| t.native_targets()).unwrap_or_default();
+        if native_targets.is_empty() {
+            return Err(anyhow::anyhow!(
+                "no native target is enabled, enable one such as [targets.linux-x64]"
+            )
+            .into());
+        }
+
+        let mut klibs = Vec::new();
+        for (name, target) in native_targets {
+            let out_dir = project_dir.join("build").join("native").join(name);
+            klibs.extend(cinterop(
+                project_dir,
+                std::slice::from_ref(config),
+                target,
+                &out_dir,
+            )?);
+        }
+        Ok(klibs)
+    }
+
+    /// Runs the built project described by the `fyg.toml` at the given path.
+    ///
+    /// The main class is taken from `build.main-class`, or found by scanning the compiled
+    /// classes for a `main` function, which must then be the only one. `args` are forwarded
+    /// verbatim to the program, which inherits stdin, stdout and stderr. A non-zero exit is
+    /// returned as a [`RunError::Exited`].
+    ///
+    /// `java` is started with `targets.jvm.jvm-args` followed by `jvm_args`, so the latter win
+    /// where both set the same option. It is started as the [`ProcessConfig`] of the current
+    /// scope says, if there is one.
+    pub fn run(
+        config_file_path: impl AsRef<Path>,
+        jvm_args: &[String],
+        args: &[String],
+    ) -> Result<(), FygError> {
+        let mut child = Self::run_command(config_file_path.as_ref(), jvm_args, args)?.spawn()?;
+        let status = cancel::wait(&mut child)?;
+        if !status.success() {
+            return Err(RunError::Exited { code: status.code() }.into());
+        }
+
+        Ok(())
+    }
+
+    /// The `java` command that [`Fyg::run`] starts.
+    fn run_command(
+        config_file_path: &Path,
+        jvm_args: &[String],
+        args: &[String],
+    ) -> Result<Command, FygError> {
+        let config_file_path = &process_config_path(config_file_path)?;
+        let fyg_toml = Self::load(config_file_path)?;
+        let project_dir = project_dir(config_file_path);
+
+        let java = java(&fyg_toml)?;
+
+        let project = &fyg_toml.project;
+        let output = run::build_output(project_dir, &project.name, &project.version)
+            .ok_or_else(|| RunError::NotBuilt(project_dir.join("build")))?;
+
+        let main_class = match fyg_toml.build.as_ref().and_then(|b| b.main_class.clone()) {
+            Some(main_class) => main_class,
+            None => {
+                let mut candidates =
+                    run::find_main_classes(&project_dir.join("build").join("classes"))?;
+                match candidates.len() {
+                    0 => return Err(RunError::MainClassNotFound.into()),
+                    1 => candidates.remove(0),
+                    _ => return Err(RunError::AmbiguousMainClass(candidates).into()),
+                }
+            }
+        };
+
+        let mut classpath = vec![output];
+        classpath.extend(build::classpath(&fyg_toml, project_dir));
+        let lock = Self::lock(project_dir, &fyg_toml, false)?;
+        classpath.extend(lock.jars(&["common", "jvm"], &fyg_toml.maven_repositories()?)?);
+        // the project has been built, so a pinned compiler is already installed
+        classpath.extend(kotlinc(&fyg_toml).ok().and_then(|k| toolchain::kotlin_stdlib(&k)));
+
+        let mut cmd = Command::new(java);
+        cmd.args(self::jvm_args(&fyg_toml))
+            .args(jvm_args)
+            .arg("-cp")
+            .arg(std::env::join_paths(&classpath)?)
+            .arg(&main_class)
+            .args(args);
+        ProcessConfig::configure(&mut cmd);
+        Ok(cmd)
+    }
+
+    /// Builds the project, then compiles and runs its tests under `src/test/kotlin/`.
+    ///
+    /// The framework is chosen by `test.framework`, defaulting to JUnit 5. `filter` selects a
+    /// single class or a `Class#method`. If any test fails, a [`testing::TestError`] is returned.
+    pub fn test(
+        config_file_path: impl AsRef<Path>,
+        filter: Option<&str>,
+    ) -> Result<TestSummary, FygError> {
+        let options = TestOptions {
+            filter: filter.map(str::to_string),
+            ..Default::default()
+        };
+        Self::test_with_options(config_file_path, &options)
+    }
+
+    /// Runs the project's tests like [`Fyg::test`], with the given [`TestOptions`].
+    ///
+    /// Failed tests are run again up to `test.retry` times, unless [`TestOptions::no_retry`] is
+    /// set. With [`TestOptions::integration`], the tests in `test.integration-tests` are compiled
+    /// and run instead of those in `src/test/kotlin/`, with the project's classes on their
+    /// classpath. The build and the tests run under [`TestOptions::process`] if it is set.
+    pub fn test_with_options(
+        config_file_path: impl AsRef<Path>,
+        options: &TestOptions,
+    ) -> Result<TestSummary, FygError> {
+        process_scope(options.process.as_ref(), || {
+            Self::test_project(&process_config_path(config_file_path.as_ref())?, options)
+        })
+    }
+
+    /// Runs the project's tests like [`Fyg::test_with_options`], in the current process scope.
+    fn test_project(
+        config_file_path: &Path,
+        options: &TestOptions,
+    ) -> Result<TestSummary, FygError> {
+        Self::build(config_file_path)?;
+
+        let fyg_toml = Self::load(config_file_path)?;
+        let project_dir = project_dir(config_file_path);
+        let test_config = fyg_toml.test.as_ref();
+        if let Some(commands) = fyg_toml.hooks.as_ref().and_then(|h| h.pre_test.as_deref()) {
+            hooks::run("pre-test", commands, project_dir)?;
+        }
+
+        let framework = match test_config.and_then(|t| t.framework.as_deref()) {
+            Some(name) => name.parse()?,
+            None => TestFramework::JUnit5,
+        };
+
+        let kotlinc = kotlinc(&fyg_toml)?;
+        let java = java(&fyg_toml)?;
+
+        let launcher = maven::fetch_jar(&TestFramework::launcher())?;
+        let mut classpath = vec![project_dir.join("build").join("classes"), launcher.clone()];
+        for artifact in framework.artifacts() {
+            classpath.push(maven::fetch_jar(&artifact)?);
+        }
+        classpath.extend(build::classpath(&fyg_toml, project_dir));
+        let lock = Self::lock(project_dir, &fyg_toml, false)?;
+        let repositories = fyg_toml.maven_repositories()?;
+        classpath.extend(lock.jars(&["common", "jvm", "test"], &repositories)?);
+
+        let build_dir = project_dir.join("build");
+        let mut test_sets = vec![(
+            project_dir.join("src").join("test").join("kotlin"),
+            build_dir.join("test-classes"),
+        )];
+        if options.integration {
+            let integration = test_config
+                .and_then(|t| t.integration_tests.as_ref())
+                .filter(|i| i.enabled)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "integration tests are not enabled, set test.integration-tests.enabled \
+                         = true in fyg.toml"
+                    )
+                })?;
+            if !integration.depends_on_test.unwrap_or(false) {
+                test_sets.clear();
+            }
+            test_sets.push((
+                project_dir.join(
+                    integration
+                        .source_dir
+                        .as_deref()
+                        .unwrap_or(testing::DEFAULT_INTEGRATION_TEST_DIR),
+                ),
+                build_dir.join("integration-test-classes"),
+            ));
+        }
+
+        // each set is compiled against those before it, and the set to run ends up first
+        let compiler_options = compiler_options(&fyg_toml, BuildMode::Debug)?;
+        for (src_dir, out_dir) in test_sets {
+            let sources = build::collect_sources(&src_dir, "kt")?;
+            if sources.is_empty() {
+                return Err(BuildError::NoSources(src_dir).into());
+            }
+
+            std::fs::create_dir_all(&out_dir)?;
+            build::compile_kotlin(&kotlinc, &sources, &classpath, &out_dir, &compiler_options)?;
+            classpath.insert(0, out_dir);
+        }
+
+        classpath.extend(toolchain::kotlin_stdlib(&kotlinc));
+        // the launcher jar is passed with -jar, so it doesn't need to be on the test classpath
+        classpath.retain(|path| *path != launcher);
+
+        let summary = testing::run_tests(
+            &java,
+            &launcher,
+            framework,
+            &classpath,
+            &fyg_toml,
+            project_dir,
+            options,
+        );
+        let post_test = fyg_toml.hooks.as_ref().and_then(|h| h.post_test.as_deref());
+        let hooked = post_test.map_or(Ok(()), |commands| {
+            hooks::run("post-test", commands, project_dir)
+        });
+        // tests that failed to run are reported over a failed hook
+        let summary = summary?;
+        hooked?;
+        Ok(summary)
+    }
+}
+
+/// Generates the Kotlin bindings of each of `cinterops` for the Kotlin/Native `target`, writing
+/// them to `cinterop/` in `out_dir`, and returns the `.klib` files they are in.
+fn cinterop(
+    project_dir: &Path,
+    cinterops: &[CinteropConfig],
+    target: &str,
+    out_dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    if cinterops.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let tool = toolchain::kotlin_native_tool("cinterop")
+        .ok_or(BuildError::NativeToolNotFound("cinterop"))?;
+    let out_dir = out_dir.join("cinterop");
+    std::fs::create_dir_all(&out_dir)?;
+
+    let mut klibs = Vec::new();
+    for config in cinterops {
+        klibs.push(build::cinterop(&tool, config, project_dir, target, &out_dir)?);
+    }
+    Ok(klibs)
+}
+
+/// Writes the resource script of the Windows target `target` to `out_dir` and returns the step
+/// name and `windres` command that compile it, along with the object the command writes, which
+/// is linked into the binary. Returns `None` if the target has no resources.
+fn windows_resources(
+    mingw: &MinGwTarget,
+    project_dir: &Path,
+    (name, target): (&str, &str),
+    out_dir: &Path,
+) -> anyhow::Result<Option<(String, Command, PathBuf)>> {
+    let Some(script) = mingw.resource_script(project_dir) else {
+        return Ok(None);
+    };
+    let windres = toolchain::windres(target).ok_or(BuildError::WindresNotFound)?;
+    let script_path = out_dir.join("resources.rc");
+    std::fs::write(&script_path, script)?;
+
+    let include_dir = match &mingw.resource_file {
+        Some(file) => project_dir.join(file).parent().unwrap_or(project_dir).to_path_buf(),
+        None => project_dir.to_path_buf(),
+    };
+    let object = out_dir.join("resources.o");
+    let cmd = build::windres_command(&windres, &script_path, &include_dir, &object);
+    Ok(Some((format!("windres {}", name), cmd, object)))
+}
+
+/// Downloads the `.klib` of the dependency `coordinate` built for the Kotlin/Native `target`.
+///
+/// A `group:artifact` coordinate takes its version from `[dependencies.common]`.
+fn native_library(fyg_toml: &FygToml, coordinate: &str, target: &str) -> anyhow::Result<PathBuf> {
+    let (group, artifact, version) = deps::parse_coordinate(coordinate)?;
+    let declared = || {
+        fyg_toml
+            .dependencies
+            .as_ref()?
+            .iter_coordinates("common")
+            .find(|c| c.group == group && c.artifact == artifact)
+            .map(|c| c.version)
+    };
+    let Some(version) = version.or_else(declared) else {
+        anyhow::bail!(
+            "{} is in targets.native.binary.export-libraries but has no version in \
+             [dependencies.common], give it as group:artifact:version",
+            coordinate
+        );
+    };
+    native_klib(&fyg_toml.maven_repositories()?, &group, &artifact, &version, target)
+}
+
+/// Downloads the `.klib` of every dependency in the `native` scope, and the `ios` scope for iOS
+/// targets, built for the Kotlin/Native target `(key, target)`.
+///
+/// Only the dependencies declared in `fyg.toml` are downloaded, not what they depend on.
+fn native_dependencies(
+    fyg_toml: &FygToml,
+    (key, target): (&str, &str),
+) -> anyhow::Result<Vec<PathBuf>> {
+    let scope = match key.starts_with("ios-") {
+        true => "ios",
+        false => "native",
+    };
+    let scopes = DependenciesConfig::visible_from(scope);
+    let declared: Vec<_> = resolver::declared(fyg_toml)
+        .into_iter()
+        .filter(|d| d.scope != "common" && scopes.contains(&d.scope))
+        .collect();
+    if declared.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let repositories = fyg_toml.maven_repositories()?;
+    declared
+        .iter()
+        .map(|d| native_klib(&repositories, &d.group, &d.artifact, &d.version, target))
+        .collect()
+}
+
+/// Downloads the `.klib` of `group:artifact` built for the Kotlin/Native `target`.
+fn native_klib(
+    repositories: &[maven::Repository],
+    group: &str,
+    artifact: &str,
+    version: &str,
+    target: &str,
+) -> anyhow::Result<PathBuf> {
+    // multiplatform libraries publish each native target as an artifact of its own, such as
+    // kotlinx-coroutines-core-iosarm64
+    let artifact = format!("{}-{}", artifact, target.replace('_', ""));
+    let version = match lock::is_dynamic(version) {
+        true => maven::latest_version(repositories, group, &artifact)?,
+        false => version.to_string(),
+    };
+    let coordinate = maven::Coordinate::new(group, &artifact, &version);
+    let (klib, _) = maven::fetch(repositories, &coordinate, "klib")?;
+    Ok(klib)
+}
+
+/// Fails unless the project can be published, which only a JVM project that isn't a workspace
+/// root can be.
+fn check_publishable(fyg_toml: &FygToml) -> anyhow::Result<()> {
+    if fyg_toml.workspace.is_some() {
+        anyhow::bail!("a workspace root can't be published, publish each member instead");
+    }
+    let jvm = fyg_toml.targets.as_ref().and_then(|t| t.jvm.as_ref());
+    if jvm.is_some_and(|jvm| !jvm.enabled) {
+        anyhow::bail!("only the JVM target can be published, and it is disabled");
+    }
+    Ok(())
+}
+
+/// Runs the project's post-build hooks, if it has any, after a build or a cache hit.
+fn post_build_hooks(
+    fyg_toml: &FygToml,
+    project_dir: &Path,
+    listener: &dyn BuildListener,
+) -> anyhow::Result<()> {
+    if let Some(commands) = fyg_toml.hooks.as_ref().and_then(|h| h.post_build.as_deref()) {
+        step(listener, "run post-build hooks", || {
+            hooks::run("post-build", commands, project_dir)
+        })?;
+    }
+    Ok(())
+}
+
+/// The arguments `targets.jvm.jvm-args` passes to every `java` the project runs in.
+fn jvm_args(fyg_toml: &FygToml) -> Vec<String> {
+    fyg_toml
+        .targets
+        .as_ref()
+        .and_then(|t| t.jvm.as_ref())
+        .and_then(|jvm| jvm.jvm_args.clone())
+        .unwrap_or_default()
+}
+
+/// Finds the `kotlinc` a project builds with, downloading the version pinned by
+/// `build.kotlin-version` if it is not installed yet.
+fn kotlinc(fyg_toml: &FygToml) -> anyhow::Result<PathBuf> {
+    match fyg_toml.build.as_ref().and_then(|b| b.kotlin_version.as_deref()) {
+        Some(version) => toolchain::install_kotlin(version),
+        None => Ok(toolchain::kotlinc().ok_or(BuildError::CompilerNotFound)?),
+    }
+}
+
+/// Finds the home of the JDK pinned by `build.java-version`, if the project pins one.
+fn java_home(fyg_toml: &FygToml) -> anyhow::Result<Option<PathBuf>> {
+    let Some(version) = fyg_toml.build.as_ref().and_then(|b| b.java_version.as_deref()) else {
+        return Ok(None);
+    };
+
+    match toolchain::jdk(version) {
+        Some(home) => Ok(Some(home)),
+        None => anyhow::bail!(
+            "could not find JDK {}, which fyg.toml asks for, install it or set JAVA_HOME",
+            version
+        ),
+    }
+}
+
+/// Finds the `java` launcher a project runs with.
+fn java(fyg_toml: &FygToml) -> anyhow::Result<PathBuf> {
+    let java = match java_home(fyg_toml)? {
+        Some(home) => toolchain::java_in(&home),
+        None => toolchain::java(),
+    };
+    Ok(java.ok_or(RunError::JavaNotFound)?)
+}
+
+/// Finds the `javac` of the JDK a project builds with.
+fn javac(fyg_toml: &FygToml) -> anyhow::Result<PathBuf> {
+    let javac = match java_home(fyg_toml)? {
+        Some(home) => toolchain::javac_in(&home),
+        None => toolchain::javac(),
+    };
+    javac.ok_or_else(|| {
+        anyhow::anyhow!(
+            "could not find javac, which is needed to compile the Java sources KAPT generates, \
+             make sure it is on your PATH or set JAVA_HOME"
+        )
+    })
+}
+
+/// Finds the `jar` tool of the JDK a project builds with.
+fn jar_tool(fyg_toml: &FygToml) -> anyhow::Result<PathBuf> {
+    let jar = match java_home(fyg_toml)? {
+        Some(home) => toolchain::jar_in(&home),
+        None => toolchain::jar(),
+    };
+    jar.ok_or_else(|| {
+        anyhow::anyhow!(
+            "could not find jar, which comes with the JDK, make sure it is on your PATH or set \
+             JAVA_HOME"
+        )
+    })
+}
+
+/// Collects the `kotlinc` settings for a project, with the profile for `mode` applied.
+fn compiler_options(fyg_toml: &FygToml, mode: BuildMode) -> anyhow::Result<CompilerOptions> {
+    let jvm = fyg_toml.targets.as_ref().and_then(|t| t.jvm.as_ref());
+    let build = fyg_toml.build.as_ref();
+    let profile = fyg_toml.profile(mode);
+
+    let mut free_args = build.and_then(|b| b.free_compiler_args.clone()).unwrap_or_default();
+    free_args.extend(profile.free_compiler_args.iter().flatten().cloned());
+
+    Ok(CompilerOptions {
+        jvm_target: jvm.and_then(|jvm| jvm.target.clone()),
+        java_home: java_home(fyg_toml)?,
+        language_version: build.and_then(|b| b.language_version.clone()),
+        api_version: build.and_then(|b| b.api_version.clone()),
+        explicit_api: build.and_then(|b| b.explicit_api),
+        opt_in: build.and_then(|b| b.opt_in.clone()).unwrap_or_default(),
+        all_warnings_as_errors: build.and_then(|b| b.all_warnings_as_errors).unwrap_or(false),
+        suppress_warnings: build.and_then(|b| b.suppress_warnings).unwrap_or(false),
+        no_null_assertions: profile.no_null_assertions.unwrap_or(false),
+        debug_symbols: profile.keeps_debug_symbols(),
+        free_args,
+        plugins: Vec::new(),
+        plugin_options: Vec::new(),
+        module_name: Some(fyg_toml.project.name.clone()),
+        friend_paths: Vec::new(),
+    })
+}
+
+/// Lists the files in `out_dir` that Kotlin/Native wrote for a binary named `base_name`, which
+/// adds an extension and sometimes a `lib` prefix depending on what it produced.
+fn native_artifacts(out_dir: &Path, base_name: &str) -> std::io::Result<Vec<PathBuf>> {
+    let library = format!("lib{}", base_name);
+    let mut artifacts = Vec::new();
+    for entry in std::fs::read_dir(out_dir)? {
+        let path = entry?.path();
+        let stem = path.file_stem().and_then(|s| s.to_str());
+        let extension = path.extension().and_then(|e| e.to_str());
+        // headers and the libraries of cinterop bindings aren't binaries
+        if stem.is_some_and(|s| s == base_name || s == library)
+            && !matches!(extension, Some("h" | "klib"))
+        {
+            artifacts.push(path);
+        }
+    }
+    artifacts.sort();
+    Ok(artifacts)
+}
+
+/// Records the artifacts of a build in `build/`, so a build restored from the cache can report
+/// them too.
+/// The sources of the project at `config_file_path`, or of every member if it is a workspace
+/// root, as listed in a build report.
+fn report_sources(config_file_path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let fyg_toml = FygToml::from_file(config_file_path)?;
+    let dirs = match fyg_toml.workspace {
+        Some(_) => Workspace::load(config_file_path)?
+            .members
+            .into_iter()
+            .map(|member| member.dir)
+            .collect(),
+        None => vec![project_dir(config_file_path).to_path_buf()],
+    };
+
+    let mut sources = Vec::new();
+    for dir in dirs {
+        sources.extend(build::collect_sources(&dir.join("src").join("kotlin"), "kt")?);
+    }
+    Ok(sources)
+}
+
+fn write_artifacts(project_dir: &Path, artifacts: &[PathBuf]) -> anyhow::Result<()> {
+    let build_dir = project_dir.join("build");
+    std::fs::create_dir_all(&build_dir)?;
+    let lines: Vec<String> = artifacts
+        .iter()
+        .map(|a| a.strip_prefix(project_dir).unwrap_or(a).display().to_string() + "\n")
+        .collect();
+    std::fs::write(build_dir.join(ARTIFACTS_FILE), lines.concat())?;
+    Ok(())
+}
+
+/// Reads the artifacts recorded by [`write_artifacts`], which are missing from builds cached
+/// before they were recorded.
+fn read_artifacts(project_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let content = match std::fs::read_to_string(project_dir.join("build").join(ARTIFACTS_FILE)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    Ok(content.lines().map(|line| project_dir.join(line)).collect())
+}
+
+/// Prefixes every line of `text` with `[name] `, so the output of members built in parallel can
+/// be told apart.
+fn prefix_lines(name: &str, text: &str) -> String {
+    text.lines()
+        .map(|line| format!("[{}] {}", name, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the directory containing the given `fyg.toml`.
+fn project_dir(config_file_path: &Path) -> &Path {
+    match config_file_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+/// The type of project that will be exported when the project is built. 
+/// 
+/// This is shown at https://kotlinlang.org/docs/multiplatform/multiplatform-build-native-binaries.html#declare-binaries
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FygBinaryType {
+    /// Factory method: `executable`
+    Executable,
+
+    /// Factory method: `test`
+    Test,
+
+    /// Factory method: `sharedLib`
+    SharedLib,
+
+    /// Factory method: `staticLib`
+    StaticLib,
+
+    /// Factory method: `framework`
+    Framework,
+
+    /// A framework for every enabled Apple target, bundled into one `.xcframework` with
+    /// `xcodebuild -create-xcframework`.
+    #[serde(rename = "xcFramework")]
+    XCFramework,
+}
+
+impl std::fmt::Display for FygBinaryType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            FygBinaryType::Executable => "executable",
+            FygBinaryType::Test => "test",
+            FygBinaryType::SharedLib => "sharedLib",
+            FygBinaryType::StaticLib => "staticLib",
+            FygBinaryType::Framework => "framework",
+            FygBinaryType::XCFramework => "xcFramework",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// The version of a project: a semantic version, or a `-SNAPSHOT` of one that is being worked
+/// towards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    pub snapshot: bool,
+}
+
+/// Which part of a [`Version`] `fyg version bump` increments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BumpKind {
+    Major,
+    Minor,
+    Patch,
+    /// Starts work on the next patch release, as a `-SNAPSHOT`.
+    Snapshot,
+}
+
+impl Version {
+    /// Whether this is a `-SNAPSHOT` of a release still being worked on.
+    pub fn is_snapshot(&self) -> bool {
+        self.snapshot
+    }
+
+    /// The version after this one.
+    ///
+    /// A snapshot is bumped to the release it leads up to if that is a release of the kind
+    /// asked for, so `1.2.4-SNAPSHOT` bumps to `1.2.4` for a patch but to `1.3.0` for a minor
+    /// release.
+    pub fn bump(&self, kind: BumpKind) -> Version {
+        let release = Version {
+            snapshot: false,
+            ..*self
+        };
+        match kind {
+            BumpKind::Major if self.snapshot && self.minor == 0 && self.patch == 0 => release,
+            BumpKind::Major => Version {
+                major: self.major + 1,
+                minor: 0,
+                patch: 0,
+                snapshot: false,
+            },
+            BumpKind::Minor if self.snapshot && self.patch == 0 => release,
+            BumpKind::Minor => Version {
+                minor: self.minor + 1,
+                patch: 0,
+                ..release
+            },
+            BumpKind::Patch if self.snapshot => release,
+            BumpKind::Patch => Version {
+                patch: self.patch + 1,
+                ..release
+            },
+            BumpKind::Snapshot => Version {
+                patch: self.patch + 1,
+                snapshot: true,
+                ..release
+            },
+        }
+    }
+}
+
+impl Ord for Version {
+    /// Orders versions by their numbers, with a snapshot coming before the release it leads up
+    /// to.
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.major, self.minor, self.patch, !self.snapshot).cmp(&(
+            other.major,
+            other.minor,
+            other.patch,
+            !other.snapshot,
+        ))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl std::str::FromStr for Version {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let version = semver::Version::parse(s)
+            .map_err(|e| anyhow::anyhow!("'{}' is not a valid semantic version: {}", s, e))?;
+        let snapshot = match version.pre.as_str() {
+            "" => false,
+            "SNAPSHOT" => true,
+            _ => anyhow::bail!("'{}' has a pre-release other than -SNAPSHOT", s),
+        };
+        if !version.build.is_empty() {
+            anyhow::bail!("'{}' has build metadata, which fyg doesn't manage", s);
+        }
+        Ok(Version {
+            major: version.major,
+            minor: version.minor,
+            patch: version.patch,
+            snapshot,
+        })
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if self.snapshot {
+            write!(f, "-SNAPSHOT")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for BumpKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "major" => Ok(BumpKind::Major),
+            "minor" => Ok(BumpKind::Minor),
+            "patch" => Ok(BumpKind::Patch),
+            "snapshot" => Ok(BumpKind::Snapshot),
+            other => anyhow::bail!(
+                "unknown version bump '{}', expected major, minor, patch or snapshot",
+                other
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        path::{Path, PathBuf},
+        process::Command,
+        time::Duration,
+    };
+
+    use crate::{
+        BuildListener, BumpKind, FygBinaryType, ProcessConfig, Version,
+        audit::{self, Severity},
+        build::{
+            self, BuildError, BuildMode, BuildOptions, CompilerWarning, report::REPORT_DIR,
+        },
+        cache::{self, BuildCache, CleanOptions},
+        cancel,
+        config::{
+            self, BuildConfig, BuildProfile, CoverageFormat, DependenciesConfig, DependencyValue,
+            DokkaFormat, ExcludeRule, HooksConfig, OptimizationLevel, PomMetadata, PublishConfig,
+            RepositoryConfig, SbomFormat, UserConfig,
+        },
+        coverage,
+        deps::{self, RemoveOutcome},
+        doctor::{self, CheckStatus},
+        env,
+        error::FygError,
+        hash, http,
+        incremental::{self, Fingerprints, Recompile, SourceHeader},
+        jar, kapt,
+        licenses::{self, DependencyLicense, LicensePolicy},
+        lint,
+        lock::{FygLock, LOCK_FILE_NAME, LockedArtifact},
+        maven::{self, Coordinate, VersionRange},
+        migrate::{self, MigrateSource},
+        output,
+        plan::PlannedAction,
+        process_config_path, publish,
+        release::{self, ReleaseOptions, ReleaseStep},
+        resolver::{self, DependencyGraph, GraphFormat, Node},
+        run, sbom,
+        scaffold::{
+            InitOptions,
+            ci::{self, CiProvider},
+            source::SourceTemplate,
+            template::ProjectTemplate,
+            vcs::Vcs,
+        },
+        testing::{
+            self,
+            report::{self, Outcome, TestReport},
+            TestFramework, TestSummary,
+        },
+        toml::{DiagnosticSeverity, FygToml},
+        toolchain,
+        workspace::{Member, Workspace},
+        xml::Element,
+        Fyg,
+    };
+
+    /// Creates an empty project directory under the system temp dir with a single source file.
+    fn temp_project(name: &str, source: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("fyg-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("Failed to create project dir");
+        Fyg::init(&dir, FygToml::new(name, "com.example"), &InitOptions::default())
+            .expect("Failed to init project");
+        std::fs::write(dir.join("src/kotlin/com/example/Main.kt"), source)
+            .expect("Failed to write source");
+        dir
+    }
+
+    #[test]
+    fn test_parse_fyg_toml() {
+        let config = FygToml::from_file("fyg.toml").expect("Failed to parse fyg.toml");
+        
+        assert_eq!(config.project.name, "dropbear");
+        assert_eq!(config.project.group, "com.dropbear");
+        assert_eq!(config.project.version, "1.0.0-SNAPSHOT");
+        
+        let build = config.build.expect("build section should exist");
+        assert_eq!(build.multiplatform, Some(true));
+        assert_eq!(build.languages, Some(vec!["kotlin".to_string(), "java".to_string()]));
+        
+        let targets = config.targets.expect("targets section should exist");
+        let jvm = targets.jvm.expect("jvm target should exist");
+        assert!(jvm.enabled);
+        assert_eq!(jvm.target, Some("17".to_string()));
+    }
+
+    #[test]
+    fn test_create_new_fyg_toml() {
+        let config = FygToml::new("my-app", "com.example");
+        
+        assert_eq!(config.project.name, "my-app");
+        assert_eq!(config.project.group, "com.example");
+        assert_eq!(config.project.version, "1.0.0-SNAPSHOT");
+    }
+
+    #[test]
+    fn test_roundtrip_serialization() {
+        let config = FygToml::new("test-app", "org.test");
+        let toml_str = config.to_toml_string().expect("Failed to serialize");
+        let parsed = FygToml::from_str(&toml_str).expect("Failed to parse back");
+        
+        assert_eq!(parsed.project.name, config.project.name);
+        assert_eq!(parsed.project.group, config.project.group);
+    }
+
+    #[test]
+    fn test_json_mirrors_toml() {
+        let config = FygToml::new("test-app", "org.test");
+        let json = config.to_json().expect("Failed to convert to JSON");
+
+        assert_eq!(json.pointer("/project/name"), Some(&"test-app".into()));
+        assert_eq!(json.pointer("/project/version"), Some(&"1.0.0-SNAPSHOT".into()));
+        assert!(json.get("build").is_none());
+    }
+
+    #[test]
+    fn test_info_field() {
+        let config = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.2.0"
+
+            [targets.jvm]
+            enabled = true
+            target = "17"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.field("project.version").unwrap(), Some("1.2.0".into()));
+        assert_eq!(config.field("targets.jvm.target").unwrap(), Some("17".into()));
+        assert_eq!(config.field("targets.jvm.enabled").unwrap(), Some(true.into()));
+        assert_eq!(config.field("project.description").unwrap(), None);
+        assert_eq!(config.field("targets.js-node.enabled").unwrap(), None);
+    }
+
+    #[test]
+    fn test_json_config_round_trip() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [build]
+            main-class = "com.example.AppKt"
+
+            [dependencies.common]
+            "org.jetbrains.kotlinx:kotlinx-coroutines-core" = "1.8.0"
+            "#,
+        )
+        .unwrap();
+
+        let json = fyg_toml.to_json_string().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value.pointer("/build/main_class"), Some(&"com.example.AppKt".into()));
+        assert_eq!(
+            value.pointer("/dependencies/common/org.jetbrains.kotlinx:kotlinx-coroutines-core"),
+            Some(&"1.8.0".into())
+        );
+
+        let parsed = FygToml::from_json(&json).unwrap();
+        assert_eq!(parsed.to_toml_string().unwrap(), fyg_toml.to_toml_string().unwrap());
+
+        let path = std::env::temp_dir().join(format!("fyg-test-{}.json", std::process::id()));
+        fyg_toml.write_to_file(&path).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), json);
+        assert_eq!(FygToml::from_file(&path).unwrap().project.name, "app");
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_init_writes_gitignore() {
+        let dir = std::env::temp_dir().join(format!("fyg-test-gitignore-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let options = InitOptions {
+            extra_gitignore_patterns: vec!["*.log".to_string()],
+            ..Default::default()
+        };
+        Fyg::init(&dir, FygToml::new("app", "com.example"), &options).unwrap();
+        let gitignore = std::fs::read_to_string(dir.join(".gitignore")).unwrap();
+        let patterns: Vec<&str> = gitignore.lines().filter(|l| !l.starts_with('#')).collect();
+        for pattern in ["build/", ".fyg/", "*.class", "*.jar", ".idea/", "*.iml", "*.log"] {
+            assert!(patterns.contains(&pattern), "{} missing from {}", pattern, gitignore);
+        }
+
+        // an existing .gitignore is left alone
+        std::fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        Fyg::init(&dir, FygToml::new("app", "com.example"), &options).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join(".gitignore")).unwrap(), "target/\n");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_env() {
+        let dir = temp_project("env", "fun main() {}\n");
+        let config = dir.join("fyg.toml");
+        let fyg_toml = std::fs::read_to_string(&config).unwrap().replacen(
+            "version = \"1.0.0-SNAPSHOT\"",
+            "version = \"${env.FYG_TEST_ENV_VERSION:-1.0.0}\"\n\
+             description = \"${env.FYG_TEST_ENV_UNSET} and ${env.FYG_TEST_ENV_UNSET}\"",
+            1,
+        );
+        std::fs::write(&config, fyg_toml).unwrap();
+
+        let env = env::run(Some(&config)).unwrap();
+        let variables: Vec<(&str, Option<&str>)> = env
+            .variables
+            .iter()
+            .map(|v| (v.name.as_str(), v.default.as_deref()))
+            .collect();
+        assert_eq!(
+            variables,
+            [("FYG_TEST_ENV_VERSION", Some("1.0.0")), ("FYG_TEST_ENV_UNSET", None)]
+        );
+        assert!(env.variables.iter().all(|v| v.value.is_none()));
+        assert_eq!(env.cache_dir, toolchain::cache_dir().unwrap());
+
+        let json = serde_json::to_value(&env).unwrap();
+        assert!(json.get("FYG_CONFIG").is_some_and(|v| v.is_string()));
+        assert!(env::run(None).unwrap().variables.is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_color_allowed() {
+        use std::ffi::OsStr;
+
+        assert!(output::color_allowed(None, None));
+        assert!(output::color_allowed(Some(OsStr::new("")), Some(OsStr::new("xterm-256color"))));
+        assert!(!output::color_allowed(Some(OsStr::new("1")), None));
+        assert!(!output::color_allowed(None, Some(OsStr::new("dumb"))));
+    }
+
+    #[test]
+    fn test_user_config() {
+        let path = std::env::temp_dir()
+            .join(format!("fyg-test-user-config-{}", std::process::id()))
+            .join("config.toml");
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+
+        let defaults = UserConfig::default().new_project("app", None);
+        assert_eq!(defaults.project.group, "com.example");
+        assert_eq!(defaults.project.version, "1.0.0-SNAPSHOT");
+
+        let mut user_config = UserConfig::default();
+        assert_eq!(user_config.set("default-group", "org.acme").unwrap(), None);
+        user_config.set("default_version", "0.1.0").unwrap();
+        user_config.set("default-authors", "Jane Doe, John Doe").unwrap();
+        user_config.set("java-home", "/opt/jdk-21").unwrap();
+        assert!(user_config.set("default-version", "next").is_err());
+        assert!(user_config.set("kotlin-version", "2.0.21").is_err());
+        assert_eq!(user_config.default_version.as_deref(), Some("0.1.0"));
+        assert_eq!(user_config.java_home, Some(PathBuf::from("/opt/jdk-21")));
+
+        user_config.write_to_file(&path).unwrap();
+        let user_config = UserConfig::from_file(&path).unwrap();
+        assert_eq!(
+            user_config.get("default-authors").unwrap(),
+            Some(::toml::Value::Array(vec!["Jane Doe".into(), "John Doe".into()]))
+        );
+        assert_eq!(user_config.get("cache-dir").unwrap(), None);
+
+        let fyg_toml = user_config.new_project("app", None);
+        assert_eq!(fyg_toml.project.group, "org.acme");
+        assert_eq!(fyg_toml.project.version, "0.1.0");
+        assert_eq!(fyg_toml.project.authors.as_ref().unwrap().len(), 2);
+        assert_eq!(user_config.new_project("app", Some("com.other")).project.group, "com.other");
+
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_init_writes_editorconfig() {
+        let dir = std::env::temp_dir()
+            .join(format!("fyg-test-editorconfig-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Fyg::init(&dir, FygToml::new("app", "com.example"), &InitOptions::default()).unwrap();
+
+        let editorconfig = std::fs::read_to_string(dir.join(".editorconfig")).unwrap();
+        assert!(editorconfig.starts_with("root = true\n"), "{}", editorconfig);
+        let section = editorconfig.split("[*.kt]\n").nth(1).unwrap();
+        for setting in ["indent_style = space", "indent_size = 4", "insert_final_newline = true"] {
+            assert!(section.lines().any(|l| l == setting), "{} missing from {}", setting, section);
+        }
+
+        // an existing .editorconfig is left alone, and --no-editorconfig writes none
+        std::fs::write(dir.join(".editorconfig"), "root = true\n").unwrap();
+        Fyg::init(&dir, FygToml::new("app", "com.example"), &InitOptions::default()).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join(".editorconfig")).unwrap(), "root = true\n");
+        std::fs::remove_file(dir.join(".editorconfig")).unwrap();
+        let options = InitOptions {
+            editorconfig: false,
+            ..Default::default()
+        };
+        Fyg::init(&dir, FygToml::new("app", "com.example"), &options).unwrap();
+        assert!(!dir.join(".editorconfig").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_new_with_git() {
+        if toolchain::find_executable("git").is_none() {
+            return;
+        }
+        // the commit is made with a fallback identity if the machine running the tests has none
+        let base = std::env::temp_dir().join(format!("fyg-test-git-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let options = InitOptions {
+            vcs: Vcs::Git,
+            ..Default::default()
+        };
+        Fyg::new(&base, FygToml::new("app", "com.example"), &options).unwrap();
+
+        let git = |args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(base.join("app")).output();
+            String::from_utf8(output.unwrap().stdout).unwrap()
+        };
+        assert_eq!(git(&["log", "--format=%s"]).trim(), "Initial commit");
+        let files = git(&["ls-files"]);
+        assert!(files.lines().any(|f| f == "fyg.toml"), "{}", files);
+        assert!(files.lines().any(|f| f == ".gitignore"), "{}", files);
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_init_with_mercurial() {
+        assert_eq!("hg".parse::<Vcs>().unwrap(), Vcs::Mercurial);
+        assert_eq!("none".parse::<Vcs>().unwrap(), Vcs::None);
+        assert!("svn".parse::<Vcs>().is_err());
+
+        let dir = std::env::temp_dir().join(format!("fyg-test-hg-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let options = InitOptions {
+            vcs: Vcs::Mercurial,
+            ..Default::default()
+        };
+        // the ignore file is written first, so it is there even without hg to commit it
+        match Fyg::init(&dir, FygToml::new("app", "com.example"), &options) {
+            Ok(()) => assert!(dir.join(".hg").is_dir()),
+            Err(FygError::ToolNotFound { tool, .. }) => assert_eq!(tool, "hg"),
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+        let hgignore = std::fs::read_to_string(dir.join(".hgignore")).unwrap();
+        assert!(hgignore.starts_with("syntax: glob\n"), "{}", hgignore);
+        for pattern in ["build/", "*.class", "*.jar"] {
+            assert!(hgignore.lines().any(|l| l == pattern), "{} missing", pattern);
+        }
+        assert!(!dir.join(".gitignore").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_new_from_template() {
+        let dir = std::env::temp_dir().join(format!("fyg-test-template-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!("ktor-server".parse::<ProjectTemplate>().unwrap(), ProjectTemplate::KtorServer);
+        assert!("rails".parse::<ProjectTemplate>().is_err());
+
+        let options = InitOptions {
+            template: Some(ProjectTemplate::Library),
+            ..Default::default()
+        };
+        Fyg::new(&dir, FygToml::new("greeter", "org.acme"), &options).unwrap();
+        let project = dir.join("greeter");
+        let fyg_toml = FygToml::from_file(project.join("fyg.toml")).unwrap();
+        assert_eq!(fyg_toml.project.name, "greeter");
+        assert_eq!(fyg_toml.project.group, "org.acme");
+        assert!(fyg_toml.validate().iter().all(|d| d.severity != DiagnosticSeverity::Error));
+
+        let source =
+            std::fs::read_to_string(project.join("src/kotlin/org/acme/Greeter.kt")).unwrap();
+        assert!(source.starts_with("package org.acme\n"));
+        assert!(project.join("src/test/kotlin/org/acme/GreeterTest.kt").exists());
+        assert!(project.join(".gitignore").exists());
+
+        for template in [
+            ProjectTemplate::Console,
+            ProjectTemplate::KtorServer,
+            ProjectTemplate::ComposeMultiplatform,
+            ProjectTemplate::SpringBoot,
+        ] {
+            let name = template.to_string();
+            let options = InitOptions {
+                template: Some(template),
+                ..Default::default()
+            };
+            Fyg::new(&dir, FygToml::new(&name, "com.example"), &options).unwrap();
+            let fyg_toml = FygToml::from_file(dir.join(&name).join("fyg.toml")).unwrap();
+            assert_eq!(fyg_toml.project.name, name);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_scaffold() {
+        let dir = temp_project("scaffold", "fun main() {}\n");
+        let config = dir.join("fyg.toml");
+
+        let template = SourceTemplate::DataClass { name: "User".to_string() };
+        let path = Fyg::scaffold(&config, &template, None).unwrap();
+        assert_eq!(path, dir.join("src/kotlin/com/example/User.kt"));
+        let source = std::fs::read_to_string(&path).unwrap();
+        assert!(source.starts_with("package com.example\n\ndata class User("));
+        assert!(Fyg::scaffold(&config, &template, None).is_err());
+
+        let output = Some(Path::new("app"));
+        let path = Fyg::scaffold(&config, &SourceTemplate::SpringBootApp, output).unwrap();
+        assert_eq!(path, dir.join("app/ScaffoldApplication.kt"));
+        let source = std::fs::read_to_string(&path).unwrap();
+        assert!(source.contains("runApplication<ScaffoldApplication>(*args)"));
+
+        let template = SourceTemplate::Object { name: "not-valid".to_string() };
+        assert!(Fyg::scaffold(&config, &template, None).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ci_config() {
+        let mut fyg_toml = FygToml::new("app", "com.example");
+        assert_eq!(ci::jdk_version(&fyg_toml), "17");
+
+        fyg_toml.targets = Some(
+            FygToml::from_str("[targets.jvm]\nenabled = true\ntarget = \"1.8\"")
+                .unwrap()
+                .targets
+                .unwrap(),
+        );
+        assert_eq!(ci::jdk_version(&fyg_toml), "8");
+
+        let provider: CiProvider = "GitHub".parse().unwrap();
+        assert_eq!(provider.config_path(), ".github/workflows/ci.yml");
+        let workflow = provider.config("21");
+        assert!(workflow.contains("java-version: '21'"));
+        assert!(workflow.contains("run: fyg build") && workflow.contains("run: fyg test"));
+        assert!("travis".parse::<CiProvider>().is_err());
+    }
+
+    #[test]
+    fn test_migrate_gradle() {
+        let dir = std::env::temp_dir().join(format!("fyg-test-gradle-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("settings.gradle.kts"), "rootProject.name = \"shared\"\n").unwrap();
+        std::fs::write(
+            dir.join("build.gradle.kts"),
+            r#"
+            plugins {
+                kotlin("multiplatform") version "2.0.21"
+            }
+
+            group = "com.acme"
+            version = "0.3.0" // released
+
+            val ktorVersion = "2.3.12"
+
+            kotlin {
+                jvm()
+                iosArm64()
+                sourceSets {
+                    commonMain.dependencies {
+                        implementation("io.ktor:ktor-client-core:$ktorVersion")
+                    }
+                    val commonTest by getting {
+                        dependencies {
+                            implementation(kotlin("test"))
+                        }
+                    }
+                }
+            }
+
+            tasks.withType<Test> { useJUnitPlatform() }
+            "#,
+        )
+        .unwrap();
+
+        let migration = migrate::migrate(&dir, MigrateSource::Gradle).unwrap();
+        let fyg_toml = migration.fyg_toml;
+        assert_eq!(fyg_toml.project.name, "shared");
+        assert_eq!(fyg_toml.project.group, "com.acme");
+        assert_eq!(fyg_toml.project.version, "0.3.0");
+
+        let build = fyg_toml.build.as_ref().unwrap();
+        assert_eq!(build.multiplatform, Some(true));
+        assert_eq!(build.kotlin_version.as_deref(), Some("2.0.21"));
+        assert_eq!(
+            fyg_toml.targets.as_ref().unwrap().enabled_targets(),
+            vec!["jvm", "ios-arm64"]
+        );
+
+        let dependencies = fyg_toml.dependencies.as_ref().unwrap();
+        assert_eq!(dependencies.find("io.ktor:ktor-client-core"), vec!["common"]);
+        assert_eq!(dependencies.find("org.jetbrains.kotlin:kotlin-test"), vec!["test"]);
+        assert_eq!(
+            migration.warnings,
+            vec!["could not translate `tasks.withType<Test> { ... }`"]
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_migrate_maven() {
+        let dir = std::env::temp_dir().join(format!("fyg-test-maven-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("pom.xml"),
+            r#"<project>
+                <parent>
+                    <groupId>com.acme</groupId>
+                    <artifactId>acme-parent</artifactId>
+                    <version>1</version>
+                </parent>
+                <artifactId>billing</artifactId>
+                <version>2.1.0</version>
+                <properties>
+                    <kotlin.version>1.9.22</kotlin.version>
+                    <jackson.version>2.16.0</jackson.version>
+                </properties>
+                <dependencies>
+                    <dependency>
+                        <groupId>com.fasterxml.jackson.core</groupId>
+                        <artifactId>jackson-databind</artifactId>
+                        <version>${jackson.version}</version>
+                    </dependency>
+                    <dependency>
+                        <groupId>jakarta.servlet</groupId>
+                        <artifactId>jakarta.servlet-api</artifactId>
+                        <version>6.0.0</version>
+                        <scope>provided</scope>
+                    </dependency>
+                    <dependency>
+                        <groupId>junit</groupId>
+                        <artifactId>junit</artifactId>
+                        <version>4.13.2</version>
+                        <scope>test</scope>
+                    </dependency>
+                </dependencies>
+            </project>"#,
+        )
+        .unwrap();
+
+        let migration = migrate::migrate(&dir, MigrateSource::Maven).unwrap();
+        let fyg_toml = migration.fyg_toml;
+        assert_eq!(fyg_toml.project.name, "billing");
+        assert_eq!(fyg_toml.project.group, "com.acme");
+        assert_eq!(fyg_toml.project.version, "2.1.0");
+        assert_eq!(
+            fyg_toml.build.as_ref().unwrap().kotlin_version.as_deref(),
+            Some("1.9.22")
+        );
+
+        let dependencies = fyg_toml.dependencies.as_ref().unwrap();
+        assert_eq!(
+            dependencies.find("com.fasterxml.jackson.core:jackson-databind"),
+            vec!["common"]
+        );
+        assert_eq!(dependencies.find("jakarta.servlet:jakarta.servlet-api"), vec!["jvm"]);
+        assert_eq!(dependencies.find("junit:junit"), vec!["test"]);
+        assert!(migration.warnings.len() == 1 && migration.warnings[0].contains("parent POM"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_doctor_reports_missing_lock() {
+        let dir = temp_project("doctor", "fun main() {}\n");
+        let config_path = dir.join("fyg.toml");
+        let mut fyg_toml = FygToml::from_file(&config_path).unwrap();
+        fyg_toml.build = Some(BuildConfig {
+            use_local_maven: Some(false),
+            ..Default::default()
+        });
+        fyg_toml.repositories = Some(
+            [("maven-central".to_string(), RepositoryConfig::Enabled(false))].into(),
+        );
+        fyg_toml.write_to_file(&config_path).unwrap();
+
+        let checks = doctor::run(Some(&config_path)).unwrap();
+        let lock = checks.iter().find(|check| check.name == LOCK_FILE_NAME).unwrap();
+        assert_eq!(lock.status, CheckStatus::Warning);
+        assert!(checks.iter().all(|check| check.name != "repository"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[ignore = "needs kotlinc"]
+    fn test_build_hello_world() {
+        let dir = temp_project("hello", "fun main() { println(\"Hello, world!\") }\n");
+        let result = Fyg::build(dir.join("fyg.toml")).expect("Failed to build");
+
+        assert!(dir.join("build/classes/MainKt.class").exists());
+        assert_eq!(result.artifacts, vec![dir.join("build/classes")]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_compiler_warnings() {
+        let stderr = "src/kotlin/Main.kt:3:9: warning: variable 'x' is never used\n\
+                      \x20   val x = 1\n\
+                      \x20       ^\n\
+                      C:\\project\\src\\Util.kt:10:1: warning: unused function\n\
+                      warning: language version 1.6 is deprecated\n\
+                      src/kotlin/Main.kt:4:1: error: unresolved reference: foo\n";
+        let warnings = CompilerWarning::parse(stderr);
+        assert_eq!(
+            warnings,
+            vec![
+                CompilerWarning {
+                    file: Some(PathBuf::from("src/kotlin/Main.kt")),
+                    line: Some(3),
+                    column: Some(9),
+                    message: "variable 'x' is never used".to_string(),
+                },
+                CompilerWarning {
+                    file: Some(PathBuf::from("C:\\project\\src\\Util.kt")),
+                    line: Some(10),
+                    column: Some(1),
+                    message: "unused function".to_string(),
+                },
+                CompilerWarning {
+                    file: None,
+                    line: None,
+                    column: None,
+                    message: "language version 1.6 is deprecated".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            warnings[0].to_string(),
+            "src/kotlin/Main.kt:3:9: variable 'x' is never used"
+        );
+    }
+
+    #[test]
+    #[ignore = "needs kotlinc"]
+    fn test_build_syntax_error() {
+        let dir = temp_project("broken", "fun main() { println(\"oops\" }\n");
+        let err = Fyg::build(dir.join("fyg.toml")).expect_err("Build should fail");
+
+        match err {
+            FygError::CompilationFailed { stderr, .. } => assert!(stderr.contains("error")),
+            other => panic!("Expected a compilation failure, got {:?}", other),
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_build_listener() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Default)]
+        struct Recorder(Mutex<Vec<String>>);
+
+        impl BuildListener for Recorder {
+            fn on_step_started(&self, step: &str) {
+                self.0.lock().unwrap().push(format!("started {}", step));
+            }
+
+            fn on_step_finished(&self, step: &str, _duration: Duration) {
+                self.0.lock().unwrap().push(format!("finished {}", step));
+            }
+
+            fn on_error(&self, err: &FygError) {
+                self.0.lock().unwrap().push(format!("error {}", err));
+            }
+        }
+
+        let dir = temp_project("listener", "fun main() {}\n");
+        let config_path = dir.join("fyg.toml");
+        let mut config = std::fs::read_to_string(&config_path).unwrap();
+        config.push_str("\n[targets.jvm]\nenabled = false\n\n[build.sbom]\nenabled = true\n");
+        std::fs::write(&config_path, config).unwrap();
+
+        let recorder = Arc::new(Recorder::default());
+        let options = BuildOptions {
+            no_cache: true,
+            listener: Some(recorder.clone()),
+            ..Default::default()
+        };
+        let result = Fyg::build_with_options(&config_path, &options).unwrap();
+        assert_eq!(result.artifacts, vec![dir.join("build/sbom.cdx.json")]);
+        assert_eq!(
+            recorder.0.lock().unwrap().drain(..).collect::<Vec<_>>(),
+            vec!["started write sbom", "finished write sbom"]
+        );
+
+        std::fs::remove_dir_all(dir.join("src")).unwrap();
+        assert!(Fyg::build_with_options(&config_path, &options).is_err());
+        let events = recorder.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert!(events[0].starts_with("error no source files found"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_build_report() {
+        let dir = temp_project("report", "fun main() {}\n");
+        let config_path = dir.join("fyg.toml");
+        let mut config = std::fs::read_to_string(&config_path).unwrap();
+        config.push_str("\n[targets.jvm]\nenabled = false\n\n[build.sbom]\nenabled = true\n");
+        std::fs::write(&config_path, config).unwrap();
+
+        let options = BuildOptions {
+            no_cache: true,
+            report: true,
+            ..Default::default()
+        };
+        Fyg::build_with_options(&config_path, &options).unwrap();
+        let report_path = dir.join(REPORT_DIR).join("index.html");
+        let report = std::fs::read_to_string(&report_path).unwrap();
+        assert!(report.contains("<p>Succeeded</p>"));
+        assert!(report.contains("<td>write sbom</td>"));
+        assert!(report.contains("<tr><td>build/sbom.cdx.json</td><td>"));
+        assert!(report.contains("<li>src/kotlin/com/example/Main.kt</li>"));
+
+        // failed builds are reported too
+        std::fs::remove_dir_all(dir.join("src")).unwrap();
+        assert!(Fyg::build_with_options(&config_path, &options).is_err());
+        let report = std::fs::read_to_string(&report_path).unwrap();
+        assert!(report.contains("Failed<pre class=\"error\">no source files found"));
+        assert!(report.contains("<h2>Sources (0)</h2>"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_fyg_errors() {
+        let dir = temp_project("errors", "fun main() {}\n");
+
+        let missing = dir.join("missing").join("fyg.toml");
+        match FygToml::from_file(&missing) {
+            Err(FygError::ConfigNotFound(path)) => assert_eq!(path, missing),
+            other => panic!("Expected a missing config, got {:?}", other),
+        }
+        assert!(matches!(
+            FygToml::from_str("[project\n"),
+            Err(FygError::ConfigParse(_))
+        ));
+
+        let config_path = dir.join("fyg.toml");
+        assert!(Fyg::check(&config_path).unwrap().iter().all(|d| !d.is_error()));
+        let mut fyg_toml = FygToml::from_file(&config_path).unwrap();
+        fyg_toml.project.version = String::new();
+        fyg_toml.write_to_file(&config_path).unwrap();
+        match Fyg::check(&config_path) {
+            Err(FygError::ValidationError(diagnostics)) => {
+                assert!(diagnostics.iter().any(|d| d.field == "project.version"))
+            }
+            other => panic!("Expected validation errors, got {:?}", other),
+        }
+
+        assert!(matches!(
+            FygError::from(anyhow::Error::from(BuildError::CompilerNotFound)),
+            FygError::ToolNotFound { tool, .. } if tool == "kotlinc"
+        ));
+        // context added to an error is kept, along with the error itself
+        let wrapped = FygError::from(
+            anyhow::Error::from(BuildError::NoSources(dir.clone())).context("member failed"),
+        );
+        assert_eq!(wrapped.to_string(), "member failed");
+        assert!(matches!(
+            wrapped.downcast_ref::<BuildError>(),
+            Some(BuildError::NoSources(_))
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_release_build_rejects_js_source_maps() {
+        let dir = temp_project("js-release", "fun main() {}\n");
+        let config_path = dir.join("fyg.toml");
+        let mut fyg_toml = FygToml::from_file(&config_path).unwrap();
+        fyg_toml.targets = Some(
+            toml::from_str(
+                "jvm = { enabled = false }\njs-browser = { enabled = true, source-maps = true }\n",
+            )
+            .unwrap(),
+        );
+        fyg_toml.write_to_file(&config_path).unwrap();
+
+        let options = BuildOptions {
+            mode: BuildMode::Release,
+            ..Default::default()
+        };
+        let err = Fyg::build_with_options(&config_path, &options).expect_err("Build should fail");
+        assert!(err.to_string().contains("source maps"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[ignore = "needs jar"]
+    fn test_fat_jar() {
+        let jar_tool = toolchain::jar().expect("jar not found");
+
+        let dir = std::env::temp_dir().join(format!("fyg-test-fat-jar-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let write = |path: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        };
+        write("dependency/org/example/Library.class");
+        write("dependency/META-INF/LIBRARY.SF");
+        write("classes/com/example/MainKt.class");
+        let dependency = dir.join("library.jar");
+        jar::create(&jar_tool, &dependency, &[dir.join("dependency")], None).unwrap();
+
+        let output = dir.join("app-all.jar");
+        let exclude = vec!["META-INF/*.SF".to_string()];
+        jar::fat_jar(
+            &jar_tool,
+            &output,
+            &[dependency, dir.join("classes")],
+            "com.example.MainKt",
+            &exclude,
+        )
+        .unwrap();
+
+        let listing = Command::new(&jar_tool).arg("tf").arg(&output).output().unwrap();
+        let entries = String::from_utf8(listing.stdout).unwrap();
+        assert!(entries.lines().any(|e| e == "com/example/MainKt.class"));
+        assert!(entries.lines().any(|e| e == "org/example/Library.class"));
+        assert!(!entries.contains("LIBRARY.SF"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_jar_glob_matches() {
+        assert!(jar::glob_matches("META-INF/**", "META-INF/versions/9/a.class"));
+        assert!(!jar::glob_matches("META-INF/*.SF", "META-INF/x/A.SF"));
+    }
+
+    #[test]
+    fn test_source_roots() {
+        let dir = temp_project("sources", "fun main() {}\n");
+        std::fs::create_dir_all(dir.join("build/generated/ksp/com/example")).unwrap();
+
+        let roots = build::source_roots(&dir, false).unwrap();
+        assert_eq!(roots, vec![dir.join("src/kotlin")]);
+
+        std::fs::create_dir_all(dir.join("src/java")).unwrap();
+        let roots = build::source_roots(&dir, true).unwrap();
+        assert_eq!(
+            roots,
+            vec![
+                dir.join("src/kotlin"),
+                dir.join("src/java"),
+                dir.join("build/generated/ksp")
+            ]
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_dokka_config() {
+        let config = FygToml::from_str(
+            r#"
+            [project]
+            name = "docs"
+            group = "com.example"
+            version = "1.0.0"
+
+            [build.dokka]
+            enabled = true
+            output-format = "javadoc"
+            "#,
+        )
+        .unwrap();
+        let dokka = config.build.unwrap().dokka.unwrap();
+        assert!(dokka.enabled);
+        assert_eq!(dokka.output_format, Some(DokkaFormat::Javadoc));
+        assert_eq!(dokka.output_dir, None);
+    }
+
+    #[test]
+    fn test_processor_needs_version() {
+        let config = FygToml::new("processed", "com.example");
+        let processors = vec!["com.squareup.moshi:moshi-kotlin-codegen".to_string()];
+        let err = resolver::processor_jars(&config, &processors).unwrap_err();
+        assert!(err.to_string().contains("needs a version"));
+    }
+
+    #[test]
+    fn test_kapt_options() {
+        let options = [
+            ("dagger.fastInit".to_string(), "enabled".to_string()),
+            ("kapt.kotlin.generated".to_string(), "/tmp/é\0".to_string()),
+        ];
+        // as written by Java's ObjectOutputStream
+        assert_eq!(
+            kapt::encode_options(&options),
+            concat!(
+                "rO0ABXdAAAAAAgAPZGFnZ2VyLmZhc3RJbml0AAdlbmFibGVkABVrYXB0LmtvdGxpbi5nZW5lcmF0ZWQ",
+                "ACS90bXAvw6nAgA=="
+            )
+        );
+
+        let config = FygToml::from_str(
+            r#"
+            [project]
+            name = "processed"
+            group = "com.example"
+            version = "1.0.0"
+
+            [build.ksp]
+            processors = ["com.squareup.moshi:moshi-kotlin-codegen:1.15.0"]
+
+            [build.kapt]
+            processors = ["com.google.dagger:dagger-compiler:2.50"]
+            correct-error-types = true
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().iter().any(|d| d.field == "build.kapt"
+            && d.severity == DiagnosticSeverity::Warning));
+    }
+
+    #[test]
+    fn test_jvm_args() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            version = "1.0.0"
+            group = "com.example"
+
+            [targets.jvm]
+            enabled = true
+            jvm-args = ["-Xmx512m", "-Dmode=dev"]
+
+            [test]
+            jvm-args = ["-Dmode=test"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(super::jvm_args(&fyg_toml), vec!["-Xmx512m", "-Dmode=dev"]);
+        assert_eq!(
+            fyg_toml.test.as_ref().unwrap().jvm_args.as_deref(),
+            Some(&["-Dmode=test".to_string()][..])
+        );
+        assert!(super::jvm_args(&FygToml::new("app", "com.example")).is_empty());
+    }
+
+    #[test]
+    fn test_test_system_properties() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            version = "1.0.0"
+            group = "com.example"
+
+            [test.system-properties]
+            "db.url" = "jdbc:h2:mem:${project.name}"
+            "db.password" = "${env.FYG_TEST_UNSET_PASSWORD:-secret}"
+
+            [test.environment]
+            APP_MODE = "test"
+            "#,
+        )
+        .unwrap();
+        let test = fyg_toml.test.unwrap();
+        let properties = test.system_properties.unwrap();
+        assert_eq!(properties["db.url"], "jdbc:h2:mem:app");
+        assert_eq!(properties["db.password"], "secret");
+        assert_eq!(test.environment.unwrap()["APP_MODE"], "test");
+    }
+
+    #[test]
+    fn test_integration_test_config() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [test.system-properties]
+            "db.url" = "jdbc:h2:mem:test"
+            "log.level" = "debug"
+
+            [test.integration-tests]
+            enabled = true
+            depends-on-test = true
+
+            [test.integration-tests.system-properties]
+            "db.url" = "jdbc:postgresql://localhost/test"
+            "#,
+        )
+        .unwrap();
+        let test = fyg_toml.test.unwrap();
+        let integration = test.integration_tests.as_ref().unwrap();
+        assert_eq!(integration.depends_on_test, Some(true));
+        assert_eq!(integration.source_dir, None);
+
+        let properties = test.for_integration_tests().system_properties.unwrap();
+        assert_eq!(properties["db.url"], "jdbc:postgresql://localhost/test");
+        assert_eq!(properties["log.level"], "debug");
+        assert_eq!(test.system_properties.unwrap()["db.url"], "jdbc:h2:mem:test");
+    }
+
+    #[test]
+    fn test_cinterop_config() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [targets.linux-x64]
+            enabled = true
+
+            [targets.windows-x64]
+            enabled = true
+
+            [targets.macos-arm64]
+            enabled = false
+
+            [[targets.native.cinterop]]
+            name = "libcurl"
+            def-file = "src/nativeInterop/cinterop/libcurl.def"
+            package = "libcurl"
+            compiler-opts = ["-I/usr/include/curl"]
+            linker-opts = ["-lcurl"]
+            "#,
+        )
+        .unwrap();
+        let targets = fyg_toml.targets.unwrap();
+        assert_eq!(
+            targets.native_targets(),
+            vec![("linux-x64", "linux_x64"), ("windows-x64", "mingw_x64")]
+        );
+
+        let cinterop = &targets.native.unwrap().cinterop.unwrap()[0];
+        assert_eq!(cinterop.name, "libcurl");
+        assert_eq!(cinterop.def_file, "src/nativeInterop/cinterop/libcurl.def");
+        assert_eq!(cinterop.package.as_deref(), Some("libcurl"));
+        assert_eq!(cinterop.compiler_opts, Some(vec!["-I/usr/include/curl".to_string()]));
+        assert_eq!(cinterop.linker_opts, Some(vec!["-lcurl".to_string()]));
+    }
+
+    #[test]
+    fn test_xcframework_validation() {
+        let without_apple = FygToml::from_str(
+            r#"
+            [targets.linux-x64]
+            enabled = true
+
+            [targets.native.binary]
+            type = "xcFramework"
+            "#,
+        )
+        .unwrap();
+        let fields: Vec<String> =
+            without_apple.validate().into_iter().map(|d| d.field).collect();
+        assert!(fields.contains(&"targets.native.binary.type".to_string()));
+        assert!(fields.contains(&"targets.linux-x64".to_string()));
+
+        let with_apple = FygToml::from_str(
+            r#"
+            [targets.ios-arm64]
+            enabled = true
+
+            [targets.ios-simulator-arm64]
+            enabled = true
+
+            [targets.native.binary]
+            type = "xcFramework"
+            base-name = "Shared"
+            "#,
+        )
+        .unwrap();
+        let binary = with_apple.targets.as_ref().unwrap().native.as_ref().unwrap();
+        let binary = binary.binary.as_ref().unwrap();
+        assert!(matches!(binary.binary_type, FygBinaryType::XCFramework));
+        assert_eq!(binary.binary_type.to_string(), "xcFramework");
+        assert!(with_apple
+            .validate()
+            .iter()
+            .all(|d| d.field != "targets.native.binary.type"));
+    }
+
+    #[test]
+    fn test_windows_arm64_target() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [targets.windows-x64]
+            enabled = true
+
+            [targets.windows-arm64]
+            enabled = true
+
+            [targets.native.binary]
+            type = "framework"
+            "#,
+        )
+        .unwrap();
+        let targets = fyg_toml.targets.as_ref().unwrap();
+        assert_eq!(
+            targets.native_targets(),
+            vec![("windows-x64", "mingw_x64"), ("windows-arm64", "mingw_arm64")]
+        );
+        assert!(targets.enabled_targets().contains(&"windows-arm64"));
+        assert!(fyg_toml
+            .validate()
+            .iter()
+            .any(|d| d.field == "targets.windows-arm64"
+                && d.severity == DiagnosticSeverity::Warning));
+    }
+
+    #[test]
+    fn test_mingw_target() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [targets.windows-x64]
+            enabled = true
+            resource-file = "src/windows/app.rc"
+            manifest = "src/windows/app.manifest"
+
+            [targets.linux-x64]
+            enabled = true
+            "#,
+        )
+        .unwrap();
+        let targets = fyg_toml.targets.as_ref().unwrap();
+        assert!(targets.mingw_target("linux-x64").is_none());
+        let mingw = targets.mingw_target("windows-x64").unwrap();
+        let project_dir = Path::new("project");
+        let path = |file: &str| project_dir.join(file).display().to_string().replace('\\', "/");
+        assert_eq!(
+            mingw.resource_script(project_dir).unwrap(),
+            format!(
+                "#include \"{}\"\n1 24 \"{}\"\n",
+                path("src/windows/app.rc"),
+                path("src/windows/app.manifest")
+            )
+        );
+        let manifest_only = config::MinGwTarget {
+            enabled: true,
+            manifest: Some("app.manifest".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            manifest_only.resource_script(project_dir).unwrap(),
+            format!("1 24 \"{}\"\n", path("app.manifest"))
+        );
+        assert!(config::MinGwTarget::default().resource_script(project_dir).is_none());
+
+        let cmd = build::windres_command(
+            Path::new("x86_64-w64-mingw32-windres"),
+            Path::new("out/resources.rc"),
+            Path::new("project/src/windows"),
+            Path::new("out/resources.o"),
+        );
+        assert_eq!(
+            build::command_line(&cmd),
+            "x86_64-w64-mingw32-windres --input-format rc --output-format coff --include-dir \
+             project/src/windows -i out/resources.rc -o out/resources.o"
+        );
+
+        let cmd = build::kotlinc_native_command(
+            Path::new("kotlinc-native"),
+            &[PathBuf::from("main.kt")],
+            &[],
+            &[],
+            Path::new("app"),
+            ("windows-x64", "mingw_x64"),
+            None,
+            &["out/resources.o".to_string()],
+        );
+        let args: Vec<String> =
+            cmd.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect();
+        let object = args.iter().position(|arg| arg == "out/resources.o").unwrap();
+        assert_eq!(args[object - 1], "-linker-option");
+        assert!(!args.iter().any(|arg| arg == "-resource" || arg == "-manifest"));
+        assert_eq!(args.last().map(String::as_str), Some("main.kt"));
+    }
+
+    #[test]
+    fn test_linux_arm32_target() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [targets.linux-arm32]
+            enabled = true
+            "#,
+        )
+        .unwrap();
+        let targets = fyg_toml.targets.as_ref().unwrap();
+        assert_eq!(targets.native_targets(), vec![("linux-arm32", "linux_arm32_hfp")]);
+        assert_eq!(targets.linux_arm32_linker(), None);
+        let warned = fyg_toml.validate().iter().any(|d| d.field == "targets.linux-arm32");
+        assert_eq!(warned, !cfg!(target_arch = "arm"));
+
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [targets.linux-arm32]
+            enabled = true
+
+            [targets.native.binary]
+            type = "executable"
+
+            [targets.native.binary.native-args.linux-arm32]
+            linker-opts = ["-fuse-ld=/usr/bin/arm-linux-gnueabihf-ld"]
+            "#,
+        )
+        .unwrap();
+        let targets = fyg_toml.targets.as_ref().unwrap();
+        assert_eq!(
+            targets.linux_arm32_linker().as_deref(),
+            Some("-fuse-ld=/usr/bin/arm-linux-gnueabihf-ld")
+        );
+        assert!(fyg_toml.validate().iter().all(|d| d.field != "targets.linux-arm32"));
+    }
+
+    #[test]
+    fn test_native_framework_options() {
+        let framework = FygToml::from_str(
+            r#"
+            [targets.ios-arm64]
+            enabled = true
+
+            [targets.native.binary]
+            type = "framework"
+            export-libraries = ["org.jetbrains.kotlinx:kotlinx-datetime"]
+            embed-bitcode = true
+            "#,
+        )
+        .unwrap();
+        let binary = framework.targets.as_ref().unwrap().native.as_ref().unwrap();
+        let binary = binary.binary.as_ref().unwrap();
+        assert!(binary.is_framework());
+        assert_eq!(
+            binary.export_libraries,
+            Some(vec!["org.jetbrains.kotlinx:kotlinx-datetime".to_string()])
+        );
+        assert!(framework
+            .validate()
+            .iter()
+            .all(|d| d.field != "targets.native.binary.embed-bitcode"));
+
+        let executable = FygToml::from_str(
+            r#"
+            [targets.linux-x64]
+            enabled = true
+
+            [targets.native.binary]
+            type = "executable"
+            embed-bitcode = true
+            "#,
+        )
+        .unwrap();
+        assert!(executable
+            .validate()
+            .iter()
+            .any(|d| d.field == "targets.native.binary.embed-bitcode"
+                && d.severity == DiagnosticSeverity::Error));
+    }
+
+    #[test]
+    fn test_native_args() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [targets.linux-x64]
+            enabled = true
+
+            [targets.native.binary]
+            type = "executable"
+            linker-opts = ["-lcurl"]
+            compiler-opts = ["-O2"]
+
+            [targets.native.binary.native-args.linux-x64]
+            linker-opts = ["-L/usr/lib/x86_64-linux-gnu"]
+            "#,
+        )
+        .unwrap();
+        let binary = fyg_toml.targets.as_ref().unwrap().native.as_ref().unwrap();
+        let binary = binary.binary.as_ref().unwrap();
+
+        let linux = binary.args_for("linux-x64");
+        assert_eq!(
+            linux.linker_opts,
+            Some(vec!["-lcurl".to_string(), "-L/usr/lib/x86_64-linux-gnu".to_string()])
+        );
+        assert_eq!(linux.compiler_opts, Some(vec!["-O2".to_string()]));
+
+        let macos = binary.args_for("macos-arm64");
+        assert_eq!(macos.linker_opts, Some(vec!["-lcurl".to_string()]));
+    }
+
+    #[test]
+    fn test_platform_dependency_scopes() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [dependencies.common]
+            "org.jetbrains.kotlinx:kotlinx-datetime" = "0.6.0"
+
+            [dependencies.native]
+            "io.ktor:ktor-client-curl" = "2.3.0"
+
+            [dependencies.ios]
+            "io.ktor:ktor-client-darwin" = "2.3.0"
+            "io.ktor:ktor-client-curl" = "2.3.0"
+
+            [dependencies.android]
+            "io.ktor:ktor-client-android" = "2.3.0"
+
+            [dependencies.jvm]
+            "io.ktor:ktor-client-android" = "2.3.0"
+            "#,
+        )
+        .unwrap();
+        let deps = fyg_toml.dependencies.as_ref().unwrap();
+        assert_eq!(deps.find("io.ktor:ktor-client-curl"), vec!["native", "ios"]);
+        assert_eq!(deps.find("io.ktor:ktor-client-darwin"), vec!["ios"]);
+
+        let scopes: Vec<(String, &str)> = resolver::declared(&fyg_toml)
+            .into_iter()
+            .map(|d| (d.artifact, d.scope))
+            .collect();
+        assert!(scopes.contains(&("ktor-client-android".to_string(), "jvm")));
+        assert!(scopes.contains(&("ktor-client-curl".to_string(), "native")));
+
+        // ios builds on native, but jvm and android don't see each other's dependencies
+        let fields: Vec<String> = fyg_toml
+            .validate()
+            .into_iter()
+            .map(|d| d.field)
+            .filter(|field| field.starts_with("dependencies."))
+            .collect();
+        assert_eq!(fields, ["dependencies.ios"]);
+        assert!(!DependenciesConfig::visible_from("jvm").contains(&"native"));
+    }
+
+    #[test]
+    #[ignore = "needs javac"]
+    fn test_find_main_classes() {
+        let javac = toolchain::javac().expect("javac not found");
+
+        let dir = std::env::temp_dir().join(format!("fyg-test-main-class-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let sources = dir.join("src/com/example");
+        std::fs::create_dir_all(&sources).unwrap();
+        std::fs::write(
+            sources.join("App.java"),
+            "package com.example;\n\npublic class App {\n    \
+             static final long LIMIT = 1L;\n    \
+             public static void main(String[] args) {}\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            sources.join("Util.java"),
+            "package com.example;\n\nclass Util {\n    void main(String[] args) {}\n}\n",
+        )
+        .unwrap();
+
+        let classes = dir.join("classes");
+        let status = Command::new(javac)
+            .arg("-d")
+            .arg(&classes)
+            .arg(sources.join("App.java"))
+            .arg(sources.join("Util.java"))
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let main_classes = run::find_main_classes(&classes).unwrap();
+        assert_eq!(main_classes, vec!["com.example.App".to_string()]);
+        assert!(run::find_main_classes(&dir.join("missing")).unwrap().is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_test_summary() {
+        let output = "\
+Test run finished after 64 ms
+[         3 containers found      ]
+[         4 tests found           ]
+[         1 tests skipped         ]
+[         2 tests successful      ]
+[         1 tests failed          ]
+";
+        let summary = TestSummary::parse(output);
+
+        let expected = TestSummary { passed: 2, failed: 1, ignored: 1, coverage: None };
+        assert_eq!(summary, expected);
+        assert_eq!("kotest".parse::<TestFramework>().unwrap(), TestFramework::Kotest);
+        assert!("spek".parse::<TestFramework>().is_err());
+    }
+
+    #[test]
+    fn test_failed_tests() {
+        let output = "\
+Failures (2):
+  JUnit Jupiter:CalculatorTest:divides(int)
+    MethodSource [className = 'com.example.CalculatorTest', methodName = 'divides', \
+methodParameterTypes = 'int']
+    => org.opentest4j.AssertionFailedError: expected: <2> but was: <3>
+  Kotest:CalculatorSpec:adds
+    ClassSource [className = 'com.example.CalculatorSpec', filePosition = null]
+    => java.lang.AssertionError
+Test run finished after 64 ms
+";
+        assert_eq!(
+            testing::failed_tests(output, TestFramework::JUnit5),
+            vec!["com.example.CalculatorTest#divides(int)", "com.example.CalculatorSpec"]
+        );
+        assert_eq!(
+            testing::failed_tests(output, TestFramework::Kotest),
+            vec!["com.example.CalculatorTest", "com.example.CalculatorSpec"]
+        );
+        let passed = "[         2 tests successful      ]\n";
+        assert!(testing::failed_tests(passed, TestFramework::JUnit5).is_empty());
+
+        let mut fyg_toml = FygToml::new("app", "com.example");
+        fyg_toml.test = FygToml::from_str("[test]\nretry = 5\ntimeout-seconds = 30")
+            .unwrap()
+            .test;
+        assert_eq!(fyg_toml.test.as_ref().unwrap().timeout_seconds, Some(30));
+        assert!(fyg_toml.validate().iter().any(|d| d.field == "test.retry"
+            && d.severity == DiagnosticSeverity::Warning));
+    }
+
+    #[test]
+    fn test_test_reports() {
+        let dir = std::env::temp_dir().join(format!("fyg-test-reports-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("retry-1")).unwrap();
+        std::fs::write(
+            dir.join("TEST-junit-jupiter.xml"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <testsuite name="JUnit Jupiter" tests="3" skipped="1" failures="1" errors="0">
+                <properties><property name="java.version" value="17"/></properties>
+                <testcase name="adds()" classname="com.example.CalculatorTest" time="0.012"/>
+                <testcase name="divides()" classname="com.example.CalculatorTest" time="0.003">
+                    <failure message="expected: &lt;2&gt; but was: &lt;3&gt;"/>
+                </testcase>
+                <testcase name="later()" classname="com.example.CalculatorTest" time="0">
+                    <skipped/>
+                </testcase>
+            </testsuite>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("retry-1/TEST-junit-jupiter.xml"),
+            r#"<testsuite name="JUnit Jupiter" tests="1">
+                <testcase name="divides()" classname="com.example.CalculatorTest" time="0.002"/>
+            </testsuite>"#,
+        )
+        .unwrap();
+
+        let cases = report::read_xml_reports(std::slice::from_ref(&dir)).unwrap();
+        assert_eq!(cases.len(), 3);
+        assert_eq!(cases[1].outcome, Outcome::Failed);
+        assert_eq!(cases[1].failure.as_deref(), Some("expected: <2> but was: <3>"));
+        assert_eq!(cases[2].outcome, Outcome::Skipped);
+
+        let cases = report::read_xml_reports(&[dir.clone(), dir.join("retry-1")]).unwrap();
+        assert_eq!(cases.len(), 3);
+        assert!(cases[1].outcome == Outcome::Passed && cases[1].retried);
+
+        let path = report::write_html(&cases, "calculator", &dir.join("html")).unwrap();
+        let html = std::fs::read_to_string(path).unwrap();
+        assert!(html.contains("2 passed, 0 failed, 1 skipped"));
+        assert!(html.contains("<td>divides()</td><td>passed on retry</td>"));
+        assert!("HTML".parse::<TestReport>().is_ok() && "pdf".parse::<TestReport>().is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let report = Element::parse(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+            <!DOCTYPE report PUBLIC "-//JACOCO//DTD Report 1.1//EN" "report.dtd">
+            <report name="app">
+                <package name="com/example">
+                    <counter type="LINE" missed="10" covered="10"/>
+                </package>
+                <counter type="INSTRUCTION" missed="12" covered="40"/>
+                <counter type="LINE" missed="5" covered="15"/>
+            </report>"#,
+        )
+        .unwrap();
+        assert_eq!(coverage::line_coverage(&report), Some(0.75));
+
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [test.coverage]
+            enabled = true
+            report-formats = ["html", "csv"]
+            minimum-coverage = 0.8
+            "#,
+        )
+        .unwrap();
+        let config = fyg_toml.test.as_ref().unwrap().coverage.as_ref().unwrap();
+        assert_eq!(config.report_formats, Some(vec![CoverageFormat::Html, CoverageFormat::Csv]));
+        assert!(coverage::check_minimum(config, 0.75).is_err());
+        assert!(coverage::check_minimum(config, 0.8).is_ok());
+
+        let fields: Vec<String> = fyg_toml.validate().into_iter().map(|d| d.field).collect();
+        assert!(fields.contains(&"test.coverage.report-formats".to_string()));
+        assert!(!fields.contains(&"test.coverage.minimum-coverage".to_string()));
+    }
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            hash::sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash::sha256_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn test_maven_checksums() {
+        assert_eq!(hash::sha1_hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+        assert_eq!(
+            hash::sha1_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+            "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+        );
+        assert_eq!(hash::md5_hex(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(
+            hash::md5_hex(b"The quick brown fox jumps over the lazy dog"),
+            "9e107d9d372bb6826bd81d3542a419d6"
+        );
+    }
+
+    #[test]
+    fn test_publish_pom() {
+        let config = FygToml::from_str(
+            r#"
+            [project]
+            name = "client"
+            group = "com.example"
+            version = "1.0.0"
+            description = "Talks to <servers>"
+
+            [dependencies.common]
+            "org.jetbrains.kotlinx:kotlinx-cli" = "LATEST"
+
+            [dependencies.test]
+            "junit:junit" = "4.13.2"
+
+            [repositories]
+            staging = { type = "maven", url = "https://repo.example.com/staging" }
+
+            [publish]
+            repositories = ["staging", "releases"]
+
+            [publish.signing]
+            key-id = "0123ABCD"
+            password-env = "SIGNING_PASSWORD"
+            "#,
+        )
+        .unwrap();
+        let locked = |group: &str, artifact: &str, version: &str, scope: &str| LockedArtifact {
+            group: group.to_string(),
+            artifact: artifact.to_string(),
+            version: version.to_string(),
+            classifier: None,
+            scope: scope.to_string(),
+            sha256: "0".repeat(64),
+            repository: "https://repo.maven.apache.org/maven2".to_string(),
+            transitive: false,
+        };
+        let lock = FygLock {
+            artifacts: vec![
+                locked("org.jetbrains.kotlinx", "kotlinx-cli", "0.3.6", "common"),
+                locked("junit", "junit", "4.13.2", "test"),
+            ],
+        };
+
+        let pom = publish::pom(&config, Path::new("."), &lock).unwrap();
+        let project = Element::parse(&pom).unwrap();
+        assert_eq!(project.child_text("artifactId"), Some("client"));
+        assert_eq!(project.child_text("description"), Some("Talks to <servers>"));
+        let dependencies: Vec<_> = project
+            .child("dependencies")
+            .unwrap()
+            .children("dependency")
+            .map(|d| (d.child_text("version").unwrap(), d.child_text("scope").unwrap()))
+            .collect();
+        assert_eq!(dependencies, vec![("0.3.6", "compile"), ("4.13.2", "test")]);
+
+        let diagnostics = config.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].field, "publish.repositories");
+        assert!(diagnostics[0].message.contains("'releases'"));
+
+        let signing = config.publish.unwrap().signing.unwrap();
+        assert_eq!(signing.key_id.as_deref(), Some("0123ABCD"));
+        assert!(!format!("{:?}", signing).contains("0123ABCD"));
+    }
+
+    #[test]
+    fn test_publish_pom_project_metadata() {
+        let config = FygToml::from_str(
+            r#"
+            [project]
+            name = "client"
+            group = "com.example"
+            version = "1.0.0"
+            license = "Apache-2.0"
+            homepage = "https://example.com/client"
+
+            [project.repository]
+            url = "https://github.com/example/client"
+            connection = "scm:git:https://github.com/example/client.git"
+            developer-connection = "scm:git:ssh://git@github.com/example/client.git"
+
+            [[project.developers]]
+            id = "jdoe"
+            name = "Jo Doe"
+            email = "jo@example.com"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_empty());
+
+        let pom = publish::pom(&config, Path::new("."), &FygLock::default()).unwrap();
+        let project = Element::parse(&pom).unwrap();
+        assert_eq!(project.child_text("url"), Some("https://example.com/client"));
+        let license = project.child("licenses").unwrap().child("license").unwrap();
+        assert_eq!(license.child_text("name"), Some("Apache-2.0"));
+        assert_eq!(
+            license.child_text("url"),
+            Some("https://spdx.org/licenses/Apache-2.0.html")
+        );
+        let developer = project.child("developers").unwrap().child("developer").unwrap();
+        assert_eq!(developer.child_text("id"), Some("jdoe"));
+        assert_eq!(developer.child_text("email"), Some("jo@example.com"));
+        let scm = project.child("scm").unwrap();
+        assert_eq!(
+            scm.child_text("developerConnection"),
+            Some("scm:git:ssh://git@github.com/example/client.git")
+        );
+
+        // [publish.pom-metadata] wins over [project]
+        let mut config = config;
+        config.publish = Some(PublishConfig {
+            pom_metadata: Some(PomMetadata {
+                url: Some("https://client.example.com".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let pom = publish::pom(&config, Path::new("."), &FygLock::default()).unwrap();
+        let project = Element::parse(&pom).unwrap();
+        assert_eq!(project.child_text("url"), Some("https://client.example.com"));
+        assert!(project.child("licenses").is_some());
+
+        assert!(licenses::is_known_spdx("Apache-2.0 OR MIT"));
+        assert!(licenses::is_known_spdx("GPL-2.0-only WITH Classpath-exception-2.0"));
+        assert!(!licenses::is_known_spdx("Apache 2"));
+        config.project.license = Some("Apache 2".to_string());
+        let diagnostics = config.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].field, "project.license");
+        assert!(!diagnostics[0].is_error());
+    }
+
+    #[test]
+    fn test_lint() {
+        let dir = temp_project("lint", "fun main() {}");
+        let config_path = dir.join("fyg.toml");
+        assert_eq!(Fyg::lint(&config_path, None).unwrap(), vec![]);
+
+        std::fs::create_dir_all(dir.join("src/test/kotlin")).unwrap();
+        std::fs::write(dir.join("src/test/kotlin/MainTest.kt"), "class MainTest").unwrap();
+        let mut config = std::fs::read_to_string(&config_path).unwrap();
+        config.push_str(
+            r#"
+            [targets.linux-x64]
+            enabled = true
+
+            [repositories]
+            staging = { type = "maven", url = "https://repo.example.com" }
+
+            [publish]
+            repositories = ["staging"]
+            "#,
+        );
+        std::fs::write(&config_path, config).unwrap();
+
+        let findings = Fyg::lint(&config_path, None).unwrap();
+        let ids: Vec<&str> = findings.iter().map(|f| f.lint.id).collect();
+        assert_eq!(ids, ["L001", "L002", "L003", "L004", "L005"]);
+        assert_eq!(findings[2].field, "test.framework");
+        assert_eq!(findings[2].lint.severity, DiagnosticSeverity::Hint);
+
+        let only = lint::parse_ids("l002, L005").unwrap();
+        let findings = Fyg::lint(&config_path, Some(&only)).unwrap();
+        let ids: Vec<&str> = findings.iter().map(|f| f.lint.id).collect();
+        assert_eq!(ids, ["L002", "L005"]);
+        assert!(lint::parse_ids("L001,L999").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_fyg_toml() {
+        let dir = temp_project("fmt", "fun main() {}");
+        let config_path = dir.join("fyg.toml");
+        // a freshly written fyg.toml is already formatted
+        assert_eq!(FygToml::format_file(&config_path, true).unwrap(), None);
+
+        let unformatted = r#"
+            [dependencies.common]
+            "org.jetbrains.kotlinx:kotlinx-cli" = "0.3.6"
+
+            [build]
+            multiplatform = false # not yet
+            main-class = "com.example.MainKt"
+
+            [project]
+            version = "1.0.0"
+            name = "fmt"
+            group = "com.example"
+            "#;
+        std::fs::write(&config_path, unformatted).unwrap();
+
+        let diff = FygToml::format_file(&config_path, true).unwrap().unwrap();
+        assert!(diff.contains("-            multiplatform = false # not yet"), "{}", diff);
+        assert_eq!(std::fs::read_to_string(&config_path).unwrap(), unformatted);
+
+        FygToml::format_file(&config_path, false).unwrap();
+        let formatted = std::fs::read_to_string(&config_path).unwrap();
+        let sections: Vec<&str> = formatted.lines().filter(|l| l.starts_with('[')).collect();
+        assert_eq!(sections, ["[project]", "[build]", "[dependencies.common]"]);
+        let build = formatted.split("[build]").nth(1).unwrap();
+        assert!(build.find("main-class").unwrap() < build.find("multiplatform").unwrap());
+        assert_eq!(FygToml::format_file(&config_path, true).unwrap(), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_get_set() {
+        let dir = temp_project("config-get-set", "fun main() {}");
+        let config_path = dir.join("fyg.toml");
+
+        let version = Fyg::get_setting(&config_path, "project.version").unwrap();
+        assert_eq!(version, Some("1.0.0-SNAPSHOT".into()));
+        assert_eq!(Fyg::get_setting(&config_path, "build.main-class").unwrap(), None);
+
+        let (old, new) = Fyg::set_setting(&config_path, "project.version", "2.0.0").unwrap();
+        assert_eq!((old, new), (Some("1.0.0-SNAPSHOT".into()), "2.0.0".into()));
+        Fyg::set_setting(&config_path, "build.kotlin_version", "2.0.21").unwrap();
+        Fyg::set_setting(&config_path, "build.multiplatform", "true").unwrap();
+
+        let config = FygToml::from_file(&config_path).unwrap();
+        assert_eq!(config.project.version, "2.0.0");
+        let build = config.build.unwrap();
+        assert_eq!(build.kotlin_version.as_deref(), Some("2.0.21"));
+        assert_eq!(build.multiplatform, Some(true));
+
+        assert!(Fyg::set_setting(&config_path, "build.multiplatform", "maybe").is_err());
+        assert!(Fyg::set_setting(&config_path, "project.version", "banana").is_err());
+        let err = Fyg::get_setting(&config_path, "project.colour").unwrap_err();
+        assert!(err.to_string().contains("unknown setting 'project.colour'"), "{}", err);
+        let version = FygToml::from_file(&config_path).unwrap().project.version;
+        assert_eq!(version, "2.0.0");
+
+        let scm = "{ url = \"https://github.com/example/app\", connection = \"scm:git:x\", \
+                   developer-connection = \"scm:git:y\" }";
+        Fyg::set_setting(&config_path, "project.repository", scm).unwrap();
+        let repository = Fyg::get_setting(&config_path, "project.repository").unwrap().unwrap();
+        assert_eq!(repository["url"].as_str(), Some("https://github.com/example/app"));
+        let repository = FygToml::from_file(&config_path).unwrap().project.repository.unwrap();
+        assert_eq!(repository.developer_connection, "scm:git:y");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_add_remove_target() {
+        let dir = temp_project("targets", "fun main() {}");
+        let config_path = dir.join("fyg.toml");
+        let enabled = |key: &str| {
+            Fyg::targets(&config_path)
+                .unwrap()
+                .into_iter()
+                .any(|(target, enabled)| target == key && enabled)
+        };
+        assert_eq!(Fyg::targets(&config_path).unwrap().len(), config::KNOWN_TARGETS.len());
+        assert!(!enabled("ios-arm64"));
+
+        assert!(Fyg::add_target(&config_path, "ios-arm64").unwrap());
+        assert!(!Fyg::add_target(&config_path, "ios-arm64").unwrap());
+        assert!(enabled("ios-arm64"));
+        let targets = FygToml::from_file(&config_path).unwrap().targets.unwrap();
+        assert!(targets.ios_arm64.unwrap().enabled);
+
+        // a disabled target keeps its section
+        assert!(Fyg::remove_target(&config_path, "ios-arm64").unwrap());
+        assert!(!Fyg::remove_target(&config_path, "ios-arm64").unwrap());
+        assert!(!Fyg::remove_target(&config_path, "tvos-arm64").unwrap());
+        let targets = FygToml::from_file(&config_path).unwrap().targets.unwrap();
+        assert!(!targets.ios_arm64.unwrap().enabled);
+        assert!(targets.tvos_arm64.is_none());
+
+        let err = Fyg::add_target(&config_path, "ios-arm65").unwrap_err();
+        assert!(err.to_string().contains("unknown target 'ios-arm65'"), "{}", err);
+
+        // a project without a [targets.jvm] section is built for the JVM
+        assert!(enabled("jvm"));
+        assert!(!Fyg::add_target(&config_path, "jvm").unwrap());
+        assert!(Fyg::remove_target(&config_path, "jvm").unwrap());
+        assert!(!enabled("jvm"));
+        let targets = FygToml::from_file(&config_path).unwrap().targets.unwrap();
+        assert!(!targets.jvm.unwrap().enabled);
+        assert!(Fyg::add_target(&config_path, "jvm").unwrap());
+        assert!(enabled("jvm"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_release_plan() {
+        let dir = temp_project("release", "fun main() {}");
+        let config_path = dir.join("fyg.toml");
+
+        // nothing changes when there is nowhere to publish to
+        let err = Fyg::release(&config_path, &ReleaseOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("no repository to publish to"), "{}", err);
+
+        let mut config = std::fs::read_to_string(&config_path).unwrap();
+        config.push_str(
+            r#"
+            [repositories]
+            staging = { type = "maven", url = "https://repo.example.com" }
+
+            [publish]
+            repositories = ["staging"]
+            "#,
+        );
+        std::fs::write(&config_path, config).unwrap();
+
+        let options = ReleaseOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let release = Fyg::release(&config_path, &options).unwrap();
+        assert_eq!(release.version.to_string(), "1.0.0");
+        assert_eq!(release.next.to_string(), "1.0.1-SNAPSHOT");
+        let steps: Vec<String> = release.steps.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            steps,
+            [
+                "set version 1.0.0",
+                "build release",
+                "test",
+                "commit \"Release v1.0.0\"",
+                "tag v1.0.0",
+                "publish",
+                "set version 1.0.1-SNAPSHOT",
+                "commit \"Start 1.0.1-SNAPSHOT\""
+            ]
+        );
+        assert_eq!(
+            FygToml::from_file(&config_path).unwrap().project.version,
+            "1.0.0-SNAPSHOT"
+        );
+
+        let options = ReleaseOptions {
+            skip_tests: true,
+            no_tag: true,
+            ..options
+        };
+        let steps = Fyg::release(&config_path, &options).unwrap().steps;
+        assert!(!steps.contains(&ReleaseStep::Test));
+        assert!(!steps.iter().any(|s| matches!(s, ReleaseStep::Tag(_) | ReleaseStep::Commit(_))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_release_commits_version() {
+        let base = std::env::temp_dir().join(format!("fyg-test-release-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        let options = InitOptions {
+            vcs: Vcs::Git,
+            ..Default::default()
+        };
+        Fyg::new(&base, FygToml::new("app", "com.example"), &options).unwrap();
+        let dir = base.join("app");
+        let git = |args: &[&str]| {
+            let output = Command::new("git").args(args).current_dir(&dir).output().unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            String::from_utf8(output.stdout).unwrap()
+        };
+        git(&["config", "user.name", "fyg"]);
+        git(&["config", "user.email", "fyg@localhost"]);
+        // something else left staged isn't swept into the release commit
+        std::fs::write(dir.join("notes.txt"), "wip").unwrap();
+        git(&["add", "notes.txt"]);
+
+        let config_path = dir.join("fyg.toml");
+        let version = |s: &str| s.parse::<Version>().unwrap();
+        Fyg::set_version(&config_path, version("1.0.0"), false).unwrap();
+        release::commit(&config_path, "Release v1.0.0").unwrap();
+        release::tag(&dir, "v1.0.0").unwrap();
+        Fyg::set_version(&config_path, version("1.0.1-SNAPSHOT"), false).unwrap();
+        release::commit(&config_path, "Start 1.0.1-SNAPSHOT").unwrap();
+
+        assert!(git(&["show", "v1.0.0:fyg.toml"]).contains("version = \"1.0.0\""));
+        assert!(git(&["show", "HEAD:fyg.toml"]).contains("version = \"1.0.1-SNAPSHOT\""));
+        assert_eq!(git(&["status", "--porcelain"]), "A  notes.txt\n");
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        let version = |s: &str| s.parse::<Version>().unwrap();
+        assert!(version("1.2.3") < version("1.10.0"));
+        assert!(version("1.2.3-SNAPSHOT") < version("1.2.3"));
+        assert!(version("1.2.3-SNAPSHOT") > version("1.2.2"));
+        assert!(version("2.0.0") > version("1.99.99"));
+        assert!(version("1.2.3-SNAPSHOT").is_snapshot());
+        assert!(!version("1.2.3").is_snapshot());
+        assert!("1.2".parse::<Version>().is_err());
+        assert!("1.2.3-RC1".parse::<Version>().is_err());
+    }
+
+    #[test]
+    fn test_version_bump() {
+        let version = |s: &str| s.parse::<Version>().unwrap();
+        let bump = |s: &str, kind| version(s).bump(kind).to_string();
+        assert_eq!(bump("1.2.3", BumpKind::Patch), "1.2.4");
+        assert_eq!(bump("1.2.3", BumpKind::Snapshot), "1.2.4-SNAPSHOT");
+        assert_eq!(bump("1.2.4-SNAPSHOT", BumpKind::Patch), "1.2.4");
+        assert_eq!(bump("1.2.4-SNAPSHOT", BumpKind::Snapshot), "1.2.5-SNAPSHOT");
+        assert_eq!(bump("1.2.3", BumpKind::Minor), "1.3.0");
+        assert_eq!(bump("1.2.4-SNAPSHOT", BumpKind::Minor), "1.3.0");
+        assert_eq!(bump("1.3.0-SNAPSHOT", BumpKind::Minor), "1.3.0");
+        assert_eq!(bump("1.2.3", BumpKind::Major), "2.0.0");
+        assert_eq!(bump("2.0.0-SNAPSHOT", BumpKind::Major), "2.0.0");
+        assert!("1.2".parse::<Version>().is_err());
+        assert!("1.2.3-rc.1".parse::<Version>().is_err());
+        assert!("1.2.3+build".parse::<Version>().is_err());
+        assert_eq!("SNAPSHOT".parse::<BumpKind>().unwrap(), BumpKind::Snapshot);
+
+        let dir = temp_project("version", "fun main() {}\n");
+        let config_path = dir.join("fyg.toml");
+        let (old, new) = Fyg::bump_version(&config_path, BumpKind::Minor, true).unwrap();
+        assert_eq!((old.to_string(), new.to_string()), ("1.0.0-SNAPSHOT".into(), "1.0.0".into()));
+        let unchanged = FygToml::from_file(&config_path).unwrap().project.version;
+        assert_eq!(unchanged, "1.0.0-SNAPSHOT");
+
+        Fyg::set_version(&config_path, version("2.0.0-SNAPSHOT"), false).unwrap();
+        assert_eq!(
+            FygToml::from_file(&config_path).unwrap().project.version,
+            "2.0.0-SNAPSHOT"
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_lock_is_sorted_and_up_to_date() {
+        let artifact = |group: &str, artifact: &str, version: &str| LockedArtifact {
+            group: group.to_string(),
+            artifact: artifact.to_string(),
+            version: version.to_string(),
+            classifier: None,
+            scope: "common".to_string(),
+            sha256: "0".repeat(64),
+            repository: "https://repo.maven.apache.org/maven2".to_string(),
+            transitive: false,
+        };
+        let lock = FygLock {
+            artifacts: vec![
+                artifact("org.jetbrains.kotlinx", "kotlinx-cli", "0.3.6"),
+                artifact("io.ktor", "ktor-client-core", "2.3.0"),
+            ],
+        };
+
+        let parsed = FygLock::from_str(&lock.to_toml_string().unwrap()).unwrap();
+        assert_eq!(parsed.artifacts[0].group, "io.ktor");
+
+        let mut config = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [dependencies.common]
+            "io.ktor:ktor-client-core" = "2.3.0"
+            "org.jetbrains.kotlinx:kotlinx-cli" = "LATEST"
+            "#,
+        )
+        .unwrap();
+        assert!(parsed.is_up_to_date(&config));
+
+        let common = config.dependencies.as_mut().unwrap().common.as_mut().unwrap();
+        common.remove("org.jetbrains.kotlinx:kotlinx-cli");
+        assert!(!parsed.is_up_to_date(&config));
+    }
+
+    #[test]
+    fn test_workspace_build_order() {
+        let dir = std::env::temp_dir().join(format!("fyg-test-workspace-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let name = dir.file_name().unwrap().to_str().unwrap();
+        Fyg::new_workspace(dir.parent().unwrap(), name, &InitOptions::default())
+            .expect("Failed to create workspace");
+
+        let mut root = FygToml::from_file(dir.join("fyg.toml")).unwrap();
+        assert!(root.project.is_empty());
+        let workspace = root.workspace.as_mut().unwrap();
+        workspace.members = vec!["app".to_string(), "lib".to_string()];
+        root.write_to_file(dir.join("fyg.toml")).unwrap();
+
+        for name in ["app", "lib"] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+        }
+        FygToml::new("lib", "com.example")
+            .write_to_file(dir.join("lib/fyg.toml"))
+            .unwrap();
+        let app = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [dependencies.common]
+            lib = { path = "../lib" }
+            "#,
+        )
+        .unwrap();
+        app.write_to_file(dir.join("app/fyg.toml")).unwrap();
+
+        let workspace = Workspace::load(dir.join("fyg.toml")).unwrap();
+        let order: Vec<_> = workspace
+            .build_order(None)
+            .unwrap()
+            .iter()
+            .map(|m| m.fyg_toml.project.name.clone())
+            .collect();
+        assert_eq!(order, vec!["lib", "app"]);
+
+        let only = workspace.member("lib").unwrap();
+        assert_eq!(workspace.build_order(Some(only)).unwrap().len(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_insert_parsed_coordinate() {
+        let (group, artifact, version) =
+            deps::parse_coordinate("io.ktor:ktor-server-core:2.3.0").unwrap();
+        assert_eq!((group.as_str(), artifact.as_str()), ("io.ktor", "ktor-server-core"));
+        assert_eq!(version.as_deref(), Some("2.3.0"));
+        assert!(deps::parse_coordinate("io.ktor").is_err());
+        assert!(deps::parse_coordinate("io.ktor::2.3.0").is_err());
+
+        let version = DependencyValue::Version("2.3.0".to_string());
+        let mut deps = DependenciesConfig::default();
+        deps.insert("test", "io.ktor:ktor-server-core".to_string(), version.clone())
+            .unwrap();
+        assert_eq!(deps.find("io.ktor:ktor-server-core"), vec!["test"]);
+        assert!(deps.insert("js", "a:b".to_string(), version).is_err());
+    }
+
+    #[test]
+    fn test_remove_dependency() {
+        let dir = temp_project("remove", "");
+        let config_path = dir.join("fyg.toml");
+        let mut config = FygToml::from_file(&config_path).unwrap();
+        let deps = config.dependencies.get_or_insert_with(DependenciesConfig::default);
+        for scope in ["common", "test"] {
+            let version = DependencyValue::Version("1.0".to_string());
+            deps.insert(scope, "com.example:core".to_string(), version).unwrap();
+        }
+        config.write_to_file(&config_path).unwrap();
+
+        assert!(deps::matches("com.example:core", "core"));
+        assert!(deps::matches("com.example:core", "com.example:core:1.0"));
+        assert!(!deps::matches("com.example:core", "org.example:core"));
+
+        let outcome = Fyg::remove(&config_path, "core", None).unwrap();
+        assert!(matches!(outcome, RemoveOutcome::Ambiguous(m) if m.len() == 2));
+
+        let outcome = Fyg::remove(&config_path, "core", Some("test")).unwrap();
+        let removed = vec![("test", "com.example:core".to_string())];
+        assert_eq!(outcome, RemoveOutcome::Removed(removed));
+
+        let config = FygToml::from_file(&config_path).unwrap();
+        let deps = config.dependencies.unwrap();
+        assert!(deps.test.is_none());
+        assert_eq!(deps.find("com.example:core"), vec!["common"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compare_versions() {
+        assert!(maven::compare_versions("1.10.2", "1.9.0").is_gt());
+        assert!(maven::compare_versions("2.0.0-RC1", "2.0.0").is_lt());
+        assert!(maven::compare_versions("1.0.1", "1.0").is_gt());
+        assert!(maven::compare_versions("1.4.11", "1.4.11").is_eq());
+    }
+
+    #[test]
+    fn test_maven_search() {
+        assert_eq!(
+            maven::search_url("com.example", "a b&c", Some("1.0+2")),
+            "https://search.maven.org/solrsearch/select?q=g%3A%22com.example%22%20AND%20\
+             a%3A%22a%20b%26c%22%20AND%20v%3A%221.0%2B2%22&core=gav&rows=1&wt=json"
+        );
+
+        let latest = r#"{"response": {"numFound": 1, "docs": [
+            {"id": "com.example:app", "g": "com.example", "a": "app",
+             "latestVersion": "2.1.0", "versionCount": 4}
+        ]}}"#;
+        assert_eq!(maven::parse_search(latest).unwrap(), Some("2.1.0".to_string()));
+
+        let version = r#"{"response": {"numFound": 1, "docs": [{"a": "app", "v": "1.0"}]}}"#;
+        assert_eq!(maven::parse_search(version).unwrap(), Some("1.0".to_string()));
+
+        let missing = r#"{"responseHeader": {}, "response": {"numFound": 0, "docs": []}}"#;
+        assert_eq!(maven::parse_search(missing).unwrap(), None);
+        assert!(maven::parse_search("<html>").is_err());
+    }
+
+    #[test]
+    fn test_local_maven_repository() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [repositories]
+            maven-central = false
+            local = { path = "/opt/m2" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(fyg_toml.repository_urls(), vec!["file:///opt/m2".to_string()]);
+
+        let mut disabled = fyg_toml.clone();
+        disabled.build = Some(BuildConfig {
+            use_local_maven: Some(false),
+            ..Default::default()
+        });
+        assert!(disabled.repository_urls().is_empty());
+    }
+
+    #[test]
+    fn test_jdk_version() {
+        let home = std::env::temp_dir().join(format!("fyg-test-jdk-{}", std::process::id()));
+        std::fs::create_dir_all(&home).unwrap();
+        std::fs::write(
+            home.join("release"),
+            "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"17.0.2\"\n",
+        )
+        .unwrap();
+        assert_eq!(toolchain::jdk_version(&home).as_deref(), Some("17.0.2"));
+
+        let _ = std::fs::remove_dir_all(&home);
+        assert_eq!(toolchain::jdk_version(&home), None);
+    }
+
+    #[test]
+    fn test_offline_build() {
+        let dir = temp_project("offline", "fun main() {}\n");
+        let config = dir.join("fyg.toml");
+        let mut fyg_toml = std::fs::read_to_string(&config).unwrap();
+        fyg_toml.push_str(
+            r#"
+[build]
+use-local-maven = false
+
+[dependencies.common]
+"com.example.fyg-offline:missing" = "1.0.0"
+"#,
+        );
+        std::fs::write(&config, fyg_toml).unwrap();
+        let options = BuildOptions {
+            offline: true,
+            ..Default::default()
+        };
+
+        let result = Fyg::build_with_options(&config, &options);
+        assert!(matches!(
+            result,
+            Err(FygError::DependencyResolutionFailed(message)) if message.contains(LOCK_FILE_NAME)
+        ));
+
+        // an outdated lock is resolved again, from the cache alone
+        FygLock::default().write_to_file(dir.join(LOCK_FILE_NAME)).unwrap();
+        let cache = dir.join("cache");
+        let cached = cache.join("com/example/fyg-offline/missing/1.0.0/missing-1.0.0.pom");
+        match toolchain::cache_dir_scope(&cache, || Fyg::build_with_options(&config, &options)) {
+            Err(FygError::DependencyResolutionFailed(message)) => {
+                assert!(message.contains("com.example.fyg-offline:missing:1.0.0"), "{}", message);
+                assert!(message.contains(&cached.display().to_string()), "{}", message);
+            }
+            other => panic!("expected the build to fail offline, got {:?}", other),
+        }
+        // nothing is left in the cache for the artifact that couldn't be fetched
+        assert!(!cache.exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_offline_requests() {
+        let dir =
+            std::env::temp_dir().join(format!("fyg-test-offline-http-{}", std::process::id()));
+        let dest = dir.join("com/example/app/1.0.0/app-1.0.0.pom");
+        let url = "https://repo.example.com/com/example/app/1.0.0/app-1.0.0.pom";
+
+        http::offline_scope(true, || {
+            let error = http::download(url, &dest, None).unwrap_err();
+            assert!(error.to_string().contains("while offline"), "{}", error);
+            assert!(http::ping(url).is_err());
+            assert!(http::post_json(url, "{}").is_err());
+        });
+        // nothing is created for a download that can't happen
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn test_plan_build_and_publish() {
+        let dir = temp_project("plan", "fun main() {}\n");
+        let config = dir.join("fyg.toml");
+        let mut fyg_toml = std::fs::read_to_string(&config).unwrap();
+        fyg_toml.push_str(
+            r#"
+[build]
+use-local-maven = false
+kotlin-version = "0.0.0-fyg-plan"
+
+[repositories]
+staging = { type = "maven", url = "https://repo.example.com/staging" }
+
+[publish]
+repositories = ["staging"]
+javadoc-jar = false
+"#,
+        );
+        std::fs::write(&config, fyg_toml).unwrap();
+
+        let actions = Fyg::plan_build(&config, &BuildOptions::default()).unwrap();
+        assert_eq!(actions[0], PlannedAction::ResolveDependencies(dir.join(LOCK_FILE_NAME)));
+        assert!(matches!(
+            &actions[1],
+            PlannedAction::Download { what, url }
+                if what == "Kotlin 0.0.0-fyg-plan"
+                    && url.ends_with("kotlin-compiler-0.0.0-fyg-plan.zip")
+        ));
+        match &actions[2] {
+            PlannedAction::Compile { target, command } => {
+                assert_eq!(target, "jvm");
+                assert!(command.contains("Main.kt"), "{}", command);
+            }
+            other => panic!("expected the JVM compilation, got {:?}", other),
+        }
+        assert_eq!(actions.len(), 3);
+        // nothing was written
+        assert!(!dir.join(LOCK_FILE_NAME).exists());
+        assert!(!dir.join("build").exists());
+
+        let actions = Fyg::plan_publish(&config, None).unwrap();
+        let libs = dir.join("build").join("libs");
+        assert!(actions.contains(&PlannedAction::Package(libs.join("plan-1.0.0-SNAPSHOT.pom"))));
+        let uploads: Vec<&str> = actions
+            .iter()
+            .filter_map(|action| match action {
+                PlannedAction::Upload { url, .. } => Some(url.as_str()),
+                _ => None,
+            })
+            .collect();
+        // the JAR, sources JAR and POM, each with its checksums
+        assert_eq!(uploads.len(), 9);
+        assert_eq!(
+            uploads[0],
+            "https://repo.example.com/staging/com/example/plan/1.0.0-SNAPSHOT/plan-1.0.0-SNAPSHOT.jar"
+        );
+        assert!(!dir.join("build").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_build_hooks() {
+        let dir = temp_project("hooks", "fun main() {}\n");
+        let config = dir.join("fyg.toml");
+        let mut fyg_toml = std::fs::read_to_string(&config).unwrap();
+        fyg_toml.push_str(
+            r#"
+[hooks]
+pre-build = ["echo $${FYG_TEST_UNSET_HOOK:-generated} > hook.txt", "echo broken >&2; exit 3"]
+post-build = ["touch post.txt"]
+"#,
+        );
+        std::fs::write(&config, fyg_toml).unwrap();
+        let hooks = FygToml::from_file(&config).unwrap().hooks.unwrap();
+        // $${ leaves the shell's own ${...} alone
+        let pre_build = hooks.pre_build.unwrap();
+        assert_eq!(pre_build[0], "echo ${FYG_TEST_UNSET_HOOK:-generated} > hook.txt");
+        assert_eq!(hooks.post_build.unwrap(), vec!["touch post.txt".to_string()]);
+
+        // the hooks run in the project, and the first to fail stops the build
+        let err = Fyg::build(&config).unwrap_err().to_string();
+        assert!(err.contains("pre-build hook 'echo broken >&2; exit 3'"), "{}", err);
+        assert!(err.contains("exit code 3: broken"), "{}", err);
+        assert_eq!(std::fs::read_to_string(dir.join("hook.txt")).unwrap(), "generated\n");
+        assert!(!dir.join("post.txt").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_post_build_hooks_on_cache_hit() {
+        let dir = temp_project("cached-hooks", "fun main() {}\n");
+        let config = dir.join("fyg.toml");
+        let mut contents = std::fs::read_to_string(&config).unwrap();
+        contents.push_str("\n[hooks]\npost-build = [\"echo ran >> hook.log\"]\n");
+        std::fs::write(&config, contents).unwrap();
+
+        // the build is stored in the cache, so neither build needs a compiler
+        let fyg_toml = Fyg::load(&config).unwrap();
+        let lock = Fyg::lock(&dir, &fyg_toml, false).unwrap();
+        let key = BuildCache::key(&fyg_toml, &lock, &dir, BuildMode::Debug).unwrap();
+        let cache = BuildCache::open().unwrap();
+        let project = BuildCache::project_id(&fyg_toml);
+        std::fs::create_dir_all(dir.join("build/classes")).unwrap();
+        std::fs::write(dir.join("build/classes/MainKt.class"), "compiled").unwrap();
+        cache.store(&key, &dir, &project).unwrap();
+
+        assert!(Fyg::build(&config).unwrap().cached);
+        assert!(Fyg::build(&config).unwrap().cached);
+        assert_eq!(std::fs::read_to_string(dir.join("hook.log")).unwrap(), "ran\nran\n");
+
+        cache.remove_project(&project).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_process_config() {
+        let dir = temp_project("process", "fun main() {}\n");
+        let config = ProcessConfig {
+            working_dir: Some(dir.clone()),
+            env_passthrough: false,
+            extra_env: HashMap::from([("FYG_TEST_SANDBOX".to_string(), "yes".to_string())]),
+            ..Default::default()
+        };
+
+        // only what fyg or the config sets is passed on, and the command runs in working_dir
+        let output = config
+            .scope(|| {
+                cancel::output(
+                    Command::new("/bin/sh")
+                        .args(["-c", "echo \"$FYG_TEST_SANDBOX:$HOME:$(pwd)\""])
+                        .env("HOME", "/sandbox"),
+                )
+            })
+            .unwrap();
+        let expected = format!("yes:/sandbox:{}\n", dir.canonicalize().unwrap().display());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+        let output = cancel::output(Command::new("/bin/sh").args(["-c", "pwd"])).unwrap();
+        assert_ne!(String::from_utf8_lossy(&output.stdout), expected);
+
+        let fyg_toml = dir.join("fyg.toml");
+        let mut contents = std::fs::read_to_string(&fyg_toml).unwrap();
+        contents.push_str("\n[hooks]\npre-build = [\"sleep 5\"]\n");
+        std::fs::write(&fyg_toml, contents).unwrap();
+        let options = BuildOptions {
+            process: Some(ProcessConfig {
+                timeout: Some(Duration::from_millis(200)),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let started = std::time::Instant::now();
+        let err = Fyg::build_with_options(&fyg_toml, &options).unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // paths into the project are absolute once subprocesses run elsewhere
+        let relative = Path::new("app/fyg.toml");
+        assert_eq!(process_config_path(relative).unwrap(), relative);
+        let absolute = config.scope(|| process_config_path(relative)).unwrap();
+        assert_eq!(absolute, std::env::current_dir().unwrap().join(relative));
+
+        // the workers building workspace members in parallel enter the caller's scope
+        let workspace = dir.join("workspace");
+        let name = workspace.file_name().unwrap().to_str().unwrap();
+        Fyg::new_workspace(&dir, name, &InitOptions::default()).unwrap();
+        let mut root = FygToml::from_file(workspace.join("fyg.toml")).unwrap();
+        root.workspace.as_mut().unwrap().members = vec!["a".to_string(), "b".to_string()];
+        root.write_to_file(workspace.join("fyg.toml")).unwrap();
+        for member in ["a", "b"] {
+            let mut fyg_toml = FygToml::new(member, "com.example");
+            fyg_toml.hooks = Some(HooksConfig {
+                pre_build: Some(vec!["echo \"$FYG_TEST_SANDBOX\" > scope.txt".to_string()]),
+                ..Default::default()
+            });
+            std::fs::create_dir_all(workspace.join(member)).unwrap();
+            fyg_toml.write_to_file(workspace.join(member).join("fyg.toml")).unwrap();
+        }
+        let options = BuildOptions {
+            jobs: Some(2),
+            ..Default::default()
+        };
+        // the members have no sources, so their builds fail once the hooks have run
+        let root = workspace.join("fyg.toml");
+        assert!(config.scope(|| Fyg::build_with_options(&root, &options)).is_err());
+        for member in ["a", "b"] {
+            let scope = std::fs::read_to_string(workspace.join(member).join("scope.txt"));
+            assert_eq!(scope.unwrap(), "yes\n");
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_repository_credentials() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [build]
+            use-local-maven = false
+
+            [repositories]
+            nexus = { type = "maven", url = "https://nexus.example.com/repository/maven", username = "ci", password = "$FYG_TEST_NEXUS_PASSWORD" }
+            "#,
+        )
+        .unwrap();
+
+        assert!(fyg_toml.maven_repositories_with(&|_| None).is_err());
+
+        let lookup = |name: &str| (name == "FYG_TEST_NEXUS_PASSWORD").then(|| "hunter2".to_string());
+        let repositories = fyg_toml.maven_repositories_with(&lookup).unwrap();
+        let credentials = repositories[1].credentials.as_ref().unwrap();
+        assert_eq!(credentials.username, "ci");
+        assert_eq!(credentials.password, "hunter2");
+        assert!(!format!("{:?}", repositories).contains("hunter2"));
+    }
+
+    #[test]
+    fn test_validate_fyg_toml() {
+        let valid = FygToml::new("hello-world", "com.example");
+        assert!(valid.validate().is_empty());
+
+        let invalid = FygToml::from_str(
+            r#"
+            [project]
+            name = "hello world"
+            group = "com.example"
+            version = "1.0"
+
+            [build]
+            languages = ["kotlin", "scala"]
+            language-version = "1.8"
+            api-version = "1.9"
+            explicit-api = "strict"
+            opt-in = ["kotlin.RequiresOptIn", "kotlinx.coroutines.2Experimental"]
+            all-warnings-as-errors = true
+            suppress-warnings = true
+
+            [targets.jvm]
+            enabled = true
+            target = "8"
+
+            [targets.android]
+            enabled = true
+            min-sdk = 24
+            target-sdk = 21
+            compile-sdk = 34
+
+            [dependencies.common]
+            "org.jetbrains.kotlinx:kotlinx-coroutines-core" = "1.8.0"
+            "kotlinx-coroutines-core" = "1.8.0"
+
+            [dependencies.jvm]
+            "org.jetbrains.kotlinx:kotlinx-coroutines-core" = "1.8.1"
+            "#,
+        )
+        .unwrap();
+        let fields: Vec<String> = invalid.validate().into_iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            [
+                "project.name",
+                "project.version",
+                "build.languages",
+                "build.api-version",
+                "build.explicit-api",
+                "build.opt-in",
+                "build.suppress-warnings",
+                "build.multiplatform",
+                "targets.jvm.target",
+                "targets.android.min-sdk",
+                "dependencies.common",
+                "dependencies.jvm",
+            ]
+        );
+
+        let mut unknown = valid.clone();
+        unknown.build = Some(BuildConfig {
+            language_version: Some("1.9.20".to_string()),
+            ..Default::default()
+        });
+        let fields: Vec<String> = unknown.validate().into_iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["build.language-version"]);
+
+        let mut empty = valid.clone();
+        empty.dependencies = Some(DependenciesConfig {
+            common: Some(HashMap::new()),
+            ..DependenciesConfig::default()
+        });
+        let diagnostics = empty.validate();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, DiagnosticSeverity::Hint);
+    }
+
+    #[test]
+    fn test_build_profiles() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [profile.release]
+            optimization = "size"
+            strip = true
+            free-compiler-args = ["-Xlambdas=indy"]
+
+            [profile.bench]
+            optimization = "speed"
+            "#,
+        )
+        .unwrap();
+
+        let debug = fyg_toml.profile(BuildMode::Debug);
+        assert_eq!(debug, BuildProfile::debug());
+        assert!(debug.keeps_debug_symbols());
+
+        let release = fyg_toml.profile(BuildMode::Release);
+        assert_eq!(release.optimization, Some(OptimizationLevel::Size));
+        assert_eq!(release.free_compiler_args, Some(vec!["-Xlambdas=indy".to_string()]));
+        assert!(!release.keeps_debug_symbols());
+
+        let fields: Vec<String> = fyg_toml.validate().into_iter().map(|e| e.field).collect();
+        assert_eq!(fields, ["profile.bench"]);
+    }
+
+    #[test]
+    fn test_release_keeps_null_assertions() {
+        let no_assertions = |fyg_toml: &FygToml| {
+            let options = super::compiler_options(fyg_toml, BuildMode::Release).unwrap();
+            let out_dir = Path::new("out");
+            let cmd = build::kotlinc_command(Path::new("kotlinc"), &[], &[], out_dir, &options)
+                .unwrap();
+            cmd.get_args().any(|arg| arg == "-Xno-param-assertions")
+        };
+        let project = "[project]\nname = \"app\"\ngroup = \"com.example\"\nversion = \"1.0.0\"\n";
+
+        let fyg_toml = FygToml::from_str(project).unwrap();
+        assert!(!no_assertions(&fyg_toml));
+
+        let fyg_toml =
+            FygToml::from_str(&format!("{}\n[profile.release]\noptimization = \"size\"\n", project))
+                .unwrap();
+        assert!(!no_assertions(&fyg_toml));
+
+        let fyg_toml = FygToml::from_str(&format!(
+            "{}\n[profile.release]\nno-null-assertions = true\n",
+            project
+        ))
+        .unwrap();
+        assert!(no_assertions(&fyg_toml));
+    }
+
+    #[test]
+    fn test_property_interpolation() {
+        let content = r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.2.0"
+            description = "${project.name} ${project.version} for $${USER}"
+
+            [dependencies.common]
+            "com.example:core" = "${project.version}"
+            "com.example:tools" = "${env.FYG_TEST_UNSET_VERSION:-0.1.0}"
+            "#;
+        let fyg_toml = FygToml::from_str(content).unwrap();
+        assert_eq!(
+            fyg_toml.project.description.as_deref(),
+            Some("app 1.2.0 for ${USER}")
+        );
+        let version = |fyg_toml: &FygToml, key: &str| {
+            let common = fyg_toml.dependencies.as_ref().and_then(|d| d.common.as_ref());
+            match common.and_then(|common| common.get(key)) {
+                Some(DependencyValue::Version(version)) => version.clone(),
+                _ => panic!("expected a version for {}", key),
+            }
+        };
+        assert_eq!(version(&fyg_toml, "com.example:core"), "1.2.0");
+        assert_eq!(version(&fyg_toml, "com.example:tools"), "0.1.0");
+
+        let raw = FygToml::from_str_uninterpolated(content).unwrap();
+        assert_eq!(version(&raw, "com.example:core"), "${project.version}");
+
+        let missing = content.replace(":-0.1.0", "");
+        let error = FygToml::from_str(&missing).unwrap_err().to_string();
+        assert!(error.contains("line 10"), "{}", error);
+        assert!(error.contains("FYG_TEST_UNSET_VERSION"), "{}", error);
+        assert!(FygToml::from_str("[project]\nname = \"${project.nme}\"").is_err());
+        let error = FygToml::from_str("[hooks]\npre-build = [\"echo ${HOME}\"]").unwrap_err();
+        assert!(error.to_string().contains("write $${HOME}"), "{}", error);
+    }
+
+    #[test]
+    fn test_newest_update() {
+        assert!(maven::is_stable("33.0.0-jre"));
+        assert!(maven::is_stable("2.0.21"));
+        for version in ["2.1.0-RC1", "1.5-beta2", "3.0.0-M1", "1.0-SNAPSHOT", "2.1.0-Beta"] {
+            assert!(!maven::is_stable(version), "{}", version);
+        }
+
+        let versions: Vec<String> = ["1.9.0", "1.9.2", "1.10.0-RC", "2.0.0", "2.1.0-RC1"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+        assert_eq!(deps::newest_update("1.9.0", &versions, false), Some("1.9.2"));
+        assert_eq!(deps::newest_update("1.9.0", &versions, true), Some("2.0.0"));
+        assert_eq!(deps::newest_update("2.0.0", &versions, true), None);
+
+        let mut metadata = maven::Metadata {
+            release: None,
+            versions,
+        };
+        assert_eq!(metadata.latest(), Some("2.0.0"));
+        assert_eq!(metadata.newest(), Some("2.1.0-RC1"));
+        metadata.release = Some("1.9.2".to_string());
+        assert_eq!(metadata.latest(), Some("1.9.2"));
+        metadata.versions.retain(|v| !maven::is_stable(v));
+        metadata.release = None;
+        assert_eq!(metadata.latest(), None);
+    }
+
+    #[test]
+    fn test_audit_osv_responses() {
+        assert_eq!("moderate".parse::<Severity>().unwrap(), Severity::Medium);
+        assert_eq!("CRITICAL".parse::<Severity>().unwrap(), Severity::Critical);
+        assert!("severe".parse::<Severity>().is_err());
+        assert!(Severity::High > Severity::Medium);
+
+        let jackson = Coordinate::new("com.fasterxml.jackson.core", "jackson-databind", "2.9.8");
+        let query = audit::batch_query(std::slice::from_ref(&jackson));
+        assert_eq!(
+            query["queries"][0]["package"]["name"],
+            "com.fasterxml.jackson.core:jackson-databind"
+        );
+        assert_eq!(query["queries"][0]["package"]["ecosystem"], "Maven");
+        assert_eq!(query["queries"][0]["version"], "2.9.8");
+
+        let ids = audit::parse_batch(
+            r#"{"results":[{"vulns":[{"id":"GHSA-1","modified":"2024-01-01T00:00:00Z"}]},{}]}"#,
+        )
+        .unwrap();
+        assert_eq!(ids, vec![vec!["GHSA-1".to_string()], vec![]]);
+
+        let high = audit::parse_vulnerability(
+            "GHSA-1",
+            r#"{"id":"GHSA-1","aliases":["CVE-2019-1"],"summary":"Deserialization of untrusted data",
+                "database_specific":{"severity":"HIGH"}}"#,
+            &jackson,
+        )
+        .unwrap();
+        assert_eq!(high.severity, Some(Severity::High));
+        assert_eq!(high.aliases, vec!["CVE-2019-1"]);
+        assert_eq!(high.dependency, "com.fasterxml.jackson.core:jackson-databind:2.9.8");
+
+        let unrated =
+            audit::parse_vulnerability("OSV-2", r#"{"details":"First line\nmore"}"#, &jackson)
+                .unwrap();
+        assert_eq!(unrated.severity, None);
+        assert_eq!(unrated.summary, "First line");
+
+        let found = [high, unrated];
+        assert!(audit::check_threshold(&found, Severity::High).is_err());
+        assert!(audit::check_threshold(&found, Severity::Critical).is_ok());
+    }
+
+    #[test]
+    fn test_license_policy() {
+        assert_eq!(
+            licenses::spdx_id(Some("The Apache Software License, Version 2.0"), None),
+            Some("Apache-2.0")
+        );
+        assert_eq!(licenses::spdx_id(Some("MIT License (MIT)"), None), Some("MIT"));
+        assert_eq!(
+            licenses::spdx_id(Some("GPL"), Some("https://www.gnu.org/licenses/gpl-3.0.html")),
+            Some("GPL-3.0")
+        );
+        assert_eq!(licenses::spdx_id(Some("Proprietary"), None), None);
+
+        let license = |dependency: &str, name: Option<&str>| DependencyLicense {
+            dependency: dependency.to_string(),
+            name: name.map(str::to_string),
+            url: None,
+            spdx: licenses::spdx_id(name, None).map(str::to_string),
+        };
+        let found = vec![
+            license("com.example:apache:1.0", Some("Apache 2.0")),
+            license("com.example:gpl:1.0", Some("GPL-3.0")),
+            license("com.example:unknown:1.0", None),
+        ];
+
+        let deny = LicensePolicy {
+            deny: vec!["gpl-3.0".to_string()],
+            ..Default::default()
+        };
+        assert!(!deny.rejects(&found[0]));
+        assert!(deny.rejects(&found[1]));
+        assert!(!deny.rejects(&found[2]));
+
+        let allow = LicensePolicy {
+            allow: vec!["Apache-2.0".to_string()],
+            ..Default::default()
+        };
+        let rejected = match licenses::check_policy(&found, &allow) {
+            Err(licenses::LicenseError::Rejected { dependencies }) => dependencies,
+            Ok(()) => panic!("expected the policy to reject licenses"),
+        };
+        assert_eq!(rejected, vec!["com.example:gpl:1.0", "com.example:unknown:1.0"]);
+        assert!(licenses::check_policy(&found[..1], &allow).is_ok());
+
+        let csv = licenses::to_csv(&[license("com.example:lib:1.0", Some("Apache, Version 2"))]);
+        assert_eq!(
+            csv,
+            "dependency,license,url,spdx\ncom.example:lib:1.0,\"Apache, Version 2\",,\n"
+        );
+    }
+
+    #[test]
+    fn test_sbom_documents() {
+        let config = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [build.sbom]
+            enabled = true
+            format = "spdx-json"
+            "#,
+        )
+        .unwrap();
+        let sbom = config.build.as_ref().unwrap().sbom.as_ref().unwrap();
+        assert!(sbom.enabled);
+        assert_eq!(sbom.format, Some(SbomFormat::SpdxJson));
+
+        let locked = |artifact: &str, scope: &str, transitive: bool| LockedArtifact {
+            group: "com.example".to_string(),
+            artifact: artifact.to_string(),
+            version: "2.0".to_string(),
+            classifier: None,
+            scope: scope.to_string(),
+            sha256: "ab".repeat(32),
+            repository: maven::MAVEN_CENTRAL.to_string(),
+            transitive,
+        };
+        let lock = FygLock {
+            artifacts: vec![
+                locked("lib", "common", false),
+                locked("core", "common", true),
+                locked("assert", "test", false),
+            ],
+        };
+
+        let bom = sbom::cyclonedx_json(&config, &lock, "2024-01-01T00:00:00Z");
+        assert_eq!(bom["bomFormat"], "CycloneDX");
+        assert_eq!(bom["metadata"]["component"]["purl"], "pkg:maven/com.example/app@1.0.0");
+        let components = bom["components"].as_array().unwrap();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[0]["name"], "assert");
+        assert_eq!(components[0]["scope"], "excluded");
+        assert_eq!(components[0]["hashes"][0]["content"], "ab".repeat(32));
+        assert_eq!(
+            bom["dependencies"][0]["dependsOn"],
+            serde_json::json!(["pkg:maven/com.example/assert@2.0", "pkg:maven/com.example/lib@2.0"])
+        );
+
+        let xml = Element::parse(&sbom::cyclonedx_xml(&config, &lock, "2024-01-01T00:00:00Z"))
+            .unwrap();
+        let components: Vec<_> = xml.child("components").unwrap().children("component").collect();
+        assert_eq!(components.len(), 3);
+        assert_eq!(components[1].child_text("name"), Some("core"));
+
+        let spdx = sbom::spdx_json(&config, &lock, "2024-01-01T00:00:00Z");
+        assert_eq!(spdx["packages"].as_array().unwrap().len(), 4);
+        assert_eq!(spdx["packages"][1]["checksums"][0]["algorithm"], "SHA256");
+        let relationships = spdx["relationships"].as_array().unwrap();
+        assert_eq!(relationships.len(), 3);
+        assert_eq!(relationships[1]["relationshipType"], "TEST_DEPENDENCY_OF");
+        assert_eq!(relationships[1]["spdxElementId"], "SPDXRef-Package-com.example-assert");
+        assert_eq!(relationships[2]["relationshipType"], "DEPENDS_ON");
+    }
+
+    #[test]
+    fn test_build_cache() {
+        let dir = temp_project("build-cache", "fun main() {}\n");
+        let fyg_toml = FygToml::from_file(dir.join("fyg.toml")).unwrap();
+        let lock = FygLock::default();
+        let key = |mode| BuildCache::key(&fyg_toml, &lock, &dir, mode).unwrap();
+
+        let debug = key(BuildMode::Debug);
+        assert_eq!(debug, key(BuildMode::Debug));
+        assert_ne!(debug, key(BuildMode::Release));
+
+        let cache = BuildCache::at(dir.join("cache"));
+        std::fs::create_dir_all(dir.join("build/classes")).unwrap();
+        std::fs::write(dir.join("build/classes/MainKt.class"), "compiled").unwrap();
+        cache.store(&debug, &dir, &BuildCache::project_id(&fyg_toml)).unwrap();
+
+        std::fs::write(dir.join("src/kotlin/com/example/Main.kt"), "fun main() { }\n").unwrap();
+        let changed = key(BuildMode::Debug);
+        assert_ne!(debug, changed);
+        assert!(!cache.restore(&changed, &dir).unwrap());
+
+        std::fs::remove_dir_all(dir.join("build")).unwrap();
+        assert!(cache.restore(&debug, &dir).unwrap());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("build/classes/MainKt.class")).unwrap(),
+            "compiled"
+        );
+
+        assert_eq!(cache.clean(std::time::Duration::from_secs(3600)).unwrap().entries, 0);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        assert_eq!(cache.clean(std::time::Duration::ZERO).unwrap().entries, 1);
+        assert!(!cache.restore(&debug, &dir).unwrap());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_build_cache_tool_version() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("fyg-test-tool-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let kotlinc = dir.join("kotlinc");
+        let install = |version: &str| {
+            let script = format!("#!/bin/sh\necho \"info: kotlinc-jvm {}\" >&2\n", version);
+            std::fs::write(&kotlinc, script).unwrap();
+            std::fs::set_permissions(&kotlinc, std::fs::Permissions::from_mode(0o755)).unwrap();
+        };
+
+        install("2.0.21");
+        assert_eq!(cache::tool_version(&kotlinc), "info: kotlinc-jvm 2.0.21");
+        // the version is asked again once the compiler is replaced
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        install("2.1.0");
+        assert_eq!(cache::tool_version(&kotlinc), "info: kotlinc-jvm 2.1.0");
+        assert_eq!(cache::tool_version(&dir.join("missing")), "");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_clean() {
+        let dir = temp_project("clean", "fun main() {}\n");
+        let config = dir.join("fyg.toml");
+        let cache_dir = dir.join("cache");
+        let cache = BuildCache::at(&cache_dir);
+        std::fs::create_dir_all(dir.join("build/classes")).unwrap();
+        std::fs::write(dir.join("build/classes/MainKt.class"), "compiled").unwrap();
+        let project = BuildCache::project_id(&FygToml::from_file(&config).unwrap());
+        cache.store("ours", &dir, &project).unwrap();
+        cache.store("theirs", &dir, "another project").unwrap();
+
+        // --cache leaves build/ alone
+        let options = CleanOptions {
+            cache: true,
+            cache_dir: Some(cache_dir.clone()),
+            ..Default::default()
+        };
+        let cleaned = Fyg::clean(&config, &options).unwrap();
+        assert!(!cleaned.build_dir);
+        assert_eq!(cleaned.entries, 1);
+        assert!(cleaned.bytes >= "compiled".len() as u64);
+        assert!(dir.join("build").exists());
+        assert!(cache_dir.join("theirs").exists() && !cache_dir.join("ours").exists());
+
+        let options = CleanOptions {
+            build: true,
+            ..options
+        };
+        let cleaned = Fyg::clean(&config, &options).unwrap();
+        assert!(cleaned.build_dir);
+        assert_eq!(cleaned.entries, 0);
+        assert!(!dir.join("build").exists());
+
+        assert_eq!(cache.clear().unwrap().entries, 1);
+        assert_eq!(output::human_size(512), "512 B");
+        assert_eq!(output::human_size(47 * 1024 * 1024), "47 MB");
+        assert_eq!(output::human_size(1288490189), "1.2 GB");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_workspace_task_graph_batches() {
+        let dir = std::env::temp_dir().join(format!("fyg-test-batches-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let name = dir.file_name().unwrap().to_str().unwrap();
+        Fyg::new_workspace(dir.parent().unwrap(), name, &InitOptions::default())
+            .expect("Failed to create workspace");
+
+        let mut root = FygToml::from_file(dir.join("fyg.toml")).unwrap();
+        root.workspace.as_mut().unwrap().members =
+            vec!["app".to_string(), "lib".to_string(), "tools".to_string()];
+        root.write_to_file(dir.join("fyg.toml")).unwrap();
+
+        for name in ["lib", "tools"] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            FygToml::new(name, "com.example")
+                .write_to_file(dir.join(name).join("fyg.toml"))
+                .unwrap();
+        }
+        std::fs::create_dir_all(dir.join("app")).unwrap();
+        let app = FygToml::from_str(
+            r#"
+            [project]
+            name = "app"
+            group = "com.example"
+            version = "1.0.0"
+
+            [dependencies.common]
+            lib = { path = "../lib" }
+            "#,
+        )
+        .unwrap();
+        app.write_to_file(dir.join("app/fyg.toml")).unwrap();
+
+        let workspace = Workspace::load(dir.join("fyg.toml")).unwrap();
+        let names = |batches: Vec<Vec<&Member>>| -> Vec<Vec<String>> {
+            batches
+                .iter()
+                .map(|batch| batch.iter().map(|m| m.fyg_toml.project.name.clone()).collect())
+                .collect()
+        };
+        let batches = workspace.task_graph(None).unwrap().batches().unwrap();
+        assert_eq!(names(batches), vec![vec!["lib", "tools"], vec!["app"]]);
+
+        let only = workspace.member("app").unwrap();
+        let batches = workspace.task_graph(Some(only)).unwrap().batches().unwrap();
+        assert_eq!(names(batches), vec![vec!["lib"], vec!["app"]]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_resolve_version_range() {
+        let versions: Vec<String> = ["1.0", "1.5", "1.9.2", "2.0", "2.1-RC1", "2.1"]
+            .iter()
+            .map(|v| v.to_string())
+            .collect();
+
+        assert!(maven::is_range("[1.0,2.0)"));
+        assert!(!maven::is_range("1.0"));
+        assert_eq!(maven::resolve_range("[1.0,2.0)", &versions), Some("1.9.2"));
+        assert_eq!(maven::resolve_range("[1.0,2.0]", &versions), Some("2.0"));
+        assert_eq!(maven::resolve_range("[2.0,)", &versions), Some("2.1"));
+        assert_eq!(maven::resolve_range("(,1.5)", &versions), Some("1.0"));
+        assert_eq!(maven::resolve_range("[1.5]", &versions), Some("1.5"));
+        assert_eq!(
+            maven::resolve_range("[1.0,1.2),[2.0,2.1)", &versions),
+            Some("2.1-RC1")
+        );
+        assert_eq!(maven::resolve_range("[3.0,)", &versions), None);
+
+        let range: VersionRange = "[1.0, 2.0)".parse().unwrap();
+        assert!(range.contains("1.0") && range.contains("1.9.2"));
+        assert!(!range.contains("2.0"));
+        assert!("[1.0".parse::<VersionRange>().is_err());
+        assert!("1.0".parse::<VersionRange>().is_err());
+        assert!("[]".parse::<VersionRange>().is_err());
+
+        let declared = DependencyValue::Version("[1.0,2.0)".to_string());
+        assert_eq!(declared.version_range().unwrap().unwrap(), range);
+        assert!(DependencyValue::Version("1.0".to_string()).version_range().is_none());
+    }
+
+    #[test]
+    fn test_parse_xml() {
+        let pom = Element::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- a comment -->
+            <project xmlns="http://maven.apache.org/POM/4.0.0">
+                <name>A &amp; B</name>
+                <dependencies>
+                    <dependency><groupId>org.example</groupId><optional/></dependency>
+                </dependencies>
+            </project>"#,
+        )
+        .unwrap();
+
+        assert_eq!(pom.name, "project");
+        assert_eq!(pom.attribute("xmlns"), Some("http://maven.apache.org/POM/4.0.0"));
+        assert_eq!(pom.child_text("name"), Some("A & B"));
+        let dep = pom.find(&["dependencies", "dependency"]).unwrap();
+        assert_eq!(dep.child_text("groupId"), Some("org.example"));
+        assert!(dep.child("optional").is_some());
+        assert!(Element::parse("<a><b></a>").is_err());
+
+        let report = Element::parse(r#"<testcase name='a()' message="x > y &amp; z"/>"#).unwrap();
+        assert_eq!(report.attribute("name"), Some("a()"));
+        assert_eq!(report.attribute("message"), Some("x > y & z"));
+
+        let pom = Element::parse(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!DOCTYPE project [
+                <!ENTITY vendor "Acme &#38; Sons">
+                <!ENTITY copy "&#169;">
+                <!-- a > inside the DOCTYPE -->
+            ]>
+            <project>
+                <name>Caf&#233; &#x2013; &vendor;</name>
+                <description><![CDATA[<b>bold</b> & raw]]> &lt;escaped&gt;</description>
+                <organization>
+                    <name>&copy; 2024 &quot;Acme&quot; &apos;Co&apos;</name>
+                </organization>
+                <url title="&vendor; &#x26; friends">https://example.com/?a=1&amp;b=2</url>
+            </project>"#,
+        )
+        .unwrap();
+        assert_eq!(pom.child_text("name"), Some("Café – Acme & Sons"));
+        assert_eq!(pom.child_text("description"), Some("<b>bold</b> & raw <escaped>"));
+        assert_eq!(pom.find(&["organization", "name"]).unwrap().text, "© 2024 \"Acme\" 'Co'");
+        let url = pom.child("url").unwrap();
+        assert_eq!(url.text, "https://example.com/?a=1&b=2");
+        assert_eq!(url.attribute("title"), Some("Acme & Sons & friends"));
+        assert!(Element::parse("<project><name>&undeclared;</name></project>").is_err());
+    }
+
+    #[test]
+    #[ignore = "needs curl"]
+    fn test_exclude_transitive_dependencies() {
+        let group = "com.example".to_string();
+        let repo = std::env::temp_dir().join(format!("fyg-test-exclude-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&repo);
+        let pom = |artifact: &str, dependencies: &[&str]| {
+            let dependencies: String = dependencies
+                .iter()
+                .map(|dep| {
+                    let (group, artifact) = dep.split_once(':').unwrap();
+                    format!(
+                        "<dependency><groupId>{}</groupId><artifactId>{}</artifactId>\
+                         <version>1.0</version></dependency>",
+                        group, artifact
+                    )
+                })
+                .collect();
+            let coordinate = Coordinate::new(&group, artifact, "1.0");
+            let path = repo.join(coordinate.repository_path("pom"));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(
+                path,
+                format!(
+                    "<project><groupId>{}</groupId><artifactId>{}</artifactId>\
+                     <version>1.0</version><dependencies>{}</dependencies></project>",
+                    group, artifact, dependencies
+                ),
+            )
+            .unwrap();
+        };
+        let logging = format!("{}:logging", group);
+        let logging_api = format!("{}:logging-api", group);
+        let json = format!("{}:json", group);
+        pom("app", &[&logging, &json]);
+        pom("logging", &[&logging_api]);
+        pom("logging-api", &[]);
+        pom("json", &[]);
+
+        let declared = |artifact: &str, exclude: Vec<ExcludeRule>| resolver::Declared {
+            scope: "common",
+            group: group.clone(),
+            artifact: artifact.to_string(),
+            version: "1.0".to_string(),
+            classifier: None,
+            exclude,
+        };
+        // resolving caches what it fetches, which mustn't end up in the user's cache
+        let cache = repo.join("cache");
+        toolchain::cache_dir_scope(&cache, || {
+            let repository = maven::Repository::new(format!("file://{}", repo.display()));
+            let resolver = resolver::Resolver::new(vec![repository]);
+            let artifacts = |graph: &DependencyGraph| {
+                let mut artifacts: Vec<String> = graph
+                    .nodes
+                    .iter()
+                    .map(|n| n.coordinate.artifact.clone())
+                    .collect();
+                artifacts.sort();
+                artifacts
+            };
+
+            // excluding logging also drops logging-api, which only it depends on
+            let exclude = ExcludeRule {
+                group: Some(group.clone()),
+                artifact: Some("logging".to_string()),
+            };
+            let graph = resolver.graph(&[declared("app", vec![exclude.clone()])]).unwrap();
+            assert_eq!(artifacts(&graph), ["app", "json"]);
+
+            // a group-only rule excludes every artifact of the group
+            let exclude_group = ExcludeRule {
+                group: Some(group.clone()),
+                artifact: None,
+            };
+            let graph = resolver.graph(&[declared("app", vec![exclude_group])]).unwrap();
+            assert_eq!(artifacts(&graph), ["app"]);
+
+            // an excluded artifact that is also declared directly stays
+            let graph = resolver
+                .graph(&[declared("app", vec![exclude]), declared("logging", Vec::new())])
+                .unwrap();
+            assert_eq!(artifacts(&graph), ["app", "json", "logging", "logging-api"]);
+            assert!(graph.nodes.iter().any(|n| n.coordinate.artifact == "logging" && n.direct));
+        });
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_dependency_classifier() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [dependencies.jvm]
+            "org.lwjgl:lwjgl" = { version = "3.3.3", classifier = "natives-linux" }
+            "#,
+        )
+        .unwrap();
+        let declared = resolver::declared(&fyg_toml);
+        assert_eq!(declared[0].classifier.as_deref(), Some("natives-linux"));
+
+        let coordinate = Coordinate::new("org.lwjgl", "lwjgl", "3.3.3")
+            .with_classifier(declared[0].classifier.as_deref());
+        assert_eq!(coordinate.to_string(), "org.lwjgl:lwjgl:3.3.3:natives-linux");
+        assert_eq!(
+            coordinate.repository_path("jar"),
+            "org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3-natives-linux.jar"
+        );
+        // every variant shares one POM
+        assert_eq!(
+            coordinate.repository_path("pom"),
+            "org/lwjgl/lwjgl/3.3.3/lwjgl-3.3.3.pom"
+        );
+    }
+
+    #[test]
+    fn test_iter_coordinates() {
+        let fyg_toml = FygToml::from_str(
+            r#"
+            [dependencies.common]
+            "org.jetbrains.kotlinx:kotlinx-cli" = "0.3.6"
+            ktor = "io.ktor:ktor-client-core:2.3.0"
+            lwjgl = { version = "org.lwjgl:lwjgl:3.3.3", classifier = "natives-linux" }
+            shared = { path = "../shared" }
+
+            [dependencies.test]
+            "junit" = "4.13.2"
+            "#,
+        )
+        .unwrap();
+        let deps = fyg_toml.dependencies.as_ref().unwrap();
+        let coordinates: Vec<String> = deps
+            .iter_coordinates("common")
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            coordinates,
+            [
+                "io.ktor:ktor-client-core:2.3.0",
+                "org.lwjgl:lwjgl:3.3.3:natives-linux",
+                "org.jetbrains.kotlinx:kotlinx-cli:0.3.6",
+            ]
+        );
+        assert_eq!(deps.iter_coordinates("test").count(), 0);
+        assert!(resolver::declared(&fyg_toml).iter().any(|d| d.artifact == "ktor-client-core"));
+
+        let fields: Vec<String> = fyg_toml
+            .validate()
+            .into_iter()
+            .filter(|d| d.field.starts_with("dependencies."))
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(fields, ["'junit:4.13.2' is not a group:artifact:version coordinate"]);
+    }
+
+    #[test]
+    #[ignore = "needs curl"]
+    fn test_bom_versions() {
+        let group = "com.example".to_string();
+        let repo = std::env::temp_dir().join(format!("fyg-test-bom-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&repo);
+        let pom = |artifact: &str, version: &str, managed: &[(&str, &str)]| {
+            let managed: String = managed
+                .iter()
+                .map(|(artifact, version)| {
+                    format!(
+                        "<dependency><groupId>{}</groupId><artifactId>{}</artifactId>\
+                         <version>{}</version></dependency>",
+                        group, artifact, version
+                    )
+                })
+                .collect();
+            let coordinate = Coordinate::new(&group, artifact, version);
+            let path = repo.join(coordinate.repository_path("pom"));
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(
+                path,
+                format!(
+                    "<project><groupId>{}</groupId><artifactId>{}</artifactId>\
+                     <version>{}</version><dependencyManagement><dependencies>{}\
+                     </dependencies></dependencyManagement></project>",
+                    group, artifact, version, managed
+                ),
+            )
+            .unwrap();
+        };
+        pom("platform", "1.0", &[("core", "1.0"), ("json", "1.0"), ("http", "1.0")]);
+        pom("platform-next", "2.0", &[("core", "2.0")]);
+        for (artifact, version) in [("core", "2.0"), ("json", "1.0"), ("http", "1.5")] {
+            pom(artifact, version, &[]);
+        }
+
+        let fyg_toml = FygToml::from_str(&format!(
+            r#"
+            [dependencies]
+            bom = ["{group}:platform:1.0", "{group}:platform-next:2.0"]
+
+            [dependencies.common]
+            "{group}:core" = {{}}
+            "{group}:json" = {{}}
+            "{group}:http" = "1.5"
+            "#,
+            group = group
+        ))
+        .unwrap();
+        assert!(fyg_toml.validate().iter().all(|d| !d.field.starts_with("dependencies")));
+
+        let declared = resolver::declared(&fyg_toml);
+        let repository = maven::Repository::new(format!("file://{}", repo.display()));
+        let boms = vec![
+            Coordinate::new(&group, "platform", "1.0"),
+            Coordinate::new(&group, "platform-next", "2.0"),
+        ];
+        // resolving caches what it fetches, which mustn't end up in the user's cache
+        let cache = repo.join("cache");
+        let graph = toolchain::cache_dir_scope(&cache, || {
+            resolver::Resolver::new(vec![repository]).with_boms(boms).graph(&declared)
+        })
+        .unwrap();
+        let mut versions: Vec<(String, String)> = graph
+            .nodes
+            .iter()
+            .map(|n| (n.coordinate.artifact.clone(), n.coordinate.version.clone()))
+            .collect();
+        versions.sort();
+        // later BOMs override earlier ones, and an explicit version overrides both
+        let expected = [("core", "2.0"), ("http", "1.5"), ("json", "1.0")]
+            .map(|(artifact, version)| (artifact.to_string(), version.to_string()));
+        assert_eq!(versions, expected);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn test_dependency_graph_tree_and_why() {
+        let node = |artifact: &str, direct: bool, dependencies: Vec<usize>| Node {
+            coordinate: Coordinate::new("com.example", artifact, "1.0"),
+            scope: "common",
+            direct,
+            packaging: "jar".to_string(),
+            license: None,
+            repository: maven::MAVEN_CENTRAL.to_string(),
+            dependencies,
+        };
+        let graph = DependencyGraph {
+            nodes: vec![
+                node("app", true, vec![1, 2]),
+                node("http", false, vec![2]),
+                node("json", false, vec![3]),
+                node("core", false, vec![]),
+            ],
+        };
+
+        let tree = graph.tree(None);
+        assert_eq!(
+            tree,
+            "\
+com.example:app:1.0 (common)
+├── com.example:http:1.0
+│   └── com.example:json:1.0
+│       └── com.example:core:1.0
+└── com.example:json:1.0 (*)
+"
+        );
+
+        let path: Vec<String> = graph
+            .why("core")
+            .unwrap()
+            .iter()
+            .map(|c| c.to_string())
+            .collect();
+        assert_eq!(
+            path,
+            vec!["com.example:app:1.0", "com.example:json:1.0", "com.example:core:1.0"]
+        );
+        assert!(graph.why("missing").is_none());
+    }
+
+    #[test]
+    fn test_dependency_graph_dot_and_json() {
+        let node = |artifact: &str, direct: bool, dependencies: Vec<usize>| Node {
+            coordinate: Coordinate::new("com.example", artifact, "1.0"),
+            scope: "common",
+            direct,
+            packaging: "jar".to_string(),
+            license: None,
+            repository: maven::MAVEN_CENTRAL.to_string(),
+            dependencies,
+        };
+        let graph = DependencyGraph {
+            nodes: vec![node("http", true, vec![1]), node("core", false, vec![])],
+        };
+
+        assert_eq!("JSON".parse::<GraphFormat>().unwrap(), GraphFormat::Json);
+        assert!("png".parse::<GraphFormat>().is_err());
+
+        let dot = graph.to_dot("com.example:app:0.1.0");
+        assert!(dot.starts_with("digraph dependencies {"));
+        assert!(dot.contains("\"com.example:http:1.0\" [style=\"rounded,bold\", penwidth=2];"));
+        assert!(dot.contains("    \"com.example:core:1.0\";\n"));
+        assert!(dot.contains("\"com.example:app:0.1.0\" -> \"com.example:http:1.0\";"));
+        assert!(dot.contains("\"com.example:http:1.0\" -> \"com.example:core:1.0\";"));
+
+        assert_eq!(
+            graph.to_json(),
+            serde_json::json!({
+                "roots": ["com.example:http:1.0"],
+                "dependencies": {
+                    "com.example:http:1.0": ["com.example:core:1.0"],
+                    "com.example:core:1.0": [],
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_incremental_recompile() {
+        let dir = temp_project("incremental", "package com.example\n\nfun main() = greet()\n");
+        let src = dir.join("src/kotlin/com/example");
+        std::fs::create_dir_all(src.join("util")).unwrap();
+        std::fs::write(
+            src.join("util/Greeting.kt"),
+            "// greetings\npackage com.example.util\n\nfun greeting() = \"hi\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            src.join("Greet.kt"),
+            "package com.example\n\nimport com.example.util.greeting as hello\n\n\
+             fun greet() = println(hello())\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("Other.kt"), "package com.example.other\n\nval x = 1\n").unwrap();
+
+        let header = SourceHeader::parse(&std::fs::read_to_string(src.join("Greet.kt")).unwrap());
+        assert_eq!(header.package, "com.example");
+        assert_eq!(header.imports, vec!["com.example.util.greeting"]);
+        assert!(header.depends_on("com.example.util"));
+        assert!(!header.depends_on("com.example.ut"));
+
+        let sources = build::collect_sources(&dir.join("src/kotlin"), "kt").unwrap();
+        let take = |settings: &str, previous: Option<&Fingerprints>| {
+            Fingerprints::take(&dir, &sources, settings.to_string(), previous).unwrap()
+        };
+        let first = take("a", None);
+        assert_eq!(first.recompile(&dir, None).unwrap(), Recompile::All);
+        first.save(&dir).unwrap();
+        let saved = Fingerprints::load(&dir).unwrap();
+        assert_eq!(saved, first);
+
+        let unchanged = take("a", Some(&saved));
+        assert_eq!(unchanged.recompile(&dir, Some(&saved)).unwrap(), Recompile::Nothing);
+        assert_eq!(take("b", Some(&saved)).recompile(&dir, Some(&saved)).unwrap(), Recompile::All);
+
+        // callers of the changed package are compiled again, along with their own package
+        std::fs::write(
+            src.join("util/Greeting.kt"),
+            "package com.example.util\n\nfun greeting() = \"hello\"\n",
+        )
+        .unwrap();
+        let changed = Fingerprints::take(&dir, &sources, "a".to_string(), None).unwrap();
+        assert_eq!(
+            changed.recompile(&dir, Some(&saved)).unwrap(),
+            Recompile::Only(vec![
+                src.join("Greet.kt"),
+                src.join("Main.kt"),
+                src.join("util/Greeting.kt"),
+            ])
+        );
+
+        std::fs::remove_file(src.join("Other.kt")).unwrap();
+        let sources = build::collect_sources(&dir.join("src/kotlin"), "kt").unwrap();
+        let removed = Fingerprints::take(&dir, &sources, "a".to_string(), None).unwrap();
+        assert_eq!(removed.recompile(&dir, Some(&saved)).unwrap(), Recompile::All);
+
+        Fingerprints::remove(&dir).unwrap();
+        assert_eq!(Fingerprints::load(&dir), None);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// A class file holding nothing but the `SourceFile` attribute naming `source`.
+    fn class_file(source: &str) -> Vec<u8> {
+        let mut class = vec![0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, 61, 0, 3];
+        for utf8 in ["SourceFile", source] {
+            class.push(1);
+            class.extend((utf8.len() as u16).to_be_bytes());
+            class.extend(utf8.as_bytes());
+        }
+        // access flags, this class, superclass, interfaces, fields and methods
+        class.extend([0; 12]);
+        class.extend([0, 1, 0, 1, 0, 0, 0, 2, 0, 2]);
+        class
+    }
+
+    #[test]
+    fn test_incremental_remove_outputs() {
+        let dir = temp_project("incremental-outputs", "package com.example\n\nclass Old\n");
+        let src = dir.join("src/kotlin/com/example");
+        std::fs::write(src.join("Util.kt"), "package com.example\n\nclass Util\n").unwrap();
+        let out_dir = dir.join("build/classes");
+        let classes = out_dir.join("com/example");
+        std::fs::create_dir_all(&classes).unwrap();
+        std::fs::write(classes.join("Old.class"), class_file("Main.kt")).unwrap();
+        std::fs::write(classes.join("Old$Inner.class"), class_file("Main.kt")).unwrap();
+        std::fs::write(classes.join("Util.class"), class_file("Util.kt")).unwrap();
+
+        let sources = build::collect_sources(&dir.join("src/kotlin"), "kt").unwrap();
+        let stale = [src.join("Main.kt")];
+        assert!(incremental::remove_outputs(&out_dir, &sources, &stale).unwrap());
+        assert!(!classes.join("Old.class").exists());
+        assert!(!classes.join("Old$Inner.class").exists());
+        assert!(classes.join("Util.class").exists());
+
+        // a source of the same name in the same package can't be told apart
+        std::fs::create_dir_all(dir.join("src/kotlin/other")).unwrap();
+        std::fs::write(dir.join("src/kotlin/other/Util.kt"), "package com.example\n").unwrap();
+        let sources = build::collect_sources(&dir.join("src/kotlin"), "kt").unwrap();
+        let stale = [src.join("Util.kt")];
+        assert!(!incremental::remove_outputs(&out_dir, &sources, &stale).unwrap());
+        assert!(classes.join("Util.class").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    #[ignore = "needs kotlinc"]
+    fn test_incremental_rename_class() {
+        let dir = temp_project("incremental-rename", "package com.example\n\nclass Old\n");
+        std::fs::write(
+            dir.join("src/kotlin/com/example/App.kt"),
+            "package com.example\n\nfun main() {}\n",
+        )
+        .unwrap();
+        Fyg::build(dir.join("fyg.toml")).unwrap();
+        let classes = dir.join("build/classes/com/example");
+        assert!(classes.join("Old.class").exists());
+
+        let main = dir.join("src/kotlin/com/example/Main.kt");
+        std::fs::write(main, "package com.example\n\nclass New\n").unwrap();
+        Fyg::build(dir.join("fyg.toml")).unwrap();
+        assert!(classes.join("New.class").exists());
+        assert!(!classes.join("Old.class").exists());
+        assert!(classes.join("AppKt.class").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_build_daemon() {
+        use std::{ffi::OsStr, time::Duration};
+
+        use crate::daemon::{self, Daemon};
+
+        let dir = std::env::temp_dir().join(format!("fyg-test-daemon-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let daemon = Daemon::at(&dir);
+
+        // stops the daemon and removes its directory even when an assertion fails
+        struct Cleanup<'a>(&'a Daemon, &'a Path);
+        impl Drop for Cleanup<'_> {
+            fn drop(&mut self) {
+                let _ = self.0.stop();
+                let _ = std::fs::remove_dir_all(self.1);
+            }
+        }
+        let _cleanup = Cleanup(&daemon, &dir);
+
+        assert_eq!(daemon.status(), None);
+        assert_eq!(daemon.stop().unwrap(), None);
+
+        let server = daemon.clone();
+        let handle = std::thread::spawn(move || server.run());
+        let mut status = None;
+        for _ in 0..50 {
+            status = daemon.status();
+            if status.is_some() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        assert_eq!(status.map(|s| s.pid), Some(std::process::id()));
+
+        // a compiler the daemon can't load is left to the build to run itself
+        let kotlinc = dir.join("kotlinc");
+        std::fs::write(&kotlinc, "").unwrap();
+        assert!(daemon.compile(&kotlinc, &[OsStr::new("-version")], None).is_none());
+
+        assert_eq!(daemon.stop().unwrap(), Some(std::process::id()));
+        handle.join().unwrap().unwrap();
+        assert_eq!(daemon.status(), None);
+
+        assert_eq!(daemon::format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(daemon::format_duration(Duration::from_secs(7_512)), "2h 5m 12s");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cancel_subprocess() {
+        use std::time::{Duration, Instant};
+
+        use crate::cancel::{self, CancelFlag};
+
+        // outside a scope, commands run as they always have
+        let output = cancel::output(Command::new("echo").arg("hello")).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello\n");
+
+        let flag = CancelFlag::default();
+        let canceller = flag.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            canceller.cancel();
+        });
+        let started = Instant::now();
+        let result = CancelFlag::scope(Some(flag), || {
+            cancel::output(Command::new("sleep").arg("30"))
+        });
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::Interrupted);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(CancelFlag::current().is_none());
+
+        // a download from a server that never answers is killed too
+        let server = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/app.jar", server.local_addr().unwrap());
+        let flag = CancelFlag::default();
+        let canceller = flag.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(200));
+            canceller.cancel();
+        });
+        let started = Instant::now();
+        let result = CancelFlag::scope(Some(flag), || http::get_string(&url));
+        assert!(result.unwrap_err().to_string().contains("cancelled"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_build_async_cancelled() {
+        use tokio_util::sync::CancellationToken;
+
+        let dir = temp_project("async", "fun main() {}\n");
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let options = BuildOptions {
+            cancel: Some(cancel),
+            ..Default::default()
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let result = runtime.block_on(Fyg::build_async(dir.join("fyg.toml"), &options));
+        assert!(matches!(result, Err(FygError::Cancelled)));
+        assert!(!dir.join("build").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
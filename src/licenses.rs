@@ -0,0 +1,307 @@
+use serde::Serialize;
+
+use crate::resolver::Node;
+
+/// SPDX identifiers of common licenses, with the names and URLs POMs give them by.
+///
+/// Names are matched ignoring case and any text in parentheses, and URLs ignoring the scheme.
+const KNOWN_LICENSES: &[(&str, &[&str], &[&str])] = &[
+    (
+        "Apache-2.0",
+        &[
+            "apache license, version 2.0",
+            "the apache license, version 2.0",
+            "the apache software license, version 2.0",
+            "apache software license - version 2.0",
+            "apache 2.0",
+            "apache 2",
+            "apache license 2.0",
+            "apache-2.0",
+        ],
+        &[
+            "www.apache.org/licenses/license-2.0",
+            "www.apache.org/licenses/license-2.0.txt",
+            "www.apache.org/licenses/license-2.0.html",
+            "opensource.org/licenses/apache-2.0",
+        ],
+    ),
+    (
+        "MIT",
+        &["mit", "mit license", "the mit license"],
+        &["opensource.org/licenses/mit", "www.opensource.org/licenses/mit-license.php"],
+    ),
+    (
+        "BSD-2-Clause",
+        &["bsd-2-clause", "bsd 2-clause license", "the bsd 2-clause license"],
+        &["opensource.org/licenses/bsd-2-clause"],
+    ),
+    (
+        "BSD-3-Clause",
+        &[
+            "bsd-3-clause",
+            "bsd 3-clause license",
+            "the bsd 3-clause license",
+            "new bsd license",
+            "revised bsd",
+        ],
+        &["opensource.org/licenses/bsd-3-clause"],
+    ),
+    (
+        "EPL-1.0",
+        &["eclipse public license 1.0", "eclipse public license - v 1.0", "epl-1.0"],
+        &["www.eclipse.org/legal/epl-v10.html"],
+    ),
+    (
+        "EPL-2.0",
+        &["eclipse public license 2.0", "eclipse public license - v 2.0", "epl-2.0"],
+        &["www.eclipse.org/legal/epl-2.0", "www.eclipse.org/legal/epl-v20.html"],
+    ),
+    (
+        "MPL-2.0",
+        &["mozilla public license 2.0", "mozilla public license version 2.0", "mpl-2.0"],
+        &["www.mozilla.org/mpl/2.0"],
+    ),
+    (
+        "LGPL-2.1",
+        &["gnu lesser general public license, version 2.1", "lgpl 2.1", "lgpl-2.1"],
+        &["www.gnu.org/licenses/old-licenses/lgpl-2.1.html"],
+    ),
+    (
+        "LGPL-3.0",
+        &["gnu lesser general public license, version 3", "lgpl 3", "lgpl-3.0"],
+        &["www.gnu.org/licenses/lgpl-3.0.html", "www.gnu.org/licenses/lgpl.html"],
+    ),
+    (
+        "GPL-2.0",
+        &["gnu general public license, version 2", "gpl 2", "gpl-2.0", "gplv2"],
+        &["www.gnu.org/licenses/old-licenses/gpl-2.0.html"],
+    ),
+    (
+        "GPL-2.0-with-classpath-exception",
+        &[
+            "gpl2 w/ cpe",
+            "gnu general public license, version 2 with the classpath exception",
+        ],
+        &["openjdk.java.net/legal/gplv2+ce.html"],
+    ),
+    (
+        "GPL-3.0",
+        &["gnu general public license, version 3", "gpl 3", "gpl-3.0", "gplv3"],
+        &["www.gnu.org/licenses/gpl-3.0.html", "www.gnu.org/licenses/gpl.html"],
+    ),
+    (
+        "AGPL-3.0",
+        &["gnu affero general public license, version 3", "agpl-3.0", "agplv3"],
+        &["www.gnu.org/licenses/agpl-3.0.html"],
+    ),
+    (
+        "CDDL-1.0",
+        &["cddl 1.0", "cddl-1.0", "common development and distribution license 1.0"],
+        &["opensource.org/licenses/cddl-1.0"],
+    ),
+    (
+        "CDDL-1.1",
+        &["cddl 1.1", "cddl-1.1", "common development and distribution license 1.1"],
+        &["glassfish.java.net/public/cddl+gpl_1_1.html"],
+    ),
+];
+
+/// SPDX identifiers of licenses that are rarely given by name in a POM, but that projects
+/// publishing with fyg may well use.
+const OTHER_SPDX_IDS: &[&str] = &[
+    "0BSD",
+    "AGPL-3.0-only",
+    "AGPL-3.0-or-later",
+    "BSL-1.0",
+    "CC0-1.0",
+    "EUPL-1.2",
+    "GPL-2.0-only",
+    "GPL-2.0-or-later",
+    "GPL-3.0-only",
+    "GPL-3.0-or-later",
+    "ISC",
+    "LGPL-2.1-only",
+    "LGPL-2.1-or-later",
+    "LGPL-3.0-only",
+    "LGPL-3.0-or-later",
+    "MIT-0",
+    "Unlicense",
+    "Zlib",
+];
+
+/// The license of a single resolved dependency.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DependencyLicense {
+    /// The `group:artifact:version` of the dependency.
+    pub dependency: String,
+    /// The license name from the dependency's POM, or `None` if it declares no usable license.
+    pub name: Option<String>,
+    pub url: Option<String>,
+    /// The SPDX identifier of the license, if it is one fyg recognises.
+    pub spdx: Option<String>,
+}
+
+impl DependencyLicense {
+    pub fn from_node(node: &Node) -> Self {
+        let license = node.license.as_ref();
+        let name = license.map(|l| l.name.clone());
+        let url = license.and_then(|l| l.url.clone());
+        let spdx = spdx_id(name.as_deref(), url.as_deref()).map(str::to_string);
+        Self {
+            dependency: format!(
+                "{}:{}:{}",
+                node.coordinate.group, node.coordinate.artifact, node.coordinate.version
+            ),
+            name,
+            url,
+            spdx,
+        }
+    }
+
+    /// Whether the dependency declares no license fyg could read.
+    pub fn is_unknown(&self) -> bool {
+        self.name.is_none()
+    }
+}
+
+/// Which licenses `fyg licenses --allow` and `--deny` accept, by SPDX identifier.
+#[derive(Clone, Debug, Default)]
+pub struct LicensePolicy {
+    /// If any are given, only these licenses are accepted.
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl LicensePolicy {
+    /// Whether `license` breaks the policy.
+    ///
+    /// A license that can't be identified only breaks the policy when there is an allow list,
+    /// since it can't be shown to be on it.
+    pub fn rejects(&self, license: &DependencyLicense) -> bool {
+        let is = |ids: &[String]| {
+            license
+                .spdx
+                .as_deref()
+                .is_some_and(|spdx| ids.iter().any(|id| id.eq_ignore_ascii_case(spdx)))
+        };
+        is(&self.deny) || (!self.allow.is_empty() && !is(&self.allow))
+    }
+}
+
+/// An error raised when dependencies use licenses the policy rejects.
+#[derive(Debug)]
+pub enum LicenseError {
+    Rejected {
+        /// The `group:artifact:version` of each dependency with a rejected license.
+        dependencies: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for LicenseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseError::Rejected { dependencies } => write!(
+                f,
+                "{} {} a license that isn't allowed: {}",
+                dependencies.len(),
+                if dependencies.len() == 1 { "dependency uses" } else { "dependencies use" },
+                dependencies.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for LicenseError {}
+
+/// Fails if any of `licenses` breaks `policy`.
+pub fn check_policy(
+    licenses: &[DependencyLicense],
+    policy: &LicensePolicy,
+) -> Result<(), LicenseError> {
+    let dependencies: Vec<String> = licenses
+        .iter()
+        .filter(|l| policy.rejects(l))
+        .map(|l| l.dependency.clone())
+        .collect();
+    match dependencies.is_empty() {
+        true => Ok(()),
+        false => Err(LicenseError::Rejected { dependencies }),
+    }
+}
+
+/// Finds the SPDX identifier of a license from the name and URL a POM gives it.
+pub fn spdx_id(name: Option<&str>, url: Option<&str>) -> Option<&'static str> {
+    let name = name.map(|name| {
+        // drop asides such as "(ASL 2.0)"
+        let name = match name.find('(') {
+            Some(start) => &name[..start],
+            None => name,
+        };
+        name.trim().to_ascii_lowercase()
+    });
+    let url = url.map(|url| {
+        url.trim()
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .trim_end_matches('/')
+            .to_ascii_lowercase()
+    });
+
+    KNOWN_LICENSES
+        .iter()
+        .find(|(id, names, urls)| {
+            name.as_deref()
+                .is_some_and(|n| n == id.to_ascii_lowercase() || names.contains(&n))
+                || url.as_deref().is_some_and(|u| urls.contains(&u))
+        })
+        .map(|(id, _, _)| *id)
+}
+
+/// Whether `license` is an SPDX identifier fyg knows, or an SPDX expression such as
+/// `Apache-2.0 OR MIT` made only of them. Exceptions named after `WITH` aren't checked.
+pub fn is_known_spdx(license: &str) -> bool {
+    let mut ids = 0;
+    let mut exception = false;
+    for token in license.split(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        match token {
+            "" => {}
+            "AND" | "OR" => exception = false,
+            "WITH" => exception = true,
+            _ if exception => {}
+            id => {
+                let id = id.strip_suffix('+').unwrap_or(id);
+                let known = KNOWN_LICENSES.iter().any(|(known, _, _)| *known == id)
+                    || OTHER_SPDX_IDS.contains(&id);
+                if !known {
+                    return false;
+                }
+                ids += 1;
+            }
+        }
+    }
+    ids > 0
+}
+
+/// Writes `licenses` as CSV, with a header row.
+pub fn to_csv(licenses: &[DependencyLicense]) -> String {
+    let mut csv = String::from("dependency,license,url,spdx\n");
+    for license in licenses {
+        let fields = [
+            license.dependency.as_str(),
+            license.name.as_deref().unwrap_or("unknown"),
+            license.url.as_deref().unwrap_or_default(),
+            license.spdx.as_deref().unwrap_or_default(),
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
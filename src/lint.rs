@@ -0,0 +1,176 @@
+use std::path::Path;
+
+use crate::{
+    FygBinaryType,
+    build::collect_sources,
+    toml::{DiagnosticSeverity, FygToml},
+};
+
+/// A best-practice rule `fyg lint` checks a project against.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lint {
+    /// The ID the lint is picked with in `fyg lint --only`, such as `L001`.
+    pub id: &'static str,
+    pub severity: DiagnosticSeverity,
+    /// Why the rule is worth following.
+    pub explanation: &'static str,
+}
+
+/// Every lint, in the order they are run.
+///
+/// A project counts as a published library when `publish.repositories` lists somewhere to
+/// publish it to.
+pub const LINTS: [Lint; 5] = [
+    Lint {
+        id: "L001",
+        severity: DiagnosticSeverity::Warning,
+        explanation: "a published library still at the 1.0.0-SNAPSHOT 'fyg init' starts with \
+                      has probably never had a version picked for it",
+    },
+    Lint {
+        id: "L002",
+        severity: DiagnosticSeverity::Warning,
+        explanation: "warnings in a library's code show up in every project using it, so \
+                      build.all-warnings-as-errors should keep them from being published",
+    },
+    Lint {
+        id: "L003",
+        severity: DiagnosticSeverity::Hint,
+        explanation: "the tests are run with JUnit 5 unless test.framework says otherwise, \
+                      which is easy to miss when they are written for kotest",
+    },
+    Lint {
+        id: "L004",
+        severity: DiagnosticSeverity::Warning,
+        explanation: "project.description is published in the POM, and repositories such as \
+                      Maven Central require one",
+    },
+    Lint {
+        id: "L005",
+        severity: DiagnosticSeverity::Warning,
+        explanation: "every enabled native target should link a binary of its own, as set in \
+                      targets.native.binary",
+    },
+];
+
+/// A place where a project breaks one of the [`LINTS`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LintFinding {
+    pub lint: Lint,
+    /// The workspace member the finding is about, or `None` for a lone project.
+    pub member: Option<String>,
+    /// The setting the finding is about, such as `project.version`.
+    pub field: String,
+    pub message: String,
+}
+
+/// Looks up each of the comma separated lint IDs in `ids`, such as `L001,L002`.
+pub fn parse_ids(ids: &str) -> anyhow::Result<Vec<&'static str>> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(|id| {
+            LINTS
+                .iter()
+                .find(|lint| lint.id.eq_ignore_ascii_case(id))
+                .map(|lint| lint.id)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = LINTS.iter().map(|lint| lint.id).collect();
+                    anyhow::anyhow!("unknown lint '{}', expected one of: {}", id, known.join(", "))
+                })
+        })
+        .collect()
+}
+
+/// Checks the project in `project_dir` against the lints with an ID in `only`, or every lint
+/// without it.
+pub(crate) fn run(
+    fyg_toml: &FygToml,
+    project_dir: &Path,
+    only: Option<&[&str]>,
+) -> anyhow::Result<Vec<LintFinding>> {
+    let published = fyg_toml.publish.as_ref().is_some_and(|p| !p.repositories.is_empty());
+    let mut findings: Vec<(&str, &str, String)> = Vec::new();
+
+    if published && fyg_toml.project.version == "1.0.0-SNAPSHOT" {
+        findings.push((
+            "L001",
+            "project.version",
+            "is still 1.0.0-SNAPSHOT, but the project is published".to_string(),
+        ));
+    }
+
+    let warnings_as_errors = fyg_toml.build.as_ref().and_then(|b| b.all_warnings_as_errors);
+    if published && warnings_as_errors != Some(true) {
+        findings.push((
+            "L002",
+            "build.all-warnings-as-errors",
+            "should be true for a published library".to_string(),
+        ));
+    }
+
+    let framework = fyg_toml.test.as_ref().and_then(|t| t.framework.as_ref());
+    let test_dir = project_dir.join("src").join("test");
+    if framework.is_none()
+        && (!collect_sources(&test_dir.join("kotlin"), "kt")?.is_empty()
+            || !collect_sources(&test_dir.join("java"), "java")?.is_empty())
+    {
+        findings.push((
+            "L003",
+            "test.framework",
+            "isn't set, but the project has tests under src/test".to_string(),
+        ));
+    }
+
+    let description = fyg_toml.project.description.as_deref().unwrap_or_default();
+    if published && description.trim().is_empty() {
+        findings.push((
+            "L004",
+            "project.description",
+            "should describe a published library".to_string(),
+        ));
+    }
+
+    if let Some(targets) = &fyg_toml.targets {
+        let native = targets.native_targets();
+        let binary = targets.native.as_ref().and_then(|n| n.binary.as_ref());
+        match binary {
+            None if !native.is_empty() => findings.push((
+                "L005",
+                "targets.native.binary",
+                format!(
+                    "isn't set, so {} link{} an executable by default",
+                    native.iter().map(|(key, _)| *key).collect::<Vec<_>>().join(", "),
+                    if native.len() == 1 { "s" } else { "" }
+                ),
+            )),
+            Some(binary) if matches!(binary.binary_type, FygBinaryType::XCFramework) => {
+                let apple = targets.apple_targets();
+                for (key, _) in native {
+                    if !apple.iter().any(|(apple, _)| *apple == key) {
+                        findings.push((
+                            "L005",
+                            "targets.native.binary.type",
+                            format!(
+                                "is xcFramework, so {} has no binary, only Apple targets do",
+                                key
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(findings
+        .into_iter()
+        .filter(|(id, _, _)| only.is_none_or(|only| only.contains(id)))
+        .map(|(id, field, message)| LintFinding {
+            lint: *LINTS.iter().find(|lint| lint.id == id).expect("every lint is in LINTS"),
+            member: None,
+            field: field.to_string(),
+            message,
+        })
+        .collect())
+}
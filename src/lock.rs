@@ -0,0 +1,191 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hash,
+    maven::{self, Coordinate, Repository},
+    resolver::{self, DependencyGraph},
+    toml::FygToml,
+};
+
+/// The name of the lock file written next to `fyg.toml`.
+pub const LOCK_FILE_NAME: &str = "fyg.lock";
+
+/// The exact versions every dependency of a project resolved to, stored in `fyg.lock`.
+///
+/// Artifacts are kept sorted by coordinate so the file diffs cleanly under version control.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FygLock {
+    #[serde(default, rename = "artifact")]
+    pub artifacts: Vec<LockedArtifact>,
+}
+
+/// A single resolved artifact in `fyg.lock`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct LockedArtifact {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    /// The classifier of the artifact's file, such as `natives-linux`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub classifier: Option<String>,
+    /// The dependency scope the artifact was declared in, such as `common` or `test`.
+    pub scope: String,
+    /// The SHA-256 checksum of the artifact's JAR.
+    pub sha256: String,
+    /// The repository the artifact was downloaded from.
+    pub repository: String,
+    /// Whether the artifact was pulled in by another dependency rather than declared directly.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub transitive: bool,
+}
+
+impl LockedArtifact {
+    pub fn coordinate(&self) -> Coordinate {
+        Coordinate::new(&self.group, &self.artifact, &self.version)
+            .with_classifier(self.classifier.as_deref())
+    }
+}
+
+impl FygLock {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Self::from_str(&content)
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(content: &str) -> anyhow::Result<Self> {
+        let lock: FygLock = toml::from_str(content)?;
+        Ok(lock)
+    }
+
+    pub fn to_toml_string(&self) -> anyhow::Result<String> {
+        let mut sorted = self.clone();
+        sorted.artifacts.sort();
+        let content = toml::to_string_pretty(&sorted)?;
+        Ok(format!(
+            "# This file is generated by fyg and should not be edited by hand.\n\n{}",
+            content
+        ))
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let content = self.to_toml_string()?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Resolves every Maven dependency of the project against its repositories.
+    pub fn resolve(fyg_toml: &FygToml) -> anyhow::Result<Self> {
+        let mut lock = FygLock::default();
+        lock.update(fyg_toml)?;
+        Ok(lock)
+    }
+
+    /// Re-resolves every dependency from the repositories, discarding the currently locked
+    /// versions.
+    pub fn update(&mut self, fyg_toml: &FygToml) -> anyhow::Result<()> {
+        let repositories = fyg_toml.maven_repositories()?;
+        let graph = DependencyGraph::resolve(fyg_toml)?;
+
+        let mut artifacts = Vec::new();
+        for node in graph.nodes.iter().filter(|n| n.packaging != "pom") {
+            // Kotlin/Native libraries are published as a .klib for each target
+            let extension = match node.packaging.as_str() {
+                "klib" => "klib",
+                _ => "jar",
+            };
+            let (jar, repository) = maven::fetch(&repositories, &node.coordinate, extension)?;
+
+            artifacts.push(LockedArtifact {
+                group: node.coordinate.group.clone(),
+                artifact: node.coordinate.artifact.clone(),
+                version: node.coordinate.version.clone(),
+                classifier: node.coordinate.classifier.clone(),
+                scope: node.scope.to_string(),
+                sha256: hash::sha256_hex(&std::fs::read(jar)?),
+                repository,
+                transitive: !node.direct,
+            });
+        }
+
+        artifacts.sort();
+        self.artifacts = artifacts;
+        Ok(())
+    }
+
+    /// Checks whether every dependency declared in `fyg.toml` is locked at a matching version,
+    /// and no other direct dependencies are locked.
+    pub fn is_up_to_date(&self, fyg_toml: &FygToml) -> bool {
+        let declared = resolver::declared(fyg_toml);
+
+        let all_locked = declared.iter().all(|dep| {
+            self.find(&dep.group, &dep.artifact).is_some_and(|locked| {
+                locked.scope == dep.scope
+                    && locked.classifier == dep.classifier
+                    && (is_dynamic(&dep.version)
+                        || dep.version.is_empty()
+                        || maven::is_range(&dep.version)
+                        || locked.version == dep.version)
+            })
+        });
+        let none_stale = self.artifacts.iter().filter(|a| !a.transitive).all(|locked| {
+            declared
+                .iter()
+                .any(|dep| dep.group == locked.group && dep.artifact == locked.artifact)
+        });
+
+        all_locked && none_stale
+    }
+
+    /// Finds the locked artifact for `group:artifact`.
+    pub fn find(&self, group: &str, artifact: &str) -> Option<&LockedArtifact> {
+        self.artifacts
+            .iter()
+            .find(|a| a.group == group && a.artifact == artifact)
+    }
+
+    /// Returns the local JARs of every locked artifact in the given scopes, downloading any that
+    /// are not cached and verifying their checksums.
+    ///
+    /// Artifacts are downloaded from the repository they were locked from, using its credentials
+    /// from `repositories` if it is listed there.
+    pub fn jars(
+        &self,
+        scopes: &[&str],
+        repositories: &[Repository],
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let mut jars = Vec::new();
+        for locked in self
+            .artifacts
+            .iter()
+            .filter(|a| scopes.contains(&a.scope.as_str()))
+        {
+            let coordinate = locked.coordinate();
+            let repository = repositories
+                .iter()
+                .find(|r| r.url == locked.repository)
+                .cloned()
+                .unwrap_or_else(|| Repository::new(locked.repository.clone()));
+            let (jar, _) = maven::fetch(&[repository], &coordinate, "jar")?;
+
+            let checksum = hash::sha256_hex(&std::fs::read(&jar)?);
+            if checksum != locked.sha256 {
+                anyhow::bail!(
+                    "checksum mismatch for {}: expected {}, found {}",
+                    coordinate,
+                    locked.sha256,
+                    checksum
+                );
+            }
+            jars.push(jar);
+        }
+        Ok(jars)
+    }
+}
+
+/// Whether a declared version asks for the newest release rather than a fixed version.
+pub(crate) fn is_dynamic(version: &str) -> bool {
+    matches!(version, "LATEST" | "RELEASE" | "+")
+}
@@ -1,10 +1,84 @@
-use std::{path::PathBuf, thread, time::Duration};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
 use console::style;
-use fyg::{toml::FygToml, Fyg};
+use fyg::{
+    audit::{self, Severity},
+    build::{report::REPORT_DIR, BuildMode, BuildOptions},
+    cache::{BuildCache, CleanOptions, Cleaned, DEFAULT_MAX_AGE_DAYS},
+    config::UserConfig,
+    coverage::CoverageError,
+    daemon::{self, Daemon},
+    deps::{AddOutcome, Outdated, RemoveOutcome},
+    doctor::CheckStatus,
+    error::FygError,
+    licenses::{self, LicensePolicy},
+    lint,
+    lock::LOCK_FILE_NAME,
+    migrate::MigrateSource,
+    output,
+    plan::PlannedAction,
+    release::ReleaseOptions,
+    resolver::GraphFormat,
+    scaffold::{
+        ci::CiProvider, source::SourceTemplate, template::ProjectTemplate, vcs::Vcs, InitOptions,
+    },
+    testing::{
+        report::{html_report_dir, TestReport},
+        TestError, TestOptions,
+    },
+    toml::{DiagnosticSeverity, FygToml},
+    workspace::Workspace,
+    BuildListener, BumpKind, ConsoleBuildListener, Fyg, Version,
+};
 use indicatif::{ProgressBar, ProgressStyle};
 
+/// Set by `--quiet`, which leaves only errors and output meant for scripts.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Prints a line of informational output like `println!`, unless `--quiet` is set. Output meant
+/// for scripts is printed with `println!` instead, so that it is always there.
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !QUIET.load(Ordering::Relaxed) {
+            println!($($arg)*)
+        }
+    };
+}
+
+/// Prints what the library logs to stderr, at the level `--verbose` and `--quiet` pick.
+struct ConsoleLogger;
+
+impl log::Log for ConsoleLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level() && metadata.target().starts_with("fyg")
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let badge = match record.level() {
+            log::Level::Error => style("✗").red().bold(),
+            log::Level::Warn => style("⚠").yellow().bold(),
+            log::Level::Info => style("ℹ").blue().bold(),
+            log::Level::Debug | log::Level::Trace => style("›").dim(),
+        };
+        eprintln!("   {} {}", badge, record.args());
+    }
+
+    fn flush(&self) {}
+}
+
 #[derive(Debug, Parser)]
 #[command(name = "fyg")]
 #[command(version, author)]
@@ -12,6 +86,27 @@ use indicatif::{ProgressBar, ProgressStyle};
 struct Args {
     #[command(subcommand)]
     command: Commands,
+
+    /// Work without network access, using only artifacts that are already cached
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Use this fyg.toml instead of the one in the current directory
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Print more of what fyg does: -v for compiler command lines, -vv for HTTP requests and
+    /// -vvv for everything subprocesses print
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Print nothing but errors
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Don't color the output, which NO_COLOR or TERM=dumb also turn off
+    #[arg(long, global = true)]
+    no_color: bool,
 }
 
 #[derive(Debug, Subcommand)]
@@ -19,19 +114,67 @@ enum Commands {
     New {
         name: String,
 
-        #[arg(short, long, default_value = "com.example")]
-        group: String,
+        /// The project's group, default-group from the user config or com.example by default
+        #[arg(short, long)]
+        group: Option<String>,
 
         #[arg(short, long)]
         path: Option<PathBuf>,
+
+        /// Create a workspace root instead of a project
+        #[arg(long)]
+        workspace: bool,
+
+        /// Don't write a .gitignore
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Don't write an .editorconfig
+        #[arg(long)]
+        no_editorconfig: bool,
+
+        /// Write a CI configuration for this provider: github, gitlab or circleci
+        #[arg(long, value_name = "PROVIDER")]
+        ci: Option<CiProvider>,
+
+        /// Start from a built-in template: console, library, ktor-server,
+        /// compose-multiplatform or spring-boot
+        #[arg(long, value_name = "NAME", conflicts_with = "workspace")]
+        template: Option<ProjectTemplate>,
+
+        /// Put the project under version control, with an initial commit: git, hg or none
+        /// [default: git or hg, whichever is on PATH]
+        #[arg(long, value_name = "VCS", conflicts_with_all = ["git", "no_git"])]
+        vcs: Option<Vcs>,
+
+        /// Initialize a git repository with an initial commit, the same as --vcs git
+        #[arg(long, overrides_with = "no_git")]
+        git: bool,
+
+        /// Don't initialize a repository, the same as --vcs none
+        #[arg(long, overrides_with = "git")]
+        no_git: bool,
     },
 
     Init {
         #[arg(short, long)]
         name: Option<String>,
 
-        #[arg(short, long, default_value = "com.example")]
-        group: String,
+        /// The project's group, default-group from the user config or com.example by default
+        #[arg(short, long)]
+        group: Option<String>,
+
+        /// Don't write a .gitignore
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Don't write an .editorconfig
+        #[arg(long)]
+        no_editorconfig: bool,
+
+        /// Write a CI configuration for this provider: github, gitlab or circleci
+        #[arg(long, value_name = "PROVIDER")]
+        ci: Option<CiProvider>,
     },
 
     Build {
@@ -40,25 +183,500 @@ enum Commands {
 
         #[arg(short, long)]
         target: Option<String>,
+
+        /// Fail instead of resolving dependencies if fyg.lock is missing or out of date
+        #[arg(long)]
+        frozen: bool,
+
+        /// Only build this workspace member and the members it depends on
+        #[arg(short, long)]
+        module: Option<String>,
+
+        /// Compile even if the build cache has the output of an identical build
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Build at most this many workspace members at once [default: number of CPUs]
+        #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        jobs: Option<u32>,
+
+        /// Build workspace members one after another
+        #[arg(long, conflicts_with = "jobs")]
+        sequential: bool,
+
+        /// Write an HTML report of the build to build/reports/build/index.html
+        #[arg(long)]
+        report: bool,
+
+        /// Print what the build would do, including the compiler commands, without doing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    Clean {
+        /// Also remove the project's cached builds from the build cache
+        #[arg(long)]
+        all: bool,
+
+        /// Only remove the project's cached builds, leaving build/ alone
+        #[arg(long, conflicts_with = "all")]
+        cache: bool,
+
+        /// The build cache to remove cached builds from [default: ~/.fyg/build-cache]
+        #[arg(long, value_name = "PATH")]
+        cache_dir: Option<PathBuf>,
+    },
+
+    /// Manage the build cache in ~/.fyg/build-cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommand,
     },
 
-    Clean,
+    /// Manage the build daemon that keeps the Kotlin compiler warm between builds
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommand,
+    },
 
     Run {
-        #[arg(trailing_var_arg = true)]
+        /// An argument for the JVM, such as -Xmx512m, passed after any in fyg.toml (repeatable)
+        #[arg(long = "jvm-arg", value_name = "ARG", allow_hyphen_values = true)]
+        jvm_args: Vec<String>,
+
+        /// Arguments forwarded verbatim to the program
+        #[arg(long, num_args = 0.., allow_hyphen_values = true)]
         args: Vec<String>,
     },
 
     Test {
+        /// Only run the given test class, or a single method with `Class#method`
         #[arg(short, long)]
         filter: Option<String>,
+
+        /// Don't re-run failed tests, even if test.retry is set
+        #[arg(long)]
+        no_retry: bool,
+
+        /// Write JUnit XML reports, even if test.xml-report is off
+        #[arg(long)]
+        xml: bool,
+
+        /// Also write a report in this format to build/reports/tests: html
+        #[arg(long, value_name = "FORMAT")]
+        report: Option<TestReport>,
+
+        /// Run the integration tests instead of the unit tests
+        #[arg(long)]
+        integration: bool,
+    },
+
+    /// Show the project's configuration
+    Info {
+        /// Print the configuration as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Only print this setting, such as `project.version`, without any decoration
+        #[arg(long, value_name = "KEY")]
+        field: Option<String>,
+    },
+
+    /// Read or change a single setting in fyg.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Enable, disable or list the targets in fyg.toml
+    Target {
+        #[command(subcommand)]
+        action: TargetAction,
+    },
+
+    /// Release the project: build, test, tag and publish it, then move on to the next snapshot
+    Release {
+        /// Release without running the tests
+        #[arg(long)]
+        skip_tests: bool,
+
+        /// Release without committing or tagging the release in git
+        #[arg(long)]
+        no_tag: bool,
+
+        /// The [repositories] entry to publish to, instead of those in publish.repositories
+        #[arg(short, long)]
+        repository: Option<String>,
+
+        /// Print the steps of the release without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Change the project's version
+    Version {
+        #[command(subcommand)]
+        action: VersionAction,
+
+        /// Print the new version without writing it
+        #[arg(long, global = true)]
+        dry_run: bool,
+    },
+
+    /// Update dependencies to their latest versions and regenerate fyg.lock
+    Update {
+        /// Only update the dependency with this artifact ID or group:artifact coordinate
+        name: Option<String>,
+
+        /// Print the changes without writing them
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show dependencies with newer versions available, without changing anything
+    Outdated {
+        /// Include updates to a new major version
+        #[arg(long)]
+        major: bool,
+
+        /// Print the outdated dependencies as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Check the resolved dependencies for known security vulnerabilities
+    Audit {
+        /// Exit with an error if a vulnerability of this severity or higher is found: low,
+        /// medium, high or critical
+        #[arg(long)]
+        fail_on: Option<Severity>,
+    },
+
+    /// Add a dependency to fyg.toml
+    Add {
+        /// The dependency as group:artifact, or group:artifact:version to pin a version
+        coordinate: String,
+
+        /// The dependency scope to add to: common, jvm, test, native, ios or android
+        #[arg(short, long, default_value = "common")]
+        scope: String,
+    },
+
+    /// List the resolved dependencies of the project
+    Deps {
+        /// Print the dependencies as a tree
+        #[arg(long)]
+        tree: bool,
+
+        /// Only show dependencies in this scope: common, jvm, test, native, ios or android
+        #[arg(short, long)]
+        scope: Option<String>,
+
+        /// Show how the given artifact ends up as a dependency
+        #[arg(long, value_name = "COORDINATE")]
+        why: Option<String>,
+
+        /// Show the license of every dependency
+        #[arg(long)]
+        licenses: bool,
+    },
+
+    /// List the license of every dependency and check them against a license policy
+    Licenses {
+        /// Write the licenses to this file as CSV instead of printing a table
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Only allow dependencies with this SPDX license, such as Apache-2.0 (repeatable)
+        #[arg(long, value_name = "SPDX_ID")]
+        allow: Vec<String>,
+
+        /// Fail if a dependency uses this SPDX license (repeatable)
+        #[arg(long, value_name = "SPDX_ID")]
+        deny: Vec<String>,
+    },
+
+    /// Write the dependency graph as Graphviz DOT, for example `fyg graph | dot -Tpng -o deps.png`
+    Graph {
+        /// Write the graph to this file instead of printing it
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// The format to write: dot or json
+        #[arg(long, default_value = "dot")]
+        format: GraphFormat,
+    },
+
+    /// Remove a dependency from fyg.toml
+    Remove {
+        /// The artifact ID or group:artifact coordinate of the dependency
+        name: String,
+
+        /// Only remove the dependency from this scope: common, jvm, test, native, ios or android
+        #[arg(short, long)]
+        scope: Option<String>,
+    },
+
+    /// Check fyg.toml for mistakes without building
+    Check,
+
+    /// Check the project against best practices, such as publishing with a description
+    Lint {
+        /// Only run the lints with these IDs, such as L001,L002
+        #[arg(long)]
+        only: Option<String>,
+    },
+
+    /// Rewrite fyg.toml in canonical form. Comments in it are lost
+    Fmt {
+        /// Print what would change and fail if fyg.toml isn't formatted, without rewriting it
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Check that the tools fyg needs are installed and the project is ready to build
+    Doctor,
+
+    /// Show the JDK, compiler and directories builds would use
+    Env {
+        /// Print the environment as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Generate API documentation with Dokka
+    Doc,
+
+    /// Generate Kotlin bindings for a C library from a targets.native.cinterop entry
+    Cinterop {
+        /// The name of the cinterop entry
+        name: String,
+    },
+
+    /// Build the project and upload it to Maven repositories
+    Publish {
+        /// The [repositories] entry to upload to, instead of those in publish.repositories
+        #[arg(short, long)]
+        repository: Option<String>,
+
+        /// Print what would be built and uploaded where, without doing it
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Convert another build tool's configuration into a fyg.toml
+    Migrate {
+        /// The build tool to migrate from: gradle or maven
+        #[arg(long)]
+        from: MigrateSource,
+
+        /// The project directory, which defaults to the current one
+        #[arg(short, long)]
+        path: Option<PathBuf>,
+    },
+
+    /// Generate a Kotlin source file from a template
+    Scaffold {
+        #[command(subcommand)]
+        kind: ScaffoldKind,
+
+        /// Where to write the file, relative to the project: a .kt file or a directory
+        #[arg(short, long, global = true, value_name = "PATH")]
+        output: Option<PathBuf>,
+    },
+
+    /// Print a shell completion script
+    ///
+    /// To install them:
+    ///   bash:        add `source <(fyg completions bash)` to ~/.bashrc
+    ///   zsh:         add `source <(fyg completions zsh)` to ~/.zshrc
+    ///   fish:        fyg completions fish --output ~/.config/fish/completions/fyg.fish
+    ///   elvish:      add `eval (fyg completions elvish | slurp)` to ~/.config/elvish/rc.elv
+    ///   powershell:  add `fyg completions powershell | Out-String | iex` to $PROFILE
+    #[command(verbatim_doc_comment)]
+    Completions {
+        /// The shell to generate completions for: bash, zsh, fish, elvish or powershell
+        shell: Shell,
+
+        /// Write the script to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
     },
 
-    Info,
+    /// Rebuild the project whenever a file in src/ changes
+    Watch {
+        /// Run the tests after every successful build
+        #[arg(long)]
+        test: bool,
 
+        /// How long to wait for changes to settle before rebuilding, in milliseconds
+        #[arg(
+            long,
+            value_name = "MS",
+            default_value_t = fyg::watch::DEFAULT_DEBOUNCE.as_millis() as u64
+        )]
+        delay: u64,
+    },
+
+}
+
+#[derive(Debug, Subcommand)]
+enum CacheCommand {
+    /// Remove cached builds that haven't been used recently
+    Clean {
+        /// Remove builds that haven't been used for this many days
+        #[arg(long, value_name = "DAYS", default_value_t = DEFAULT_MAX_AGE_DAYS)]
+        older_than: u64,
+
+        /// Remove every cached build of every project, however recently it was used
+        #[arg(long, conflicts_with = "older_than")]
+        global: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DaemonCommand {
+    /// Start the build daemon in the background
+    Start,
+    /// Stop the build daemon
+    Stop,
+    /// Show whether the build daemon is running
+    Status,
+    /// Run the build daemon in the foreground
+    #[command(hide = true)]
+    Run {
+        #[arg(long)]
+        dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum VersionAction {
+    /// Bump the version: major, minor, patch or snapshot
+    Bump { kind: BumpKind },
+
+    /// Set the version, such as 2.0.0 or 2.1.0-SNAPSHOT
+    Set { version: Version },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+    /// Print the value of a setting, such as project.version
+    Get {
+        key: String,
+
+        /// Read a setting, such as default-group, from the user config instead
+        #[arg(long)]
+        global: bool,
+    },
+
+    /// Change a setting, such as project.version, in fyg.toml
+    Set {
+        key: String,
+        value: String,
+
+        /// Change a setting, such as default-group, in the user config instead
+        #[arg(long)]
+        global: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TargetAction {
+    /// Enable a target, such as ios-arm64
+    Add { target: String },
+
+    /// Disable a target, keeping its other settings
+    Remove { target: String },
+
+    /// List every target, marking those that are enabled
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum ScaffoldKind {
+    /// Main.kt with a main function
+    MainClass,
+
+    /// A data class
+    DataClass { name: String },
+
+    /// An interface
+    Interface { name: String },
+
+    /// A singleton object
+    Object { name: String },
+
+    /// A Spring Boot application class and main function
+    SpringBootApp,
+}
+
+impl From<ScaffoldKind> for SourceTemplate {
+    fn from(kind: ScaffoldKind) -> Self {
+        match kind {
+            ScaffoldKind::MainClass => SourceTemplate::MainClass,
+            ScaffoldKind::DataClass { name } => SourceTemplate::DataClass { name },
+            ScaffoldKind::Interface { name } => SourceTemplate::Interface { name },
+            ScaffoldKind::Object { name } => SourceTemplate::Object { name },
+            ScaffoldKind::SpringBootApp => SourceTemplate::SpringBootApp,
+        }
+    }
+}
+
+fn init_options(no_gitignore: bool, no_editorconfig: bool, ci: Option<CiProvider>) -> InitOptions {
+    InitOptions {
+        gitignore: !no_gitignore,
+        editorconfig: !no_editorconfig,
+        ci,
+        ..Default::default()
+    }
+}
+
+/// The version control system `fyg new` puts the project under, as picked by `--vcs`, `--git`
+/// or `--no-git`, or whichever is on PATH without them.
+fn new_vcs(vcs: Option<Vcs>, git: bool, no_git: bool) -> Vcs {
+    match (vcs, git, no_git) {
+        (Some(vcs), _, _) => vcs,
+        (None, true, _) => Vcs::Git,
+        (None, _, true) => Vcs::None,
+        (None, false, false) => Vcs::detect(),
+    }
+}
+
+/// A setting's value as `fyg config` prints it, with strings left unquoted.
+fn setting_value(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// The listener that shows the progress of a build, or none with `--quiet`.
+fn console_listener() -> Option<Arc<dyn BuildListener + Send + Sync>> {
+    match QUIET.load(Ordering::Relaxed) {
+        true => None,
+        false => Some(Arc::new(ConsoleBuildListener)),
+    }
+}
+
+/// Prints what `--dry-run` found a command would do, which `--quiet` leaves in.
+fn print_plan(actions: &[PlannedAction], outcome: &str) {
+    for action in actions {
+        println!("{} {}", style("[dry-run]").yellow(), action);
+    }
+    say!();
+    say!(
+        "{} {}",
+        style("Dry run:").yellow().bold(),
+        style(outcome).white()
+    );
+    say!();
 }
 
 fn create_spinner(msg: &str) -> ProgressBar {
+    if QUIET.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
     let pb = ProgressBar::new_spinner();
     pb.set_style(
         ProgressStyle::default_spinner()
@@ -73,60 +691,177 @@ fn create_spinner(msg: &str) -> ProgressBar {
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    output::init(args.no_color);
+    QUIET.store(args.quiet, Ordering::Relaxed);
+    log::set_logger(&ConsoleLogger)?;
+    log::set_max_level(match (args.quiet, args.verbose) {
+        (true, _) => log::LevelFilter::Error,
+        (false, 0) => log::LevelFilter::Warn,
+        (false, 1) => log::LevelFilter::Info,
+        (false, 2) => log::LevelFilter::Debug,
+        (false, _) => log::LevelFilter::Trace,
+    });
+
+    let user_config = UserConfig::load()?;
+    let config_file = match &args.config {
+        Some(path) => {
+            let mut path = std::env::current_dir()?.join(path);
+            if path.is_dir() {
+                path.push("fyg.toml");
+            }
+            // paths in fyg.toml are relative to the directory it is in
+            let dir = path
+                .parent()
+                .filter(|dir| dir.is_dir())
+                .ok_or_else(|| anyhow::anyhow!("No fyg.toml found at {}", path.display()))?;
+            std::env::set_current_dir(dir)?;
+            path
+        }
+        None => std::env::current_dir()?.join("fyg.toml"),
+    };
 
+    fyg::http::offline_scope(args.offline, || run_command(args, config_file, user_config))
+}
+
+/// Runs the command `args` names, with the project's `fyg.toml` at `config_file`.
+fn run_command(args: Args, config_file: PathBuf, user_config: UserConfig) -> anyhow::Result<()> {
     match args.command {
-        Commands::New { name, group, path } => {
+        Commands::New {
+            name,
+            path,
+            workspace: true,
+            no_gitignore,
+            no_editorconfig,
+            ci,
+            vcs,
+            git,
+            no_git,
+            ..
+        } => {
+            let base_path = path.unwrap_or_else(|| std::env::current_dir().unwrap());
+
+            say!();
+            say!(
+                "Creating new workspace {}",
+                style(&name).cyan().bold()
+            );
+            say!(
+                "   {} {}",
+                style("Location:").dim(),
+                style(base_path.join(&name).display()).dim()
+            );
+            say!();
+
+            let spinner = create_spinner("Generating fyg.toml...");
+            let vcs = new_vcs(vcs, git, no_git);
+            let options = InitOptions {
+                vcs,
+                ..init_options(no_gitignore, no_editorconfig, ci)
+            };
+            Fyg::new_workspace(&base_path, &name, &options)?;
+            spinner.finish_and_clear();
+            say!("   {} Generating fyg.toml", style("✓").green().bold());
+            if let Some(ci) = ci {
+                say!("   {} Writing {}", style("✓").green().bold(), ci.config_path());
+            }
+            if vcs != Vcs::None {
+                say!("   {} Initializing {} repository", style("✓").green().bold(), vcs);
+            }
+
+            say!();
+            say!(
+                "{} {} created successfully!",
+                style("Done!").green().bold(),
+                style(&name).cyan()
+            );
+            say!();
+            say!("Add projects to the workspace by listing them in [workspace] members.");
+            say!();
+        }
+
+        Commands::New {
+            name,
+            group,
+            path,
+            no_gitignore,
+            no_editorconfig,
+            ci,
+            template,
+            vcs,
+            git,
+            no_git,
+            ..
+        } => {
             let base_path = path.unwrap_or_else(|| std::env::current_dir().unwrap());
             let project_path = base_path.join(&name);
-            let fyg_toml = FygToml::new(&name, &group);
+            let fyg_toml = user_config.new_project(&name, group.as_deref());
 
-            println!();
-            println!(
+            say!();
+            say!(
                 "Creating new project {}",
                 style(&name).cyan().bold()
             );
-            println!(
+            say!(
                 "   {} {}",
                 style("Location:").dim(),
                 style(project_path.display()).dim()
             );
-            println!();
+            say!();
 
             let spinner = create_spinner("Creating project directory...");
             spinner.finish_and_clear();
-            println!(
+            say!(
                 "   {} Creating project directory",
                 style("✓").green().bold()
             );
 
             let spinner = create_spinner("Generating fyg.toml...");
-            Fyg::new(&base_path, fyg_toml)?;
+            let vcs = new_vcs(vcs, git, no_git);
+            let options = InitOptions {
+                template,
+                vcs,
+                ..init_options(no_gitignore, no_editorconfig, ci)
+            };
+            Fyg::new(&base_path, fyg_toml, &options)?;
             spinner.finish_and_clear();
-            println!("   {} Generating fyg.toml", style("✓").green().bold());
+            say!("   {} Generating fyg.toml", style("✓").green().bold());
+            if let Some(ci) = ci {
+                say!("   {} Writing {}", style("✓").green().bold(), ci.config_path());
+            }
 
             let spinner = create_spinner("Setting up source directories...");
             thread::sleep(Duration::from_millis(150));
             spinner.finish_and_clear();
-            println!(
+            say!(
                 "   {} Setting up source directories",
                 style("✓").green().bold()
             );
+            if let Some(template) = template {
+                say!(
+                    "   {} Using the {} template",
+                    style("✓").green().bold(),
+                    style(template).cyan()
+                );
+            }
+            if vcs != Vcs::None {
+                say!("   {} Initializing {} repository", style("✓").green().bold(), vcs);
+            }
 
-            println!();
-            println!(
+            say!();
+            say!(
                 "{} {} created successfully!",
                 style("Done!").green().bold(),
                 style(&name).cyan()
             );
-            println!();
-            println!("To get started:");
-            println!();
-            println!("   {} {}", style("$").dim(), style(format!("cd {}", name)).yellow());
-            println!("   {} {}", style("$").dim(), style("fyg build").yellow());
-            println!();
+            say!();
+            say!("To get started:");
+            say!();
+            say!("   {} {}", style("$").dim(), style(format!("cd {}", name)).yellow());
+            say!("   {} {}", style("$").dim(), style("fyg build").yellow());
+            say!();
         }
 
-        Commands::Init { name, group } => {
+        Commands::Init { name, group, no_gitignore, no_editorconfig, ci } => {
             let current_dir = std::env::current_dir()?;
             let project_name = name.unwrap_or_else(|| {
                 current_dir
@@ -137,180 +872,455 @@ fn main() -> anyhow::Result<()> {
             });
 
             if current_dir.join("fyg.toml").exists() {
-                println!();
-                println!(
+                say!();
+                say!(
                     "   {} {}",
                     style("✗").red().bold(),
                     style("fyg.toml already exists in this directory").red()
                 );
-                println!();
+                say!();
                 anyhow::bail!("Project already initialised");
             }
 
-            let fyg_toml = FygToml::new(&project_name, &group);
+            let fyg_toml = user_config.new_project(&project_name, group.as_deref());
 
-            println!();
-            println!(
+            say!();
+            say!(
                 "Initialising project {}",
                 style(&project_name).cyan().bold()
             );
-            println!(
+            say!(
                 "   {} {}",
                 style("Location:").dim(),
                 style(current_dir.display()).dim()
             );
-            println!();
+            say!();
 
             let spinner = create_spinner("Generating fyg.toml...");
-            Fyg::init(&current_dir, fyg_toml)?;
+            Fyg::init(&current_dir, fyg_toml, &init_options(no_gitignore, no_editorconfig, ci))?;
             spinner.finish_and_clear();
-            println!("   {} Generating fyg.toml", style("✓").green().bold());
+            say!("   {} Generating fyg.toml", style("✓").green().bold());
+            if let Some(ci) = ci {
+                say!("   {} Writing {}", style("✓").green().bold(), ci.config_path());
+            }
 
             let spinner = create_spinner("Setting up source directories...");
             thread::sleep(Duration::from_millis(150));
             spinner.finish_and_clear();
-            println!(
+            say!(
                 "   {} Setting up source directories",
                 style("✓").green().bold()
             );
 
-            println!();
-            println!(
+            say!();
+            say!(
                 "{} Project {} initialised successfully!",
                 style("Done!").green().bold(),
                 style(&project_name).cyan()
             );
-            println!();
-            println!("To get started:");
-            println!();
-            println!("   {} {}", style("$").dim(), style("fyg build").yellow());
-            println!();
+            say!();
+            say!("To get started:");
+            say!();
+            say!("   {} {}", style("$").dim(), style("fyg build").yellow());
+            say!();
         }        
         
-        Commands::Build { release, target } => {
+        Commands::Build {
+            release,
+            target,
+            frozen,
+            module,
+            no_cache,
+            jobs,
+            sequential,
+            report,
+            dry_run,
+        } => {
             let current_dir = std::env::current_dir()?;
-            let config_path = current_dir.join("fyg.toml");
+            let config_path = config_file;
             
             if !config_path.exists() {
                 anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
             }
 
             let config = FygToml::from_file(&config_path)?;
-            let mode = if release { "release" } else { "debug" };
+            let mode = if release { BuildMode::Release } else { BuildMode::Debug };
             
-            println!("Building {} ({} mode)...", config.project.name, mode);
+            match (&config.workspace, &module) {
+                (_, Some(module)) => {
+                    say!("Building {} ({} mode)...", module, mode.profile_name())
+                }
+                (Some(workspace), None) => say!(
+                    "Building workspace with {} members ({} mode)...",
+                    workspace.members.len(),
+                    mode.profile_name()
+                ),
+                (None, None) => say!(
+                    "Building {} ({} mode)...",
+                    config.project.name,
+                    mode.profile_name()
+                ),
+            }
             if let Some(t) = &target {
-                println!("  Target: {}", t);
+                say!("  Target: {}", t);
             }
             
-            Fyg::build(&config_path)?;
-        }
-
-        Commands::Clean => {
-            let current_dir = std::env::current_dir()?;
-            let build_dir = current_dir.join("build");
-
-            println!();
-            if build_dir.exists() {
-                let spinner = create_spinner("Cleaning build directory...");
-                std::fs::remove_dir_all(&build_dir)?;
-                thread::sleep(Duration::from_millis(200));
-                spinner.finish_and_clear();
-                println!(
-                    "   {} Removed build directory",
-                    style("✓").green().bold()
-                );
-                println!();
-                println!(
-                    "{} {}",
-                    style("Done!").green().bold(),
-                    style("Clean complete").white()
+            let options = BuildOptions {
+                frozen,
+                offline: args.offline,
+                module,
+                mode,
+                no_cache,
+                jobs: match sequential {
+                    true => Some(1),
+                    false => jobs.map(|n| n as usize),
+                },
+                report,
+                listener: console_listener(),
+                ..Default::default()
+            };
+            if dry_run {
+                print_plan(&Fyg::plan_build(&config_path, &options)?, "nothing was built");
+                return Ok(());
+            }
+            let result = Fyg::build_with_options(&config_path, &options);
+            let reported = report
+                || config.build.as_ref().and_then(|b| b.generate_report).unwrap_or(false);
+            let report_path = current_dir.join(REPORT_DIR).join("index.html");
+            if reported && result.is_err() && report_path.exists() {
+                say!(
+                    "   {} Build report written to {}",
+                    style("ℹ").blue().bold(),
+                    report_path.display()
                 );
-            } else {
-                println!(
-                    "   {} {}",
+            }
+            let result = result?;
+
+            say!();
+            for warning in &result.warnings {
+                say!("   {} {}", style("⚠").yellow().bold(), warning);
+            }
+            let artifacts = result.artifacts.len();
+            let warnings = result.warnings.len();
+            say!(
+                "   {} Built {} {} in {:.1}s, {} {}{}",
+                style("✓").green().bold(),
+                artifacts,
+                if artifacts == 1 { "artifact" } else { "artifacts" },
+                result.duration.as_secs_f64(),
+                warnings,
+                if warnings == 1 { "warning" } else { "warnings" },
+                if result.cached { " (cached)" } else { "" }
+            );
+            if reported {
+                say!(
+                    "   {} Build report written to {}",
                     style("ℹ").blue().bold(),
-                    style("Nothing to clean - build directory does not exist").dim()
+                    report_path.display()
                 );
             }
-            println!();
+            say!();
         }
 
-        Commands::Run { args } => {
-            let current_dir = std::env::current_dir()?;
-            let config_path = current_dir.join("fyg.toml");
-            
-            if !config_path.exists() {
-                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+        Commands::Cache { command: CacheCommand::Clean { older_than, global } } => {
+            let cache = BuildCache::open()?;
+
+            say!();
+            let spinner = create_spinner("Cleaning build cache...");
+            let cleaned = match global {
+                true => cache.clear(),
+                false => cache.clean(Duration::from_secs(older_than * 24 * 60 * 60)),
+            };
+            spinner.finish_and_clear();
+            let cleaned = cleaned?;
+
+            let removed = cleaned.entries;
+            let builds = if removed == 1 { "build" } else { "builds" };
+            match global {
+                true => say!(
+                    "   {} Removed {} cached {} from {}",
+                    style("✓").green().bold(),
+                    removed,
+                    builds,
+                    cache.dir().display()
+                ),
+                false => say!(
+                    "   {} Removed {} cached {} unused for more than {} days from {}",
+                    style("✓").green().bold(),
+                    removed,
+                    builds,
+                    older_than,
+                    cache.dir().display()
+                ),
             }
+            say!(
+                "   {} Freed {}",
+                style("ℹ").blue().bold(),
+                output::human_size(cleaned.bytes)
+            );
+            say!();
+        }
 
-            let config = FygToml::from_file(&config_path)?;
-            println!("Running {}...", config.project.name);
-            if !args.is_empty() {
-                println!("  Args: {:?}", args);
+        Commands::Daemon { command } => {
+            let daemon = Daemon::open()?;
+            match command {
+                DaemonCommand::Start => {
+                    say!();
+                    let spinner = create_spinner("Starting build daemon...");
+                    let status = daemon.start();
+                    spinner.finish_and_clear();
+                    let status = status?;
+
+                    say!(
+                        "   {} Build daemon started with PID {}",
+                        style("✓").green().bold(),
+                        status.pid
+                    );
+                    say!();
+                }
+                DaemonCommand::Stop => {
+                    say!();
+                    match daemon.stop()? {
+                        Some(pid) => say!(
+                            "   {} Stopped the build daemon with PID {}",
+                            style("✓").green().bold(),
+                            pid
+                        ),
+                        None => say!(
+                            "   {} The build daemon isn't running",
+                            style("ℹ").blue().bold()
+                        ),
+                    }
+                    say!();
+                }
+                DaemonCommand::Status => {
+                    say!();
+                    match daemon.status() {
+                        Some(status) => {
+                            say!(
+                                "   {} The build daemon is running",
+                                style("✓").green().bold()
+                            );
+                            say!("   {} {}", style("PID:").dim(), status.pid);
+                            say!(
+                                "   {} {}",
+                                style("Uptime:").dim(),
+                                daemon::format_duration(status.uptime())
+                            );
+                        }
+                        None => say!(
+                            "   {} The build daemon isn't running",
+                            style("ℹ").blue().bold()
+                        ),
+                    }
+                    say!();
+                }
+                DaemonCommand::Run { dir } => match dir {
+                    Some(dir) => Daemon::at(dir).run()?,
+                    None => daemon.run()?,
+                },
             }
-            
-            // todo: create run command
         }
 
-        Commands::Test { filter } => {
-            let current_dir = std::env::current_dir()?;
-            let config_path = current_dir.join("fyg.toml");
-            
-            if !config_path.exists() {
+        Commands::Clean { all, cache, cache_dir } => {
+            let config_path = config_file;
+            if (all || cache) && !config_path.exists() {
                 anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
             }
 
-            let config = FygToml::from_file(&config_path)?;
-            println!("Running tests for {}...", config.project.name);
-            if let Some(f) = &filter {
-                println!("  Filter: {}", f);
+            say!();
+            let spinner = create_spinner("Cleaning...");
+            let cleaned = Fyg::clean(
+                &config_path,
+                &CleanOptions {
+                    build: !cache,
+                    cache: all || cache,
+                    cache_dir,
+                },
+            );
+            spinner.finish_and_clear();
+            let cleaned = cleaned?;
+
+            if cleaned.build_dir {
+                say!(
+                    "   {} Removed build directory",
+                    style("✓").green().bold()
+                );
+            }
+            if cleaned.entries > 0 {
+                say!(
+                    "   {} Removed {} cached {}",
+                    style("✓").green().bold(),
+                    cleaned.entries,
+                    if cleaned.entries == 1 { "build" } else { "builds" }
+                );
+            }
+            if cleaned == Cleaned::default() {
+                let nothing = match (all, cache) {
+                    (false, false) => "Nothing to clean - build directory does not exist",
+                    (_, true) => "Nothing to clean - the build cache has no builds of this project",
+                    (true, _) => "Nothing to clean - no build directory or cached builds",
+                };
+                say!("   {} {}", style("ℹ").blue().bold(), style(nothing).dim());
+            } else {
+                say!();
+                say!(
+                    "{} {}",
+                    style("Done!").green().bold(),
+                    style(format!("Freed {}", output::human_size(cleaned.bytes))).white()
+                );
             }
+            say!();
+        }
+
+        Commands::Run { jvm_args, args } => {
+            let config_path = config_file;
             
-            // todo: create test command
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let config = FygToml::from_file(&config_path)?;
+            say!("Running {}...", config.project.name);
+            if !args.is_empty() {
+                say!("  Args: {:?}", args);
+            }
+
+            Fyg::run(&config_path, &jvm_args, &args)?;
         }
 
-        Commands::Info => {
+        Commands::Test {
+            filter,
+            no_retry,
+            xml,
+            report,
+            integration,
+        } => {
             let current_dir = std::env::current_dir()?;
-            let config_path = current_dir.join("fyg.toml");
+            let config_path = config_file;
+            
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let config = FygToml::from_file(&config_path)?;
+            say!("Running tests for {}...", config.project.name);
+            if let Some(f) = &filter {
+                say!("  Filter: {}", f);
+            }
+
+            if !no_retry {
+                for warning in config.validate().iter().filter(|d| d.field == "test.retry") {
+                    say!(
+                        "  {} test.retry {}",
+                        style("⚠").yellow().bold(),
+                        warning.message
+                    );
+                }
+            }
+
+            let options = TestOptions {
+                filter,
+                no_retry,
+                xml_report: xml,
+                report,
+                integration,
+                quiet: args.quiet,
+                ..Default::default()
+            };
+            let result = Fyg::test_with_options(&config_path, &options);
+
+            // the report is also written when tests fail, but not when they couldn't run
+            let tests_ran = match &result {
+                Ok(_) => true,
+                Err(e) => {
+                    matches!(e.downcast_ref(), Some(TestError::Failed { .. }))
+                        || e.downcast_ref::<CoverageError>().is_some()
+                }
+            };
+            let report_path = current_dir
+                .join(html_report_dir(integration))
+                .join("index.html");
+            if report.is_some() && tests_ran {
+                say!();
+                say!(
+                    "   {} Test report written to {}",
+                    style("ℹ").blue().bold(),
+                    report_path.display()
+                );
+            }
+            let summary = result?;
+
+            say!();
+            say!(
+                "{} {}",
+                style("Done!").green().bold(),
+                style(summary).white()
+            );
+            say!();
+        }
+
+        Commands::Info { json, field } => {
+            let config_path = config_file;
+
+            // both are for scripts, so nothing but the value goes to stdout
+            if json || field.is_some() {
+                if !config_path.exists() {
+                    anyhow::bail!("No fyg.toml found in current directory");
+                }
+
+                let config = FygToml::from_file(&config_path)?;
+                let Some(field) = field else {
+                    println!("{}", serde_json::to_string_pretty(&config.to_json()?)?);
+                    return Ok(());
+                };
+
+                match config.field(&field)? {
+                    // strings are printed bare so they can be used directly in scripts
+                    Some(serde_json::Value::String(text)) => println!("{}", text),
+                    Some(value) => println!("{}", serde_json::to_string_pretty(&value)?),
+                    None => anyhow::bail!("fyg.toml has no setting named {}", field),
+                }
+                return Ok(());
+            }
 
             if !config_path.exists() {
-                println!();
-                println!(
+                say!();
+                say!(
                     "   {} {}",
                     style("✗").red().bold(),
                     style("No fyg.toml found in current directory").red()
                 );
-                println!();
+                say!();
                 anyhow::bail!("No fyg.toml found");
             }
 
             let config = FygToml::from_file(&config_path)?;
 
-            println!();
-            println!(
+            say!();
+            say!(
                 "{} Project Information",
                 style("ℹ").blue().bold()
             );
-            println!();
-            println!(
+            say!();
+            say!(
                 "   {} {}",
                 style("Name:").dim(),
                 style(&config.project.name).cyan().bold()
             );
-            println!(
+            say!(
                 "   {} {}",
                 style("Group:").dim(),
                 style(&config.project.group).white()
             );
-            println!(
+            say!(
                 "   {} {}",
                 style("Version:").dim(),
                 style(&config.project.version).white()
             );
 
             if let Some(desc) = &config.project.description {
-                println!(
+                say!(
                     "   {} {}",
                     style("Description:").dim(),
                     style(desc).white()
@@ -318,7 +1328,7 @@ fn main() -> anyhow::Result<()> {
             }
 
             if let Some(authors) = &config.project.authors {
-                println!(
+                say!(
                     "   {} {}",
                     style("Authors:").dim(),
                     style(authors.join(", ")).white()
@@ -326,10 +1336,10 @@ fn main() -> anyhow::Result<()> {
             }
 
             if let Some(build) = &config.build {
-                println!();
-                println!("   {}", style("Build Configuration:").dim());
+                say!();
+                say!("   {}", style("Build Configuration:").dim());
                 if let Some(mp) = build.multiplatform {
-                    println!(
+                    say!(
                         "      {} {}",
                         style("Multiplatform:").dim(),
                         if mp {
@@ -340,7 +1350,7 @@ fn main() -> anyhow::Result<()> {
                     );
                 }
                 if let Some(langs) = &build.languages {
-                    println!(
+                    say!(
                         "      {} {}",
                         style("Languages:").dim(),
                         style(langs.join(", ")).white()
@@ -349,32 +1359,1114 @@ fn main() -> anyhow::Result<()> {
             }
 
             if let Some(targets) = &config.targets {
-                println!();
-                println!("   {}", style("Targets:").dim());
-                if let Some(jvm) = &targets.jvm {
-                    if jvm.enabled {
-                        let ver = jvm.target.as_deref().unwrap_or("default");
-                        println!(
-                            "      {} JVM (target: {})",
+                say!();
+                say!("   {}", style("Targets:").dim());
+                if let Some(jvm) = targets.jvm.as_ref().filter(|jvm| jvm.enabled) {
+                    let ver = jvm.target.as_deref().unwrap_or("default");
+                    say!(
+                        "      {} JVM (target: {})",
+                        style("✓").green(),
+                        style(ver).cyan()
+                    );
+                }
+                if targets.linux_x64.as_ref().map(|t| t.enabled).unwrap_or(false) {
+                    say!("      {} Linux x64", style("✓").green());
+                }
+                if targets.linux_arm64.as_ref().map(|t| t.enabled).unwrap_or(false) {
+                    say!("      {} Linux ARM64", style("✓").green());
+                }
+                if targets.linux_arm32.as_ref().map(|t| t.enabled).unwrap_or(false) {
+                    say!("      {} Linux ARM32", style("✓").green());
+                }
+                if targets.windows_x64.as_ref().map(|t| t.enabled).unwrap_or(false) {
+                    say!("      {} Windows x64", style("✓").green());
+                }
+                if targets.windows_arm64.as_ref().map(|t| t.enabled).unwrap_or(false) {
+                    say!("      {} Windows ARM64", style("✓").green());
+                }
+                let apple = targets.apple_targets();
+                if !apple.is_empty() {
+                    say!("      {}", style("Apple:").dim());
+                    for (_, name) in apple {
+                        say!("         {} {}", style("✓").green(), name);
+                    }
+                }
+                let js = [("Browser", &targets.js_browser), ("Node.js", &targets.js_node)];
+                if js.iter().any(|(_, t)| t.as_ref().is_some_and(|t| t.enabled)) {
+                    say!("      {}", style("JavaScript:").dim());
+                    for (name, target) in js {
+                        let Some(target) = target.as_ref().filter(|t| t.enabled) else {
+                            continue;
+                        };
+                        let module_kind = target
+                            .module_kind
+                            .map_or("default".to_string(), |kind| kind.to_string());
+                        say!(
+                            "         {} {} (module: {}, source maps: {})",
                             style("✓").green(),
-                            style(ver).cyan()
+                            name,
+                            style(module_kind).cyan(),
+                            style(match target.source_maps {
+                                Some(true) => "yes",
+                                Some(false) => "no",
+                                None => "debug only",
+                            })
+                            .cyan()
                         );
                     }
                 }
-                if targets.linux_x64.as_ref().map(|t| t.enabled).unwrap_or(false) {
-                    println!("      {} Linux x64", style("✓").green());
+                let wasm = [("Wasm JS", &targets.wasm_js), ("Wasm WASI", &targets.wasm_wasi)];
+                for (name, target) in wasm {
+                    if target.as_ref().is_some_and(|t| t.enabled) {
+                        say!("      {} {}", style("✓").green(), name);
+                    }
                 }
-                if targets.macos_arm64.as_ref().map(|t| t.enabled).unwrap_or(false) {
-                    println!("      {} macOS ARM64", style("✓").green());
+                if let Some(android) = targets.android.as_ref().filter(|a| a.enabled) {
+                    let sdk = |level: Option<u32>| {
+                        level.map_or("default".to_string(), |level| level.to_string())
+                    };
+                    say!(
+                        "      {} Android (min SDK: {}, target SDK: {}, compile SDK: {})",
+                        style("✓").green(),
+                        style(sdk(android.min_sdk)).cyan(),
+                        style(sdk(android.target_sdk)).cyan(),
+                        style(sdk(android.compile_sdk)).cyan()
+                    );
+                    if let Some(namespace) = &android.namespace {
+                        say!(
+                            "        {} {}",
+                            style("Namespace:").dim(),
+                            style(namespace).white()
+                        );
+                    }
                 }
-                if targets.windows_x64.as_ref().map(|t| t.enabled).unwrap_or(false) {
-                    println!("      {} Windows x64", style("✓").green());
+            }
+            say!();
+        }
+
+        Commands::Config { action } => {
+            let config_path = config_file;
+            let global = match &action {
+                ConfigAction::Get { global, .. } | ConfigAction::Set { global, .. } => *global,
+            };
+            if !global && !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            match action {
+                ConfigAction::Get { key, global } => {
+                    let value = if global {
+                        Fyg::get_user_setting(&key)?
+                    } else {
+                        Fyg::get_setting(&config_path, &key)?
+                    };
+                    match value {
+                        // printed bare, so scripts can use it as it is
+                        Some(value) => println!("{}", setting_value(&value)),
+                        None => anyhow::bail!("{} is not set", key),
+                    }
+                }
+                ConfigAction::Set { key, value, global } => {
+                    let (old, new) = if global {
+                        Fyg::set_user_setting(&key, &value)?
+                    } else {
+                        Fyg::set_setting(&config_path, &key, &value)?
+                    };
+                    let old = old.map_or("(not set)".to_string(), |old| setting_value(&old));
+
+                    say!();
+                    say!(
+                        "   {} {} {} → {}",
+                        style("↑").green().bold(),
+                        style(key.replace('_', "-")).cyan(),
+                        style(old).dim(),
+                        style(setting_value(&new)).green()
+                    );
+                    say!();
+                    let updated = if global {
+                        UserConfig::path()?.display().to_string()
+                    } else {
+                        "fyg.toml".to_string()
+                    };
+                    say!(
+                        "{} {}",
+                        style("Done!").green().bold(),
+                        style(format!("Updated {}", updated)).white()
+                    );
+                    say!();
+                }
+            }
+        }
+
+        Commands::Target { action } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            match action {
+                TargetAction::Add { target } => match Fyg::add_target(&config_path, &target)? {
+                    true => say!(
+                        "   {} Enabled {}",
+                        style("✓").green().bold(),
+                        style(&target).cyan()
+                    ),
+                    false => say!(
+                        "   {} {} is already enabled",
+                        style("ℹ").blue().bold(),
+                        style(&target).cyan()
+                    ),
+                },
+                TargetAction::Remove { target } => {
+                    match Fyg::remove_target(&config_path, &target)? {
+                        true => say!(
+                            "   {} Disabled {}",
+                            style("✓").green().bold(),
+                            style(&target).cyan()
+                        ),
+                        false => say!(
+                            "   {} {} isn't enabled",
+                            style("ℹ").blue().bold(),
+                            style(&target).cyan()
+                        ),
+                    }
+                }
+                TargetAction::List => {
+                    for (target, enabled) in Fyg::targets(&config_path)? {
+                        match enabled {
+                            true => say!("   {} {}", style("✓").green(), target),
+                            false => say!("     {}", style(target).dim()),
+                        }
+                    }
+                }
+            }
+            say!();
+        }
+
+        Commands::Release {
+            skip_tests,
+            no_tag,
+            repository,
+            dry_run,
+        } => {
+            let config_path = config_file;
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let config = FygToml::from_file(&config_path)?;
+            say!();
+            say!(
+                "Releasing {} {}",
+                style(&config.project.name).cyan().bold(),
+                style(&config.project.version).dim()
+            );
+            say!();
+
+            let release = Fyg::release(
+                &config_path,
+                &ReleaseOptions {
+                    skip_tests,
+                    no_tag,
+                    repository,
+                    dry_run,
+                    listener: console_listener(),
+                },
+            )?;
+
+            if dry_run {
+                for step in &release.steps {
+                    say!("   {} {}", style("ℹ").blue().bold(), step);
+                }
+            }
+            say!();
+            if dry_run {
+                say!(
+                    "{} {}",
+                    style("Dry run:").yellow().bold(),
+                    style("nothing was released").white()
+                );
+            } else {
+                say!(
+                    "{} {}",
+                    style("Done!").green().bold(),
+                    style(format!(
+                        "Released {}, now working on {}",
+                        release.version, release.next
+                    ))
+                    .white()
+                );
+            }
+            say!();
+        }
+
+        Commands::Version { action, dry_run } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let (old, new) = match action {
+                VersionAction::Bump { kind } => Fyg::bump_version(&config_path, kind, dry_run)?,
+                VersionAction::Set { version } => Fyg::set_version(&config_path, version, dry_run)?,
+            };
+
+            say!();
+            say!(
+                "   {} {} → {}",
+                style("↑").green().bold(),
+                style(old).dim(),
+                style(new).green()
+            );
+            say!();
+            if dry_run {
+                say!(
+                    "{} {}",
+                    style("Dry run:").yellow().bold(),
+                    style("no files were changed").white()
+                );
+            } else {
+                say!(
+                    "{} {}",
+                    style("Done!").green().bold(),
+                    style("Updated fyg.toml").white()
+                );
+            }
+            say!();
+        }
+
+        Commands::Update { name, dry_run } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            let spinner = create_spinner("Checking for newer versions...");
+            let changes = Fyg::update(&config_path, name.as_deref(), dry_run);
+            spinner.finish_and_clear();
+            let changes = changes?;
+
+            for change in &changes {
+                say!(
+                    "   {} {} {} → {}",
+                    style("↑").green().bold(),
+                    style(&change.key).cyan(),
+                    style(&change.old).dim(),
+                    style(&change.new).green()
+                );
+            }
+            if changes.is_empty() {
+                say!(
+                    "   {} {}",
+                    style("ℹ").blue().bold(),
+                    style("All dependencies are up to date").dim()
+                );
+            }
+
+            say!();
+            if dry_run {
+                say!(
+                    "{} {}",
+                    style("Dry run:").yellow().bold(),
+                    style("no files were changed").white()
+                );
+            } else {
+                say!(
+                    "{} {}",
+                    style("Done!").green().bold(),
+                    style(format!("Updated fyg.toml and {}", LOCK_FILE_NAME)).white()
+                );
+            }
+            say!();
+        }
+
+        Commands::Outdated { major, json } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            if json {
+                let outdated = Fyg::outdated(&config_path, major)?;
+                println!("{}", serde_json::to_string_pretty(&outdated)?);
+                return Ok(());
+            }
+
+            say!();
+            let spinner = create_spinner("Checking for newer versions...");
+            let outdated = Fyg::outdated(&config_path, major);
+            spinner.finish_and_clear();
+            let outdated = outdated?;
+
+            if outdated.is_empty() {
+                say!(
+                    "   {} {}",
+                    style("ℹ").blue().bold(),
+                    style("All dependencies are up to date").dim()
+                );
+                say!();
+                return Ok(());
+            }
+
+            let width = |column: fn(&Outdated) -> &str, header: &str| {
+                outdated
+                    .iter()
+                    .map(|o| column(o).len())
+                    .chain([header.len()])
+                    .max()
+                    .unwrap_or_default()
+            };
+            let key = width(|o| &o.key, "Dependency");
+            let current = width(|o| &o.current, "Current");
+            let stable = width(|o| &o.stable, "Stable");
+            say!(
+                "   {}",
+                style(format!(
+                    "{:key$}  {:current$}  {:stable$}  {}",
+                    "Dependency", "Current", "Stable", "Latest"
+                ))
+                .bold()
+            );
+            for o in &outdated {
+                say!(
+                    "   {}  {}  {}  {}",
+                    style(format!("{:key$}", o.key)).cyan(),
+                    style(format!("{:current$}", o.current)).dim(),
+                    style(format!("{:stable$}", o.stable)).green(),
+                    style(&o.latest).yellow()
+                );
+            }
+            say!();
+        }
+
+        Commands::Audit { fail_on } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            let spinner = create_spinner("Checking dependencies for known vulnerabilities...");
+            let vulnerabilities = Fyg::audit(&config_path);
+            spinner.finish_and_clear();
+            let vulnerabilities = vulnerabilities?;
+
+            if vulnerabilities.is_empty() {
+                say!(
+                    "   {} {}",
+                    style("✓").green().bold(),
+                    style("No known vulnerabilities found").dim()
+                );
+                say!();
+                return Ok(());
+            }
+
+            let groups = [
+                (Some(Severity::Critical), "Critical"),
+                (Some(Severity::High), "High"),
+                (Some(Severity::Medium), "Medium"),
+                (Some(Severity::Low), "Low"),
+                (None, "Unrated"),
+            ];
+            for (severity, label) in groups {
+                let group: Vec<_> = vulnerabilities
+                    .iter()
+                    .filter(|v| v.severity == severity)
+                    .collect();
+                if group.is_empty() {
+                    continue;
+                }
+
+                let heading = format!("{} ({})", label, group.len());
+                let heading = match severity {
+                    Some(Severity::Critical) | Some(Severity::High) => style(heading).red(),
+                    Some(Severity::Medium) => style(heading).yellow(),
+                    _ => style(heading).dim(),
+                };
+                say!("   {}", heading.bold());
+                for v in group {
+                    let aliases = match v.aliases.is_empty() {
+                        true => String::new(),
+                        false => format!(" ({})", v.aliases.join(", ")),
+                    };
+                    say!(
+                        "     {} {}{}",
+                        style(&v.dependency).cyan(),
+                        style(&v.id).white(),
+                        style(aliases).dim()
+                    );
+                    if !v.summary.is_empty() {
+                        say!("       {}", style(&v.summary).dim());
+                    }
+                }
+                say!();
+            }
+
+            if let Some(threshold) = fail_on {
+                audit::check_threshold(&vulnerabilities, threshold)?;
+            }
+        }
+
+        Commands::Add { coordinate, scope } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            let spinner = create_spinner(&format!("Looking up {}...", coordinate));
+            let outcome = Fyg::add(&config_path, &coordinate, &scope);
+            spinner.finish_and_clear();
+
+            match outcome? {
+                AddOutcome::Added { key, version, scope } => {
+                    say!(
+                        "   {} Added {} {} to [dependencies.{}]",
+                        style("✓").green().bold(),
+                        style(key).cyan(),
+                        style(version).white(),
+                        scope
+                    );
                 }
-                if targets.ios_arm64.as_ref().map(|t| t.enabled).unwrap_or(false) {
-                    println!("      {} iOS ARM64", style("✓").green());
+                AddOutcome::AlreadyPresent { key, scopes } => {
+                    say!(
+                        "   {} {} is already a dependency in [dependencies.{}]",
+                        style("⚠").yellow().bold(),
+                        style(key).cyan(),
+                        scopes.join("], [dependencies.")
+                    );
+                }
+            }
+            say!();
+        }
+
+        Commands::Remove { name, scope } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            match Fyg::remove(&config_path, &name, scope.as_deref())? {
+                RemoveOutcome::Removed(removed) => {
+                    for (scope, key) in removed {
+                        say!(
+                            "   {} Removed {} from [dependencies.{}]",
+                            style("✓").green().bold(),
+                            style(key).cyan(),
+                            scope
+                        );
+                    }
+                }
+                RemoveOutcome::Ambiguous(matches) => {
+                    say!(
+                        "   {} {} matches more than one dependency:",
+                        style("⚠").yellow().bold(),
+                        style(&name).cyan()
+                    );
+                    for (scope, key) in matches {
+                        say!("      {} [dependencies.{}]", style(key).white(), scope);
+                    }
+                    say!();
+                    say!(
+                        "   Specify one with {} or a full group:artifact coordinate",
+                        style("--scope").yellow()
+                    );
+                }
+            }
+            say!();
+        }
+
+        Commands::Licenses { output, allow, deny } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            let spinner = create_spinner("Reading dependency licenses...");
+            let found = Fyg::licenses(&config_path);
+            spinner.finish_and_clear();
+            let found = found?;
+            let policy = LicensePolicy { allow, deny };
+
+            if let Some(output) = &output {
+                std::fs::write(output, licenses::to_csv(&found))?;
+                say!(
+                    "   {} Wrote the licenses of {} dependencies to {}",
+                    style("✓").green().bold(),
+                    found.len(),
+                    output.display()
+                );
+            } else {
+                let width = found
+                    .iter()
+                    .map(|l| l.dependency.len())
+                    .chain(["Dependency".len()])
+                    .max()
+                    .unwrap_or_default();
+                say!(
+                    "   {}",
+                    style(format!("{:width$}  {}", "Dependency", "License")).bold()
+                );
+                for license in &found {
+                    let name = match &license.name {
+                        Some(name) => style(name.clone()).white(),
+                        None => style("unknown".to_string()).yellow(),
+                    };
+                    let marker = match policy.rejects(license) {
+                        true => style("✗").red().bold(),
+                        false => style(" ").dim(),
+                    };
+                    let line = format!(
+                        "   {}  {} {}",
+                        style(format!("{:width$}", license.dependency)).cyan(),
+                        marker,
+                        name
+                    );
+                    match &license.url {
+                        Some(url) => say!("{} {}", line, style(url).dim()),
+                        None => say!("{}", line),
+                    }
+                }
+            }
+
+            let unknown = found.iter().filter(|l| l.is_unknown()).count();
+            if unknown > 0 {
+                say!();
+                say!(
+                    "   {} {} {} no license in {} POM",
+                    style("⚠").yellow().bold(),
+                    unknown,
+                    if unknown == 1 { "dependency declares" } else { "dependencies declare" },
+                    if unknown == 1 { "its" } else { "their" }
+                );
+            }
+            say!();
+
+            licenses::check_policy(&found, &policy)?;
+        }
+
+        Commands::Graph { output, format } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let spinner = create_spinner("Resolving dependencies...");
+            let graph = Fyg::dependency_graph(&config_path);
+            spinner.finish_and_clear();
+            let graph = graph?;
+
+            let rendered = match format {
+                GraphFormat::Dot => {
+                    let project = Fyg::load(&config_path)?.project;
+                    let name = format!("{}:{}:{}", project.group, project.name, project.version);
+                    graph.to_dot(&name)
+                }
+                GraphFormat::Json => {
+                    format!("{}\n", serde_json::to_string_pretty(&graph.to_json())?)
+                }
+            };
+
+            match output {
+                Some(output) => {
+                    std::fs::write(&output, rendered)?;
+                    say!();
+                    say!(
+                        "   {} Wrote the dependency graph to {}",
+                        style("✓").green().bold(),
+                        output.display()
+                    );
+                    say!();
+                }
+                None => print!("{}", rendered),
+            }
+        }
+
+        Commands::Deps { tree, scope, why, licenses } => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let spinner = create_spinner("Resolving dependencies...");
+            let graph = Fyg::dependency_graph(&config_path);
+            spinner.finish_and_clear();
+            let graph = graph?;
+
+            say!();
+            if let Some(name) = &why {
+                match graph.why(name) {
+                    Some(path) => {
+                        let path: Vec<String> = path.iter().map(|c| c.to_string()).collect();
+                        say!("   {}", path.join(&format!(" {} ", style("→").dim())));
+                    }
+                    None => say!(
+                        "   {} {} is not a dependency of this project",
+                        style("ℹ").blue().bold(),
+                        style(name).cyan()
+                    ),
+                }
+            } else if tree {
+                for line in graph.tree(scope.as_deref()).lines() {
+                    say!("   {}", line);
+                }
+            } else {
+                let mut nodes = graph.reachable(scope.as_deref());
+                nodes.sort_by(|a, b| a.coordinate.cmp(&b.coordinate));
+                for node in nodes {
+                    if licenses {
+                        say!(
+                            "   {} {}",
+                            style(&node.coordinate).cyan(),
+                            style(node.license.as_ref().map_or("unknown", |l| &l.name)).white()
+                        );
+                    } else {
+                        say!(
+                            "   {} {}",
+                            style(&node.coordinate).cyan(),
+                            style(format!("({})", node.scope)).dim()
+                        );
+                    }
+                }
+            }
+            say!();
+        }
+
+        Commands::Check => {
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let problems = match Fyg::check(&config_path) {
+                Ok(problems) | Err(FygError::ValidationError(problems)) => problems,
+                Err(e) => return Err(e.into()),
+            };
+
+            say!();
+            for problem in &problems {
+                let badge = match problem.severity {
+                    DiagnosticSeverity::Error => style("✗").red().bold(),
+                    DiagnosticSeverity::Warning => style("⚠").yellow().bold(),
+                    DiagnosticSeverity::Hint => style("ℹ").cyan().bold(),
+                };
+                say!(
+                    "   {} {} {}",
+                    badge,
+                    style(format!("{}:", problem.field)).yellow(),
+                    style(&problem.message).white()
+                );
+            }
+
+            let errors: Vec<_> = problems.iter().filter(|p| p.is_error()).collect();
+            if errors.is_empty() {
+                say!(
+                    "   {} {}",
+                    style("✓").green().bold(),
+                    style("fyg.toml is valid").white()
+                );
+                say!();
+                return Ok(());
+            }
+
+            say!();
+            anyhow::bail!(
+                "fyg.toml has {} error{}",
+                errors.len(),
+                if errors.len() == 1 { "" } else { "s" }
+            );
+        }
+
+        Commands::Lint { only } => {
+            let config_path = config_file;
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let only = only.as_deref().map(lint::parse_ids).transpose()?;
+            let findings = Fyg::lint(&config_path, only.as_deref())?;
+
+            say!();
+            for finding in &findings {
+                let badge = match finding.lint.severity {
+                    DiagnosticSeverity::Error => style("✗").red().bold(),
+                    DiagnosticSeverity::Warning => style("⚠").yellow().bold(),
+                    DiagnosticSeverity::Hint => style("ℹ").cyan().bold(),
+                };
+                let field = match &finding.member {
+                    Some(member) => format!("{}: {}:", member, finding.field),
+                    None => format!("{}:", finding.field),
+                };
+                say!(
+                    "   {} {} {} {}",
+                    badge,
+                    style(finding.lint.id).dim(),
+                    style(field).yellow(),
+                    style(&finding.message).white()
+                );
+                say!("        {}", style(finding.lint.explanation).dim());
+            }
+
+            if findings.is_empty() {
+                say!(
+                    "   {} {}",
+                    style("✓").green().bold(),
+                    style("No lints found anything to fix").white()
+                );
+            }
+            say!();
+        }
+
+        Commands::Fmt { check } => {
+            let config_path = config_file;
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let diff = FygToml::format_file(&config_path, check)?;
+            say!();
+            match (diff, check) {
+                (None, _) => say!(
+                    "   {} {}",
+                    style("✓").green().bold(),
+                    style("fyg.toml is already formatted").white()
+                ),
+                (Some(diff), true) => {
+                    for line in diff.lines() {
+                        let line = match line.chars().next() {
+                            Some('+') if !line.starts_with("+++") => style(line).green(),
+                            Some('-') if !line.starts_with("---") => style(line).red(),
+                            Some('@') => style(line).cyan(),
+                            _ => style(line).dim(),
+                        };
+                        say!("{}", line);
+                    }
+                    say!();
+                    anyhow::bail!("fyg.toml is not formatted, run 'fyg fmt' to format it");
+                }
+                (Some(_), false) => say!(
+                    "   {} {}",
+                    style("✓").green().bold(),
+                    style("Formatted fyg.toml").white()
+                ),
+            }
+            say!();
+        }
+
+        Commands::Doctor => {
+            let config_path = config_file;
+            let config_path = config_path.exists().then_some(config_path);
+            let checks = fyg::doctor::run(config_path.as_deref())?;
+
+            say!();
+            for check in &checks {
+                let badge = match check.status {
+                    CheckStatus::Passed => style("✓").green().bold(),
+                    CheckStatus::Warning => style("⚠").yellow().bold(),
+                    CheckStatus::Failed => style("✗").red().bold(),
+                };
+                say!(
+                    "   {} {} {}",
+                    badge,
+                    style(format!("{}:", check.name)).yellow(),
+                    style(&check.detail).white()
+                );
+            }
+            say!();
+
+            let failed = checks
+                .iter()
+                .filter(|check| check.status == CheckStatus::Failed)
+                .count();
+            if failed > 0 {
+                anyhow::bail!(
+                    "fyg doctor found {} problem{}",
+                    failed,
+                    if failed == 1 { "" } else { "s" }
+                );
+            }
+        }
+
+        Commands::Env { json } => {
+            let config_path = config_file;
+            let config_path = config_path.exists().then_some(config_path);
+            let env = fyg::env::run(config_path.as_deref())?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&env)?);
+                return Ok(());
+            }
+
+            let path = |path: &Option<PathBuf>, missing: &str| match path {
+                Some(path) => style(path.display().to_string()).white(),
+                None => style(missing.to_string()).dim(),
+            };
+            let version = |version: &Option<String>| match version {
+                Some(version) => style(version.clone()).white(),
+                None => style("(unknown)".to_string()).dim(),
+            };
+            let user_config = if env.user_config.exists() {
+                style(env.user_config.display().to_string()).white()
+            } else {
+                style(format!("{} (not created)", env.user_config.display())).dim()
+            };
+
+            say!();
+            say!("   {} {}", style("JAVA_HOME:").yellow(), path(&env.java_home, "(not set)"));
+            say!("   {} {}", style("KOTLINC:").yellow(), path(&env.kotlinc, "(not found)"));
+            say!(
+                "   {} {}",
+                style("FYG_CACHE_DIR:").yellow(),
+                style(env.cache_dir.display()).white()
+            );
+            say!("   {} {}", style("FYG_CONFIG:").yellow(), user_config);
+            say!("   {} {}", style("Java version:").yellow(), version(&env.java_version));
+            say!("   {} {}", style("Kotlin version:").yellow(), version(&env.kotlin_version));
+
+            if !env.variables.is_empty() {
+                say!();
+                say!("   {}", style("Used by fyg.toml:").bold());
+                for variable in &env.variables {
+                    let value = match (&variable.value, &variable.default) {
+                        (Some(value), _) => style(value.clone()).white(),
+                        (None, Some(default)) => {
+                            style(format!("(not set, defaults to {})", default)).dim()
+                        }
+                        (None, None) => style("(not set)".to_string()).red(),
+                    };
+                    say!(
+                        "   {} {}",
+                        style(format!("{}:", variable.name)).yellow(),
+                        value
+                    );
+                }
+            }
+            say!();
+        }
+
+        Commands::Scaffold { kind, output } => {
+            let current_dir = std::env::current_dir()?;
+            let config_path = config_file;
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let path = Fyg::scaffold(&config_path, &kind.into(), output.as_deref())?;
+            let path = path.strip_prefix(&current_dir).unwrap_or(&path);
+            say!();
+            say!("   {} Writing {}", style("✓").green().bold(), path.display());
+            say!();
+        }
+
+        Commands::Cinterop { name } => {
+            let current_dir = std::env::current_dir()?;
+            let config_path = config_file;
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            let spinner = create_spinner(&format!("Generating bindings for {}...", name));
+            let result = Fyg::cinterop(&config_path, &name);
+            spinner.finish_and_clear();
+            for klib in result? {
+                let klib = klib.strip_prefix(&current_dir).unwrap_or(&klib);
+                say!("   {} Writing {}", style("✓").green().bold(), klib.display());
+            }
+            say!();
+        }
+
+        Commands::Doc => {
+            let config_path = config_file;
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            say!();
+            let spinner = create_spinner("Generating documentation...");
+            let result = Fyg::doc(&config_path);
+            spinner.finish_and_clear();
+            let output_dir = result?;
+            say!(
+                "   {} Documentation written to {}",
+                style("✓").green().bold(),
+                style(output_dir.display()).cyan()
+            );
+            say!();
+        }
+
+        Commands::Publish { repository, dry_run } => {
+            let config_path = config_file;
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let config = FygToml::from_file(&config_path)?;
+            say!();
+            say!(
+                "Publishing {} {}",
+                style(&config.project.name).cyan().bold(),
+                style(&config.project.version).dim()
+            );
+            say!();
+
+            if dry_run {
+                let actions = Fyg::plan_publish(&config_path, repository.as_deref())?;
+                print_plan(&actions, "nothing was published");
+                return Ok(());
+            }
+            let uploaded = Fyg::publish(&config_path, repository.as_deref())?;
+            for url in &uploaded {
+                say!("   {} {}", style("✓").green().bold(), style(url).dim());
+            }
+            say!();
+            say!(
+                "   {} Published {} files",
+                style("✓").green().bold(),
+                uploaded.len()
+            );
+            say!();
+        }
+
+        Commands::Migrate { from, path } => {
+            let dir = match path {
+                Some(path) => path,
+                None => std::env::current_dir()?,
+            };
+            let config_path = dir.join("fyg.toml");
+            if config_path.exists() {
+                anyhow::bail!("{} already exists", config_path.display());
+            }
+
+            let migration = fyg::migrate::migrate(&dir, from)?;
+            migration.fyg_toml.write_to_file(&config_path)?;
+
+            say!();
+            say!(
+                "Migrated {} from {}",
+                style(&migration.fyg_toml.project.name).cyan().bold(),
+                from
+            );
+            say!();
+            for warning in &migration.warnings {
+                say!("   {} {}", style("⚠").yellow().bold(), warning);
+            }
+            say!(
+                "   {} Writing {}",
+                style("✓").green().bold(),
+                config_path.display()
+            );
+            say!();
+        }
+
+        Commands::Completions { shell, output } => match output {
+            Some(path) => {
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent)?;
                 }
+                let mut file = std::fs::File::create(&path)?;
+                clap_complete::generate(shell, &mut Args::command(), "fyg", &mut file);
             }
-            println!();
+            None => {
+                clap_complete::generate(shell, &mut Args::command(), "fyg", &mut std::io::stdout())
+            }
+        },
+
+        Commands::Watch { test, delay } => {
+            let current_dir = std::env::current_dir()?;
+            let config_path = config_file;
+
+            if !config_path.exists() {
+                anyhow::bail!("No fyg.toml found. Run 'fyg init' first.");
+            }
+
+            let config = FygToml::from_file(&config_path)?;
+            let dirs: Vec<PathBuf> = if config.workspace.is_some() {
+                Workspace::load(&config_path)?
+                    .members
+                    .iter()
+                    .map(|member| member.dir.join("src"))
+                    .collect()
+            } else {
+                vec![current_dir.join("src")]
+            };
+
+            // let a build that is underway finish instead of killing it part way through
+            let stop = Arc::new(AtomicBool::new(false));
+            {
+                let stop = stop.clone();
+                ctrlc::set_handler(move || stop.store(true, Ordering::SeqCst))?;
+            }
+
+            let rebuild = || {
+                say!("{}", style("─".repeat(60)).dim());
+                match Fyg::build(&config_path) {
+                    Ok(_) => say!(
+                        "{} {}",
+                        style("✓").green().bold(),
+                        style("Build succeeded").green()
+                    ),
+                    Err(e) => {
+                        say!("{} {}", style("✗").red().bold(), style("Build failed").red());
+                        say!("   {}", e);
+                        return;
+                    }
+                }
+
+                if test {
+                    match Fyg::test(&config_path, None) {
+                        Ok(summary) => say!(
+                            "{} {}",
+                            style("✓").green().bold(),
+                            style(format!("Tests passed: {}", summary)).green()
+                        ),
+                        Err(e) => {
+                            say!(
+                                "{} {}",
+                                style("✗").red().bold(),
+                                style("Tests failed").red()
+                            );
+                            say!("   {}", e);
+                        }
+                    }
+                }
+            };
+
+            say!();
+            say!(
+                "{} Watching {} for changes (press Ctrl-C to stop)",
+                style("ℹ").blue().bold(),
+                dirs.iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            rebuild();
+
+            fyg::watch::watch(&dirs, Duration::from_millis(delay), &stop, |_| rebuild())?;
+
+            say!();
+            say!(
+                "{} {}",
+                style("Done!").green().bold(),
+                style("Stopped watching").white()
+            );
+            say!();
         }
     }
 
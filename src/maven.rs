@@ -0,0 +1,545 @@
+use std::{
+    cmp::Ordering,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+
+use crate::{http, toolchain, xml::Element};
+
+/// The URL of the Maven Central repository.
+pub const MAVEN_CENTRAL: &str = "https://repo.maven.apache.org/maven2";
+
+/// A Maven artifact coordinate in the form `group:artifact:version`, or
+/// `group:artifact:version:classifier` for a variant of the artifact.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Coordinate {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    /// Which of the files published under the coordinate to use, such as `natives-linux`.
+    pub classifier: Option<String>,
+}
+
+impl Coordinate {
+    pub fn new(group: &str, artifact: &str, version: &str) -> Self {
+        Self {
+            group: group.to_string(),
+            artifact: artifact.to_string(),
+            version: version.to_string(),
+            classifier: None,
+        }
+    }
+
+    /// Sets the classifier of the coordinate.
+    pub fn with_classifier(mut self, classifier: Option<&str>) -> Self {
+        self.classifier = classifier.map(str::to_string);
+        self
+    }
+
+    /// The path of this artifact's file with the given extension, relative to a repository root.
+    ///
+    /// The classifier is part of the file name, except for the POM, which every variant shares.
+    pub fn repository_path(&self, extension: &str) -> String {
+        let classifier = match &self.classifier {
+            Some(classifier) if extension != "pom" => format!("-{}", classifier),
+            _ => String::new(),
+        };
+        format!(
+            "{}/{}/{}/{}-{}{}.{}",
+            self.group.replace('.', "/"),
+            self.artifact,
+            self.version,
+            self.artifact,
+            self.version,
+            classifier,
+            extension
+        )
+    }
+}
+
+impl std::fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.group, self.artifact, self.version)?;
+        if let Some(classifier) = &self.classifier {
+            write!(f, ":{}", classifier)?;
+        }
+        Ok(())
+    }
+}
+
+/// A Maven repository that artifacts are downloaded from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repository {
+    pub url: String,
+    /// Credentials sent with every request, for private repositories.
+    pub credentials: Option<Credentials>,
+}
+
+impl Repository {
+    /// A public repository that needs no credentials.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            credentials: None,
+        }
+    }
+}
+
+/// A username and password for HTTP Basic authentication.
+///
+/// The password is redacted from `Debug` output so it can't end up in logs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("password", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Returns the local path of the given artifact file, downloading it into `~/.fyg/cache/` from
+/// the first repository that has it if it has not been fetched before.
+///
+/// The URL of the repository the file originally came from is returned alongside it.
+pub fn fetch(
+    repositories: &[Repository],
+    coordinate: &Coordinate,
+    extension: &str,
+) -> anyhow::Result<(PathBuf, String)> {
+    let relative = coordinate.repository_path(extension);
+    let path = toolchain::cache_dir()?.join(&relative);
+    // a sidecar file recording where the artifact was downloaded from
+    let origin = path.with_extension(format!("{}.origin", extension));
+
+    if path.is_file() {
+        let repository = std::fs::read_to_string(&origin).unwrap_or_default();
+        return Ok((path, repository.trim().to_string()));
+    }
+
+    for repository in repositories {
+        let url = format!("{}/{}", repository.url.trim_end_matches('/'), relative);
+        if http::download(&url, &path, repository.credentials.as_ref()).is_ok() {
+            std::fs::write(&origin, &repository.url)?;
+            if !repository.url.starts_with("file://") {
+                publish_locally(repositories, &path, &relative);
+            }
+            return Ok((path, repository.url.clone()));
+        }
+    }
+
+    if http::is_offline() {
+        return Err(NotCached {
+            coordinate: coordinate.clone(),
+            path,
+        }
+        .into());
+    }
+
+    let urls: Vec<&str> = repositories.iter().map(|r| r.url.as_str()).collect();
+    anyhow::bail!(
+        "could not find {} in any repository ({})",
+        coordinate,
+        urls.join(", ")
+    )
+}
+
+/// An artifact [`fetch`] couldn't find in the cache or a local repository while offline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotCached {
+    pub coordinate: Coordinate,
+    /// Where the artifact is looked for in `~/.fyg/cache/`.
+    pub path: PathBuf,
+}
+
+impl std::fmt::Display for NotCached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not cached at {}, and can't be downloaded while offline",
+            self.coordinate,
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for NotCached {}
+
+/// Copies a downloaded file into the first `file://` repository, which is the local Maven
+/// repository when one is enabled, so that Maven and other tools can reuse it.
+///
+/// This is best effort: a local repository that can't be written to is left alone.
+fn publish_locally(repositories: &[Repository], downloaded: &Path, relative: &str) {
+    let Some(local) = repositories
+        .iter()
+        .find_map(|r| r.url.strip_prefix("file://"))
+    else {
+        return;
+    };
+
+    let published = Path::new(local).join(relative);
+    if published.exists() {
+        return;
+    }
+    if let Some(parent) = published.parent()
+        && std::fs::create_dir_all(parent).is_ok()
+    {
+        let _ = std::fs::copy(downloaded, published);
+    }
+}
+
+/// How long a downloaded `maven-metadata.xml` is reused before it is fetched again.
+const METADATA_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The versions of an artifact listed in a repository's `maven-metadata.xml`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The newest non-snapshot release, if the repository records one in `<release>`.
+    pub release: Option<String>,
+    /// Every published version, oldest first.
+    pub versions: Vec<String>,
+}
+
+impl Metadata {
+    /// The newest release, falling back to the newest stable version listed, as the newest
+    /// listed version may be a pre-release.
+    pub fn latest(&self) -> Option<&str> {
+        self.release.as_deref().or_else(|| {
+            self.versions
+                .iter()
+                .map(String::as_str)
+                .filter(|v| is_stable(v))
+                .max_by(|a, b| compare_versions(a, b))
+        })
+    }
+
+    /// The newest version listed, which may be a pre-release.
+    pub fn newest(&self) -> Option<&str> {
+        self.versions
+            .iter()
+            .map(String::as_str)
+            .max_by(|a, b| compare_versions(a, b))
+    }
+}
+
+/// Reads the `maven-metadata.xml` of `group:artifact` from the first repository that has it.
+///
+/// Metadata is cached in `~/.fyg/cache/` and reused for a day before being fetched again.
+pub fn metadata(
+    repositories: &[Repository],
+    group: &str,
+    artifact: &str,
+) -> anyhow::Result<Metadata> {
+    let relative = format!(
+        "{}/{}/maven-metadata.xml",
+        group.replace('.', "/"),
+        artifact
+    );
+    let cached = toolchain::cache_dir()?.join(&relative);
+
+    let fresh = std::fs::metadata(&cached)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age < METADATA_TTL);
+
+    if !fresh {
+        let downloaded = repositories.iter().any(|repository| {
+            let url = format!("{}/{}", repository.url.trim_end_matches('/'), relative);
+            http::download(&url, &cached, repository.credentials.as_ref()).is_ok()
+        });
+        // a stale copy is still better than nothing when the repositories can't be reached
+        if !downloaded && !cached.is_file() {
+            if http::is_offline() {
+                anyhow::bail!(
+                    "the versions of {}:{} are not cached at {}, and can't be downloaded while \
+                     offline",
+                    group,
+                    artifact,
+                    cached.display()
+                );
+            }
+            anyhow::bail!("could not find any versions of {}:{}", group, artifact);
+        }
+    }
+
+    let document = Element::parse(&std::fs::read_to_string(&cached)?)?;
+    let versioning = document.child("versioning");
+    Ok(Metadata {
+        // <latest> is left out, as it can be a snapshot
+        release: versioning
+            .and_then(|v| v.child_text("release"))
+            .map(str::to_string),
+        versions: versioning
+            .and_then(|v| v.child("versions"))
+            .into_iter()
+            .flat_map(|v| v.children("version"))
+            .map(|v| v.text.clone())
+            .collect(),
+    })
+}
+
+/// Looks up the newest release of `group:artifact` from the repositories' `maven-metadata.xml`,
+/// as [`Metadata::latest`] picks it.
+pub fn latest_version(
+    repositories: &[Repository],
+    group: &str,
+    artifact: &str,
+) -> anyhow::Result<String> {
+    metadata(repositories, group, artifact)?
+        .latest()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("could not find a stable release of {}:{}", group, artifact))
+}
+
+/// Whether `spec` is a Maven version range such as `[1.0,2.0)`, rather than a single version.
+pub fn is_range(spec: &str) -> bool {
+    spec.trim().starts_with(['[', '('])
+}
+
+/// Picks the newest of `versions` that satisfies the Maven version range `spec`, or `None` if
+/// none does or `spec` isn't a range.
+pub fn resolve_range<'a>(spec: &str, versions: &'a [String]) -> Option<&'a str> {
+    spec.parse::<VersionRange>().ok()?.newest(versions)
+}
+
+/// A bound of a version interval, and whether it is inclusive.
+type Bound = Option<(String, bool)>;
+
+/// A Maven version range, such as `[1.0,2.0)`.
+///
+/// Ranges use Maven's interval syntax: `[1.0,2.0)` is at least 1.0 and below 2.0, `[1.0,)` is 1.0
+/// or newer, and `[1.0]` is exactly 1.0. Several ranges can be separated by commas, as in
+/// `[1.0,1.5),[2.0,)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionRange {
+    intervals: Vec<(Bound, Bound)>,
+}
+
+impl VersionRange {
+    /// Whether `version` falls within the range.
+    pub fn contains(&self, version: &str) -> bool {
+        self.intervals.iter().any(|(lower, upper)| {
+            let above = lower.as_ref().is_none_or(|(bound, inclusive)| {
+                let ordering = compare_versions(version, bound);
+                ordering.is_gt() || (*inclusive && ordering.is_eq())
+            });
+            let below = upper.as_ref().is_none_or(|(bound, inclusive)| {
+                let ordering = compare_versions(version, bound);
+                ordering.is_lt() || (*inclusive && ordering.is_eq())
+            });
+            above && below
+        })
+    }
+
+    /// The newest of `versions` within the range.
+    pub fn newest<'a>(&self, versions: &'a [String]) -> Option<&'a str> {
+        versions
+            .iter()
+            .filter(|version| self.contains(version))
+            .max_by(|a, b| compare_versions(a, b))
+            .map(String::as_str)
+    }
+}
+
+impl std::str::FromStr for VersionRange {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let invalid = || anyhow::anyhow!("'{}' is not a valid version range", spec);
+        let mut intervals = Vec::new();
+        let mut rest = spec.trim();
+
+        while !rest.is_empty() {
+            let lower_inclusive = match rest.chars().next() {
+                Some('[') => true,
+                Some('(') => false,
+                _ => return Err(invalid()),
+            };
+            let end = rest.find([']', ')']).ok_or_else(invalid)?;
+            let upper_inclusive = rest[end..].starts_with(']');
+            let body = &rest[1..end];
+            rest = rest[end + 1..]
+                .trim_start()
+                .trim_start_matches(',')
+                .trim_start();
+
+            let bound = |version: &str, inclusive| {
+                let version = version.trim();
+                (!version.is_empty()).then(|| (version.to_string(), inclusive))
+            };
+            match body.split_once(',') {
+                Some((lower, upper)) => {
+                    intervals.push((bound(lower, lower_inclusive), bound(upper, upper_inclusive)))
+                }
+                // `[1.0]` pins an exact version
+                None if !body.trim().is_empty() => {
+                    intervals.push((bound(body, true), bound(body, true)))
+                }
+                None => return Err(invalid()),
+            }
+        }
+
+        if intervals.is_empty() {
+            return Err(invalid());
+        }
+        Ok(VersionRange { intervals })
+    }
+}
+
+/// Compares two Maven version strings, such as `1.10.2` and `1.9.0-RC`.
+///
+/// Versions are compared segment by segment, splitting on `.` and `-`. Numeric segments compare
+/// numerically, and a qualifier such as `RC` or `SNAPSHOT` sorts before the release it precedes.
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a = a.split(['.', '-']);
+    let mut b = b.split(['.', '-']);
+
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (None, None) => return Ordering::Equal,
+            (Some(x), None) => release_or_qualifier(x),
+            (None, Some(y)) => release_or_qualifier(y).reverse(),
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Err(_), Err(_)) => x.to_lowercase().cmp(&y.to_lowercase()),
+            },
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Whether `version` is a stable release rather than a pre-release such as `2.0.0-RC1`,
+/// `1.5-beta2`, `3.0.0-M1` or a snapshot.
+pub fn is_stable(version: &str) -> bool {
+    const PRE_RELEASE: [&str; 9] = [
+        "alpha",
+        "beta",
+        "rc",
+        "cr",
+        "snapshot",
+        "dev",
+        "eap",
+        "preview",
+        "milestone",
+    ];
+    !version.split(['.', '-']).any(|segment| {
+        let segment = segment.to_ascii_lowercase();
+        PRE_RELEASE.iter().any(|q| segment.starts_with(q))
+            || segment == "pre"
+            // short forms such as M1, a2 or b3
+            || segment
+                .strip_prefix(['m', 'a', 'b'])
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    })
+}
+
+/// How a version with an extra trailing segment compares to one without it.
+fn release_or_qualifier(segment: &str) -> Ordering {
+    if segment.parse::<u64>().is_ok() {
+        Ordering::Greater
+    } else {
+        Ordering::Less
+    }
+}
+
+/// The Maven Central search API.
+const SEARCH_URL: &str = "https://search.maven.org/solrsearch/select";
+
+/// Looks up `group:artifact` with the Maven Central search API, returning its latest version, or
+/// `None` if no such artifact exists.
+///
+/// With a `version`, only that version is looked for, and it is returned if it exists.
+pub fn search(
+    group: &str,
+    artifact: &str,
+    version: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let response = http::get_string(&search_url(group, artifact, version))?;
+    parse_search(&response).map_err(|e| {
+        anyhow::anyhow!("unexpected search response for {}:{}: {}", group, artifact, e)
+    })
+}
+
+/// The search API query for `group:artifact`, or for one version of it.
+pub(crate) fn search_url(group: &str, artifact: &str, version: Option<&str>) -> String {
+    let mut query = format!("g:\"{}\" AND a:\"{}\"", group, artifact);
+    if let Some(version) = version {
+        query.push_str(&format!(" AND v:\"{}\"", version));
+    }
+    // versions are only searchable in the `gav` core, which lists each version separately
+    let core = if version.is_some() { "&core=gav" } else { "" };
+    format!("{}?q={}{}&rows=1&wt=json", SEARCH_URL, percent_encode(&query), core)
+}
+
+/// Reads the version found by a search API query, or `None` if nothing matched.
+pub(crate) fn parse_search(body: &str) -> anyhow::Result<Option<String>> {
+    let response: SearchResponse = serde_json::from_str(body)?;
+    if response.response.num_found == 0 {
+        return Ok(None);
+    }
+    let doc = response
+        .response
+        .docs
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("no results listed"))?;
+    doc.latest_version
+        .or(doc.v)
+        .map(Some)
+        .ok_or_else(|| anyhow::anyhow!("no version listed"))
+}
+
+#[derive(Deserialize)]
+struct SearchResponse {
+    response: SearchResults,
+}
+
+#[derive(Deserialize)]
+struct SearchResults {
+    #[serde(rename = "numFound")]
+    num_found: u64,
+    #[serde(default)]
+    docs: Vec<SearchDoc>,
+}
+
+/// One search result: an artifact with its latest version, or one version of it in the `gav`
+/// core.
+#[derive(Deserialize)]
+struct SearchDoc {
+    #[serde(rename = "latestVersion")]
+    latest_version: Option<String>,
+    v: Option<String>,
+}
+
+/// Escapes everything in `value` but unreserved characters, so it can go in a URL query.
+fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'_' | b'.' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Returns the local path of the given artifact's JAR, downloading it from Maven Central into
+/// `~/.fyg/cache/` first if it has not been fetched before.
+pub fn fetch_jar(coordinate: &Coordinate) -> anyhow::Result<PathBuf> {
+    let (path, _) = fetch(&[Repository::new(MAVEN_CENTRAL)], coordinate, "jar")?;
+    Ok(path)
+}
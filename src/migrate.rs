@@ -0,0 +1,67 @@
+pub mod gradle;
+pub mod maven;
+
+use std::{path::Path, str::FromStr};
+
+use crate::toml::FygToml;
+
+/// A build tool whose configuration `fyg migrate` can convert into a `fyg.toml`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MigrateSource {
+    /// Gradle, configured by `build.gradle.kts` or `build.gradle`.
+    Gradle,
+
+    /// Maven, configured by `pom.xml`.
+    Maven,
+}
+
+impl FromStr for MigrateSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "gradle" => Ok(MigrateSource::Gradle),
+            "maven" => Ok(MigrateSource::Maven),
+            other => anyhow::bail!("unknown build tool '{}', expected gradle or maven", other),
+        }
+    }
+}
+
+impl std::fmt::Display for MigrateSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MigrateSource::Gradle => write!(f, "gradle"),
+            MigrateSource::Maven => write!(f, "maven"),
+        }
+    }
+}
+
+/// A `fyg.toml` converted from another build tool's configuration.
+#[derive(Clone, Debug)]
+pub struct Migration {
+    pub fyg_toml: FygToml,
+    /// Everything in the original configuration that couldn't be translated, one message each.
+    pub warnings: Vec<String>,
+}
+
+/// Converts the build of the project in `dir` from the given build tool.
+///
+/// The conversion is a best effort: whatever has no `fyg.toml` equivalent is left out and
+/// described in [`Migration::warnings`].
+pub fn migrate(dir: &Path, from: MigrateSource) -> anyhow::Result<Migration> {
+    let mut migration = match from {
+        MigrateSource::Gradle => gradle::migrate(dir)?,
+        MigrateSource::Maven => maven::migrate(dir)?,
+    };
+
+    // Both tools keep main sources where fyg doesn't look for them
+    for source_dir in ["src/main/kotlin", "src/commonMain/kotlin"] {
+        if dir.join(source_dir).is_dir() {
+            migration.warnings.push(format!(
+                "sources in {} need moving to src/kotlin",
+                source_dir
+            ));
+        }
+    }
+    Ok(migration)
+}
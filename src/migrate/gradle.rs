@@ -0,0 +1,858 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    config::{
+        AndroidTarget, BuildConfig, CustomRepository, DependencyValue, ExplicitApiMode, JsTarget,
+        JvmTarget, RepositoryConfig, TargetEnabled, TargetsConfig,
+    },
+    migrate::Migration,
+    toml::FygToml,
+};
+
+/// The build scripts looked for, Kotlin DSL first.
+const BUILD_SCRIPTS: [&str; 2] = ["build.gradle.kts", "build.gradle"];
+
+/// The settings scripts the root project name is read from.
+const SETTINGS_SCRIPTS: [&str; 2] = ["settings.gradle.kts", "settings.gradle"];
+
+/// Kotlin source sets whose dependencies are migrated, and the fyg scope each one maps to.
+const SOURCE_SETS: [(&str, &str); 4] = [
+    ("commonMain", "common"),
+    ("jvmMain", "jvm"),
+    ("commonTest", "test"),
+    ("jvmTest", "test"),
+];
+
+/// Repositories declared with a shorthand like `google()`, by shorthand, name and URL.
+const KNOWN_REPOSITORIES: [(&str, &str, &str); 2] = [
+    ("google", "google", "https://maven.google.com"),
+    (
+        "gradlePluginPortal",
+        "gradle-plugin-portal",
+        "https://plugins.gradle.org/m2",
+    ),
+];
+
+/// Converts the Gradle build in `dir`, reading `build.gradle.kts` or `build.gradle` along with
+/// `settings.gradle(.kts)` and `gradle.properties` if they exist.
+pub fn migrate(dir: &Path) -> anyhow::Result<Migration> {
+    let script = BUILD_SCRIPTS
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "no build.gradle.kts or build.gradle found in {}",
+                dir.display()
+            )
+        })?;
+
+    let settings = SETTINGS_SCRIPTS
+        .iter()
+        .find_map(|name| std::fs::read_to_string(dir.join(name)).ok())
+        .map(|settings| tokenize(&settings))
+        .unwrap_or_default();
+    let name = settings
+        .iter()
+        .find_map(|token| match token {
+            Token::Statement(statement) => match assignment(statement) {
+                Some(("rootProject.name", value)) => literals(value).first().map(|s| s.to_string()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .or_else(|| {
+            let dir = dir.canonicalize().ok()?;
+            Some(dir.file_name()?.to_str()?.to_string())
+        })
+        .unwrap_or_else(|| "my-project".to_string());
+
+    let properties = std::fs::read_to_string(dir.join("gradle.properties"))
+        .map(|properties| parse_properties(&properties))
+        .unwrap_or_default();
+
+    let mut converter = Converter::new(&name, properties);
+    for token in tokenize(&std::fs::read_to_string(&script)?) {
+        converter.token(token);
+    }
+    let mut migration = converter.finish();
+
+    let includes = settings.iter().any(
+        |token| matches!(token, Token::Statement(statement) if statement.starts_with("include")),
+    );
+    if includes {
+        migration.warnings.push(
+            "subprojects included by the settings script are not migrated, run fyg migrate in \
+             each one and list them as workspace members"
+                .to_string(),
+        );
+    }
+    Ok(migration)
+}
+
+/// A piece of a build script: a statement, or the start or end of a `{ }` block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Statement(String),
+    /// The opening of a block, holding whatever came before the `{`, such as `dependencies`.
+    Open(String),
+    Close,
+}
+
+/// Splits a Kotlin or Groovy build script into statements and blocks, dropping comments.
+///
+/// Statements end at a newline or `;` outside parentheses, and whitespace within them is
+/// collapsed to single spaces.
+fn tokenize(source: &str) -> Vec<Token> {
+    fn statement(tokens: &mut Vec<Token>, current: &mut String) {
+        let text = current.split_whitespace().collect::<Vec<_>>().join(" ");
+        current.clear();
+        if !text.is_empty() {
+            tokens.push(Token::Statement(text));
+        }
+    }
+
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                current.push(c);
+                while let Some(s) = chars.next() {
+                    current.push(s);
+                    if s == '\\' {
+                        current.extend(chars.next());
+                    } else if s == c {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|&n| n != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for n in chars.by_ref() {
+                    if previous == '*' && n == '/' {
+                        break;
+                    }
+                    previous = n;
+                }
+            }
+            '(' | '[' => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' | ']' => {
+                depth = depth.saturating_sub(1);
+                current.push(c);
+            }
+            '{' => {
+                let header = current.split_whitespace().collect::<Vec<_>>().join(" ");
+                current.clear();
+                tokens.push(Token::Open(header));
+            }
+            '}' => {
+                statement(&mut tokens, &mut current);
+                tokens.push(Token::Close);
+            }
+            '\n' | ';' if depth == 0 => statement(&mut tokens, &mut current),
+            _ => current.push(c),
+        }
+    }
+    statement(&mut tokens, &mut current);
+    tokens
+}
+
+/// Reads the `key=value` lines of a `gradle.properties` file.
+fn parse_properties(properties: &str) -> HashMap<String, String> {
+    properties
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '!']))
+        .filter_map(|line| line.split_once(['=', ':']))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+/// Returns the contents of every string literal in `text`.
+fn literals(text: &str) -> Vec<&str> {
+    let mut literals = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(['"', '\'']) {
+        let quote = rest[start..].chars().next().unwrap_or('"');
+        let after = &rest[start + 1..];
+        let Some(len) = after.find(quote) else {
+            break;
+        };
+        literals.push(&after[..len]);
+        rest = &after[len + 1..];
+    }
+    literals
+}
+
+/// Splits `name = value` into its sides, dropping a `val`, `var` or `def` keyword and any type
+/// annotation from the name.
+fn assignment(statement: &str) -> Option<(&str, &str)> {
+    let (name, value) = statement.split_once('=')?;
+    if value.starts_with('=') || name.ends_with(['!', '<', '>']) {
+        return None;
+    }
+    let name = ["val ", "var ", "def "]
+        .iter()
+        .find_map(|keyword| name.trim().strip_prefix(keyword))
+        .unwrap_or(name);
+    let name = name.split(':').next().unwrap_or(name).trim();
+    Some((name, value.trim()))
+}
+
+/// Splits a call like `implementation("a:b:1.0")`, or Groovy's `implementation 'a:b:1.0'`, into
+/// its name and arguments.
+fn call(statement: &str) -> Option<(&str, &str)> {
+    let end = statement.find(|c: char| c == '(' || c.is_whitespace())?;
+    let (name, rest) = statement.split_at(end);
+    let args = match rest.strip_prefix('(') {
+        Some(args) => args.strip_suffix(')')?,
+        None => rest,
+    };
+    Some((name, args.trim()))
+}
+
+/// Reads a Java version from a toolchain or compatibility setting, such as `17`,
+/// `JavaVersion.VERSION_1_8` or `JavaLanguageVersion.of(17)`.
+fn java_version(value: &str) -> Option<String> {
+    if let Some((_, version)) = value.split_once("VERSION_") {
+        return Some(version.replace('_', "."));
+    }
+    let value = match value.split_once("of(") {
+        Some((_, rest)) => rest.split(')').next()?,
+        None => value,
+    };
+    let value = value.trim().trim_matches(['"', '\'']);
+    (!value.is_empty() && value.chars().all(|c| c.is_ascii_digit() || c == '.'))
+        .then(|| value.to_string())
+}
+
+/// Returns the fyg scope of the source set that a block header in `sourceSets` names, and
+/// whether the header opens its dependencies directly, as `commonMain.dependencies {` does.
+fn source_set(header: &str) -> Option<(&'static str, bool)> {
+    let (name, dependencies) = match header.strip_suffix(".dependencies") {
+        Some(name) => (name, true),
+        None => (header, false),
+    };
+    // `commonMain`, `val commonMain by getting` or `getByName("commonMain")`
+    let name = match literals(name).first() {
+        Some(name) => name,
+        None => name
+            .trim_start_matches("val ")
+            .split(" by ")
+            .next()
+            .unwrap_or(name),
+    };
+    SOURCE_SETS
+        .iter()
+        .find(|(set, _)| *set == name.trim())
+        .map(|(_, scope)| (*scope, dependencies))
+}
+
+/// Enables the target that a Kotlin target function like `iosArm64` declares, returning
+/// `false` if `name` isn't one.
+fn enable_target(targets: &mut TargetsConfig, name: &str) -> bool {
+    let target = match name {
+        "jvm" => {
+            targets.jvm.get_or_insert(JvmTarget {
+                enabled: true,
+                target: None,
+                jvm_args: None,
+            });
+            return true;
+        }
+        "android" | "androidTarget" => {
+            targets.android = Some(AndroidTarget {
+                enabled: true,
+                min_sdk: None,
+                compile_sdk: None,
+                target_sdk: None,
+                namespace: None,
+            });
+            return true;
+        }
+        "mingwX64" | "mingwArm64" => {
+            let target = match name {
+                "mingwX64" => &mut targets.windows_x64,
+                _ => &mut targets.windows_arm64,
+            };
+            target.get_or_insert_default().enabled = true;
+            return true;
+        }
+        "iosArm64" => &mut targets.ios_arm64,
+        "iosX64" => &mut targets.ios_x64,
+        "iosSimulatorArm64" => &mut targets.ios_simulator_arm64,
+        "watchosArm64" => &mut targets.watchos_arm64,
+        "watchosArm32" => &mut targets.watchos_arm32,
+        "watchosSimulatorArm64" => &mut targets.watchos_simulator_arm64,
+        "tvosArm64" => &mut targets.tvos_arm64,
+        "tvosSimulatorArm64" => &mut targets.tvos_simulator_arm64,
+        "linuxX64" => &mut targets.linux_x64,
+        "linuxArm64" => &mut targets.linux_arm64,
+        "linuxArm32Hfp" => &mut targets.linux_arm32,
+        "macosArm64" => &mut targets.macos_arm64,
+        "macosX64" => &mut targets.macos_x64,
+        "wasmJs" => &mut targets.wasm_js,
+        "wasmWasi" => &mut targets.wasm_wasi,
+        _ => return false,
+    };
+    *target = Some(TargetEnabled { enabled: true });
+    true
+}
+
+/// Which Kotlin Gradle plugin the build applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum KotlinPlugin {
+    Jvm,
+    Multiplatform,
+}
+
+/// A `{ }` block of the build script, which decides how the statements in it are translated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Block {
+    Plugins,
+    Repositories,
+    /// A `maven { }` repository, whose settings are collected in [`Converter::maven`].
+    Maven,
+    /// A `dependencies { }` block, adding to the given fyg scope.
+    Dependencies(&'static str),
+    Kotlin,
+    SourceSets,
+    SourceSet(&'static str),
+    Js,
+    Application,
+    Java,
+    Ext,
+    /// A block that was warned about or needs no translation, along with everything in it.
+    Skipped,
+}
+
+/// Translates a build script token by token into a `fyg.toml`.
+struct Converter {
+    fyg_toml: FygToml,
+    warnings: Vec<String>,
+    /// Values from `gradle.properties` and variable declarations, for `$name` in strings.
+    variables: HashMap<String, String>,
+    /// The blocks enclosing the current token, innermost last.
+    blocks: Vec<Block>,
+    plugin: Option<KotlinPlugin>,
+    targets: TargetsConfig,
+    /// Whether a `js` target was declared, which fyg builds for the browser unless it says
+    /// otherwise.
+    js: bool,
+    /// The Java version from a toolchain or compatibility setting.
+    jvm_version: Option<String>,
+    /// The name and URL of the `maven { }` repository being read.
+    maven: (Option<String>, Option<String>),
+}
+
+impl Converter {
+    fn new(name: &str, variables: HashMap<String, String>) -> Self {
+        let mut fyg_toml = FygToml::new(name, "com.example");
+        if let Some(group) = variables.get("group") {
+            fyg_toml.project.group = group.clone();
+        }
+        if let Some(version) = variables.get("version") {
+            fyg_toml.project.version = version.clone();
+        }
+
+        Self {
+            fyg_toml,
+            warnings: Vec::new(),
+            variables,
+            blocks: Vec::new(),
+            plugin: None,
+            targets: TargetsConfig::default(),
+            js: false,
+            jvm_version: None,
+            maven: (None, None),
+        }
+    }
+
+    fn token(&mut self, token: Token) {
+        match token {
+            Token::Statement(statement) => self.statement(&statement),
+            Token::Open(header) => {
+                let block = self.open(&header);
+                self.blocks.push(block);
+            }
+            Token::Close => {
+                if self.blocks.pop() == Some(Block::Maven) {
+                    match std::mem::take(&mut self.maven) {
+                        (name, Some(url)) => self.add_repository(name, url),
+                        (_, None) => self.warn("a maven { } repository has no url"),
+                    }
+                }
+            }
+        }
+    }
+
+    fn finish(mut self) -> Migration {
+        match self.plugin {
+            Some(KotlinPlugin::Multiplatform) => {
+                self.build().multiplatform = Some(true);
+                if self.js && self.targets.js_browser.is_none() && self.targets.js_node.is_none() {
+                    self.enable_js("browser");
+                }
+                if let Some(jvm) = &mut self.targets.jvm {
+                    jvm.target = self.jvm_version.take();
+                }
+            }
+            Some(KotlinPlugin::Jvm) => {
+                if let Some(version) = self.jvm_version.take() {
+                    self.targets.jvm = Some(JvmTarget {
+                        enabled: true,
+                        target: Some(version),
+                        jvm_args: None,
+                    });
+                }
+            }
+            None => {
+                self.warn("no Kotlin plugin is applied, so the project is migrated as Kotlin/JVM")
+            }
+        }
+
+        // a missing [targets.jvm] counts as enabled, so only a jvm target on its own is implied
+        if self.targets.jvm.is_some() || self.targets.enabled_targets() != ["jvm"] {
+            self.fyg_toml.targets = Some(self.targets);
+        }
+        Migration {
+            fyg_toml: self.fyg_toml,
+            warnings: self.warnings,
+        }
+    }
+
+    /// Decides how to translate the block opened by `header`.
+    fn open(&mut self, header: &str) -> Block {
+        let Some(parent) = self.blocks.last().copied() else {
+            return match header {
+                "plugins" => Block::Plugins,
+                "repositories" => Block::Repositories,
+                "dependencies" => Block::Dependencies("common"),
+                "kotlin" => Block::Kotlin,
+                "application" => Block::Application,
+                "java" => Block::Java,
+                "ext" => Block::Ext,
+                _ => self.skip(header),
+            };
+        };
+
+        match (parent, header) {
+            (Block::Skipped, _) => Block::Skipped,
+            (Block::Repositories, "maven") => Block::Maven,
+            (Block::Kotlin, "sourceSets") => Block::SourceSets,
+            (Block::Kotlin, _) if self.enable_targets(header) => {
+                if header.starts_with("js") {
+                    Block::Js
+                } else {
+                    Block::Skipped
+                }
+            }
+            (Block::SourceSets, _) => match source_set(header) {
+                Some((scope, true)) => Block::Dependencies(scope),
+                Some((scope, false)) => Block::SourceSet(scope),
+                None => self.skip(header),
+            },
+            (Block::SourceSet(scope), "dependencies") => Block::Dependencies(scope),
+            (Block::Js, "browser" | "nodejs") => {
+                self.enable_js(header);
+                Block::Skipped
+            }
+            (Block::Java, "toolchain") => Block::Java,
+            (Block::Dependencies(scope), _) => {
+                self.dependency(scope, header);
+                self.skip(header)
+            }
+            _ => self.skip(header),
+        }
+    }
+
+    fn statement(&mut self, statement: &str) {
+        let Some(block) = self.blocks.last().copied() else {
+            return self.root_statement(statement);
+        };
+
+        match block {
+            Block::Plugins => self.plugin(statement),
+            Block::Repositories => self.repository(statement),
+            Block::Maven => {
+                let value = self.string(statement);
+                if statement.starts_with("url") || statement.starts_with("setUrl") {
+                    self.maven.1 = value;
+                } else if statement.starts_with("name") {
+                    self.maven.0 = value;
+                } else {
+                    self.untranslated(statement);
+                }
+            }
+            Block::Dependencies(scope) => self.dependency(scope, statement),
+            Block::Kotlin => self.kotlin_statement(statement),
+            Block::Js => {
+                if let Some(environment @ ("browser" | "nodejs")) = statement.strip_suffix("()") {
+                    self.enable_js(environment);
+                }
+            }
+            Block::Application => match self.string(statement) {
+                Some(main_class) if statement.starts_with("mainClass") => {
+                    self.build().main_class = Some(main_class);
+                }
+                _ => self.untranslated(statement),
+            },
+            Block::Java => match java_version(statement) {
+                Some(version)
+                    if statement.contains("languageVersion")
+                        || statement.contains("Compatibility") =>
+                {
+                    self.jvm_version = Some(version);
+                }
+                _ => self.untranslated(statement),
+            },
+            Block::Ext => match assignment(statement) {
+                Some((name, value)) => self.declare(name, value),
+                None => self.untranslated(statement),
+            },
+            Block::SourceSets | Block::SourceSet(_) | Block::Skipped => {}
+        }
+    }
+
+    fn root_statement(&mut self, statement: &str) {
+        if let Some((name, value)) = assignment(statement) {
+            match name {
+                "group" | "version" | "description" => {
+                    if let Some(value) = self.string(value) {
+                        self.variables.insert(name.to_string(), value.clone());
+                        let project = &mut self.fyg_toml.project;
+                        match name {
+                            "group" => project.group = value,
+                            "version" => project.version = value,
+                            _ => project.description = Some(value),
+                        }
+                        return;
+                    }
+                }
+                "mainClassName" => {
+                    if let Some(main_class) = self.string(value) {
+                        self.build().main_class = Some(main_class);
+                        return;
+                    }
+                }
+                "sourceCompatibility"
+                | "targetCompatibility"
+                | "java.sourceCompatibility"
+                | "java.targetCompatibility" => {
+                    if let Some(version) = java_version(value) {
+                        self.jvm_version = Some(version);
+                        return;
+                    }
+                }
+                _ if ["val ", "var ", "def "]
+                    .iter()
+                    .any(|k| statement.starts_with(k)) =>
+                {
+                    return self.declare(name, value);
+                }
+                _ => {
+                    if let Some(name) = name.strip_prefix("ext.") {
+                        return self.declare(name, value);
+                    }
+                }
+            }
+        }
+
+        // `val kotlinVersion: String by project` reads gradle.properties, which is loaded
+        // already
+        if !statement.ends_with(" by project") {
+            self.untranslated(statement);
+        }
+    }
+
+    fn plugin(&mut self, statement: &str) {
+        // kotlin("jvm") version "2.0.21", id("org.jetbrains.kotlin.jvm") version "2.0.21" or
+        // id 'org.jetbrains.kotlin.jvm' version '2.0.21'
+        let (declaration, version) = match statement.split_once(" version ") {
+            Some((declaration, version)) => (declaration, Some(version)),
+            None => (statement, None),
+        };
+        let id = match declaration.strip_prefix("kotlin") {
+            Some(rest) => literals(rest)
+                .first()
+                .map(|name| format!("org.jetbrains.kotlin.{}", name)),
+            None if declaration.starts_with("id") => {
+                literals(declaration).first().map(|id| id.to_string())
+            }
+            None => Some(declaration.trim_matches('`').to_string()),
+        };
+        let Some(id) = id else {
+            return self.untranslated(statement);
+        };
+
+        let plugin = match id.as_str() {
+            "org.jetbrains.kotlin.jvm" => KotlinPlugin::Jvm,
+            "org.jetbrains.kotlin.multiplatform" => KotlinPlugin::Multiplatform,
+            "application" | "java" | "java-library" => return,
+            _ => return self.warn(format!("the {} plugin has no fyg equivalent", id)),
+        };
+        self.plugin = Some(plugin);
+        if let Some(version) = version {
+            match self.string(version) {
+                Some(version) => self.build().kotlin_version = Some(version),
+                None => self.warn(format!(
+                    "could not read the Kotlin version from `{}`",
+                    statement
+                )),
+            }
+        }
+    }
+
+    fn repository(&mut self, statement: &str) {
+        // Maven Central and the local Maven repository are both searched by default
+        if statement == "mavenCentral()" || statement == "mavenLocal()" {
+            return;
+        }
+
+        let known = statement
+            .strip_suffix("()")
+            .and_then(|call| KNOWN_REPOSITORIES.iter().find(|(name, ..)| *name == call));
+        if let Some((_, name, url)) = known {
+            self.add_repository(Some(name.to_string()), url.to_string());
+        } else if statement.starts_with("maven")
+            && let Some(url) = self.string(statement)
+        {
+            self.add_repository(None, url);
+        } else {
+            self.untranslated(statement);
+        }
+    }
+
+    fn add_repository(&mut self, name: Option<String>, url: String) {
+        // Without a name, use the domain, like `jitpack` for https://jitpack.io
+        let name = name.unwrap_or_else(|| {
+            let host = url.split("://").last().unwrap_or(&url);
+            let host = host.split(['/', ':']).next().unwrap_or(host);
+            let labels: Vec<&str> = host.split('.').collect();
+            labels[labels.len().saturating_sub(2)].to_string()
+        });
+        let name = name
+            .to_lowercase()
+            .replace(|c: char| !c.is_alphanumeric(), "-");
+
+        let repositories = self.fyg_toml.repositories.get_or_insert_with(HashMap::new);
+        let key = (1..)
+            .map(|n| {
+                if n == 1 {
+                    name.clone()
+                } else {
+                    format!("{}-{}", name, n)
+                }
+            })
+            .find(|key| !repositories.contains_key(key))
+            .unwrap_or(name);
+        repositories.insert(
+            key,
+            RepositoryConfig::Custom(CustomRepository {
+                repo_type: "maven".to_string(),
+                url,
+                username: None,
+                password: None,
+            }),
+        );
+    }
+
+    fn dependency(&mut self, scope: &'static str, statement: &str) {
+        let Some((configuration, args)) = call(statement) else {
+            return self.untranslated(statement);
+        };
+        let scope = match configuration {
+            "implementation" | "api" | "runtimeOnly" => scope,
+            "compileOnly" => {
+                self.warn(format!(
+                    "`{}` is migrated as a regular dependency",
+                    statement
+                ));
+                scope
+            }
+            "testImplementation" | "testApi" | "testRuntimeOnly" | "testCompileOnly" => "test",
+            _ => return self.untranslated(statement),
+        };
+
+        let coordinates = if let Some(module) = args.strip_prefix("kotlin(") {
+            let Some(module) = literals(module).first().copied() else {
+                return self.untranslated(statement);
+            };
+            // The standard library comes with the compiler
+            if module.starts_with("stdlib") {
+                return;
+            }
+            let version = self
+                .fyg_toml
+                .build
+                .as_ref()
+                .and_then(|b| b.kotlin_version.clone());
+            let Some(version) = version else {
+                return self.warn(format!(
+                    "`{}` needs build.kotlin-version to be migrated",
+                    statement
+                ));
+            };
+            Some(format!(
+                "org.jetbrains.kotlin:kotlin-{}:{}",
+                module, version
+            ))
+        } else if args.starts_with("group:") {
+            // Groovy's group: 'org.example', name: 'library', version: '1.0'
+            let part = |key: &str| {
+                let (_, rest) = args.split_once(&format!("{}:", key))?;
+                let value = literals(rest).first().copied()?;
+                self.resolve(value)
+            };
+            part("group")
+                .zip(part("name"))
+                .zip(part("version"))
+                .map(|((group, name), version)| format!("{}:{}:{}", group, name, version))
+        } else if args.starts_with(['"', '\'']) {
+            self.string(args)
+        } else {
+            None
+        };
+        let Some(coordinates) = coordinates else {
+            return self.untranslated(statement);
+        };
+
+        let mut parts: Vec<String> = coordinates.split(':').map(str::to_string).collect();
+        // Gradle versions Kotlin's own libraries to match the plugin
+        if let [group, _] = parts.as_slice()
+            && group == "org.jetbrains.kotlin"
+            && let Some(version) = self
+                .fyg_toml
+                .build
+                .as_ref()
+                .and_then(|b| b.kotlin_version.clone())
+        {
+            parts.push(version);
+        }
+        let [group, artifact, version, rest @ ..] = parts.as_slice() else {
+            return self.warn(format!("`{}` has no version", statement));
+        };
+        if !rest.is_empty() {
+            self.warn(format!("the classifier of `{}` is not migrated", statement));
+        }
+        let dependencies = self
+            .fyg_toml
+            .dependencies
+            .get_or_insert_with(Default::default);
+        let key = format!("{}:{}", group, artifact);
+        let value = DependencyValue::Version(version.to_string());
+        if let Err(e) = dependencies.insert(scope, key, value) {
+            self.warn(e.to_string());
+        }
+    }
+
+    fn kotlin_statement(&mut self, statement: &str) {
+        match statement {
+            "explicitApi()" => self.build().explicit_api = Some(ExplicitApiMode::Strict),
+            "explicitApiWarning()" => self.build().explicit_api = Some(ExplicitApiMode::Warning),
+            "applyDefaultHierarchyTemplate()" => {}
+            _ if statement.starts_with("jvmToolchain(") => {
+                match call(statement).and_then(|(_, args)| java_version(args)) {
+                    Some(version) => self.jvm_version = Some(version),
+                    None => self.untranslated(statement),
+                }
+            }
+            _ if self.enable_targets(statement) => {}
+            _ => self.untranslated(statement),
+        }
+    }
+
+    /// Enables every Kotlin target that `text` declares, such as in `iosArm64()` or
+    /// `listOf(iosX64(), iosArm64())`, returning whether it declared any.
+    fn enable_targets(&mut self, text: &str) -> bool {
+        let mut found = false;
+        for name in text.split(|c: char| !c.is_alphanumeric()) {
+            if name == "js" {
+                self.js = true;
+                found = true;
+            } else {
+                found |= enable_target(&mut self.targets, name);
+            }
+        }
+        found
+    }
+
+    /// Enables the Kotlin/JS target for `browser` or `nodejs`.
+    fn enable_js(&mut self, environment: &str) {
+        let target = Some(JsTarget {
+            enabled: true,
+            module_kind: None,
+            source_maps: None,
+        });
+        match environment {
+            "browser" => self.targets.js_browser = target,
+            _ => self.targets.js_node = target,
+        }
+    }
+
+    fn declare(&mut self, name: &str, value: &str) {
+        match self.string(value) {
+            Some(value) => {
+                self.variables.insert(name.to_string(), value);
+            }
+            None => self.warn(format!("could not read the value of {}", name)),
+        }
+    }
+
+    /// Returns the first string literal in `text`, with any `$name` or `${name}` in it replaced.
+    fn string(&self, text: &str) -> Option<String> {
+        self.resolve(literals(text).first()?)
+    }
+
+    /// Replaces each `$name` or `${name}` in `literal` with the variable's value, or returns
+    /// `None` if one isn't known.
+    fn resolve(&self, literal: &str) -> Option<String> {
+        let mut resolved = String::new();
+        let mut rest = literal;
+        while let Some(start) = rest.find('$') {
+            resolved.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let (name, len) = match after.strip_prefix('{') {
+                Some(inner) => {
+                    let end = inner.find('}')?;
+                    (&inner[..end], end + 2)
+                }
+                None => {
+                    let end = after
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .unwrap_or(after.len());
+                    (&after[..end], end)
+                }
+            };
+            resolved.push_str(self.variables.get(name)?);
+            rest = &after[len..];
+        }
+        resolved.push_str(rest);
+        Some(resolved)
+    }
+
+    fn build(&mut self) -> &mut BuildConfig {
+        self.fyg_toml.build.get_or_insert_with(BuildConfig::default)
+    }
+
+    fn skip(&mut self, header: &str) -> Block {
+        self.warn(format!("could not translate `{} {{ ... }}`", header));
+        Block::Skipped
+    }
+
+    fn untranslated(&mut self, statement: &str) {
+        self.warn(format!("could not translate `{}`", statement));
+    }
+
+    fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+}
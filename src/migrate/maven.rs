@@ -0,0 +1,247 @@
+use std::{collections::HashMap, path::Path};
+
+use crate::{
+    config::{
+        BuildConfig, CustomRepository, DependenciesConfig, DependencyValue, JvmTarget,
+        RepositoryConfig, TargetsConfig,
+    },
+    migrate::Migration,
+    resolver::interpolate,
+    toml::FygToml,
+    xml::Element,
+};
+
+/// Properties holding the JVM version to compile for, most specific first.
+const JVM_TARGET_PROPERTIES: [&str; 3] = [
+    "kotlin.compiler.jvmTarget",
+    "maven.compiler.release",
+    "maven.compiler.target",
+];
+
+/// Build plugins whose work fyg does itself, so they are left out without a warning.
+const BUILT_IN_PLUGINS: [&str; 6] = [
+    "kotlin-maven-plugin",
+    "maven-compiler-plugin",
+    "maven-surefire-plugin",
+    "maven-jar-plugin",
+    "maven-resources-plugin",
+    "exec-maven-plugin",
+];
+
+/// Converts the Maven build in `dir`, reading its `pom.xml`.
+pub fn migrate(dir: &Path) -> anyhow::Result<Migration> {
+    let path = dir.join("pom.xml");
+    if !path.is_file() {
+        anyhow::bail!("no pom.xml found in {}", dir.display());
+    }
+    let project = Element::parse(&std::fs::read_to_string(&path)?)
+        .map_err(|e| anyhow::anyhow!("failed to parse {}: {}", path.display(), e))?;
+
+    let mut warnings = Vec::new();
+    let parent = project.child("parent");
+    if let Some(parent) = parent {
+        warnings.push(format!(
+            "the parent POM {}:{} is not migrated, so anything inherited from it is missing",
+            parent.child_text("groupId").unwrap_or_default(),
+            parent.child_text("artifactId").unwrap_or_default()
+        ));
+    }
+
+    let text = |name| {
+        project
+            .child_text(name)
+            .or_else(|| parent.and_then(|p| p.child_text(name)))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let (group, artifact, version) = (text("groupId"), text("artifactId"), text("version"));
+
+    let mut properties: HashMap<String, String> = project
+        .child("properties")
+        .into_iter()
+        .flat_map(|properties| &properties.children)
+        .map(|property| (property.name.clone(), property.text.clone()))
+        .collect();
+    for (key, value) in [
+        ("project.groupId", &group),
+        ("project.artifactId", &artifact),
+        ("project.version", &version),
+    ] {
+        properties.insert(key.to_string(), value.clone());
+    }
+    let property = |name: &str| {
+        properties
+            .get(name)
+            .map(|value| interpolate(value, &properties))
+    };
+
+    let mut fyg_toml = FygToml::new(&artifact, &group);
+    if !version.is_empty() {
+        fyg_toml.project.version = version.clone();
+    }
+    fyg_toml.project.description = project.child_text("description").map(str::to_string);
+
+    let plugins: Vec<&Element> = project
+        .find(&["build", "plugins"])
+        .into_iter()
+        .flat_map(|plugins| plugins.children("plugin"))
+        .collect();
+    let kotlin_plugin = plugins
+        .iter()
+        .find(|plugin| plugin.child_text("artifactId") == Some("kotlin-maven-plugin"));
+
+    let kotlin_version = property("kotlin.version").or_else(|| {
+        let version = kotlin_plugin?.child_text("version")?;
+        Some(interpolate(version, &properties))
+    });
+    let main_class = plugins.iter().find_map(|plugin| {
+        let configuration = plugin.child("configuration")?;
+        configuration
+            .child_text("mainClass")
+            .or_else(|| {
+                configuration
+                    .find(&["archive", "manifest", "mainClass"])
+                    .map(|e| e.text.as_str())
+            })
+            .map(|main_class| interpolate(main_class, &properties))
+    });
+    if kotlin_version.is_some() || main_class.is_some() {
+        fyg_toml.build = Some(BuildConfig {
+            kotlin_version,
+            main_class,
+            ..Default::default()
+        });
+    }
+
+    let jvm_target = JVM_TARGET_PROPERTIES
+        .iter()
+        .find_map(|name| property(name))
+        .or_else(|| {
+            let target = kotlin_plugin?.find(&["configuration", "jvmTarget"])?;
+            Some(interpolate(&target.text, &properties))
+        });
+    if let Some(target) = jvm_target {
+        fyg_toml.targets = Some(TargetsConfig {
+            jvm: Some(JvmTarget {
+                enabled: true,
+                target: Some(target),
+                jvm_args: None,
+            }),
+            ..Default::default()
+        });
+    }
+
+    for plugin in &plugins {
+        let artifact = plugin.child_text("artifactId").unwrap_or_default();
+        if !BUILT_IN_PLUGINS.contains(&artifact) {
+            warnings.push(format!("{} has no fyg equivalent", artifact));
+        }
+    }
+
+    let managed: Vec<&Element> = project
+        .find(&["dependencyManagement", "dependencies"])
+        .into_iter()
+        .flat_map(|dependencies| dependencies.children("dependency"))
+        .collect();
+    let mut dependencies = DependenciesConfig::default();
+    for dependency in project
+        .child("dependencies")
+        .into_iter()
+        .flat_map(|dependencies| dependencies.children("dependency"))
+    {
+        let text = |name| {
+            dependency
+                .child_text(name)
+                .map(|t| interpolate(t, &properties))
+        };
+        let key = dependency_key(dependency, &properties);
+        // The standard library comes with the compiler
+        if key.starts_with("org.jetbrains.kotlin:kotlin-stdlib") {
+            continue;
+        }
+
+        let scope = match text("scope").as_deref() {
+            None | Some("compile") | Some("runtime") => "common",
+            Some("test") => "test",
+            Some("provided") => "jvm",
+            Some(scope) => {
+                warnings.push(format!(
+                    "{} has the {} scope, which fyg has no equivalent for",
+                    key, scope
+                ));
+                continue;
+            }
+        };
+        let version = text("version").or_else(|| {
+            let managed = managed
+                .iter()
+                .find(|managed| dependency_key(managed, &properties) == key)?;
+            Some(interpolate(managed.child_text("version")?, &properties))
+        });
+        let Some(version) = version.filter(|version| !version.contains("${")) else {
+            warnings.push(format!("could not find the version of {}", key));
+            continue;
+        };
+
+        if text("classifier").is_some() {
+            warnings.push(format!("the classifier of {} is not migrated", key));
+        }
+        if dependency.child("exclusions").is_some() {
+            warnings.push(format!("the exclusions of {} are not migrated", key));
+        }
+        dependencies.insert(scope, key, DependencyValue::Version(version))?;
+    }
+    if dependencies
+        .scopes()
+        .iter()
+        .any(|(_, entries)| entries.is_some())
+    {
+        fyg_toml.dependencies = Some(dependencies);
+    }
+
+    let mut repositories = HashMap::new();
+    for repository in project
+        .child("repositories")
+        .into_iter()
+        .flat_map(|repositories| repositories.children("repository"))
+    {
+        let Some(url) = repository.child_text("url") else {
+            continue;
+        };
+        let url = interpolate(url, &properties);
+        // Maven Central is searched by default
+        if url.contains("repo.maven.apache.org") || url.contains("repo1.maven.org") {
+            continue;
+        }
+        let id = repository.child_text("id").unwrap_or("repository");
+        repositories.insert(
+            id.to_lowercase()
+                .replace(|c: char| !c.is_alphanumeric(), "-"),
+            RepositoryConfig::Custom(CustomRepository {
+                repo_type: "maven".to_string(),
+                url,
+                username: None,
+                password: None,
+            }),
+        );
+    }
+    if !repositories.is_empty() {
+        fyg_toml.repositories = Some(repositories);
+    }
+
+    if project.child("modules").is_some() {
+        warnings.push(
+            "modules are not migrated, run fyg migrate in each one and list them as workspace \
+             members"
+                .to_string(),
+        );
+    }
+
+    Ok(Migration { fyg_toml, warnings })
+}
+
+/// Returns the `group:artifact` key of a `<dependency>`.
+fn dependency_key(dependency: &Element, properties: &HashMap<String, String>) -> String {
+    let text = |name| interpolate(dependency.child_text(name).unwrap_or_default(), properties);
+    format!("{}:{}", text("groupId"), text("artifactId"))
+}
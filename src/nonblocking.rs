@@ -0,0 +1,123 @@
+use std::path::{Path, PathBuf};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    BuildResult, Fyg, ProcessConfig, http, process_scope,
+    build::BuildOptions,
+    cancel::CancelFlag,
+    error::FygError,
+    run::RunError,
+    testing::{TestOptions, TestSummary},
+};
+
+impl Fyg {
+    /// Compiles the project like [`Fyg::build_with_options`], without blocking the async runtime.
+    ///
+    /// Cancelling [`BuildOptions::cancel`] kills the compiler that is running and returns
+    /// [`FygError::Cancelled`]. Builds are never handed to the build daemon, whose compiles
+    /// can't be stopped part way.
+    ///
+    /// The build runs on tokio's blocking thread pool rather than through `tokio::process`, so
+    /// it is the very same build [`Fyg::build_with_options`] runs instead of a second copy of
+    /// every step. While a compiler runs, the thread waiting on it checks for cancellation every
+    /// 50ms and kills it once the token is cancelled.
+    pub async fn build_async(
+        config_file_path: impl AsRef<Path>,
+        options: &BuildOptions,
+    ) -> Result<BuildResult, FygError> {
+        let config_file_path = config_file_path.as_ref().to_path_buf();
+        let options = options.clone();
+        let cancel = options.cancel.clone().unwrap_or_default();
+        cancellable(cancel, move || {
+            Self::build_with_options(&config_file_path, &options)
+        })
+        .await
+    }
+
+    /// Builds and tests the project like [`Fyg::test_with_options`], without blocking the async
+    /// runtime, on tokio's blocking thread pool like [`Fyg::build_async`]. Cancelling
+    /// [`TestOptions::cancel`] kills the compiler or test run that is running.
+    pub async fn test_async(
+        config_file_path: impl AsRef<Path>,
+        options: &TestOptions,
+    ) -> Result<TestSummary, FygError> {
+        let config_file_path = config_file_path.as_ref().to_path_buf();
+        let options = options.clone();
+        let cancel = options.cancel.clone().unwrap_or_default();
+        cancellable(cancel, move || {
+            Self::test_with_options(&config_file_path, &options)
+        })
+        .await
+    }
+
+    /// Runs the built project like [`Fyg::run`], without blocking the async runtime. Cancelling
+    /// `cancel` kills the program, which is started with `tokio::process` as it can run for as
+    /// long as the caller likes.
+    pub async fn run_async(
+        config_file_path: impl AsRef<Path>,
+        jvm_args: &[String],
+        args: &[String],
+        cancel: CancellationToken,
+    ) -> Result<(), FygError> {
+        let config_file_path: PathBuf = config_file_path.as_ref().to_path_buf();
+        let (jvm_args, args) = (jvm_args.to_vec(), args.to_vec());
+        // working out the command can resolve dependencies, which blocks
+        let cmd = cancellable(cancel.clone(), move || {
+            Self::run_command(&config_file_path, &jvm_args, &args)
+        })
+        .await?;
+
+        let mut child = tokio::process::Command::from(cmd)
+            .kill_on_drop(true)
+            .spawn()?;
+        let status = tokio::select! {
+            status = child.wait() => status?,
+            _ = cancel.cancelled() => {
+                child.kill().await?;
+                return Err(FygError::Cancelled);
+            }
+        };
+        if !status.success() {
+            return Err(RunError::Exited { code: status.code() }.into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Runs `f` on the blocking thread pool, killing the subprocesses it starts once `cancel` is
+/// cancelled.
+async fn cancellable<T, F>(cancel: CancellationToken, f: F) -> Result<T, FygError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, FygError> + Send + 'static,
+{
+    if cancel.is_cancelled() {
+        return Err(FygError::Cancelled);
+    }
+
+    let flag = CancelFlag::default();
+    let scoped = flag.clone();
+    // the task runs on another thread, which has to enter the caller's scopes itself
+    let process = ProcessConfig::current();
+    let offline = http::is_offline();
+    let mut task = tokio::task::spawn_blocking(move || {
+        process_scope(process.as_ref(), || {
+            http::offline_scope(offline, || CancelFlag::scope(Some(scoped), f))
+        })
+    });
+    let result = tokio::select! {
+        result = &mut task => result,
+        _ = cancel.cancelled() => {
+            flag.cancel();
+            // the task returns once the subprocess it was waiting on is killed
+            let _ = task.await;
+            return Err(FygError::Cancelled);
+        }
+    };
+    match result {
+        Ok(result) => result,
+        Err(e) => Err(anyhow::anyhow!("the build task panicked: {}", e).into()),
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    ffi::OsStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Set by `--no-color`.
+static NO_COLOR: AtomicBool = AtomicBool::new(false);
+
+/// Whether what fyg prints should be colored.
+///
+/// It isn't with `--no-color`, when `NO_COLOR` is set to anything but an empty string, as
+/// <https://no-color.org> asks, or when `TERM` is `dumb`. Otherwise it is up to `console`, which
+/// leaves it out when the output isn't a terminal.
+pub fn use_color() -> bool {
+    !NO_COLOR.load(Ordering::Relaxed)
+        && color_allowed(
+            std::env::var_os("NO_COLOR").as_deref(),
+            std::env::var_os("TERM").as_deref(),
+        )
+        && console::colors_enabled()
+}
+
+/// Turns color off with `no_color`, as `--no-color` does, and applies [`use_color`] to the
+/// styles of everything fyg prints, which are all made with `console`.
+pub fn init(no_color: bool) {
+    NO_COLOR.store(no_color, Ordering::Relaxed);
+    if !use_color() {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+}
+
+/// Whether the `NO_COLOR` and `TERM` environment variables allow color.
+pub(crate) fn color_allowed(no_color: Option<&OsStr>, term: Option<&OsStr>) -> bool {
+    no_color.is_none_or(OsStr::is_empty) && term.is_none_or(|term| term != "dumb")
+}
+
+/// Formats a size in bytes the way fyg prints it, such as `47 MB` or `1.2 GB`.
+pub fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match size < 10.0 {
+        true => format!("{:.1} {}", size, UNITS[unit]),
+        false => format!("{:.0} {}", size, UNITS[unit]),
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+/// Something [`Fyg::plan_build`](crate::Fyg::plan_build) or
+/// [`Fyg::plan_publish`](crate::Fyg::plan_publish) finds a build or publish would do, as
+/// `--dry-run` lists it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlannedAction {
+    /// Takes the dependencies from `fyg.lock`, which is up to date with `fyg.toml`.
+    UseLock(PathBuf),
+    /// Resolves the dependencies from the repositories and writes them to `fyg.lock`, which is
+    /// missing or out of date. What would be downloaded isn't known until then.
+    ResolveDependencies(PathBuf),
+    /// Downloads something that isn't cached yet, such as a JAR or a Kotlin release.
+    Download { what: String, url: String },
+    /// Runs a compiler for `target`, such as `jvm` or `js-browser`.
+    Compile { target: String, command: String },
+    /// A step of the build whose command depends on what earlier steps produce, such as
+    /// `run ksp`.
+    Step(String),
+    /// Writes an artifact to publish, such as the JAR or the POM.
+    Package(PathBuf),
+    /// Uploads a packaged file to `url`.
+    Upload { file: PathBuf, url: String },
+}
+
+impl std::fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlannedAction::UseLock(path) => {
+                write!(f, "resolve dependencies from {}", path.display())
+            }
+            PlannedAction::ResolveDependencies(path) => {
+                write!(f, "resolve dependencies and write {}", path.display())
+            }
+            PlannedAction::Download { what, url } => write!(f, "download {} from {}", what, url),
+            PlannedAction::Compile { target, command } => {
+                write!(f, "compile {}: {}", target, command)
+            }
+            PlannedAction::Step(name) => write!(f, "{}", name),
+            PlannedAction::Package(path) => write!(f, "package {}", path.display()),
+            PlannedAction::Upload { file, url } => {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                write!(f, "upload {} to {}", name, url)
+            }
+        }
+    }
+}
@@ -0,0 +1,540 @@
+use std::{
+    ffi::OsStr,
+    fmt::Write as _,
+    io::Write as _,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    ProcessConfig, build, cancel,
+    config::{
+        DependenciesConfig, DependencyValue, PomDeveloper, PomLicense, PomMetadata, PomScm,
+        RepositoryConfig, SigningConfig,
+    },
+    doc, hash, http, jar,
+    lock::FygLock,
+    maven::{Coordinate, Repository},
+    resolver,
+    toml::FygToml,
+    toolchain,
+};
+
+/// The opening tag of a POM, declaring the Maven 4.0.0 schema.
+const PROJECT_TAG: &str = concat!(
+    r#"<project xmlns="http://maven.apache.org/POM/4.0.0" "#,
+    r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" "#,
+    r#"xsi:schemaLocation="http://maven.apache.org/POM/4.0.0 "#,
+    r#"https://maven.apache.org/xsd/maven-4.0.0.xsd">"#,
+);
+
+/// Returns the repositories to publish to: the `[repositories]` entry called `name` if one is
+/// given, otherwise every entry listed in `publish.repositories`.
+pub(crate) fn repositories(
+    fyg_toml: &FygToml,
+    name: Option<&str>,
+) -> anyhow::Result<Vec<Repository>> {
+    let names: Vec<&str> = match name {
+        Some(name) => vec![name],
+        None => fyg_toml
+            .publish
+            .iter()
+            .flat_map(|p| &p.repositories)
+            .map(String::as_str)
+            .collect(),
+    };
+    if names.is_empty() {
+        anyhow::bail!(
+            "no repository to publish to, list one in publish.repositories or pass --repository"
+        );
+    }
+
+    names
+        .into_iter()
+        .map(
+            |name| match fyg_toml.repositories.as_ref().and_then(|r| r.get(name)) {
+                Some(RepositoryConfig::Custom(custom)) => Ok(Repository {
+                    url: custom.url.clone(),
+                    credentials: custom.credentials()?,
+                }),
+                Some(RepositoryConfig::LocalMaven { path }) => {
+                    let path = match path {
+                        Some(path) => path.clone(),
+                        None => toolchain::local_maven_repository()?,
+                    };
+                    Ok(Repository::new(format!("file://{}", path.display())))
+                }
+                Some(RepositoryConfig::Enabled(_)) => {
+                    anyhow::bail!("'{}' can't be published to, only downloaded from", name)
+                }
+                None => anyhow::bail!("no repository named '{}' in [repositories]", name),
+            },
+        )
+        .collect()
+}
+
+/// Packages the built project into `build/libs`, returning every file to publish.
+///
+/// These are the JAR, the POM and, unless `[publish]` turns them off, the sources and Javadoc
+/// JARs, each followed by its `.asc` signature when `publish.signing` is set and by its `.md5`
+/// and `.sha1` checksums.
+pub(crate) fn package(
+    fyg_toml: &FygToml,
+    project_dir: &Path,
+    lock: &FygLock,
+    jar_tool: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let project = &fyg_toml.project;
+    let publish = fyg_toml.publish.clone().unwrap_or_default();
+    let build_dir = project_dir.join("build");
+    let libs = build_dir.join("libs");
+    std::fs::create_dir_all(&libs)?;
+    let base = format!("{}-{}", project.name, project.version);
+
+    let mut artifacts = vec![libs.join(format!("{}.jar", base))];
+    let main_class = fyg_toml.build.as_ref().and_then(|b| b.main_class.as_deref());
+    jar::create(jar_tool, &artifacts[0], &[build_dir.join("classes")], main_class)?;
+
+    if publish.sources_jar.unwrap_or(true) {
+        let path = libs.join(format!("{}-sources.jar", base));
+        let include_generated = fyg_toml
+            .build
+            .as_ref()
+            .and_then(|b| b.include_generated_sources)
+            .unwrap_or(false);
+        let roots = build::source_roots(project_dir, include_generated)?;
+        jar::create(jar_tool, &path, &roots, None)?;
+        artifacts.push(path);
+    }
+    if publish.javadoc_jar.unwrap_or(true) {
+        let dokka = fyg_toml
+            .build
+            .as_ref()
+            .and_then(|b| b.dokka.as_ref())
+            .filter(|d| d.enabled);
+        let docs = match dokka {
+            // the build has just generated it
+            Some(dokka) => project_dir.join(
+                dokka
+                    .output_dir
+                    .as_deref()
+                    .unwrap_or(doc::DEFAULT_OUTPUT_DIR),
+            ),
+            // Maven Central only checks that a Javadoc JAR exists, so without Dokka it holds a
+            // README
+            None => {
+                let docs = build_dir.join("javadoc");
+                std::fs::create_dir_all(&docs)?;
+                std::fs::write(
+                    docs.join("README.md"),
+                    format!(
+                        "# {}\n\nAPI documentation for {} is not included.\n",
+                        project.name, base
+                    ),
+                )?;
+                docs
+            }
+        };
+        let path = libs.join(format!("{}-javadoc.jar", base));
+        jar::create(jar_tool, &path, &[docs], None)?;
+        artifacts.push(path);
+    }
+
+    let pom_path = libs.join(format!("{}.pom", base));
+    std::fs::write(&pom_path, pom(fyg_toml, project_dir, lock)?)?;
+    artifacts.push(pom_path);
+
+    let signatures = match &publish.signing {
+        Some(signing) => sign(&artifacts, signing, &build_dir)?,
+        None => Vec::new(),
+    };
+
+    let mut files = Vec::new();
+    for (i, artifact) in artifacts.into_iter().enumerate() {
+        let data = std::fs::read(&artifact)?;
+        let mut checksums = Vec::new();
+        for (extension, checksum) in [
+            ("md5", hash::md5_hex(&data)),
+            ("sha1", hash::sha1_hex(&data)),
+        ] {
+            let path = with_extension(&artifact, extension);
+            std::fs::write(&path, checksum)?;
+            checksums.push(path);
+        }
+        files.push(artifact);
+        files.extend(signatures.get(i).cloned());
+        files.extend(checksums);
+    }
+    Ok(files)
+}
+
+/// The artifacts [`package`] writes to `build/libs`, and every file it returns for them, in
+/// the same order, without writing anything.
+pub(crate) fn planned_files(
+    fyg_toml: &FygToml,
+    project_dir: &Path,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let project = &fyg_toml.project;
+    let publish = fyg_toml.publish.clone().unwrap_or_default();
+    let libs = project_dir.join("build").join("libs");
+    let base = format!("{}-{}", project.name, project.version);
+
+    let mut artifacts = vec![libs.join(format!("{}.jar", base))];
+    if publish.sources_jar.unwrap_or(true) {
+        artifacts.push(libs.join(format!("{}-sources.jar", base)));
+    }
+    if publish.javadoc_jar.unwrap_or(true) {
+        artifacts.push(libs.join(format!("{}-javadoc.jar", base)));
+    }
+    artifacts.push(libs.join(format!("{}.pom", base)));
+
+    let mut files = Vec::new();
+    for artifact in &artifacts {
+        files.push(artifact.clone());
+        if publish.signing.is_some() {
+            files.push(with_extension(artifact, "asc"));
+        }
+        files.push(with_extension(artifact, "md5"));
+        files.push(with_extension(artifact, "sha1"));
+    }
+    (artifacts, files)
+}
+
+/// Signs each of `artifacts` with GPG, returning the `.asc` signature written next to each one.
+///
+/// A `secret-key-file` is imported into a keyring of its own under `build`, which is deleted
+/// afterwards, so the user's keyring is left alone. Neither the key nor its passphrase is ever
+/// passed on the command line or included in an error.
+fn sign(
+    artifacts: &[PathBuf],
+    signing: &SigningConfig,
+    build_dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    let gpg = toolchain::find_executable("gpg")
+        .ok_or_else(|| anyhow::anyhow!("gpg is needed to sign artifacts but isn't on PATH"))?;
+    let passphrase = match &signing.password_env {
+        Some(name) => Some(std::env::var(name).map_err(|_| {
+            anyhow::anyhow!("{} must hold the passphrase of the signing key", name)
+        })?),
+        None => None,
+    };
+
+    let home = match &signing.secret_key_file {
+        Some(key_file) => {
+            let home = build_dir.join("gnupg");
+            if home.exists() {
+                std::fs::remove_dir_all(&home)?;
+            }
+            std::fs::create_dir_all(&home)?;
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                std::fs::set_permissions(&home, std::fs::Permissions::from_mode(0o700))?;
+            }
+            let status = cancel::output(
+                Command::new(&gpg)
+                    .arg("--homedir")
+                    .arg(&home)
+                    .args(["--batch", "--quiet", "--import"])
+                    .arg(key_file),
+            )?
+            .status;
+            if !status.success() {
+                let _ = std::fs::remove_dir_all(&home);
+                anyhow::bail!("failed to import publish.signing.secret-key-file");
+            }
+            Some(home)
+        }
+        None => None,
+    };
+
+    let signatures = artifacts
+        .iter()
+        .map(|artifact| {
+            let signature = with_extension(artifact, "asc");
+            let mut command = Command::new(&gpg);
+            ProcessConfig::configure(&mut command);
+            if let Some(home) = &home {
+                command.arg("--homedir").arg(home);
+            }
+            command.args(["--batch", "--yes", "--detach-sign", "--armor"]);
+            if let Some(key_id) = &signing.key_id {
+                command.arg("--local-user").arg(key_id);
+            }
+            if passphrase.is_some() {
+                command
+                    .args(["--pinentry-mode", "loopback", "--passphrase-fd", "0"])
+                    .stdin(Stdio::piped());
+            } else {
+                command.stdin(Stdio::null());
+            }
+            let mut child = command
+                .arg("--output")
+                .arg(&signature)
+                .arg(artifact)
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()?;
+            if let (Some(passphrase), Some(mut stdin)) = (&passphrase, child.stdin.take()) {
+                writeln!(stdin, "{}", passphrase)?;
+            }
+            // gpg's own messages name the key, so only the artifact is reported
+            if !cancel::wait(&mut child)?.success() {
+                anyhow::bail!(
+                    "failed to sign {}, check publish.signing",
+                    artifact
+                        .file_name()
+                        .unwrap_or(OsStr::new(""))
+                        .to_string_lossy()
+                );
+            }
+            Ok(signature)
+        })
+        .collect();
+
+    if let Some(home) = home {
+        let _ = std::fs::remove_dir_all(home);
+    }
+    signatures
+}
+
+/// Returns `path` with `.extension` appended to its file name.
+fn with_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// Uploads `files` into the project's version directory of `repository`, returning the URL of
+/// each one.
+pub(crate) fn upload(
+    fyg_toml: &FygToml,
+    files: &[PathBuf],
+    repository: &Repository,
+) -> anyhow::Result<Vec<String>> {
+    let mut uploaded = Vec::new();
+    for file in files {
+        let url = upload_url(fyg_toml, file, repository);
+        match url.strip_prefix("file://") {
+            Some(path) => {
+                let path = Path::new(path);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::copy(file, path)?;
+            }
+            None => http::upload(&url, file, repository.credentials.as_ref())?,
+        }
+        uploaded.push(url);
+    }
+    Ok(uploaded)
+}
+
+/// The URL [`upload`] uploads `file` to, in the project's version directory of `repository`.
+pub(crate) fn upload_url(fyg_toml: &FygToml, file: &Path, repository: &Repository) -> String {
+    let project = &fyg_toml.project;
+    let coordinate = Coordinate::new(&project.group, &project.name, &project.version);
+    let pom_path = coordinate.repository_path("pom");
+    let (dir, _) = pom_path.rsplit_once('/').unwrap_or_default();
+    let name = file
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default();
+    format!("{}/{}/{}", repository.url.trim_end_matches('/'), dir, name)
+}
+
+/// Generates a POM describing the project and the dependencies its users need.
+///
+/// Maven dependencies are listed at the versions locked in `fyg.lock`, and local `path`
+/// dependencies at the coordinates of the projects they point to. The Kotlin standard library
+/// is listed too when `build.kotlin-version` pins it.
+pub fn pom(fyg_toml: &FygToml, project_dir: &Path, lock: &FygLock) -> anyhow::Result<String> {
+    let project = &fyg_toml.project;
+    let mut dependencies = Vec::new();
+
+    let kotlin_version = fyg_toml
+        .build
+        .as_ref()
+        .and_then(|b| b.kotlin_version.as_deref());
+    if let Some(version) = kotlin_version {
+        let stdlib = Coordinate::new("org.jetbrains.kotlin", "kotlin-stdlib", version);
+        dependencies.push((stdlib, "compile"));
+    }
+    // the published JAR is for the JVM, so native and Android dependencies are left out
+    let jvm_scopes = DependenciesConfig::visible_from("test");
+    for declared in resolver::declared(fyg_toml) {
+        if !jvm_scopes.contains(&declared.scope) {
+            continue;
+        }
+        let locked = lock
+            .find(&declared.group, &declared.artifact)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}:{} is not in fyg.lock",
+                    declared.group,
+                    declared.artifact
+                )
+            })?;
+        dependencies.push((locked.coordinate(), maven_scope(declared.scope)));
+    }
+    for (scope, entries) in fyg_toml.dependencies.iter().flat_map(|d| d.scopes()) {
+        if !jvm_scopes.contains(&scope) {
+            continue;
+        }
+        let mut paths: Vec<&String> = entries
+            .into_iter()
+            .flatten()
+            .filter_map(|(_, value)| match value {
+                DependencyValue::Complex(dep) => dep.path.as_ref(),
+                DependencyValue::Version(_) => None,
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            let member = FygToml::from_file(project_dir.join(path).join("fyg.toml"))?;
+            let member = &member.project;
+            let coordinate = Coordinate::new(&member.group, &member.name, &member.version);
+            dependencies.push((coordinate, maven_scope(scope)));
+        }
+    }
+
+    let mut pom = String::new();
+    writeln!(pom, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(pom, "{}", PROJECT_TAG)?;
+    element(&mut pom, 1, "modelVersion", "4.0.0");
+    element(&mut pom, 1, "groupId", &project.group);
+    element(&mut pom, 1, "artifactId", &project.name);
+    element(&mut pom, 1, "version", &project.version);
+    element(&mut pom, 1, "packaging", "jar");
+    element(&mut pom, 1, "name", &project.name);
+    if let Some(description) = &project.description {
+        element(&mut pom, 1, "description", description);
+    }
+
+    metadata_elements(&mut pom, &pom_metadata(fyg_toml));
+
+    if !dependencies.is_empty() {
+        pom.push_str("  <dependencies>\n");
+        for (coordinate, scope) in &dependencies {
+            pom.push_str("    <dependency>\n");
+            element(&mut pom, 3, "groupId", &coordinate.group);
+            element(&mut pom, 3, "artifactId", &coordinate.artifact);
+            element(&mut pom, 3, "version", &coordinate.version);
+            if let Some(classifier) = &coordinate.classifier {
+                element(&mut pom, 3, "classifier", classifier);
+            }
+            element(&mut pom, 3, "scope", scope);
+            pom.push_str("    </dependency>\n");
+        }
+        pom.push_str("  </dependencies>\n");
+    }
+    pom.push_str("</project>\n");
+    Ok(pom)
+}
+
+/// The information about the project published in its POM, taken from
+/// `[publish.pom-metadata]` where that sets it and from `[project]` otherwise.
+fn pom_metadata(fyg_toml: &FygToml) -> PomMetadata {
+    let project = &fyg_toml.project;
+    let mut metadata = fyg_toml
+        .publish
+        .as_ref()
+        .and_then(|p| p.pom_metadata.clone())
+        .unwrap_or_default();
+
+    if metadata.url.is_none() {
+        metadata.url = project.homepage.clone();
+    }
+    if metadata.licenses.is_empty()
+        && let Some(license) = &project.license
+    {
+        metadata.licenses.push(PomLicense {
+            name: license.clone(),
+            // an expression such as `Apache-2.0 OR MIT` has no page of its own
+            url: (!license.contains(' '))
+                .then(|| format!("https://spdx.org/licenses/{}.html", license)),
+        });
+    }
+    if metadata.developers.is_empty() {
+        metadata.developers = project
+            .developers
+            .iter()
+            .flatten()
+            .map(|developer| PomDeveloper {
+                id: Some(developer.id.clone()),
+                name: developer.name.clone(),
+                email: developer.email.clone(),
+            })
+            .collect();
+    }
+    if metadata.scm.is_none() {
+        metadata.scm = project.repository.as_ref().map(|repository| PomScm {
+            url: repository.url.clone(),
+            connection: Some(repository.connection.clone()),
+            developer_connection: Some(repository.developer_connection.clone()),
+        });
+    }
+    metadata
+}
+
+fn metadata_elements(pom: &mut String, metadata: &PomMetadata) {
+    if let Some(url) = &metadata.url {
+        element(pom, 1, "url", url);
+    }
+    if !metadata.licenses.is_empty() {
+        pom.push_str("  <licenses>\n");
+        for license in &metadata.licenses {
+            pom.push_str("    <license>\n");
+            element(pom, 3, "name", &license.name);
+            if let Some(url) = &license.url {
+                element(pom, 3, "url", url);
+            }
+            pom.push_str("    </license>\n");
+        }
+        pom.push_str("  </licenses>\n");
+    }
+    if !metadata.developers.is_empty() {
+        pom.push_str("  <developers>\n");
+        for developer in &metadata.developers {
+            pom.push_str("    <developer>\n");
+            if let Some(id) = &developer.id {
+                element(pom, 3, "id", id);
+            }
+            element(pom, 3, "name", &developer.name);
+            if let Some(email) = &developer.email {
+                element(pom, 3, "email", email);
+            }
+            pom.push_str("    </developer>\n");
+        }
+        pom.push_str("  </developers>\n");
+    }
+    if let Some(scm) = &metadata.scm {
+        pom.push_str("  <scm>\n");
+        element(pom, 2, "url", &scm.url);
+        if let Some(connection) = &scm.connection {
+            element(pom, 2, "connection", connection);
+        }
+        if let Some(connection) = &scm.developer_connection {
+            element(pom, 2, "developerConnection", connection);
+        }
+        pom.push_str("  </scm>\n");
+    }
+}
+
+/// Writes `<name>value</name>` on its own line, indented by `depth` levels.
+fn element(pom: &mut String, depth: usize, name: &str, value: &str) {
+    let value = value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    let _ = writeln!(pom, "{}<{}>{}</{}>", "  ".repeat(depth), name, value, name);
+}
+
+/// Returns the Maven scope of a dependency from the given fyg scope.
+fn maven_scope(scope: &str) -> &'static str {
+    match scope {
+        "test" => "test",
+        _ => "compile",
+    }
+}
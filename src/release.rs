@@ -0,0 +1,153 @@
+use std::{path::Path, process::Command, sync::Arc};
+
+use crate::{BuildListener, BumpKind, Version, cancel, error::FygError};
+
+/// Options controlling how [`Fyg::release`](crate::Fyg::release) behaves.
+#[derive(Clone, Debug, Default)]
+pub struct ReleaseOptions {
+    /// Releases without running the tests first.
+    pub skip_tests: bool,
+
+    /// Releases without tagging the release in git, or committing the version changes to it.
+    pub no_tag: bool,
+
+    /// The `[repositories]` entry to publish to, instead of those in `publish.repositories`.
+    pub repository: Option<String>,
+
+    /// Works out the steps of the release without running any of them.
+    pub dry_run: bool,
+
+    /// Told about each step of the release as it runs.
+    pub listener: Option<Arc<dyn BuildListener + Send + Sync>>,
+}
+
+/// A step of a release, in the order [`Fyg::release`](crate::Fyg::release) runs them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReleaseStep {
+    /// Drops `-SNAPSHOT` from `project.version`.
+    SetVersion(Version),
+    /// Commits `fyg.toml` to git with this message, after its version was changed.
+    Commit(String),
+    /// Builds the project in release mode.
+    Build,
+    Test,
+    /// Tags the release in git, such as `v1.2.0`.
+    Tag(String),
+    Publish,
+    /// Starts work on the next release by setting `project.version` to its `-SNAPSHOT`.
+    NextSnapshot(Version),
+}
+
+impl std::fmt::Display for ReleaseStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReleaseStep::SetVersion(version) | ReleaseStep::NextSnapshot(version) => {
+                write!(f, "set version {}", version)
+            }
+            ReleaseStep::Build => write!(f, "build release"),
+            ReleaseStep::Test => write!(f, "test"),
+            ReleaseStep::Commit(message) => write!(f, "commit \"{}\"", message),
+            ReleaseStep::Tag(tag) => write!(f, "tag {}", tag),
+            ReleaseStep::Publish => write!(f, "publish"),
+        }
+    }
+}
+
+/// What [`Fyg::release`](crate::Fyg::release) did, or would do with
+/// [`ReleaseOptions::dry_run`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Release {
+    /// The version that was released.
+    pub version: Version,
+    /// The `-SNAPSHOT` version the project is at afterwards.
+    pub next: Version,
+    pub steps: Vec<ReleaseStep>,
+}
+
+impl Release {
+    /// Plans the release of a project at `current`.
+    pub(crate) fn plan(current: Version, options: &ReleaseOptions) -> Release {
+        let version = Version {
+            snapshot: false,
+            ..current
+        };
+        let next = version.bump(BumpKind::Snapshot);
+
+        let tag = format!("v{}", version);
+        let mut steps = Vec::new();
+        if current.is_snapshot() {
+            steps.push(ReleaseStep::SetVersion(version));
+        }
+        steps.push(ReleaseStep::Build);
+        if !options.skip_tests {
+            steps.push(ReleaseStep::Test);
+        }
+        if !options.no_tag {
+            // the tag has to point at the fyg.toml with the release version
+            if current.is_snapshot() {
+                steps.push(ReleaseStep::Commit(format!("Release {}", tag)));
+            }
+            steps.push(ReleaseStep::Tag(tag));
+        }
+        steps.push(ReleaseStep::Publish);
+        steps.push(ReleaseStep::NextSnapshot(next));
+        if !options.no_tag {
+            steps.push(ReleaseStep::Commit(format!("Start {}", next)));
+        }
+
+        Release {
+            version,
+            next,
+            steps,
+        }
+    }
+}
+
+/// A release that stopped because one of its steps failed. The steps before it were done, so
+/// `project.version` may be left at the release version.
+#[derive(Debug)]
+pub struct ReleaseError {
+    pub step: ReleaseStep,
+    pub error: FygError,
+}
+
+impl std::fmt::Display for ReleaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the release stopped at '{}': {}", self.step, self.error)
+    }
+}
+
+impl std::error::Error for ReleaseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Creates the annotated git tag `tag` in the repository `project_dir` is in.
+pub(crate) fn tag(project_dir: &Path, tag: &str) -> Result<(), FygError> {
+    git(project_dir, &["tag", tag, "-m", &format!("Release {}", tag)])
+}
+
+/// Commits `config_file_path`, and nothing else that is staged, to the repository it is in.
+pub(crate) fn commit(config_file_path: &Path, message: &str) -> Result<(), FygError> {
+    let project_dir = config_file_path.parent().unwrap_or(Path::new("."));
+    let file = config_file_path.file_name().unwrap_or_default().to_string_lossy();
+    git(project_dir, &["commit", "--quiet", "-m", message, "--", &file])
+}
+
+fn git(project_dir: &Path, args: &[&str]) -> Result<(), FygError> {
+    let output = cancel::output(Command::new("git").args(args).current_dir(project_dir))
+        .map_err(|_| FygError::ToolNotFound {
+            tool: "git".to_string(),
+            hint: "which is needed to tag the release, or pass --no-tag".to_string(),
+        })?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(())
+}
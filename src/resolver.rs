@@ -0,0 +1,866 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    Version,
+    config::{ConflictStrategy, DependenciesConfig, DependencyValue, ExcludeRule, PomLicense},
+    deps, lock,
+    maven::{self, Coordinate, Repository, VersionRange},
+    toml::FygToml,
+    xml::Element,
+};
+
+/// A format `fyg graph` can write the dependency graph in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// A Graphviz digraph, which `dot -Tpng` can render.
+    #[default]
+    Dot,
+    /// A JSON object listing the dependencies of each artifact.
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            other => anyhow::bail!("unknown graph format '{}', expected dot or json", other),
+        }
+    }
+}
+
+impl std::fmt::Display for GraphFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphFormat::Dot => write!(f, "dot"),
+            GraphFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// How many parent POMs or imported BOMs deep resolution will follow before giving up.
+const MAX_POM_DEPTH: usize = 16;
+
+/// A Maven dependency declared in `fyg.toml`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Declared {
+    pub scope: &'static str,
+    pub group: String,
+    pub artifact: String,
+    /// The version as written, which may be `LATEST`, or empty for a version a BOM gives.
+    pub version: String,
+    /// The classifier of the file to use, such as `natives-linux`.
+    pub classifier: Option<String>,
+    /// Transitive dependencies of this one to leave out.
+    pub exclude: Vec<ExcludeRule>,
+}
+
+/// Collects the Maven dependencies declared across every scope of `fyg.toml`.
+///
+/// Entries without a Maven coordinate, such as local `path` dependencies, are skipped.
+/// A dependency declared in more than one scope is kept in the first one.
+pub fn declared(fyg_toml: &FygToml) -> Vec<Declared> {
+    let Some(deps) = &fyg_toml.dependencies else {
+        return Vec::new();
+    };
+
+    let mut declared: Vec<Declared> = Vec::new();
+    for (scope, entries) in deps.scopes() {
+        let mut entries: Vec<_> = entries.into_iter().flatten().collect();
+        entries.sort_by_key(|(key, _)| key.as_str());
+
+        for (key, value) in entries {
+            let coordinate = match (value.coordinate(key), value.unversioned(key)) {
+                (Some(Ok(coordinate)), _) => coordinate,
+                (_, Some((group, artifact))) => Coordinate::new(&group, &artifact, "")
+                    .with_classifier(match value {
+                        DependencyValue::Complex(dep) => dep.classifier.as_deref(),
+                        DependencyValue::Version(_) => None,
+                    }),
+                _ => continue,
+            };
+            if declared
+                .iter()
+                .any(|d| d.group == coordinate.group && d.artifact == coordinate.artifact)
+            {
+                continue;
+            }
+
+            let exclude = match value {
+                DependencyValue::Complex(dep) => dep.exclude.clone().unwrap_or_default(),
+                DependencyValue::Version(_) => Vec::new(),
+            };
+            declared.push(Declared {
+                scope,
+                group: coordinate.group,
+                artifact: coordinate.artifact,
+                version: coordinate.version,
+                classifier: coordinate.classifier,
+                exclude,
+            });
+        }
+    }
+    declared
+}
+
+/// A resolved artifact in a [`DependencyGraph`].
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub coordinate: Coordinate,
+    /// The scope of the direct dependency this artifact was first reached from.
+    pub scope: &'static str,
+    /// Whether the artifact is declared in `fyg.toml` rather than pulled in by another artifact.
+    pub direct: bool,
+    /// The POM packaging, such as `jar`, or `pom` for artifacts with no JAR.
+    pub packaging: String,
+    /// The artifact's license, as declared in its POM.
+    pub license: Option<PomLicense>,
+    /// The URL of the repository the artifact's POM was downloaded from.
+    pub repository: String,
+    /// Indices of the nodes this artifact depends on.
+    pub dependencies: Vec<usize>,
+}
+
+/// The full graph of a project's direct and transitive Maven dependencies.
+///
+/// Each `group:artifact` appears once. When it is reachable at several versions, the one closest
+/// to the project wins, as in Maven.
+#[derive(Clone, Debug, Default)]
+pub struct DependencyGraph {
+    pub nodes: Vec<Node>,
+}
+
+impl DependencyGraph {
+    /// Resolves every dependency declared in `fyg.toml`, following the POMs of each artifact to
+    /// find its transitive dependencies.
+    pub fn resolve(fyg_toml: &FygToml) -> anyhow::Result<Self> {
+        Resolver::for_project(fyg_toml)?.graph(&declared(fyg_toml))
+    }
+
+    fn add(&mut self, coordinate: Coordinate, scope: &'static str, direct: bool) -> usize {
+        self.nodes.push(Node {
+            coordinate,
+            scope,
+            direct,
+            packaging: "jar".to_string(),
+            license: None,
+            repository: String::new(),
+            dependencies: Vec::new(),
+        });
+        self.nodes.len() - 1
+    }
+
+    fn position(&self, group: &str, artifact: &str) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|n| n.coordinate.group == group && n.coordinate.artifact == artifact)
+    }
+
+    /// Drops the nodes that are no longer reachable from any direct dependency.
+    fn prune(self) -> Self {
+        let keep = self.reachable_indices(None);
+        let remap: HashMap<usize, usize> = keep
+            .iter()
+            .enumerate()
+            .map(|(new, &old)| (old, new))
+            .collect();
+
+        let nodes = keep
+            .into_iter()
+            .map(|i| {
+                let mut node = self.nodes[i].clone();
+                node.dependencies = node
+                    .dependencies
+                    .iter()
+                    .filter_map(|d| remap.get(d).copied())
+                    .collect();
+                node
+            })
+            .collect();
+        Self { nodes }
+    }
+
+    /// Returns the direct dependencies, optionally limited to one scope.
+    pub fn roots(&self, scope: Option<&str>) -> impl Iterator<Item = (usize, &Node)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(move |(_, n)| n.direct && scope.is_none_or(|s| s == n.scope))
+    }
+
+    /// Returns every node reachable from the direct dependencies in the given scope, or every
+    /// node if no scope is given.
+    pub fn reachable(&self, scope: Option<&str>) -> Vec<&Node> {
+        self.reachable_indices(scope)
+            .into_iter()
+            .map(|i| &self.nodes[i])
+            .collect()
+    }
+
+    fn reachable_indices(&self, scope: Option<&str>) -> Vec<usize> {
+        let mut seen = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = self.roots(scope).map(|(i, _)| i).collect();
+        while let Some(i) = stack.pop() {
+            if !seen[i] {
+                seen[i] = true;
+                stack.extend(&self.nodes[i].dependencies);
+            }
+        }
+
+        (0..self.nodes.len()).filter(|&i| seen[i]).collect()
+    }
+
+    /// Renders the graph as an indented tree, one direct dependency at a time.
+    ///
+    /// Artifacts whose dependencies were already listed higher up are marked with `(*)`.
+    pub fn tree(&self, scope: Option<&str>) -> String {
+        let mut out = String::new();
+        let mut expanded = vec![false; self.nodes.len()];
+
+        for (index, node) in self.roots(scope) {
+            out.push_str(&format!("{} ({})\n", node.coordinate, node.scope));
+            self.write_children(index, "", &mut expanded, &mut out);
+        }
+        out
+    }
+
+    fn write_children(
+        &self,
+        index: usize,
+        prefix: &str,
+        expanded: &mut [bool],
+        out: &mut String,
+    ) {
+        if std::mem::replace(&mut expanded[index], true) {
+            return;
+        }
+
+        let children = &self.nodes[index].dependencies;
+        for (i, &child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+
+            let node = &self.nodes[child];
+            let repeated = expanded[child] && !node.dependencies.is_empty();
+            out.push_str(&format!(
+                "{}{}{}{}\n",
+                prefix,
+                branch,
+                node.coordinate,
+                if repeated { " (*)" } else { "" }
+            ));
+            self.write_children(child, &format!("{}{}", prefix, indent), expanded, out);
+        }
+    }
+
+    /// Renders the graph as a Graphviz digraph, with an edge from each artifact to each of its
+    /// dependencies.
+    ///
+    /// The project is drawn as a filled box labelled `project`, pointing at the direct
+    /// dependencies, which have a bold border.
+    pub fn to_dot(&self, project: &str) -> String {
+        let quote = |text: &str| {
+            format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+
+        let mut out = String::from("digraph dependencies {\n    rankdir=LR;\n");
+        out.push_str("    node [shape=box, style=rounded, fontname=\"Helvetica\"];\n");
+        out.push_str(&format!("    {} [style=\"rounded,filled\"];\n", quote(project)));
+        for node in &self.nodes {
+            let style = match node.direct {
+                true => " [style=\"rounded,bold\", penwidth=2]",
+                false => "",
+            };
+            out.push_str(&format!("    {}{};\n", quote(&node.coordinate.to_string()), style));
+        }
+        for (_, node) in self.roots(None) {
+            out.push_str(&format!(
+                "    {} -> {};\n",
+                quote(project),
+                quote(&node.coordinate.to_string())
+            ));
+        }
+        for node in &self.nodes {
+            for &child in &node.dependencies {
+                out.push_str(&format!(
+                    "    {} -> {};\n",
+                    quote(&node.coordinate.to_string()),
+                    quote(&self.nodes[child].coordinate.to_string())
+                ));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Describes the graph as JSON: the direct dependencies under `roots`, and the dependencies
+    /// of every artifact under `dependencies`, keyed by coordinate.
+    pub fn to_json(&self) -> serde_json::Value {
+        let roots: Vec<String> = self.roots(None).map(|(_, n)| n.coordinate.to_string()).collect();
+        let dependencies: serde_json::Map<String, serde_json::Value> = self
+            .nodes
+            .iter()
+            .map(|node| {
+                let children: Vec<String> = node
+                    .dependencies
+                    .iter()
+                    .map(|&child| self.nodes[child].coordinate.to_string())
+                    .collect();
+                (node.coordinate.to_string(), children.into())
+            })
+            .collect();
+        serde_json::json!({ "roots": roots, "dependencies": dependencies })
+    }
+
+    /// Finds the shortest chain of dependencies from a direct dependency to the artifact
+    /// matching `name`, which may be an artifact ID or a `group:artifact[:version]` coordinate.
+    pub fn why(&self, name: &str) -> Option<Vec<&Coordinate>> {
+        let mut previous: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut seen = vec![false; self.nodes.len()];
+        let mut queue: VecDeque<usize> = self.roots(None).map(|(i, _)| i).collect();
+        for &root in &queue {
+            seen[root] = true;
+        }
+
+        while let Some(index) = queue.pop_front() {
+            let coordinate = &self.nodes[index].coordinate;
+            let key = format!("{}:{}", coordinate.group, coordinate.artifact);
+            if crate::deps::matches(&key, name) {
+                let mut path = vec![coordinate];
+                let mut current = index;
+                while let Some(prev) = previous[current] {
+                    path.push(&self.nodes[prev].coordinate);
+                    current = prev;
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            for &child in &self.nodes[index].dependencies {
+                if !std::mem::replace(&mut seen[child], true) {
+                    previous[child] = Some(index);
+                    queue.push_back(child);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// An artifact resolved by a [`Resolver`], with where to download its JAR from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResolvedArtifact {
+    pub group: String,
+    pub artifact: String,
+    pub version: String,
+    /// The SHA-1 checksum the repository publishes for the JAR, if it has one.
+    pub sha1: Option<String>,
+    pub url: String,
+}
+
+/// Resolves Maven dependencies and their transitive dependencies against a list of repositories.
+#[derive(Clone, Debug)]
+pub struct Resolver {
+    repositories: Vec<Repository>,
+    strategy: ConflictStrategy,
+    boms: Vec<Coordinate>,
+}
+
+impl Resolver {
+    pub fn new(repositories: Vec<Repository>) -> Self {
+        Self {
+            repositories,
+            strategy: ConflictStrategy::default(),
+            boms: Vec::new(),
+        }
+    }
+
+    /// Takes the versions of dependencies declared without one from these BOMs, with later
+    /// BOMs overriding earlier ones.
+    pub fn with_boms(mut self, boms: Vec<Coordinate>) -> Self {
+        self.boms = boms;
+        self
+    }
+
+    /// Sets how conflicting versions of the same artifact are settled.
+    pub fn with_strategy(mut self, strategy: ConflictStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Searches the local Maven repository at `path` before any remote repository, copying
+    /// remote downloads into it.
+    pub fn with_local_repository(mut self, path: &Path) -> Self {
+        let local = Repository::new(format!("file://{}", path.display()));
+        self.repositories.insert(0, local);
+        self
+    }
+
+    /// A resolver using the project's repositories, `[build] conflict-strategy` and
+    /// `dependencies.bom`.
+    pub fn for_project(fyg_toml: &FygToml) -> anyhow::Result<Self> {
+        let strategy = fyg_toml
+            .build
+            .as_ref()
+            .and_then(|b| b.conflict_strategy)
+            .unwrap_or_default();
+        let boms = fyg_toml
+            .dependencies
+            .iter()
+            .flat_map(|d| d.bom.iter().flatten())
+            .map(|bom| match deps::parse_coordinate(bom)? {
+                (group, artifact, Some(version)) => {
+                    Ok(Coordinate::new(&group, &artifact, &version))
+                }
+                _ => anyhow::bail!("the BOM '{}' has no version", bom),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self::new(fyg_toml.maven_repositories()?)
+            .with_strategy(strategy)
+            .with_boms(boms))
+    }
+
+    /// Resolves `(group, artifact, version)` requirements into a flat list of every artifact
+    /// needed, including transitive ones.
+    ///
+    /// Versions may be exact, a range such as `[1.0,2.0)`, or `LATEST`.
+    pub fn resolve(
+        &self,
+        requirements: &[(String, String, String)],
+    ) -> anyhow::Result<Vec<ResolvedArtifact>> {
+        let declared: Vec<Declared> = requirements
+            .iter()
+            .map(|(group, artifact, version)| Declared {
+                scope: "common",
+                group: group.clone(),
+                artifact: artifact.clone(),
+                version: version.clone(),
+                classifier: None,
+                exclude: Vec::new(),
+            })
+            .collect();
+        let graph = self.graph(&declared)?;
+
+        Ok(graph
+            .nodes
+            .iter()
+            .filter(|n| n.packaging != "pom")
+            .map(|node| {
+                let coordinate = &node.coordinate;
+                let sha1 = maven::fetch(&self.repositories, coordinate, "jar.sha1")
+                    .ok()
+                    .and_then(|(path, _)| std::fs::read_to_string(path).ok())
+                    .and_then(|text| text.split_whitespace().next().map(str::to_string));
+                let repository = match node.repository.as_str() {
+                    "" => maven::MAVEN_CENTRAL,
+                    repository => repository,
+                };
+
+                ResolvedArtifact {
+                    group: coordinate.group.clone(),
+                    artifact: coordinate.artifact.clone(),
+                    version: coordinate.version.clone(),
+                    sha1,
+                    url: format!(
+                        "{}/{}",
+                        repository.trim_end_matches('/'),
+                        coordinate.repository_path("jar")
+                    ),
+                }
+            })
+            .collect())
+    }
+
+    /// Resolves the declared dependencies into a [`DependencyGraph`].
+    pub fn graph(&self, declared: &[Declared]) -> anyhow::Result<DependencyGraph> {
+        let mut poms = PomCache::new(&self.repositories);
+        let mut graph = DependencyGraph::default();
+        let mut queue = VecDeque::new();
+        // the exclusions of the direct dependency each node was first reached from, by index
+        let mut exclusions: Vec<Vec<ExcludeRule>> = Vec::new();
+
+        let mut managed: HashMap<(String, String), String> = HashMap::new();
+        for bom in &self.boms {
+            let mut versions = HashMap::new();
+            // the first entry for an artifact wins within a BOM, as its parents' come later
+            for dep in poms.load(bom, 0)?.managed {
+                if let Some(version) = dep.version {
+                    versions.entry((dep.group, dep.artifact)).or_insert(version);
+                }
+            }
+            managed.extend(versions);
+        }
+
+        for dep in declared {
+            let requested = match dep.version.as_str() {
+                "" => managed
+                    .get(&(dep.group.clone(), dep.artifact.clone()))
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "{}:{} has no version, and no BOM in dependencies.bom manages it",
+                            dep.group,
+                            dep.artifact
+                        )
+                    })?,
+                version => version,
+            };
+            let version = self.version(&dep.group, &dep.artifact, requested)?;
+            let coordinate = Coordinate::new(&dep.group, &dep.artifact, &version)
+                .with_classifier(dep.classifier.as_deref());
+            queue.push_back(graph.add(coordinate, dep.scope, true));
+            exclusions.push(dep.exclude.clone());
+        }
+
+        while let Some(index) = queue.pop_front() {
+            let pom = poms.load(&graph.nodes[index].coordinate, 0)?;
+            let node = &mut graph.nodes[index];
+            node.packaging = pom.packaging.clone();
+            node.license = pom.licenses.first().cloned();
+            node.repository = pom.repository.clone();
+            // a node is revisited after its version changes, so start its edges over
+            node.dependencies.clear();
+
+            let scope = node.scope;
+            for dep in &pom.dependencies {
+                let runtime = matches!(dep.scope.as_deref(), None | Some("compile" | "runtime"));
+                if dep.optional || !runtime {
+                    continue;
+                }
+                // an excluded artifact that is also declared directly stays, just not as a child
+                if exclusions[index]
+                    .iter()
+                    .any(|rule| rule.matches(&dep.group, &dep.artifact))
+                {
+                    continue;
+                }
+                let Some(version) = &dep.version else {
+                    continue;
+                };
+                let version = self.version(&dep.group, &dep.artifact, version)?;
+
+                let child = match graph.position(&dep.group, &dep.artifact) {
+                    Some(existing) => {
+                        if self.settle(&mut graph.nodes[existing], &version)? {
+                            queue.push_back(existing);
+                        }
+                        existing
+                    }
+                    None => {
+                        let coordinate = Coordinate::new(&dep.group, &dep.artifact, &version);
+                        let child = graph.add(coordinate, scope, false);
+                        exclusions.push(exclusions[index].clone());
+                        queue.push_back(child);
+                        child
+                    }
+                };
+
+                if child != index && !graph.nodes[index].dependencies.contains(&child) {
+                    graph.nodes[index].dependencies.push(child);
+                }
+            }
+        }
+
+        // artifacts only needed by versions that lost a conflict are dropped
+        let mut graph = graph.prune();
+
+        // a transitive artifact shared between scopes belongs to the broadest one that reaches it
+        let mut assigned: Vec<bool> = graph.nodes.iter().map(|n| n.direct).collect();
+        for scope in DependenciesConfig::SCOPES {
+            for index in graph.reachable_indices(Some(scope)) {
+                if !std::mem::replace(&mut assigned[index], true) {
+                    graph.nodes[index].scope = scope;
+                }
+            }
+        }
+
+        Ok(graph)
+    }
+
+    /// Turns a requested version into a concrete one, looking up `LATEST` and ranges in the
+    /// repository metadata.
+    fn version(&self, group: &str, artifact: &str, requested: &str) -> anyhow::Result<String> {
+        if lock::is_dynamic(requested) {
+            return maven::latest_version(&self.repositories, group, artifact);
+        }
+        if !maven::is_range(requested) {
+            return Ok(requested.trim().to_string());
+        }
+
+        let range: VersionRange = requested.parse()?;
+        let metadata = maven::metadata(&self.repositories, group, artifact)?;
+        range
+            .newest(&metadata.versions)
+            .map(str::to_string)
+            .ok_or_else(|| {
+                anyhow::anyhow!("no version of {}:{} matches {}", group, artifact, requested)
+            })
+    }
+
+    /// Applies the conflict strategy when `node` is asked for again at `version`, returning
+    /// whether the node's version changed.
+    fn settle(&self, node: &mut Node, version: &str) -> anyhow::Result<bool> {
+        let current = &node.coordinate.version;
+        if node.direct || current == version {
+            return Ok(false);
+        }
+
+        let replace = match self.strategy {
+            ConflictStrategy::Newest => compare_versions(version, current).is_gt(),
+            ConflictStrategy::Oldest => compare_versions(version, current).is_lt(),
+            ConflictStrategy::Fail => anyhow::bail!(
+                "conflicting versions of {}:{}: {} and {} (declare the one to use in fyg.toml)",
+                node.coordinate.group,
+                node.coordinate.artifact,
+                current,
+                version
+            ),
+        };
+        if replace {
+            node.coordinate.version = version.to_string();
+        }
+        Ok(replace)
+    }
+}
+
+/// Compares two versions of an artifact, as semantic versions where both are, or otherwise as
+/// Maven orders them.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (a.parse::<Version>(), b.parse::<Version>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => maven::compare_versions(a, b),
+    }
+}
+
+/// A `<dependency>` entry from a POM.
+#[derive(Clone, Debug)]
+struct PomDependency {
+    group: String,
+    artifact: String,
+    version: Option<String>,
+    scope: Option<String>,
+    kind: Option<String>,
+    optional: bool,
+}
+
+/// The parts of an effective POM, with its parents merged in, needed for resolution.
+#[derive(Clone, Debug, Default)]
+struct Pom {
+    packaging: String,
+    repository: String,
+    properties: HashMap<String, String>,
+    dependencies: Vec<PomDependency>,
+    managed: Vec<PomDependency>,
+    licenses: Vec<PomLicense>,
+}
+
+/// Downloads and merges POMs, remembering each one so shared parents are only read once.
+struct PomCache<'a> {
+    repositories: &'a [Repository],
+    poms: HashMap<Coordinate, Pom>,
+}
+
+impl<'a> PomCache<'a> {
+    fn new(repositories: &'a [Repository]) -> Self {
+        Self {
+            repositories,
+            poms: HashMap::new(),
+        }
+    }
+
+    fn load(&mut self, coordinate: &Coordinate, depth: usize) -> anyhow::Result<Pom> {
+        if let Some(pom) = self.poms.get(coordinate) {
+            return Ok(pom.clone());
+        }
+        if depth > MAX_POM_DEPTH {
+            anyhow::bail!("too many nested parent POMs while resolving {}", coordinate);
+        }
+
+        let (path, repository) = maven::fetch(self.repositories, coordinate, "pom")?;
+        let project = Element::parse(&std::fs::read_to_string(path)?)
+            .map_err(|e| anyhow::anyhow!("failed to parse the POM of {}: {}", coordinate, e))?;
+
+        let parent = match project.child("parent") {
+            Some(parent) => {
+                let parent = Coordinate::new(
+                    parent.child_text("groupId").unwrap_or_default(),
+                    parent.child_text("artifactId").unwrap_or_default(),
+                    parent.child_text("version").unwrap_or_default(),
+                );
+                Some((self.load(&parent, depth + 1)?, parent))
+            }
+            None => None,
+        };
+
+        let mut pom = Pom {
+            packaging: project.child_text("packaging").unwrap_or("jar").to_string(),
+            repository,
+            ..Default::default()
+        };
+
+        if let Some((parent_pom, parent)) = &parent {
+            pom.properties = parent_pom.properties.clone();
+            pom.properties
+                .insert("project.parent.version".to_string(), parent.version.clone());
+            pom.properties
+                .insert("project.parent.groupId".to_string(), parent.group.clone());
+        }
+        if let Some(properties) = project.child("properties") {
+            for property in &properties.children {
+                pom.properties
+                    .insert(property.name.clone(), property.text.clone());
+            }
+        }
+        for (key, value) in [
+            ("project.groupId", &coordinate.group),
+            ("project.artifactId", &coordinate.artifact),
+            ("project.version", &coordinate.version),
+            ("pom.version", &coordinate.version),
+            ("version", &coordinate.version),
+        ] {
+            pom.properties.insert(key.to_string(), value.clone());
+        }
+
+        let read = |element: Option<&Element>| -> Vec<PomDependency> {
+            element
+                .into_iter()
+                .flat_map(|deps| deps.children("dependency"))
+                .map(|dep| {
+                    let text = |name| dep.child_text(name).map(|t| interpolate(t, &pom.properties));
+                    PomDependency {
+                        group: text("groupId").unwrap_or_default(),
+                        artifact: text("artifactId").unwrap_or_default(),
+                        version: text("version"),
+                        scope: text("scope"),
+                        kind: text("type"),
+                        optional: text("optional").as_deref() == Some("true"),
+                    }
+                })
+                .collect()
+        };
+
+        let mut managed = read(project.find(&["dependencyManagement", "dependencies"]));
+        let mut dependencies = read(project.child("dependencies"));
+
+        // imported BOMs contribute their own managed versions
+        let imports: Vec<PomDependency> = managed
+            .iter()
+            .filter(|d| d.scope.as_deref() == Some("import") && d.kind.as_deref() == Some("pom"))
+            .cloned()
+            .collect();
+        for import in imports {
+            let Some(version) = &import.version else {
+                continue;
+            };
+            let bom = Coordinate::new(&import.group, &import.artifact, version);
+            managed.extend(self.load(&bom, depth + 1)?.managed);
+        }
+
+        if let Some((parent_pom, _)) = parent {
+            managed.extend(parent_pom.managed);
+            dependencies.extend(parent_pom.dependencies);
+            if pom.licenses.is_empty() {
+                pom.licenses = parent_pom.licenses;
+            }
+        }
+
+        for dep in &mut dependencies {
+            let managed = managed
+                .iter()
+                .find(|m| m.group == dep.group && m.artifact == dep.artifact);
+            if let Some(managed) = managed {
+                dep.version = dep.version.take().or_else(|| managed.version.clone());
+                dep.scope = dep.scope.take().or_else(|| managed.scope.clone());
+            }
+        }
+
+        // a license without a name can't be told apart from a missing one
+        let licenses: Vec<PomLicense> = project
+            .find(&["licenses"])
+            .into_iter()
+            .flat_map(|l| l.children("license"))
+            .filter_map(|l| {
+                Some(PomLicense {
+                    name: l.child_text("name").filter(|n| !n.is_empty())?.to_string(),
+                    url: l.child_text("url").map(str::to_string),
+                })
+            })
+            .collect();
+        if !licenses.is_empty() {
+            pom.licenses = licenses;
+        }
+
+        pom.managed = managed;
+        pom.dependencies = dependencies;
+        self.poms.insert(coordinate.clone(), pom.clone());
+        Ok(pom)
+    }
+}
+
+/// Resolves annotation processors given as `group:artifact:version` coordinates, along with
+/// their dependencies, against the project's repositories and returns their JARs.
+///
+/// Processors only run inside the compiler, so they are kept out of `fyg.lock` and the
+/// project's classpath.
+pub(crate) fn processor_jars(
+    fyg_toml: &FygToml,
+    processors: &[String],
+) -> anyhow::Result<Vec<PathBuf>> {
+    let mut requirements = Vec::new();
+    for processor in processors {
+        let (group, artifact, version) = deps::parse_coordinate(processor)?;
+        let version = version.ok_or_else(|| {
+            anyhow::anyhow!(
+                "the processor '{}' needs a version, such as {}:1.0.0",
+                processor,
+                processor
+            )
+        })?;
+        requirements.push((group, artifact, version));
+    }
+
+    let repositories = fyg_toml.maven_repositories()?;
+    Resolver::for_project(fyg_toml)?
+        .resolve(&requirements)?
+        .iter()
+        .map(|artifact| {
+            let coordinate =
+                Coordinate::new(&artifact.group, &artifact.artifact, &artifact.version);
+            let (jar, _) = maven::fetch(&repositories, &coordinate, "jar")?;
+            Ok(jar)
+        })
+        .collect()
+}
+
+/// Replaces `${property}` references in `value` with their values.
+pub(crate) fn interpolate(value: &str, properties: &HashMap<String, String>) -> String {
+    let mut value = value.to_string();
+
+    // properties can refer to other properties, so substitute until nothing changes
+    for _ in 0..MAX_POM_DEPTH {
+        let Some(start) = value.find("${") else {
+            break;
+        };
+        let Some(len) = value[start..].find('}') else {
+            break;
+        };
+        let name = &value[start + 2..start + len];
+        let Some(replacement) = properties.get(name) else {
+            break;
+        };
+        value.replace_range(start..start + len + 1, replacement);
+    }
+
+    value
+}
@@ -0,0 +1,219 @@
+use std::path::{Path, PathBuf};
+
+/// An error raised while running a built fyg project.
+#[derive(Debug)]
+pub enum RunError {
+    /// No `java` executable could be found.
+    JavaNotFound,
+
+    /// The project has not been built yet.
+    NotBuilt(PathBuf),
+
+    /// No main class was configured and none could be found in the compiled classes.
+    MainClassNotFound,
+
+    /// No main class was configured and several classes have a `main` function.
+    AmbiguousMainClass(Vec<String>),
+
+    /// The program exited unsuccessfully.
+    Exited {
+        /// The exit code of the program, or `None` if it was killed by a signal.
+        code: Option<i32>,
+    },
+}
+
+impl std::fmt::Display for RunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RunError::JavaNotFound => write!(
+                f,
+                "could not find java, make sure it is on your PATH or set JAVA_HOME"
+            ),
+            RunError::NotBuilt(dir) => write!(
+                f,
+                "no build output found at {}, run 'fyg build' first",
+                dir.display()
+            ),
+            RunError::MainClassNotFound => write!(
+                f,
+                "could not find a main function, set build.main-class in fyg.toml"
+            ),
+            RunError::AmbiguousMainClass(candidates) => write!(
+                f,
+                "found a main function in {}, set build.main-class in fyg.toml to pick one",
+                candidates.join(", ")
+            ),
+            RunError::Exited { code: Some(code) } => {
+                write!(f, "process exited with code {}", code)
+            }
+            RunError::Exited { code: None } => write!(f, "process was terminated by a signal"),
+        }
+    }
+}
+
+impl std::error::Error for RunError {}
+
+/// Finds every class compiled into `classes_dir` that declares a `public static void
+/// main(String[])`.
+///
+/// Kotlin compiles top-level functions in `Foo.kt` into a class named `FooKt`, and gives a
+/// `main()` without parameters a bridge taking `String[]`, so any top-level `main` function is
+/// found. The class files are read directly, without loading them into a JVM.
+pub(crate) fn find_main_classes(classes_dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut class_files = Vec::new();
+    collect_class_files(classes_dir, &mut class_files)?;
+    class_files.sort();
+
+    let mut main_classes = Vec::new();
+    for class_file in class_files {
+        let bytes = std::fs::read(&class_file)?;
+        if let Some(name) = ClassReader::new(&bytes).main_class() {
+            main_classes.push(name);
+        }
+    }
+    Ok(main_classes)
+}
+
+fn collect_class_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_class_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "class") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+const ACC_PUBLIC: u16 = 0x0001;
+const ACC_STATIC: u16 = 0x0008;
+
+/// Just enough of a class file parser to find a `main` method, following chapter 4 of the JVM
+/// specification.
+struct ClassReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ClassReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// The binary name of the class, such as `com.example.MainKt`, if it declares a `main`
+    /// method, or `None` if it doesn't or isn't a valid class file.
+    fn main_class(mut self) -> Option<String> {
+        if self.u4()? != 0xCAFE_BABE {
+            return None;
+        }
+        self.skip(4)?; // minor and major version
+
+        // entries are indexed from 1, and longs and doubles take up two of them
+        let count = self.u2()? as usize;
+        let mut utf8 = vec![None; count];
+        let mut class_names = vec![None; count];
+        let mut index = 1;
+        while index < count {
+            match self.u1()? {
+                1 => {
+                    let len = self.u2()? as usize;
+                    let bytes = self.take(len)?;
+                    utf8[index] = Some(String::from_utf8_lossy(bytes).into_owned());
+                }
+                7 => class_names[index] = Some(self.u2()? as usize),
+                8 | 16 | 19 | 20 => self.skip(2)?,
+                15 => self.skip(3)?,
+                3 | 4 | 9 | 10 | 11 | 12 | 17 | 18 => self.skip(4)?,
+                5 | 6 => {
+                    self.skip(8)?;
+                    index += 1;
+                }
+                _ => return None,
+            }
+            index += 1;
+        }
+        let utf8_at = |index: usize| utf8.get(index)?.as_deref();
+
+        self.skip(2)?; // access flags
+        let this_class = self.u2()? as usize;
+        self.skip(2)?; // super class
+        let interfaces = self.u2()? as usize;
+        self.skip(interfaces * 2)?;
+        self.skip_members()?; // fields
+
+        let mut has_main = false;
+        for _ in 0..self.u2()? {
+            let access = self.u2()?;
+            let name = self.u2()? as usize;
+            let descriptor = self.u2()? as usize;
+            self.skip_attributes()?;
+            has_main |= access & (ACC_PUBLIC | ACC_STATIC) == ACC_PUBLIC | ACC_STATIC
+                && utf8_at(name) == Some("main")
+                && utf8_at(descriptor) == Some("([Ljava/lang/String;)V");
+        }
+        if !has_main {
+            return None;
+        }
+
+        let name = utf8_at((*class_names.get(this_class)?)?)?;
+        Some(name.replace('/', "."))
+    }
+
+    fn skip_members(&mut self) -> Option<()> {
+        for _ in 0..self.u2()? {
+            self.skip(6)?; // access flags, name and descriptor
+            self.skip_attributes()?;
+        }
+        Some(())
+    }
+
+    fn skip_attributes(&mut self) -> Option<()> {
+        for _ in 0..self.u2()? {
+            self.skip(2)?;
+            let len = self.u4()? as usize;
+            self.skip(len)?;
+        }
+        Some(())
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    fn u1(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u2(&mut self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.take(2)?.try_into().ok()?))
+    }
+
+    fn u4(&mut self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.take(4)?.try_into().ok()?))
+    }
+}
+
+/// Returns the packaged JAR for the project if one exists, otherwise the class output directory.
+pub(crate) fn build_output(project_dir: &Path, name: &str, version: &str) -> Option<PathBuf> {
+    let build_dir = project_dir.join("build");
+
+    let jar = build_dir
+        .join("libs")
+        .join(format!("{}-{}.jar", name, version));
+    if jar.is_file() {
+        return Some(jar);
+    }
+
+    let classes = build_dir.join("classes");
+    classes.is_dir().then_some(classes)
+}
@@ -0,0 +1,360 @@
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::json;
+
+use crate::{
+    config::{SbomConfig, SbomFormat},
+    lock::{FygLock, LockedArtifact},
+    toml::FygToml,
+};
+
+/// The CycloneDX schema version SBOMs are written against.
+const CYCLONEDX_VERSION: &str = "1.5";
+
+/// Writes the SBOM `build.sbom` asks for, listing every artifact in `lock`, and returns its path.
+pub(crate) fn write(
+    config: &SbomConfig,
+    fyg_toml: &FygToml,
+    lock: &FygLock,
+    project_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let format = config.format.unwrap_or_default();
+    let output = project_dir.join(config.output.as_deref().unwrap_or(match format {
+        SbomFormat::CycloneDxJson => "build/sbom.cdx.json",
+        SbomFormat::CycloneDxXml => "build/sbom.cdx.xml",
+        SbomFormat::SpdxJson => "build/sbom.spdx.json",
+    }));
+
+    let timestamp = timestamp();
+    let content = match format {
+        SbomFormat::CycloneDxJson => {
+            serde_json::to_string_pretty(&cyclonedx_json(fyg_toml, lock, &timestamp))?
+        }
+        SbomFormat::CycloneDxXml => cyclonedx_xml(fyg_toml, lock, &timestamp),
+        SbomFormat::SpdxJson => {
+            serde_json::to_string_pretty(&spdx_json(fyg_toml, lock, &timestamp))?
+        }
+    };
+
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&output, content)?;
+    Ok(output)
+}
+
+/// Builds a CycloneDX JSON document describing the project and the artifacts in `lock`.
+pub fn cyclonedx_json(fyg_toml: &FygToml, lock: &FygLock, timestamp: &str) -> serde_json::Value {
+    let project = &fyg_toml.project;
+    let project_purl = purl(&project.group, &project.name, &project.version, None);
+
+    let components: Vec<_> = sorted(lock)
+        .into_iter()
+        .map(|artifact| {
+            let purl = artifact_purl(artifact);
+            let mut component = json!({
+                "type": "library",
+                "bom-ref": purl,
+                "group": artifact.group,
+                "name": artifact.artifact,
+                "version": artifact.version,
+                "scope": cyclonedx_scope(artifact),
+                "purl": purl,
+            });
+            if !artifact.sha256.is_empty() {
+                component["hashes"] = json!([{ "alg": "SHA-256", "content": artifact.sha256 }]);
+            }
+            component
+        })
+        .collect();
+
+    json!({
+        "bomFormat": "CycloneDX",
+        "specVersion": CYCLONEDX_VERSION,
+        "version": 1,
+        "metadata": {
+            "timestamp": timestamp,
+            "tools": {
+                "components": [{
+                    "type": "application",
+                    "name": "fyg",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }],
+            },
+            "component": {
+                "type": project_type(fyg_toml),
+                "bom-ref": project_purl,
+                "group": project.group,
+                "name": project.name,
+                "version": project.version,
+                "purl": project_purl,
+            },
+        },
+        "components": components,
+        "dependencies": [{
+            "ref": project_purl,
+            "dependsOn": direct(lock).iter().map(|a| artifact_purl(a)).collect::<Vec<_>>(),
+        }],
+    })
+}
+
+/// Builds a CycloneDX XML document describing the project and the artifacts in `lock`.
+pub fn cyclonedx_xml(fyg_toml: &FygToml, lock: &FygLock, timestamp: &str) -> String {
+    let project = &fyg_toml.project;
+    let project_purl = purl(&project.group, &project.name, &project.version, None);
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<bom xmlns=\"http://cyclonedx.org/schema/bom/{}\" version=\"1\">",
+        CYCLONEDX_VERSION
+    );
+    xml.push_str("  <metadata>\n");
+    element(&mut xml, 2, "timestamp", timestamp);
+    xml.push_str("    <tools>\n      <components>\n");
+    xml.push_str("        <component type=\"application\">\n");
+    element(&mut xml, 5, "name", "fyg");
+    element(&mut xml, 5, "version", env!("CARGO_PKG_VERSION"));
+    xml.push_str("        </component>\n      </components>\n    </tools>\n");
+    let _ = writeln!(
+        xml,
+        "    <component type=\"{}\" bom-ref=\"{}\">",
+        project_type(fyg_toml),
+        escape(&project_purl)
+    );
+    element(&mut xml, 3, "group", &project.group);
+    element(&mut xml, 3, "name", &project.name);
+    element(&mut xml, 3, "version", &project.version);
+    element(&mut xml, 3, "purl", &project_purl);
+    xml.push_str("    </component>\n  </metadata>\n");
+
+    xml.push_str("  <components>\n");
+    for artifact in sorted(lock) {
+        let purl = artifact_purl(artifact);
+        let _ = writeln!(
+            xml,
+            "    <component type=\"library\" bom-ref=\"{}\">",
+            escape(&purl)
+        );
+        element(&mut xml, 3, "group", &artifact.group);
+        element(&mut xml, 3, "name", &artifact.artifact);
+        element(&mut xml, 3, "version", &artifact.version);
+        element(&mut xml, 3, "scope", cyclonedx_scope(artifact));
+        if !artifact.sha256.is_empty() {
+            xml.push_str("      <hashes>\n");
+            let _ = writeln!(
+                xml,
+                "        <hash alg=\"SHA-256\">{}</hash>",
+                escape(&artifact.sha256)
+            );
+            xml.push_str("      </hashes>\n");
+        }
+        element(&mut xml, 3, "purl", &purl);
+        xml.push_str("    </component>\n");
+    }
+    xml.push_str("  </components>\n");
+
+    xml.push_str("  <dependencies>\n");
+    let _ = writeln!(xml, "    <dependency ref=\"{}\">", escape(&project_purl));
+    for artifact in direct(lock) {
+        let _ = writeln!(
+            xml,
+            "      <dependency ref=\"{}\"/>",
+            escape(&artifact_purl(artifact))
+        );
+    }
+    xml.push_str("    </dependency>\n  </dependencies>\n</bom>\n");
+    xml
+}
+
+/// Builds an SPDX JSON document describing the project and the artifacts in `lock`.
+pub fn spdx_json(fyg_toml: &FygToml, lock: &FygLock, timestamp: &str) -> serde_json::Value {
+    let project = &fyg_toml.project;
+    let project_id = spdx_id(&project.group, &project.name, None);
+    let project_purl = purl(&project.group, &project.name, &project.version, None);
+
+    let mut packages = vec![json!({
+        "name": project.name,
+        "SPDXID": project_id,
+        "versionInfo": project.version,
+        "downloadLocation": "NOASSERTION",
+        "filesAnalyzed": false,
+        "externalRefs": [spdx_purl(&project_purl)],
+    })];
+    let mut relationships = vec![json!({
+        "spdxElementId": "SPDXRef-DOCUMENT",
+        "relationshipType": "DESCRIBES",
+        "relatedSpdxElement": project_id,
+    })];
+
+    for artifact in sorted(lock) {
+        let id = spdx_id(&artifact.group, &artifact.artifact, artifact.classifier.as_deref());
+        let mut package = json!({
+            "name": format!("{}:{}", artifact.group, artifact.artifact),
+            "SPDXID": id,
+            "versionInfo": artifact.version,
+            "downloadLocation": artifact.repository,
+            "filesAnalyzed": false,
+            "externalRefs": [spdx_purl(&artifact_purl(artifact))],
+        });
+        if !artifact.sha256.is_empty() {
+            package["checksums"] =
+                json!([{ "algorithm": "SHA256", "checksumValue": artifact.sha256 }]);
+        }
+        packages.push(package);
+
+        if !artifact.transitive {
+            // a test dependency relationship points from the dependency to what depends on it
+            let (from, kind, to) = match artifact.scope.as_str() {
+                "test" => (&id, "TEST_DEPENDENCY_OF", &project_id),
+                _ => (&project_id, "DEPENDS_ON", &id),
+            };
+            relationships.push(json!({
+                "spdxElementId": from,
+                "relationshipType": kind,
+                "relatedSpdxElement": to,
+            }));
+        }
+    }
+
+    json!({
+        "spdxVersion": "SPDX-2.3",
+        "dataLicense": "CC0-1.0",
+        "SPDXID": "SPDXRef-DOCUMENT",
+        "name": format!("{}-{}", project.name, project.version),
+        "documentNamespace": format!(
+            "https://spdx.org/spdxdocs/{}-{}-{}",
+            project.name,
+            project.version,
+            timestamp.replace(':', "")
+        ),
+        "creationInfo": {
+            "created": timestamp,
+            "creators": [format!("Tool: fyg-{}", env!("CARGO_PKG_VERSION"))],
+        },
+        "packages": packages,
+        "relationships": relationships,
+    })
+}
+
+/// The artifacts in `lock`, sorted by coordinate.
+fn sorted(lock: &FygLock) -> Vec<&LockedArtifact> {
+    let mut artifacts: Vec<&LockedArtifact> = lock.artifacts.iter().collect();
+    artifacts.sort();
+    artifacts
+}
+
+fn direct(lock: &FygLock) -> Vec<&LockedArtifact> {
+    sorted(lock).into_iter().filter(|a| !a.transitive).collect()
+}
+
+fn project_type(fyg_toml: &FygToml) -> &'static str {
+    match fyg_toml.build.as_ref().is_some_and(|b| b.main_class.is_some()) {
+        true => "application",
+        false => "library",
+    }
+}
+
+/// Test dependencies aren't shipped with the project, which CycloneDX calls `excluded`.
+fn cyclonedx_scope(artifact: &LockedArtifact) -> &'static str {
+    match artifact.scope.as_str() {
+        "test" => "excluded",
+        _ => "required",
+    }
+}
+
+/// The package URL identifying a Maven artifact, such as `pkg:maven/com.example/lib@1.0`.
+pub fn purl(group: &str, artifact: &str, version: &str, classifier: Option<&str>) -> String {
+    let purl = format!("pkg:maven/{}/{}@{}", group, artifact, version);
+    match classifier {
+        Some(classifier) => format!("{}?classifier={}", purl, classifier),
+        None => purl,
+    }
+}
+
+fn artifact_purl(artifact: &LockedArtifact) -> String {
+    purl(
+        &artifact.group,
+        &artifact.artifact,
+        &artifact.version,
+        artifact.classifier.as_deref(),
+    )
+}
+
+fn spdx_purl(purl: &str) -> serde_json::Value {
+    json!({
+        "referenceCategory": "PACKAGE-MANAGER",
+        "referenceType": "purl",
+        "referenceLocator": purl,
+    })
+}
+
+/// An SPDX element ID, which may only contain letters, digits, `.` and `-`.
+fn spdx_id(group: &str, artifact: &str, classifier: Option<&str>) -> String {
+    let name = [Some(group), Some(artifact), classifier]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join("-");
+    let name: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+            true => c,
+            false => '-',
+        })
+        .collect();
+    format!("SPDXRef-Package-{}", name)
+}
+
+/// The current time in RFC 3339 format, or the time in `SOURCE_DATE_EPOCH` if it is set so
+/// builds can be reproduced.
+fn timestamp() -> String {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs())
+        });
+
+    // converts days since the epoch to a calendar date, from Howard Hinnant's date algorithms
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let time = seconds % 86400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Writes `<name>value</name>` on its own line, indented by `depth` levels.
+fn element(xml: &mut String, depth: usize, name: &str, value: &str) {
+    let _ = writeln!(xml, "{}<{}>{}</{}>", "  ".repeat(depth), name, escape(value), name);
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
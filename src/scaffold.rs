@@ -0,0 +1,138 @@
+pub mod ci;
+pub mod source;
+pub mod template;
+pub mod vcs;
+
+use std::path::Path;
+
+use crate::{
+    scaffold::{ci::CiProvider, template::ProjectTemplate, vcs::Vcs},
+    toml::FygToml,
+};
+
+/// Options controlling which files [`Fyg::init`](crate::Fyg::init) and
+/// [`Fyg::new`](crate::Fyg::new) create besides `fyg.toml`.
+#[derive(Clone, Debug)]
+pub struct InitOptions {
+    /// Writes a `.gitignore` for build output and editor files, or a `.hgignore` when
+    /// [`InitOptions::vcs`] is Mercurial, unless one already exists.
+    pub gitignore: bool,
+
+    /// Patterns added to the end of the generated `.gitignore`.
+    pub extra_gitignore_patterns: Vec<String>,
+
+    /// Writes an `.editorconfig` with the usual Kotlin code style, unless one already exists.
+    pub editorconfig: bool,
+
+    /// Writes a CI configuration that builds and tests the project with this provider.
+    pub ci: Option<CiProvider>,
+
+    /// Starts the project from this template, which gives it a `fyg.toml` and sources of its
+    /// own, instead of an empty source directory.
+    pub template: Option<ProjectTemplate>,
+
+    /// Puts the project under this version control system, with everything created for it
+    /// added in an initial commit.
+    pub vcs: Vcs,
+}
+
+impl Default for InitOptions {
+    fn default() -> Self {
+        Self {
+            gitignore: true,
+            extra_gitignore_patterns: Vec::new(),
+            editorconfig: true,
+            ci: None,
+            template: None,
+            vcs: Vcs::None,
+        }
+    }
+}
+
+/// The patterns every generated `.gitignore` starts with, grouped under a comment.
+const GITIGNORE_SECTIONS: [(&str, &[&str]); 2] = [
+    ("Build output", &["build/", ".fyg/", "*.class", "*.jar"]),
+    ("IntelliJ IDEA", &[".idea/", "*.iml"]),
+];
+
+/// The sections of every generated `.editorconfig`, each with its settings.
+const EDITORCONFIG_SECTIONS: [(&str, &[(&str, &str)]); 1] = [(
+    "*.kt",
+    &[
+        ("indent_style", "space"),
+        ("indent_size", "4"),
+        ("end_of_line", "lf"),
+        ("charset", "utf-8"),
+        ("trim_trailing_whitespace", "true"),
+        ("insert_final_newline", "true"),
+    ],
+)];
+
+/// Writes the files besides `fyg.toml` that `options` asks for into `dir`.
+pub(crate) fn write_extras(
+    dir: &Path,
+    fyg_toml: &FygToml,
+    options: &InitOptions,
+) -> anyhow::Result<()> {
+    write_gitignore(dir, options)?;
+    write_editorconfig(dir, options)?;
+    if let Some(provider) = options.ci {
+        ci::write_config(dir, provider, fyg_toml)?;
+    }
+    Ok(())
+}
+
+/// Writes a `.gitignore` or `.hgignore` to `dir` as described by `options`, leaving an existing
+/// one untouched.
+fn write_gitignore(dir: &Path, options: &InitOptions) -> anyhow::Result<()> {
+    let mercurial = options.vcs == Vcs::Mercurial;
+    let path = dir.join(options.vcs.ignore_file().unwrap_or(".gitignore"));
+    if !options.gitignore || path.exists() {
+        return Ok(());
+    }
+
+    let mut contents = gitignore(&options.extra_gitignore_patterns);
+    if mercurial {
+        // the patterns are the same, but Mercurial reads regular expressions by default
+        contents.insert_str(0, "syntax: glob\n\n");
+    }
+    std::fs::write(path, contents)?;
+    Ok(())
+}
+
+fn gitignore(extra_patterns: &[String]) -> String {
+    let mut sections: Vec<String> = GITIGNORE_SECTIONS
+        .iter()
+        .map(|(title, patterns)| format!("# {}\n{}\n", title, patterns.join("\n")))
+        .collect();
+    if !extra_patterns.is_empty() {
+        sections.push(format!("{}\n", extra_patterns.join("\n")));
+    }
+    sections.join("\n")
+}
+
+/// Writes an `.editorconfig` to `dir` as described by `options`, leaving an existing one
+/// untouched.
+fn write_editorconfig(dir: &Path, options: &InitOptions) -> anyhow::Result<()> {
+    let path = dir.join(".editorconfig");
+    if !options.editorconfig || path.exists() {
+        return Ok(());
+    }
+
+    std::fs::write(path, editorconfig())?;
+    Ok(())
+}
+
+fn editorconfig() -> String {
+    let sections: Vec<String> = EDITORCONFIG_SECTIONS
+        .iter()
+        .map(|(glob, settings)| {
+            let settings: Vec<String> = settings
+                .iter()
+                .map(|(key, value)| format!("{} = {}", key, value))
+                .collect();
+            format!("[{}]\n{}\n", glob, settings.join("\n"))
+        })
+        .collect();
+    format!("root = true\n\n{}", sections.join("\n"))
+}
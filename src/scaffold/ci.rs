@@ -0,0 +1,169 @@
+use std::{path::Path, str::FromStr};
+
+use crate::toml::FygToml;
+
+/// The JDK CI installs when `targets.jvm.target` isn't set.
+const DEFAULT_JDK: &str = "17";
+
+/// How CI installs fyg before building.
+const INSTALL_FYG: &str = "cargo install --locked --git https://github.com/tirbofish/fyg";
+
+const GITHUB_WORKFLOW: &str = r#"name: CI
+
+on:
+  push:
+  pull_request:
+
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - uses: actions/setup-java@v4
+        with:
+          distribution: temurin
+          java-version: '{jdk}'
+      - name: Install fyg
+        run: {install}
+      - name: Build
+        run: fyg build
+      - name: Test
+        run: fyg test
+"#;
+
+const GITLAB_PIPELINE: &str = r#"image: eclipse-temurin:{jdk}-jdk
+
+ci:
+  before_script:
+    - apt-get update && apt-get install -y build-essential curl unzip
+    - curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y
+    - . "$HOME/.cargo/env"
+    - {install}
+  script:
+    - fyg build
+    - fyg test
+"#;
+
+const CIRCLECI_CONFIG: &str = r#"version: 2.1
+
+jobs:
+  build:
+    docker:
+      - image: cimg/openjdk:{jdk}.0
+    steps:
+      - checkout
+      - run:
+          name: Install fyg
+          command: |
+            curl --proto '=https' --tlsv1.2 -sSf https://sh.rustup.rs | sh -s -- -y
+            echo '. "$HOME/.cargo/env"' >> "$BASH_ENV"
+            . "$HOME/.cargo/env"
+            {install}
+      - run:
+          name: Build
+          command: fyg build
+      - run:
+          name: Test
+          command: fyg test
+
+workflows:
+  ci:
+    jobs:
+      - build
+"#;
+
+/// A CI service that `fyg new --ci` and `fyg init --ci` can write a configuration for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CiProvider {
+    /// GitHub Actions, configured by `.github/workflows/ci.yml`.
+    GitHub,
+
+    /// GitLab CI/CD, configured by `.gitlab-ci.yml`.
+    GitLab,
+
+    /// CircleCI, configured by `.circleci/config.yml`.
+    CircleCI,
+}
+
+impl FromStr for CiProvider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "github" => Ok(CiProvider::GitHub),
+            "gitlab" => Ok(CiProvider::GitLab),
+            "circleci" => Ok(CiProvider::CircleCI),
+            other => anyhow::bail!(
+                "unknown CI provider '{}', expected github, gitlab or circleci",
+                other
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for CiProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            CiProvider::GitHub => "github",
+            CiProvider::GitLab => "gitlab",
+            CiProvider::CircleCI => "circleci",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl CiProvider {
+    /// Returns where the provider expects its configuration, relative to the project directory.
+    pub fn config_path(&self) -> &'static str {
+        match self {
+            CiProvider::GitHub => ".github/workflows/ci.yml",
+            CiProvider::GitLab => ".gitlab-ci.yml",
+            CiProvider::CircleCI => ".circleci/config.yml",
+        }
+    }
+
+    /// Returns a configuration that builds and tests the project on the given JDK version.
+    pub fn config(&self, jdk: &str) -> String {
+        let template = match self {
+            CiProvider::GitHub => GITHUB_WORKFLOW,
+            CiProvider::GitLab => GITLAB_PIPELINE,
+            CiProvider::CircleCI => CIRCLECI_CONFIG,
+        };
+        template
+            .replace("{jdk}", jdk)
+            .replace("{install}", INSTALL_FYG)
+    }
+}
+
+/// Returns the JDK version CI should build with: the one `targets.jvm.target` compiles for, or
+/// 17 if it isn't set.
+pub fn jdk_version(fyg_toml: &FygToml) -> String {
+    let target = fyg_toml
+        .targets
+        .as_ref()
+        .and_then(|t| t.jvm.as_ref())
+        .and_then(|jvm| jvm.target.as_deref());
+    match target {
+        // Java 8 is targeted as 1.8 but installed as 8
+        Some(target) => target.strip_prefix("1.").unwrap_or(target).to_string(),
+        None => DEFAULT_JDK.to_string(),
+    }
+}
+
+/// Writes the CI configuration for `provider` into `dir`, leaving an existing one untouched.
+pub(crate) fn write_config(
+    dir: &Path,
+    provider: CiProvider,
+    fyg_toml: &FygToml,
+) -> anyhow::Result<()> {
+    let path = dir.join(provider.config_path());
+    if path.exists() {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, provider.config(&jdk_version(fyg_toml)))?;
+    Ok(())
+}
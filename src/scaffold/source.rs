@@ -0,0 +1,124 @@
+use std::path::{Path, PathBuf};
+
+use crate::toml::FygToml;
+
+/// A Kotlin source file `fyg scaffold` can generate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SourceTemplate {
+    /// `Main.kt`, with a `main` function.
+    MainClass,
+
+    /// A data class with a single property to start from.
+    DataClass { name: String },
+
+    /// An empty interface.
+    Interface { name: String },
+
+    /// An empty singleton object.
+    Object { name: String },
+
+    /// A Spring Boot application class named after the project, and a `main` function that
+    /// runs it.
+    SpringBootApp,
+}
+
+impl SourceTemplate {
+    /// The name of the class the file declares, which is also the file's name.
+    pub fn class_name(&self, project_name: &str) -> String {
+        match self {
+            SourceTemplate::MainClass => "Main".to_string(),
+            SourceTemplate::DataClass { name }
+            | SourceTemplate::Interface { name }
+            | SourceTemplate::Object { name } => name.clone(),
+            SourceTemplate::SpringBootApp => format!("{}Application", pascal_case(project_name)),
+        }
+    }
+
+    /// Renders the file's contents, declared in `package`.
+    pub fn render(&self, package: &str, project_name: &str) -> String {
+        let class_name = self.class_name(project_name);
+        let body = match self {
+            SourceTemplate::MainClass => format!(
+                "fun main(args: Array<String>) {{\n    println(\"Hello from {}!\")\n}}\n",
+                project_name
+            ),
+            SourceTemplate::DataClass { .. } => {
+                format!("data class {}(\n    val id: Long,\n)\n", class_name)
+            }
+            SourceTemplate::Interface { .. } => format!("interface {}\n", class_name),
+            SourceTemplate::Object { .. } => format!("object {}\n", class_name),
+            SourceTemplate::SpringBootApp => format!(
+                "import org.springframework.boot.autoconfigure.SpringBootApplication\n\
+                 import org.springframework.boot.runApplication\n\n\
+                 @SpringBootApplication\n\
+                 class {0}\n\n\
+                 fun main(args: Array<String>) {{\n    runApplication<{0}>(*args)\n}}\n",
+                class_name
+            ),
+        };
+        format!("package {}\n\n{}", package, body)
+    }
+}
+
+/// Writes the file for `template` into the project in `project_dir`, returning its path.
+///
+/// The file goes in the directory of the `project.group` package under `src/kotlin`, unless
+/// `output` names another place relative to the project: a `.kt` file, or a directory to put
+/// the file in. An existing file is never overwritten.
+pub(crate) fn write_source(
+    project_dir: &Path,
+    fyg_toml: &FygToml,
+    template: &SourceTemplate,
+    output: Option<&Path>,
+) -> anyhow::Result<PathBuf> {
+    let project = &fyg_toml.project;
+    let class_name = template.class_name(&project.name);
+    if !is_identifier(&class_name) {
+        anyhow::bail!("'{}' is not a valid Kotlin class name", class_name);
+    }
+
+    let file_name = format!("{}.kt", class_name);
+    let path = match output {
+        Some(output) if output.extension().is_some_and(|ext| ext == "kt") => {
+            project_dir.join(output)
+        }
+        Some(output) => project_dir.join(output).join(file_name),
+        None => project_dir
+            .join("src")
+            .join("kotlin")
+            .join(project.group.replace('.', "/"))
+            .join(file_name),
+    };
+    if path.exists() {
+        anyhow::bail!("{} already exists", path.display());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, template.render(&project.group, &project.name))?;
+    Ok(path)
+}
+
+/// Whether `name` can be used as a Kotlin identifier without backticks.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Converts a project name such as `my-app` into `MyApp`.
+fn pascal_case(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
@@ -0,0 +1,100 @@
+use std::{path::Path, str::FromStr};
+
+use include_dir::{Dir, include_dir};
+
+use crate::toml::FygToml;
+
+/// Every template, one directory each, as they are in `templates/` in the fyg repository.
+static TEMPLATES: Dir<'static> = include_dir!("$CARGO_MANIFEST_DIR/templates");
+
+/// A kind of project `fyg new --template` can start from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProjectTemplate {
+    /// A command line app with a `main` function.
+    Console,
+
+    /// A Kotlin Multiplatform library for the JVM and native targets.
+    Library,
+
+    /// A Ktor HTTP server with routing and JSON responses.
+    KtorServer,
+
+    /// A Compose Multiplatform UI for desktop and Android.
+    ComposeMultiplatform,
+
+    /// A Spring Boot web application.
+    SpringBoot,
+}
+
+impl FromStr for ProjectTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "console" => Ok(ProjectTemplate::Console),
+            "library" => Ok(ProjectTemplate::Library),
+            "ktor-server" => Ok(ProjectTemplate::KtorServer),
+            "compose-multiplatform" => Ok(ProjectTemplate::ComposeMultiplatform),
+            "spring-boot" => Ok(ProjectTemplate::SpringBoot),
+            other => anyhow::bail!(
+                "unknown template '{}', expected console, library, ktor-server, \
+                 compose-multiplatform or spring-boot",
+                other
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for ProjectTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            ProjectTemplate::Console => "console",
+            ProjectTemplate::Library => "library",
+            ProjectTemplate::KtorServer => "ktor-server",
+            ProjectTemplate::ComposeMultiplatform => "compose-multiplatform",
+            ProjectTemplate::SpringBoot => "spring-boot",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Writes the files of `template`, including its `fyg.toml`, into `dir`.
+///
+/// `{{project.name}}`, `{{project.group}}` and `{{project.version}}` in the files are replaced
+/// with the settings from `fyg_toml`. They can be used in paths too, where the group becomes
+/// the directory of its package, such as `com/example`.
+pub(crate) fn write_template(
+    dir: &Path,
+    template: ProjectTemplate,
+    fyg_toml: &FygToml,
+) -> anyhow::Result<()> {
+    let name = template.to_string();
+    let template_dir = TEMPLATES
+        .get_dir(&name)
+        .ok_or_else(|| anyhow::anyhow!("the {} template is missing", name))?;
+
+    let mut pending = vec![template_dir];
+    while let Some(current) = pending.pop() {
+        pending.extend(current.dirs());
+        for file in current.files() {
+            let relative = file.path().strip_prefix(&name)?.to_string_lossy();
+            let path = dir.join(render(&relative, fyg_toml, "/"));
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            let contents = std::str::from_utf8(file.contents())?;
+            std::fs::write(path, render(contents, fyg_toml, "."))?;
+        }
+    }
+    Ok(())
+}
+
+/// Replaces the template variables in `text`, joining the parts of the group with
+/// `group_separator`.
+fn render(text: &str, fyg_toml: &FygToml, group_separator: &str) -> String {
+    let project = &fyg_toml.project;
+    text.replace("{{project.name}}", &project.name)
+        .replace("{{project.group}}", &project.group.replace('.', group_separator))
+        .replace("{{project.version}}", &project.version)
+}
@@ -0,0 +1,150 @@
+use std::{
+    path::Path,
+    process::{Command, Output},
+    str::FromStr,
+};
+
+use crate::{cancel, error::FygError, toolchain};
+
+/// A version control system [`Fyg::init`](crate::Fyg::init) can put a new project under.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Vcs {
+    Git,
+
+    /// Mercurial, whose command is `hg`.
+    Mercurial,
+
+    /// Leaves the project out of version control.
+    #[default]
+    None,
+}
+
+impl Vcs {
+    /// Picks the version control system to use when none is asked for: git if it is on `PATH`,
+    /// otherwise Mercurial if that is, otherwise none.
+    pub fn detect() -> Vcs {
+        [Vcs::Git, Vcs::Mercurial]
+            .into_iter()
+            .find(|vcs| vcs.command().and_then(toolchain::find_executable).is_some())
+            .unwrap_or(Vcs::None)
+    }
+
+    /// The command the version control system is run with, such as `git`.
+    pub fn command(&self) -> Option<&'static str> {
+        match self {
+            Vcs::Git => Some("git"),
+            Vcs::Mercurial => Some("hg"),
+            Vcs::None => None,
+        }
+    }
+
+    /// The file listing what the version control system ignores, such as `.gitignore`.
+    pub fn ignore_file(&self) -> Option<&'static str> {
+        match self {
+            Vcs::Git => Some(".gitignore"),
+            Vcs::Mercurial => Some(".hgignore"),
+            Vcs::None => None,
+        }
+    }
+}
+
+impl FromStr for Vcs {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "git" => Ok(Vcs::Git),
+            "hg" | "mercurial" => Ok(Vcs::Mercurial),
+            "none" => Ok(Vcs::None),
+            other => anyhow::bail!(
+                "unknown version control system '{}', expected git, hg or none",
+                other
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Vcs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            Vcs::Git => "git",
+            Vcs::Mercurial => "hg",
+            Vcs::None => "none",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+/// Who the initial commit is made by when no identity is configured, as on a fresh CI
+/// container, so that `fyg new` doesn't fail there.
+const FALLBACK_NAME: &str = "fyg";
+const FALLBACK_EMAIL: &str = "fyg@localhost";
+
+/// Makes `dir` a repository of `vcs`, then commits everything in it as the initial commit.
+///
+/// If the version control system has no identity to commit as, the commit is made as
+/// [`FALLBACK_NAME`] and a warning is logged. Only that commit uses it, nothing is configured.
+pub(crate) fn init_repository(dir: &Path, vcs: Vcs) -> anyhow::Result<()> {
+    let Some(command) = vcs.command() else {
+        return Ok(());
+    };
+    let program = toolchain::find_executable(command).ok_or_else(|| FygError::ToolNotFound {
+        tool: command.to_string(),
+        hint: format!("which is needed to initialize a {} repository, or pass --vcs none", vcs),
+    })?;
+    let run = |args: &[&str]| -> anyhow::Result<Output> {
+        Ok(cancel::output(Command::new(&program).args(args).current_dir(dir))?)
+    };
+    let check = |args: &[&str]| -> anyhow::Result<()> {
+        let output = run(args)?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "{} {} failed: {}",
+                command,
+                args[0],
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    };
+
+    let (init, add): (&[&str], &[&str]) = match vcs {
+        Vcs::Git => (&["init", "--quiet"], &["add", "."]),
+        _ => (&["init"], &["add", "--quiet"]),
+    };
+    check(init)?;
+    check(add)?;
+
+    let fallback = format!("{} <{}>", FALLBACK_NAME, FALLBACK_EMAIL);
+    let has_identity = match vcs {
+        // git var fails the same way git commit would without a usable identity
+        Vcs::Git => ["GIT_AUTHOR_IDENT", "GIT_COMMITTER_IDENT"]
+            .iter()
+            .all(|ident| run(&["var", ident]).is_ok_and(|o| o.status.success())),
+        _ => {
+            std::env::var_os("HGUSER").is_some()
+                || run(&["config", "ui.username"]).is_ok_and(|o| o.status.success())
+        }
+    };
+    let name = format!("user.name={}", FALLBACK_NAME);
+    let email = format!("user.email={}", FALLBACK_EMAIL);
+    let identity: Vec<&str> = match (has_identity, vcs) {
+        (true, _) => Vec::new(),
+        (false, Vcs::Git) => vec!["-c", &name, "-c", &email],
+        (false, _) => vec!["-u", &fallback],
+    };
+    if !has_identity {
+        log::warn!(
+            "no {} identity is configured, so the initial commit is made as {}",
+            command,
+            fallback
+        );
+    }
+
+    let commit = ["commit", "--quiet", "-m", "Initial commit"];
+    match vcs {
+        // git's -c options go before the subcommand, hg's -u after it
+        Vcs::Git => check(&[identity.as_slice(), &commit].concat()),
+        _ => check(&[&commit[..], identity.as_slice()].concat()),
+    }
+}
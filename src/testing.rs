@@ -0,0 +1,550 @@
+pub mod report;
+
+use std::{
+    io::{BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    str::FromStr,
+};
+
+use crate::{
+    ProcessConfig, cancel,
+    config::TestConfig,
+    coverage,
+    maven::Coordinate,
+    toml::FygToml,
+};
+
+use self::report::TestReport;
+
+/// Where JUnit XML reports are written when `test.xml-report-dir` isn't set, relative to the
+/// project. Those of integration tests go in an `integration-test` directory inside it.
+pub const DEFAULT_XML_REPORT_DIR: &str = "build/test-results";
+
+/// Where integration tests are when `test.integration-tests.source-dir` isn't set, relative to
+/// the project.
+pub const DEFAULT_INTEGRATION_TEST_DIR: &str = "src/integration-test/kotlin";
+
+/// The version of the JUnit Platform console launcher used to run tests.
+const JUNIT_PLATFORM_VERSION: &str = "1.10.2";
+
+/// The version of Kotest put on the classpath when `test.framework = "kotest"`.
+const KOTEST_VERSION: &str = "5.8.1";
+
+/// A test framework supported by `fyg test`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestFramework {
+    /// JUnit 5 (Jupiter), run with the JUnit Platform console launcher.
+    JUnit5,
+
+    /// Kotest, run through its JUnit Platform engine.
+    Kotest,
+}
+
+impl FromStr for TestFramework {
+    type Err = TestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "junit5" | "junit" => Ok(TestFramework::JUnit5),
+            "kotest" => Ok(TestFramework::Kotest),
+            other => Err(TestError::UnknownFramework(other.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for TestFramework {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match self {
+            TestFramework::JUnit5 => "junit5",
+            TestFramework::Kotest => "kotest",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl TestFramework {
+    /// The JUnit Platform console launcher, which also bundles the Jupiter API and engine.
+    pub fn launcher() -> Coordinate {
+        Coordinate::new(
+            "org.junit.platform",
+            "junit-platform-console-standalone",
+            JUNIT_PLATFORM_VERSION,
+        )
+    }
+
+    /// Any artifacts besides the launcher needed to compile and run tests with this framework.
+    pub fn artifacts(&self) -> Vec<Coordinate> {
+        match self {
+            TestFramework::JUnit5 => Vec::new(),
+            TestFramework::Kotest => {
+                let mut artifacts: Vec<Coordinate> = [
+                    "kotest-runner-junit5-jvm",
+                    "kotest-framework-api-jvm",
+                    "kotest-framework-engine-jvm",
+                    "kotest-framework-discovery-jvm",
+                    "kotest-framework-concurrency-jvm",
+                    "kotest-common-jvm",
+                    "kotest-assertions-api-jvm",
+                    "kotest-assertions-core-jvm",
+                    "kotest-assertions-shared-jvm",
+                    "kotest-extensions-jvm",
+                ]
+                .into_iter()
+                .map(|artifact| Coordinate::new("io.kotest", artifact, KOTEST_VERSION))
+                .collect();
+
+                artifacts.extend([
+                    Coordinate::new("org.jetbrains.kotlinx", "kotlinx-coroutines-core-jvm", "1.7.3"),
+                    Coordinate::new("org.jetbrains.kotlinx", "kotlinx-coroutines-test-jvm", "1.7.3"),
+                    Coordinate::new("org.jetbrains.kotlin", "kotlin-reflect", "1.9.22"),
+                    Coordinate::new("io.github.classgraph", "classgraph", "4.8.162"),
+                ]);
+                artifacts
+            }
+        }
+    }
+}
+
+/// The outcome of a test run.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TestSummary {
+    pub passed: u32,
+    pub failed: u32,
+    pub ignored: u32,
+    /// The share of lines the tests covered, from `0.0` to `1.0`, if `test.coverage` is enabled.
+    pub coverage: Option<f32>,
+}
+
+impl std::fmt::Display for TestSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} passed, {} failed, {} ignored",
+            self.passed, self.failed, self.ignored
+        )?;
+        if let Some(coverage) = self.coverage {
+            write!(f, ", {:.1}% line coverage", coverage * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl TestSummary {
+    /// Parses the summary table printed by the JUnit Platform console launcher, such as
+    /// `[         2 tests successful      ]`.
+    pub fn parse(output: &str) -> Self {
+        let mut summary = TestSummary::default();
+
+        for line in output.lines() {
+            let line = line.trim().trim_start_matches('[').trim_end_matches(']');
+            let mut words = line.split_whitespace();
+            let (Some(count), Some("tests"), Some(kind)) = (words.next(), words.next(), words.next())
+            else {
+                continue;
+            };
+            let Ok(count) = count.parse::<u32>() else {
+                continue;
+            };
+
+            match kind {
+                "successful" => summary.passed += count,
+                "failed" => summary.failed += count,
+                "skipped" | "aborted" => summary.ignored += count,
+                _ => {}
+            }
+        }
+
+        summary
+    }
+}
+
+/// An error raised while running a project's tests.
+#[derive(Debug)]
+pub enum TestError {
+    /// `test.framework` names a framework fyg does not support.
+    UnknownFramework(String),
+
+    /// The test runner exited unsuccessfully.
+    Failed {
+        /// The exit code of the runner, if it exited normally.
+        code: Option<i32>,
+        /// The results parsed from the runner's output.
+        summary: TestSummary,
+    },
+}
+
+impl std::fmt::Display for TestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestError::UnknownFramework(name) => write!(
+                f,
+                "unknown test framework '{}', expected 'junit5' or 'kotest'",
+                name
+            ),
+            TestError::Failed { summary, .. } => write!(f, "tests failed ({})", summary),
+        }
+    }
+}
+
+impl std::error::Error for TestError {}
+
+/// Options controlling how [`Fyg::test_with_options`](crate::Fyg::test_with_options) behaves.
+#[derive(Clone, Debug, Default)]
+pub struct TestOptions {
+    /// Only runs the given test class, or a single method with `Class#method`.
+    pub filter: Option<String>,
+
+    /// Never re-runs failed tests, whatever `test.retry` says.
+    pub no_retry: bool,
+
+    /// Writes JUnit XML reports even if `test.xml-report` is off.
+    pub xml_report: bool,
+
+    /// A report to write once the tests have run, from the JUnit XML reports.
+    pub report: Option<TestReport>,
+
+    /// Runs the integration tests in `test.integration-tests` instead of the unit tests.
+    pub integration: bool,
+
+    /// Doesn't echo the runner's output, which is still read for the summary and failures.
+    pub quiet: bool,
+
+    /// How the build, the compiler and the test runner are started, like
+    /// [`BuildOptions::process`](crate::BuildOptions::process).
+    pub process: Option<ProcessConfig>,
+
+    /// Stops tests started with [`Fyg::test_async`](crate::Fyg::test_async) once it is
+    /// cancelled, like [`BuildOptions::cancel`](crate::BuildOptions::cancel).
+    #[cfg(feature = "async")]
+    pub cancel: Option<tokio_util::sync::CancellationToken>,
+}
+
+/// Runs the compiled tests on `classpath` with the JUnit Platform console launcher.
+///
+/// Tests are only looked for in the first entry of `classpath`, which holds the compiled tests.
+/// The integration tests run with [`TestConfig::for_integration_tests`] if
+/// [`TestOptions::integration`] is set.
+///
+/// [`TestOptions::filter`] is either a class name or `Class#method`. For JUnit it becomes a
+/// `--select-class` or `--select-method` selector, and for Kotest it is passed through Kotest's
+/// filter properties. `targets.jvm.jvm-args` and then `test.jvm-args` are passed to `java` after
+/// `test.max-heap` and before `test.system-properties`, and `test.environment` is added to its
+/// environment. The runner's output is echoed as it is produced, unless [`TestOptions::quiet`]
+/// is set.
+///
+/// Tests that fail are run again, up to `test.retry` more times unless [`TestOptions::no_retry`]
+/// is set, and count as passed if they pass on one of those runs. JUnit tests are re-run one
+/// method at a time, Kotest specs as a whole.
+///
+/// With `test.coverage` enabled, the tests run under a coverage agent, and a
+/// [`CoverageError`](crate::coverage::CoverageError) is returned if they pass but cover less
+/// than `test.coverage.minimum-coverage`.
+///
+/// JUnit XML reports go to `test.xml-report-dir`, with those of each re-run in a `retry-<n>`
+/// directory inside it. An HTML report asked for by [`TestOptions::report`] is written even if
+/// tests fail.
+pub(crate) fn run_tests(
+    java: &Path,
+    launcher: &Path,
+    framework: TestFramework,
+    classpath: &[PathBuf],
+    fyg_toml: &FygToml,
+    project_dir: &Path,
+    options: &TestOptions,
+) -> anyhow::Result<TestSummary> {
+    let integration_config = fyg_toml
+        .test
+        .as_ref()
+        .filter(|_| options.integration)
+        .map(TestConfig::for_integration_tests);
+    let config = match options.integration {
+        true => integration_config.as_ref(),
+        false => fyg_toml.test.as_ref(),
+    };
+    let mut jvm_args = crate::jvm_args(fyg_toml);
+    jvm_args.extend(config.and_then(|c| c.jvm_args.clone()).unwrap_or_default());
+
+    let coverage = config
+        .and_then(|c| c.coverage.as_ref())
+        .filter(|c| c.enabled);
+    if let Some(coverage) = coverage {
+        jvm_args.push(coverage::agent_arg(coverage, project_dir, || {
+            crate::jar_tool(fyg_toml)
+        })?);
+    }
+
+    let test_classes = classpath
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("the test classpath is empty"))?;
+    let filter = options.filter.as_deref();
+    let retry = match options.no_retry {
+        true => 0,
+        false => config.and_then(|c| c.retry).unwrap_or(0),
+    };
+
+    let xml_report = options.xml_report
+        || options.report.is_some()
+        || config.and_then(|c| c.xml_report).unwrap_or(false);
+    let xml_report_dir = xml_report.then(|| {
+        let dir = project_dir.join(
+            config
+                .and_then(|c| c.xml_report_dir.as_deref())
+                .unwrap_or(DEFAULT_XML_REPORT_DIR),
+        );
+        match options.integration {
+            true => dir.join("integration-test"),
+            false => dir,
+        }
+    });
+    let mut report_dirs = Vec::new();
+    if let Some(dir) = &xml_report_dir {
+        remove_retry_reports(dir)?;
+        report_dirs.push(dir.clone());
+    }
+
+    let mut cmd =
+        launcher_command(java, launcher, framework, classpath, filter, config, &jvm_args)?;
+    if let Some(dir) = &xml_report_dir {
+        cmd.arg("--reports-dir").arg(dir);
+    }
+    match (framework, filter) {
+        (TestFramework::JUnit5, Some(filter)) => match split_filter(filter) {
+            (_, Some(_)) => cmd.arg("--select-method").arg(filter),
+            (class, None) => cmd.arg("--select-class").arg(class),
+        },
+        // only the tests themselves, not the classes they test or depend on
+        _ => cmd.arg(format!("--scan-class-path={}", test_classes.display())),
+    };
+    let (mut code, output) = launch(cmd, options.quiet)?;
+    let mut summary = TestSummary::parse(&output);
+    let mut failures = failed_tests(&output, framework);
+
+    for attempt in 1..=retry {
+        if code == Some(0) || failures.is_empty() {
+            break;
+        }
+
+        let mut cmd =
+            launcher_command(java, launcher, framework, classpath, None, config, &jvm_args)?;
+        if let Some(dir) = &xml_report_dir {
+            let dir = dir.join(format!("retry-{}", attempt));
+            cmd.arg("--reports-dir").arg(&dir);
+            report_dirs.push(dir);
+        }
+        for failure in &failures {
+            match failure.split_once('#') {
+                Some(_) => cmd.arg("--select-method").arg(failure),
+                None => cmd.arg("--select-class").arg(failure),
+            };
+        }
+        let (rerun_code, output) = launch(cmd, options.quiet)?;
+        let still_failed = TestSummary::parse(&output).failed.min(summary.failed);
+        summary.passed += summary.failed - still_failed;
+        summary.failed = still_failed;
+        code = rerun_code;
+        failures = failed_tests(&output, framework);
+    }
+
+    if options.report == Some(TestReport::Html) {
+        let cases = report::read_xml_reports(&report_dirs)?;
+        report::write_html(
+            &cases,
+            &fyg_toml.project.name,
+            &project_dir.join(report::html_report_dir(options.integration)),
+        )?;
+    }
+
+    if let Some(coverage) = coverage {
+        summary.coverage = Some(coverage::report(
+            coverage,
+            java,
+            project_dir,
+            &fyg_toml.project.name,
+        )?);
+    }
+
+    if code != Some(0) {
+        return Err(TestError::Failed { code, summary }.into());
+    }
+    if let (Some(coverage), Some(covered)) = (coverage, summary.coverage) {
+        coverage::check_minimum(coverage, covered)?;
+    }
+
+    Ok(summary)
+}
+
+/// Removes the `retry-<n>` report directories a previous run left in `dir`.
+fn remove_retry_reports(dir: &Path) -> std::io::Result<()> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Ok(());
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let is_retry = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("retry-"))
+            .is_some_and(|n| n.parse::<u32>().is_ok());
+        if is_retry && path.is_dir() {
+            std::fs::remove_dir_all(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Builds the `java` invocation of the console launcher, without any test selectors.
+fn launcher_command(
+    java: &Path,
+    launcher: &Path,
+    framework: TestFramework,
+    classpath: &[PathBuf],
+    filter: Option<&str>,
+    config: Option<&TestConfig>,
+    jvm_args: &[String],
+) -> anyhow::Result<Command> {
+    let mut cmd = Command::new(java);
+
+    if let Some(max_heap) = config.and_then(|c| c.max_heap.as_ref()) {
+        cmd.arg(format!("-Xmx{}", max_heap));
+    }
+    cmd.args(jvm_args);
+
+    if let Some(properties) = config.and_then(|c| c.system_properties.as_ref()) {
+        let mut properties: Vec<_> = properties.iter().collect();
+        properties.sort();
+        for (key, value) in properties {
+            cmd.arg(format!("-D{}={}", key, value));
+        }
+    }
+    if let Some(environment) = config.and_then(|c| c.environment.as_ref()) {
+        cmd.envs(environment);
+    }
+
+    let parallel = config.and_then(|c| c.parallel).unwrap_or(false);
+    let timeout = config.and_then(|c| c.timeout_seconds);
+    if framework == TestFramework::Kotest {
+        if let Some(filter) = filter {
+            let (spec, test) = split_filter(filter);
+            cmd.arg(format!("-Dkotest.filter.specs={}", spec));
+            if let Some(test) = test {
+                cmd.arg(format!("-Dkotest.filter.tests={}", test));
+            }
+        }
+        if parallel {
+            let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+            cmd.arg(format!("-Dkotest.framework.parallelism={}", threads));
+        }
+        if let Some(timeout) = timeout {
+            cmd.arg(format!("-Dkotest.framework.timeout={}", timeout * 1000));
+        }
+    }
+
+    cmd.arg("-jar")
+        .arg(launcher)
+        .arg("execute")
+        .arg("--disable-banner")
+        .arg("--class-path")
+        .arg(std::env::join_paths(classpath)?);
+
+    if framework == TestFramework::JUnit5 {
+        if parallel {
+            cmd.args([
+                "--config=junit.jupiter.execution.parallel.enabled=true",
+                "--config=junit.jupiter.execution.parallel.mode.default=concurrent",
+            ]);
+        }
+        if let Some(timeout) = timeout {
+            cmd.arg(format!("--config=junit.jupiter.execution.timeout.default={} s", timeout));
+        }
+    }
+
+    Ok(cmd)
+}
+
+/// Runs the launcher, echoing its output as it is produced unless `quiet` is set, and returns
+/// its exit code and output.
+fn launch(mut cmd: Command, quiet: bool) -> anyhow::Result<(Option<i32>, String)> {
+    let mut child = ProcessConfig::configure(&mut cmd).stdout(Stdio::piped()).spawn()?;
+
+    // the output is read on its own thread, so the launcher can be killed part way through if
+    // the run is cancelled
+    let stdout = child.stdout.take();
+    let reader = std::thread::spawn(move || -> std::io::Result<String> {
+        let mut output = String::new();
+        if let Some(stdout) = stdout {
+            for line in BufReader::new(stdout).lines() {
+                let line = line?;
+                if !quiet {
+                    println!("{}", line);
+                }
+                output.push_str(&line);
+                output.push('\n');
+            }
+        }
+        Ok(output)
+    });
+
+    let status = cancel::wait(&mut child)?;
+    let output = reader
+        .join()
+        .map_err(|_| anyhow::anyhow!("could not read the test output"))??;
+    Ok((status.code(), output))
+}
+
+/// Finds the tests listed under `Failures` in the console launcher's output, as `Class#method`
+/// for JUnit and as the spec class for Kotest, in the order they were reported.
+pub(crate) fn failed_tests(output: &str, framework: TestFramework) -> Vec<String> {
+    let mut failures = Vec::new();
+    let mut in_failures = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("Failures (") {
+            in_failures = true;
+            continue;
+        }
+        if !in_failures {
+            continue;
+        }
+        if line.starts_with("Test run finished") {
+            break;
+        }
+
+        let Some(source) = line
+            .strip_prefix("MethodSource [")
+            .or_else(|| line.strip_prefix("ClassSource ["))
+        else {
+            continue;
+        };
+        let Some(class) = source_field(source, "className") else {
+            continue;
+        };
+        let failure = match (framework, source_field(source, "methodName")) {
+            (TestFramework::JUnit5, Some(method)) => {
+                let parameters = source_field(source, "methodParameterTypes").unwrap_or_default();
+                format!("{}#{}({})", class, method, parameters)
+            }
+            _ => class.to_string(),
+        };
+        if !failures.contains(&failure) {
+            failures.push(failure);
+        }
+    }
+
+    failures
+}
+
+/// Reads `key = 'value'` out of a test source printed by the console launcher.
+fn source_field<'a>(source: &'a str, key: &str) -> Option<&'a str> {
+    let start = source.find(&format!("{} = '", key))? + key.len() + 4;
+    let len = source[start..].find('\'')?;
+    Some(&source[start..start + len])
+}
+
+fn split_filter(filter: &str) -> (&str, Option<&str>) {
+    match filter.split_once('#') {
+        Some((class, method)) => (class, Some(method)),
+        None => (filter, None),
+    }
+}
@@ -0,0 +1,211 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::xml::Element;
+
+/// Where `fyg test --report html` writes its report, relative to the project.
+pub const HTML_REPORT_DIR: &str = "build/reports/tests";
+
+/// Where `fyg test --integration --report html` writes its report, relative to the project.
+pub const INTEGRATION_HTML_REPORT_DIR: &str = "build/reports/integration-tests";
+
+/// The directory the HTML report for unit or integration tests is written to.
+pub fn html_report_dir(integration: bool) -> &'static str {
+    match integration {
+        true => INTEGRATION_HTML_REPORT_DIR,
+        false => HTML_REPORT_DIR,
+    }
+}
+
+/// A report `fyg test --report` can write once the tests have run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TestReport {
+    /// A single HTML page listing every test and why each failure failed.
+    Html,
+}
+
+impl FromStr for TestReport {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "html" => Ok(TestReport::Html),
+            other => anyhow::bail!("unknown report format '{}', expected html", other),
+        }
+    }
+}
+
+impl std::fmt::Display for TestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestReport::Html => write!(f, "html"),
+        }
+    }
+}
+
+/// How a single test ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+/// A test case read from a JUnit XML report.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestCase {
+    pub class: String,
+    pub name: String,
+    pub seconds: f64,
+    pub outcome: Outcome,
+    /// The failure message and stack trace, for a failed test.
+    pub failure: Option<String>,
+    /// Whether the test failed at first and this is its result from a re-run.
+    pub retried: bool,
+}
+
+/// Reads the test cases from the `TEST-*.xml` reports in each of `dirs`.
+///
+/// The directories are read in order, and a test's result in a later one replaces its result in
+/// an earlier one, so re-runs of failed tests can be written to directories of their own.
+pub(crate) fn read_xml_reports(dirs: &[PathBuf]) -> anyhow::Result<Vec<TestCase>> {
+    let mut cases: Vec<TestCase> = Vec::new();
+
+    for (index, dir) in dirs.iter().enumerate() {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let mut reports: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with("TEST-") && name.ends_with(".xml"))
+            })
+            .collect();
+        reports.sort();
+
+        for report in reports {
+            let suite = Element::parse(&std::fs::read_to_string(&report)?)?;
+            for case in suite.children("testcase") {
+                let case = test_case(case, index > 0);
+                match cases
+                    .iter_mut()
+                    .find(|c| c.class == case.class && c.name == case.name)
+                {
+                    Some(existing) => *existing = case,
+                    None => cases.push(case),
+                }
+            }
+        }
+    }
+
+    Ok(cases)
+}
+
+fn test_case(case: &Element, retried: bool) -> TestCase {
+    let failure = case.child("failure").or_else(|| case.child("error"));
+    let outcome = match failure {
+        Some(_) => Outcome::Failed,
+        None if case.child("skipped").is_some() => Outcome::Skipped,
+        None => Outcome::Passed,
+    };
+
+    TestCase {
+        class: case.attribute("classname").unwrap_or_default().to_string(),
+        name: case.attribute("name").unwrap_or_default().to_string(),
+        seconds: case
+            .attribute("time")
+            .and_then(|time| time.parse().ok())
+            .unwrap_or_default(),
+        outcome,
+        failure: failure.map(|f| {
+            Some(f.text.as_str())
+                .filter(|text| !text.is_empty())
+                .or(f.attribute("message"))
+                .unwrap_or_default()
+                .to_string()
+        }),
+        retried,
+    }
+}
+
+/// Writes an HTML page summarising `cases` to `index.html` in `output_dir`, returning its path.
+pub(crate) fn write_html(
+    cases: &[TestCase],
+    project_name: &str,
+    output_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let count = |outcome: Outcome| cases.iter().filter(|c| c.outcome == outcome).count();
+
+    let mut rows = String::new();
+    for case in cases {
+        let (class, label) = match case.outcome {
+            Outcome::Passed if case.retried => ("flaky", "passed on retry"),
+            Outcome::Passed => ("passed", "passed"),
+            Outcome::Failed => ("failed", "failed"),
+            Outcome::Skipped => ("skipped", "skipped"),
+        };
+        rows.push_str(&format!(
+            "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{:.3}s</td></tr>\n",
+            class,
+            escape(&case.class),
+            escape(&case.name),
+            label,
+            case.seconds
+        ));
+        if let Some(failure) = &case.failure {
+            rows.push_str(&format!(
+                "<tr class=\"{}\"><td colspan=\"4\"><pre>{}</pre></td></tr>\n",
+                class,
+                escape(failure)
+            ));
+        }
+    }
+
+    let html = format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>Test results for {name}</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+td, th {{ border-bottom: 1px solid #ddd; padding: 4px 8px; text-align: left; }}
+.passed td:nth-child(3) {{ color: #2a7d2a; }}
+.flaky td:nth-child(3) {{ color: #b07d00; }}
+.failed td:nth-child(3), .failed pre {{ color: #b00020; }}
+.skipped td:nth-child(3) {{ color: #777; }}
+</style>
+</head>
+<body>
+<h1>Test results for {name}</h1>
+<p>{passed} passed, {failed} failed, {skipped} skipped</p>
+<table>
+<tr><th>Class</th><th>Test</th><th>Result</th><th>Time</th></tr>
+{rows}</table>
+</body>
+</html>
+",
+        name = escape(project_name),
+        passed = count(Outcome::Passed),
+        failed = count(Outcome::Failed),
+        skipped = count(Outcome::Skipped),
+        rows = rows,
+    );
+
+    std::fs::create_dir_all(output_dir)?;
+    let path = output_dir.join("index.html");
+    std::fs::write(&path, html)?;
+    Ok(path)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -1,13 +1,28 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 
-use crate::config::*;
+use crate::{
+    FygBinaryType, Version,
+    build::BuildMode,
+    config::*,
+    error::FygError,
+    licenses,
+    maven::{self, MAVEN_CENTRAL, Repository},
+    toolchain,
+};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FygToml {
+    /// The project metadata, which may only be left out of a workspace root.
+    #[serde(default, skip_serializing_if = "ProjectConfig::is_empty")]
     pub project: ProjectConfig,
     #[serde(default)]
+    pub workspace: Option<WorkspaceConfig>,
+    #[serde(default)]
     pub build: Option<BuildConfig>,
     #[serde(default)]
     pub targets: Option<TargetsConfig>,
@@ -17,8 +32,129 @@ pub struct FygToml {
     pub test: Option<TestConfig>,
     #[serde(default)]
     pub repositories: Option<HashMap<String, RepositoryConfig>>,
+    /// Settings for each build mode, keyed by profile name, from `[profile.<name>]` sections.
+    #[serde(default, rename = "profile")]
+    pub profiles: Option<HashMap<String, BuildProfile>>,
+    #[serde(default)]
+    pub publish: Option<PublishConfig>,
+    #[serde(default)]
+    pub hooks: Option<HooksConfig>,
+}
+
+/// How serious a [`ValidationDiagnostic`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticSeverity {
+    /// The configuration can't be built.
+    Error,
+    /// The configuration is valid but probably won't do what was intended.
+    Warning,
+    /// A suggestion for tidying up the configuration.
+    Hint,
+}
+
+/// A problem found in `fyg.toml` by [`FygToml::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationDiagnostic {
+    pub severity: DiagnosticSeverity,
+    /// The setting with the problem, such as `project.version`.
+    pub field: String,
+    pub message: String,
+}
+
+impl ValidationDiagnostic {
+    fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Error,
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Warning,
+            ..Self::new(field, message)
+        }
+    }
+
+    fn hint(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity: DiagnosticSeverity::Hint,
+            ..Self::new(field, message)
+        }
+    }
+
+    /// Whether the problem keeps the configuration from being built.
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
 }
 
+impl std::fmt::Display for ValidationDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+impl std::error::Error for ValidationDiagnostic {}
+
+/// The order of the sections of `fyg.toml` in its canonical form.
+pub const SECTION_ORDER: [&str; 10] = [
+    "project",
+    "workspace",
+    "build",
+    "targets",
+    "dependencies",
+    "test",
+    "repositories",
+    "profile",
+    "publish",
+    "hooks",
+];
+
+/// The settings `fyg config get` and `fyg config set` can read and change.
+pub const SETTINGS: [&str; 20] = [
+    "project.name",
+    "project.group",
+    "project.version",
+    "project.description",
+    "project.license",
+    "project.homepage",
+    "project.repository",
+    "build.main-class",
+    "build.kotlin-version",
+    "build.java-version",
+    "build.language-version",
+    "build.api-version",
+    "build.multiplatform",
+    "build.conflict-strategy",
+    "build.all-warnings-as-errors",
+    "build.suppress-warnings",
+    "build.sources-jar",
+    "build.incremental",
+    "build.generate-report",
+    "build.use-local-maven",
+];
+
+/// Looks up `key` in [`SETTINGS`], accepting `_` in place of `-`.
+fn setting(key: &str) -> anyhow::Result<&'static str> {
+    let key = key.replace('_', "-");
+    SETTINGS
+        .iter()
+        .find(|setting| **setting == key)
+        .copied()
+        .ok_or_else(|| {
+            anyhow::anyhow!("unknown setting '{}', expected one of: {}", key, SETTINGS.join(", "))
+        })
+}
+
+/// The languages fyg can compile.
+pub const SUPPORTED_LANGUAGES: [&str; 2] = ["kotlin", "java"];
+
+/// The values `kotlinc` accepts for `-language-version` and `-api-version`.
+pub const KOTLIN_LANGUAGE_VERSIONS: [&str; 10] =
+    ["1.4", "1.5", "1.6", "1.7", "1.8", "1.9", "2.0", "2.1", "2.2", "2.3"];
+
 impl FygToml {
     pub fn new(name: &str, group: &str) -> Self {
         Self {
@@ -28,33 +164,1002 @@ impl FygToml {
                 version: "1.0.0-SNAPSHOT".to_string(),
                 authors: None,
                 description: None,
+                ..Default::default()
             },
+            workspace: None,
             build: None,
             targets: None,
             dependencies: None,
             test: None,
             repositories: None,
+            profiles: None,
+            publish: None,
+            hooks: None,
+        }
+    }
+
+    /// Creates a workspace root with an empty `members` list and no `[project]` section.
+    pub fn new_workspace() -> Self {
+        Self {
+            project: ProjectConfig::default(),
+            workspace: Some(WorkspaceConfig::default()),
+            build: None,
+            targets: None,
+            dependencies: None,
+            test: None,
+            repositories: None,
+            profiles: None,
+            publish: None,
+            hooks: None,
+        }
+    }
+
+    /// Returns the profile to build with in the given mode: the built-in `debug` or `release`
+    /// profile with any settings from the matching `[profile.<name>]` section applied over it.
+    pub fn profile(&self, mode: BuildMode) -> BuildProfile {
+        let base = match mode {
+            BuildMode::Debug => BuildProfile::debug(),
+            BuildMode::Release => BuildProfile::release(),
+        };
+        match self.profiles.as_ref().and_then(|p| p.get(mode.profile_name())) {
+            Some(overrides) => base.merge(overrides),
+            None => base,
+        }
+    }
+
+    /// Reads a `fyg.toml`, or a JSON configuration if the file name ends in `.json`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, FygError> {
+        let path = path.as_ref();
+        let content = read_config(path)?;
+        if is_json(path) {
+            Self::from_json(&content)
+        } else {
+            Self::from_str(&content)
         }
     }
 
-    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let content = std::fs::read_to_string(path)?;
-        Self::from_str(&content)
+    /// Parses a `fyg.toml`, expanding `${...}` references first.
+    ///
+    /// `${project.name}`, `${project.version}` and `${project.group}` expand to those settings,
+    /// and `${env.VAR}` to the environment variable `VAR`, or to `default` if it is written as
+    /// `${env.VAR:-default}`. `$${` is a literal `${`. Values are inserted as they are, so a
+    /// reference to a string should be written inside one.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(content: &str) -> Result<Self, FygError> {
+        let content = interpolate(content)?;
+        Self::from_str_uninterpolated(&content)
     }
 
-    pub fn from_str(content: &str) -> anyhow::Result<Self> {
+    /// Parses a `fyg.toml` like [`FygToml::from_file`], but leaves `${...}` references as they
+    /// are.
+    ///
+    /// Commands that rewrite `fyg.toml` read it this way, so they don't write environment
+    /// variables into it or lose the references.
+    pub fn from_file_uninterpolated<P: AsRef<Path>>(path: P) -> Result<Self, FygError> {
+        let path = path.as_ref();
+        let content = read_config(path)?;
+        if is_json(path) {
+            Self::from_json(&content)
+        } else {
+            Self::from_str_uninterpolated(&content)
+        }
+    }
+
+    /// Parses a JSON configuration, as written by [`FygToml::to_json_string`].
+    ///
+    /// `${...}` references are not expanded in JSON.
+    pub fn from_json(content: &str) -> Result<Self, FygError> {
+        let value: serde_json::Value = serde_json::from_str(content)?;
+        let value = rename_keys(value, &mut Vec::new(), &|key| key.replace('_', "-"));
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Converts the configuration to a JSON document that [`FygToml::from_json`] can read back.
+    ///
+    /// Unlike [`FygToml::to_json`], field names are written in snake_case, such as
+    /// `main_class` for `main-class`. Names chosen in `fyg.toml`, such as dependency
+    /// coordinates, are kept as they are.
+    pub fn to_json_string(&self) -> Result<String, FygError> {
+        let value = rename_keys(self.to_json()?, &mut Vec::new(), &|key| key.replace('-', "_"));
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+
+    /// Parses a `fyg.toml` like [`FygToml::from_str`], but leaves `${...}` references as they
+    /// are.
+    pub fn from_str_uninterpolated(content: &str) -> Result<Self, FygError> {
         let config: FygToml = toml::from_str(content)?;
         Ok(config)
     }
 
-    pub fn to_toml_string(&self) -> anyhow::Result<String> {
-        let content = toml::to_string_pretty(self)?;
-        Ok(content)
+    /// Writes the configuration in the canonical form `fyg fmt` gives `fyg.toml`: sections in
+    /// the order of [`SECTION_ORDER`], with the keys within them sorted alphabetically.
+    ///
+    /// Comments aren't kept, since they are lost when the file is parsed.
+    pub fn to_toml_string(&self) -> Result<String, FygError> {
+        // tables keep their keys sorted, which sorts the keys in every section
+        let mut table = toml::Table::try_from(self)?;
+        let mut sections = Vec::new();
+        for name in SECTION_ORDER {
+            if let Some(value) = table.remove(name) {
+                sections.push(toml::to_string_pretty(&toml::Table::from_iter([(
+                    name.to_string(),
+                    value,
+                )]))?);
+            }
+        }
+        // anything without a place in the order goes last
+        if !table.is_empty() {
+            sections.push(toml::to_string_pretty(&table)?);
+        }
+        Ok(sections.join("\n"))
+    }
+
+    /// Rewrites the `fyg.toml` at `path` in canonical form, as
+    /// [`FygToml::to_toml_string`] writes it, returning a unified diff of what changed, or
+    /// `None` if it already was. With `check`, the file is left as it is.
+    ///
+    /// Comments in the file are lost when it is rewritten.
+    pub fn format_file<P: AsRef<Path>>(path: P, check: bool) -> Result<Option<String>, FygError> {
+        let path = path.as_ref();
+        let original = read_config(path)?;
+        if is_json(path) {
+            return Err(anyhow::anyhow!("only TOML configurations can be formatted").into());
+        }
+        let formatted = Self::from_str_uninterpolated(&original)?.to_toml_string()?;
+        if formatted == original {
+            return Ok(None);
+        }
+
+        let name = path.file_name().map_or("fyg.toml".into(), |n| n.to_string_lossy());
+        let diff = similar::TextDiff::from_lines(&original, &formatted)
+            .unified_diff()
+            .header(&name, &format!("{} (formatted)", name))
+            .to_string();
+        if !check {
+            std::fs::write(path, formatted)?;
+        }
+        Ok(Some(diff))
+    }
+
+    /// Converts the configuration to JSON with the same field names and nesting as the TOML.
+    ///
+    /// Settings that are left out of `fyg.toml` are left out of the JSON too, rather than
+    /// appearing as `null`.
+    pub fn to_json(&self) -> Result<serde_json::Value, FygError> {
+        let table = toml::Table::try_from(self)?;
+        Ok(serde_json::to_value(table)?)
+    }
+
+    /// Returns the value of the setting at `key`, a path such as `project.version` from
+    /// [`SETTINGS`], or `None` if it isn't set.
+    pub fn get(&self, key: &str) -> anyhow::Result<Option<toml::Value>> {
+        let key = setting(key)?;
+        let mut value = toml::Value::Table(toml::Table::try_from(self)?);
+        for part in key.split('.') {
+            match value.get(part) {
+                Some(inner) => value = inner.clone(),
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(value))
+    }
+
+    /// Sets the setting at `key`, a path such as `project.version` from [`SETTINGS`], returning
+    /// the value it had before.
+    ///
+    /// `value` is taken as a string, or as a TOML value such as `true`, `3` or an inline table
+    /// for settings that aren't strings. Fails if the setting can't hold the value, or the value
+    /// doesn't pass [`FygToml::validate`].
+    pub fn set(&mut self, key: &str, value: &str) -> anyhow::Result<Option<toml::Value>> {
+        let key = setting(key)?;
+        let old = self.get(key)?;
+        let table = toml::Table::try_from(&*self)?;
+
+        let mut candidates = vec![toml::Value::String(value.to_string())];
+        if let Ok(literal) = format!("value = {}", value).parse::<toml::Table>()
+            && let Some(literal) = literal.get("value")
+            && !literal.is_str()
+        {
+            candidates.push(literal.clone());
+        }
+
+        let mut error = None;
+        for candidate in candidates {
+            let mut table = table.clone();
+            let (path, field) = key.rsplit_once('.').unwrap_or(("", key));
+            let mut section = &mut table;
+            for part in path.split('.').filter(|p| !p.is_empty()) {
+                section = match section
+                    .entry(part)
+                    .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+                {
+                    toml::Value::Table(inner) => inner,
+                    _ => anyhow::bail!("{} is not a table", part),
+                };
+            }
+            section.insert(field.to_string(), candidate);
+
+            match table.try_into::<FygToml>() {
+                Ok(config) => {
+                    let diagnostics = config.validate();
+                    if let Some(diagnostic) =
+                        diagnostics.iter().find(|d| d.is_error() && d.field == key)
+                    {
+                        anyhow::bail!("{}", diagnostic);
+                    }
+                    *self = config;
+                    return Ok(old);
+                }
+                Err(e) => error = Some(e),
+            }
+        }
+        match error {
+            Some(e) => anyhow::bail!("'{}' is not a valid value for {}: {}", value, key, e),
+            None => unreachable!("there is always a candidate value"),
+        }
+    }
+
+    /// Returns the setting at a dotted path such as `targets.jvm.target`, as it appears in
+    /// [`FygToml::to_json`], or `None` if it isn't set.
+    pub fn field(&self, path: &str) -> Result<Option<serde_json::Value>, FygError> {
+        let pointer = format!("/{}", path.replace('.', "/"));
+        Ok(self
+            .to_json()?
+            .pointer(&pointer)
+            .filter(|value| !value.is_null())
+            .cloned())
+    }
+
+    /// Returns the URLs of every enabled repository, in the order they should be searched.
+    ///
+    /// The local Maven repository is searched first, as a `file://` URL, followed by Maven
+    /// Central unless `maven-central = false` is set.
+    pub fn repository_urls(&self) -> Vec<String> {
+        self.repository_entries()
+            .into_iter()
+            .map(|(url, _)| url)
+            .collect()
+    }
+
+    /// Returns every enabled repository along with its credentials, in the order they should be
+    /// searched.
+    ///
+    /// Fails if a credential refers to an environment variable that isn't set.
+    pub fn maven_repositories(&self) -> anyhow::Result<Vec<Repository>> {
+        self.maven_repositories_with(&|name| std::env::var(name).ok())
+    }
+
+    /// Like [`FygToml::maven_repositories`], but looks up the environment variables credentials
+    /// refer to with `lookup`.
+    pub fn maven_repositories_with(
+        &self,
+        lookup: &dyn Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Vec<Repository>> {
+        self.repository_entries()
+            .into_iter()
+            .map(|(url, custom)| {
+                Ok(Repository {
+                    url,
+                    credentials: match custom {
+                        Some(custom) => custom.credentials_with(lookup)?,
+                        None => None,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn repository_entries(&self) -> Vec<(String, Option<&CustomRepository>)> {
+        let repositories = self.repositories.as_ref();
+
+        let maven_central = repositories
+            .and_then(|r| r.get("maven-central"))
+            .map(|r| !matches!(r, RepositoryConfig::Enabled(false)))
+            .unwrap_or(true);
+
+        let mut urls = Vec::new();
+        if let Some(local) = self.local_maven_repository() {
+            urls.push((format!("file://{}", local.display()), None));
+        }
+        if maven_central {
+            urls.push((MAVEN_CENTRAL.to_string(), None));
+        }
+
+        let mut custom: Vec<_> = repositories
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, repo)| match repo {
+                RepositoryConfig::Custom(custom) => Some((name, custom)),
+                RepositoryConfig::Enabled(_) | RepositoryConfig::LocalMaven { .. } => None,
+            })
+            .collect();
+        custom.sort_by_key(|(name, _)| name.as_str());
+        urls.extend(
+            custom
+                .into_iter()
+                .map(|(_, custom)| (custom.url.clone(), Some(custom))),
+        );
+
+        urls
+    }
+
+    /// Returns the local Maven repository to search before any remote one, if it is enabled.
+    ///
+    /// A `path` from a local Maven entry in `[repositories]` is always used. Otherwise
+    /// `~/.m2/repository` is used if it exists.
+    pub fn local_maven_repository(&self) -> Option<PathBuf> {
+        let enabled = self.build.as_ref().and_then(|b| b.use_local_maven);
+        if enabled == Some(false) {
+            return None;
+        }
+
+        let configured = self
+            .repositories
+            .iter()
+            .flatten()
+            .find_map(|(_, repo)| match repo {
+                RepositoryConfig::LocalMaven { path } => path.clone(),
+                _ => None,
+            });
+        configured.or_else(|| {
+            toolchain::local_maven_repository()
+                .ok()
+                .filter(|path| path.is_dir())
+        })
     }
 
-    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
-        let content = self.to_toml_string()?;
+    /// Checks the configuration for mistakes that parsing alone doesn't catch, returning every
+    /// problem found.
+    ///
+    /// Only problems with [`DiagnosticSeverity::Error`] stop the project from building.
+    pub fn validate(&self) -> Vec<ValidationDiagnostic> {
+        let mut diagnostics = Vec::new();
+
+        if !self.project.is_empty() || self.workspace.is_none() {
+            if !is_identifier(&self.project.name) {
+                diagnostics.push(ValidationDiagnostic::new(
+                    "project.name",
+                    format!(
+                        "'{}' may only contain letters, digits, '-' and '_'",
+                        self.project.name
+                    ),
+                ));
+            }
+            if let Err(e) = self.project.version.parse::<Version>() {
+                diagnostics.push(ValidationDiagnostic::new("project.version", e.to_string()));
+            }
+        }
+        if let Some(license) = &self.project.license
+            && !licenses::is_known_spdx(license)
+        {
+            diagnostics.push(ValidationDiagnostic::warning(
+                "project.license",
+                format!(
+                    "'{}' is not a known SPDX license identifier, such as Apache-2.0 or MIT",
+                    license
+                ),
+            ));
+        }
+
+        for language in self.build.iter().flat_map(|b| b.languages.iter().flatten()) {
+            if !SUPPORTED_LANGUAGES.contains(&language.as_str()) {
+                diagnostics.push(ValidationDiagnostic::new(
+                    "build.languages",
+                    format!(
+                        "'{}' is not supported, expected one of: {}",
+                        language,
+                        SUPPORTED_LANGUAGES.join(", ")
+                    ),
+                ));
+            }
+        }
+
+        let build = self.build.as_ref();
+        let versions = [
+            ("language-version", build.and_then(|b| b.language_version.as_deref())),
+            ("api-version", build.and_then(|b| b.api_version.as_deref())),
+        ];
+        for (key, version) in versions {
+            if let Some(version) = version
+                && !KOTLIN_LANGUAGE_VERSIONS.contains(&version)
+            {
+                diagnostics.push(ValidationDiagnostic::new(
+                    format!("build.{}", key),
+                    format!(
+                        "'{}' is not a Kotlin language version, expected one of: {}",
+                        version,
+                        KOTLIN_LANGUAGE_VERSIONS.join(", ")
+                    ),
+                ));
+            }
+        }
+        if let [(_, Some(language)), (_, Some(api))] = versions
+            && maven::compare_versions(api, language) == std::cmp::Ordering::Greater
+        {
+            diagnostics.push(ValidationDiagnostic::new(
+                "build.api-version",
+                format!("{} is newer than build.language-version ({})", api, language),
+            ));
+        }
+
+        let explicit_api = build.and_then(|b| b.explicit_api);
+        if explicit_api.is_some_and(|mode| mode != ExplicitApiMode::Disabled)
+            && !build.and_then(|b| b.multiplatform).unwrap_or(false)
+        {
+            diagnostics.push(ValidationDiagnostic::warning(
+                "build.explicit-api",
+                "is mostly useful for libraries shared between targets, but multiplatform is off",
+            ));
+        }
+
+        if build.is_some_and(|b| b.ksp.is_some() && b.kapt.is_some()) {
+            diagnostics.push(ValidationDiagnostic::warning(
+                "build.kapt",
+                "is set along with build.ksp, which usually means a processor was only half \
+                 moved to KSP",
+            ));
+        }
+
+        let coverage = self.test.as_ref().and_then(|t| t.coverage.as_ref());
+        if let Some(minimum) = coverage.and_then(|c| c.minimum_coverage)
+            && !(0.0..=1.0).contains(&minimum)
+        {
+            diagnostics.push(ValidationDiagnostic::new(
+                "test.coverage.minimum-coverage",
+                format!("{} is not between 0.0 and 1.0, such as 0.8 for 80%", minimum),
+            ));
+        }
+        if let Some(coverage) = coverage
+            && coverage.engine.unwrap_or_default() == CoverageEngine::Kover
+            && coverage
+                .report_formats
+                .iter()
+                .flatten()
+                .any(|f| *f == CoverageFormat::Csv)
+        {
+            diagnostics.push(ValidationDiagnostic::new(
+                "test.coverage.report-formats",
+                "kover can't write csv reports, use engine = \"jacoco\" or another format",
+            ));
+        }
+
+        if let Some(retry) = self.test.as_ref().and_then(|t| t.retry).filter(|r| *r > 3) {
+            diagnostics.push(ValidationDiagnostic::warning(
+                "test.retry",
+                format!(
+                    "retrying a test {} times can hide genuine failures, consider fixing or \
+                     quarantining flaky tests instead",
+                    retry
+                ),
+            ));
+        }
+
+        for annotation in build.iter().flat_map(|b| b.opt_in.iter().flatten()) {
+            if !is_class_name(annotation) {
+                diagnostics.push(ValidationDiagnostic::new(
+                    "build.opt-in",
+                    format!(
+                        "'{}' is not a fully qualified class name, such as kotlin.RequiresOptIn",
+                        annotation
+                    ),
+                ));
+            }
+        }
+
+        if build.is_some_and(|b| {
+            b.all_warnings_as_errors == Some(true) && b.suppress_warnings == Some(true)
+        }) {
+            diagnostics.push(ValidationDiagnostic::new(
+                "build.suppress-warnings",
+                "can't be combined with all-warnings-as-diagnostics",
+            ));
+        }
+
+        let enabled_targets = self.targets.as_ref().map_or_else(
+            || TargetsConfig::default().enabled_targets(),
+            TargetsConfig::enabled_targets,
+        );
+        let multiplatform = build.and_then(|b| b.multiplatform).unwrap_or(false);
+        if multiplatform && enabled_targets.len() < 2 {
+            diagnostics.push(ValidationDiagnostic::warning(
+                "build.multiplatform",
+                format!(
+                    "is on, but only {} target{} enabled",
+                    enabled_targets.len(),
+                    if enabled_targets.len() == 1 { " is" } else { "s are" }
+                ),
+            ));
+        } else if !multiplatform && enabled_targets.len() > 1 {
+            diagnostics.push(ValidationDiagnostic::warning(
+                "build.multiplatform",
+                format!(
+                    "is off, but several targets are enabled: {}",
+                    enabled_targets.join(", ")
+                ),
+            ));
+        }
+
+        if let Some(targets) = &self.targets {
+            let jvm_target = targets
+                .jvm
+                .as_ref()
+                .filter(|jvm| jvm.enabled)
+                .and_then(|jvm| jvm.target.as_deref());
+            if let Some(target) = jvm_target
+                && !is_jvm_target(target)
+            {
+                diagnostics.push(ValidationDiagnostic::new(
+                    "targets.jvm.target",
+                    format!("'{}' is not a JVM version, such as 1.8 or 17", target),
+                ));
+            }
+
+            if let Some(android) = targets.android.as_ref().filter(|a| a.enabled) {
+                let sdks = [
+                    ("min-sdk", android.min_sdk),
+                    ("target-sdk", android.target_sdk),
+                    ("compile-sdk", android.compile_sdk),
+                ];
+                let sdks: Vec<(&str, u32)> = sdks
+                    .into_iter()
+                    .filter_map(|(name, level)| level.map(|level| (name, level)))
+                    .collect();
+                for pair in sdks.windows(2) {
+                    let [(lower, lower_level), (upper, upper_level)] = pair else {
+                        continue;
+                    };
+                    if lower_level > upper_level {
+                        diagnostics.push(ValidationDiagnostic::new(
+                            format!("targets.android.{}", lower),
+                            format!(
+                                "API level {} is higher than {} ({})",
+                                lower_level, upper, upper_level
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            if !cfg!(target_arch = "arm")
+                && targets.linux_arm32.as_ref().is_some_and(|t| t.enabled)
+                && targets.linux_arm32_linker().is_none()
+            {
+                diagnostics.push(ValidationDiagnostic::warning(
+                    "targets.linux-arm32",
+                    format!(
+                        "is cross-compiled on this machine, which needs an ARM cross-linker \
+                         such as {}-ld in targets.native.binary.linker-opts",
+                        LINUX_ARM32_TRIPLET
+                    ),
+                ));
+            }
+
+            if !cfg!(target_os = "macos") {
+                for (key, name) in targets.apple_targets() {
+                    diagnostics.push(ValidationDiagnostic::warning(
+                        format!("targets.{}", key),
+                        format!("{} can only be built on macOS", name),
+                    ));
+                }
+            }
+
+            // Kotlin/Wasm was only split into separate js and wasi targets in 1.9.20
+            let kotlin_version = self.build.as_ref().and_then(|b| b.kotlin_version.as_deref());
+            if let Some(version) = kotlin_version
+                && maven::compare_versions(version, "1.9.20") == std::cmp::Ordering::Less
+            {
+                let wasm = [("wasm-js", &targets.wasm_js), ("wasm-wasi", &targets.wasm_wasi)];
+                for (key, target) in wasm {
+                    if target.as_ref().is_some_and(|t| t.enabled) {
+                        diagnostics.push(ValidationDiagnostic::warning(
+                            format!("targets.{}", key),
+                            format!(
+                                "needs Kotlin 1.9.20 or newer, but build.kotlin-version is {}",
+                                version
+                            ),
+                        ));
+                    }
+                }
+            }
+
+            let binary = targets.native.as_ref().and_then(|n| n.binary.as_ref());
+            if binary.is_some_and(|b| matches!(b.binary_type, FygBinaryType::XCFramework)) {
+                let apple_targets = targets.apple_targets();
+                if apple_targets.is_empty() {
+                    diagnostics.push(ValidationDiagnostic::new(
+                        "targets.native.binary.type",
+                        "xcFramework needs an Apple target, enable one such as \
+                         [targets.ios-arm64]",
+                    ));
+                }
+                for (key, _) in targets.native_targets() {
+                    if !apple_targets.iter().any(|(apple, _)| *apple == key) {
+                        diagnostics.push(ValidationDiagnostic::warning(
+                            format!("targets.{}", key),
+                            "isn't an Apple target, so it is left out of the xcFramework",
+                        ));
+                    }
+                }
+            }
+
+            if binary.is_some_and(|b| matches!(b.binary_type, FygBinaryType::Framework))
+                && targets.windows_arm64.as_ref().is_some_and(|t| t.enabled)
+            {
+                diagnostics.push(ValidationDiagnostic::warning(
+                    "targets.windows-arm64",
+                    "can't be built as a framework, which only Apple targets can be",
+                ));
+            }
+
+            if binary.is_some_and(|b| b.embed_bitcode == Some(true) && !b.is_framework()) {
+                diagnostics.push(ValidationDiagnostic::new(
+                    "targets.native.binary.embed-bitcode",
+                    "only applies to frameworks, but type isn't framework or xcFramework",
+                ));
+            }
+
+            let base_name = binary.and_then(|b| b.base_name.as_deref());
+            if let Some(base_name) = base_name
+                && !is_identifier(base_name)
+            {
+                diagnostics.push(ValidationDiagnostic::new(
+                    "targets.native.binary.base-name",
+                    format!(
+                        "'{}' may only contain letters, digits, '-' and '_'",
+                        base_name
+                    ),
+                ));
+            }
+        }
+
+        let boms = self.dependencies.iter().flat_map(|d| d.bom.iter().flatten());
+        let mut has_bom = false;
+        for bom in boms {
+            has_bom = true;
+            if !matches!(crate::deps::parse_coordinate(bom), Ok((_, _, Some(_)))) {
+                diagnostics.push(ValidationDiagnostic::new(
+                    "dependencies.bom",
+                    format!("'{}' is not a group:artifact:version coordinate", bom),
+                ));
+            }
+        }
+
+        for (scope, entries) in self.dependencies.iter().flat_map(|d| d.scopes()) {
+            for (key, value) in entries.into_iter().flatten() {
+                if let DependencyValue::Complex(dep) = value
+                    && dep
+                        .exclude
+                        .iter()
+                        .flatten()
+                        .any(|rule| rule.group.is_none() && rule.artifact.is_none())
+                {
+                    diagnostics.push(ValidationDiagnostic::new(
+                        format!("dependencies.{}", scope),
+                        format!(
+                            "an exclude rule of '{}' has no group or artifact, so it would \
+                             exclude everything",
+                            key
+                        ),
+                    ));
+                }
+
+                if let Some(Err(e)) = value.coordinate(key)
+                    && !(has_bom && value.unversioned(key).is_some())
+                {
+                    diagnostics.push(ValidationDiagnostic::new(
+                        format!("dependencies.{}", scope),
+                        e.to_string(),
+                    ));
+                }
+
+                if let Some(Err(e)) = value.version_range() {
+                    diagnostics.push(ValidationDiagnostic::new(
+                        format!("dependencies.{}", scope),
+                        format!("{} for '{}'", e, key),
+                    ));
+                }
+            }
+        }
+
+        if let Some(common) = self.dependencies.as_ref().and_then(|d| d.common.as_ref())
+            && common.is_empty()
+        {
+            diagnostics.push(ValidationDiagnostic::hint(
+                "dependencies.common",
+                "is empty and can be removed",
+            ));
+        }
+
+        let mut profiles: Vec<&String> = self.profiles.iter().flat_map(|p| p.keys()).collect();
+        profiles.sort();
+        for name in profiles {
+            if !["debug", "release"].contains(&name.as_str()) {
+                diagnostics.push(ValidationDiagnostic::hint(
+                    format!("profile.{}", name),
+                    "is never used, fyg only builds with the debug and release profiles",
+                ));
+            }
+        }
+
+        for name in self.publish.iter().flat_map(|p| &p.repositories) {
+            match self.repositories.as_ref().and_then(|r| r.get(name)) {
+                Some(RepositoryConfig::Custom(_) | RepositoryConfig::LocalMaven { .. }) => {}
+                Some(RepositoryConfig::Enabled(_)) => {
+                    diagnostics.push(ValidationDiagnostic::new(
+                        "publish.repositories",
+                        format!("'{}' can't be published to, only downloaded from", name),
+                    ));
+                }
+                None => diagnostics.push(ValidationDiagnostic::new(
+                    "publish.repositories",
+                    format!("'{}' is not in [repositories]", name),
+                )),
+            }
+        }
+
+        // a dependency in a scope another one builds on is already on that one's classpath
+        let scopes = self.dependencies.as_ref().map(|d| d.scopes()).unwrap_or_default();
+        for (scope, entries) in &scopes {
+            let mut keys: Vec<&String> = entries.iter().flat_map(|e| e.keys()).collect();
+            keys.sort();
+            let visible = DependenciesConfig::visible_from(scope);
+            for key in keys {
+                let earlier = scopes.iter().find(|(other, entries)| {
+                    other != scope
+                        && visible.contains(other)
+                        && entries.is_some_and(|entries| entries.contains_key(key))
+                });
+                if let Some((other, _)) = earlier {
+                    diagnostics.push(ValidationDiagnostic::warning(
+                        format!("dependencies.{}", scope),
+                        format!("'{}' is already declared in dependencies.{}", key, other),
+                    ));
+                }
+            }
+        }
+
+        diagnostics
+    }
+
+    /// Replaces every `workspace = true` dependency with its entry from the root's
+    /// `[workspace.dependencies]`.
+    pub fn inherit_workspace(&mut self, root: &FygToml) -> Result<(), FygError> {
+        let shared = root.workspace.as_ref().and_then(|w| w.dependencies.as_ref());
+        let Some(deps) = &mut self.dependencies else {
+            return Ok(());
+        };
+
+        for scope in [
+            &mut deps.common,
+            &mut deps.jvm,
+            &mut deps.test,
+            &mut deps.native,
+            &mut deps.ios,
+            &mut deps.android,
+        ] {
+            for (key, value) in scope.iter_mut().flatten() {
+                let DependencyValue::Complex(DependencyConfig {
+                    workspace: Some(true),
+                    ..
+                }) = value
+                else {
+                    continue;
+                };
+
+                *value = shared
+                    .and_then(|shared| shared.get(key))
+                    .cloned()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "{} uses workspace = true but is not in [workspace.dependencies]",
+                            key
+                        )
+                    })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the configuration to `path`, as JSON if the file name ends in `.json` and as TOML
+    /// otherwise.
+    pub fn write_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), FygError> {
+        let path = path.as_ref();
+        let content = if is_json(path) {
+            self.to_json_string()?
+        } else {
+            self.to_toml_string()?
+        };
         std::fs::write(path, content)?;
         Ok(())
     }
+}
+
+/// Reads the configuration file at `path`.
+fn read_config(path: &Path) -> Result<String, FygError> {
+    std::fs::read_to_string(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => FygError::ConfigNotFound(path.to_path_buf()),
+        _ => e.into(),
+    })
+}
+
+/// Whether the configuration at `path` is JSON rather than TOML.
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+/// Renames every field name in `value` with `rename`, which converts between the TOML and JSON
+/// spellings. `path` holds the keys leading to `value`.
+fn rename_keys(
+    value: serde_json::Value,
+    path: &mut Vec<String>,
+    rename: &impl Fn(&str) -> String,
+) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => {
+            // the keys of these tables are names chosen in fyg.toml, not field names
+            let named = matches!(
+                path.iter().map(String::as_str).collect::<Vec<_>>().as_slice(),
+                ["dependencies", _] | ["workspace", "dependencies"] | ["repositories"] | ["profile"]
+            );
+            let map = map
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = if named { key } else { rename(&key) };
+                    path.push(key.clone());
+                    let value = rename_keys(value, path, rename);
+                    path.pop();
+                    (key, value)
+                })
+                .collect();
+            serde_json::Value::Object(map)
+        }
+        serde_json::Value::Array(items) => serde_json::Value::Array(
+            items
+                .into_iter()
+                .map(|item| rename_keys(item, path, rename))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+/// The properties `${...}` can refer to, besides `env.VAR`.
+const PROJECT_PROPERTIES: [&str; 3] = ["project.name", "project.version", "project.group"];
+
+/// Expands the `${...}` references in a `fyg.toml`, as described on [`FygToml::from_str`].
+fn interpolate(content: &str) -> anyhow::Result<String> {
+    if !content.contains("${") {
+        return Ok(content.to_string());
+    }
+
+    // environment variables go first, so the [project] section can use them too
+    let content = expand(content, false, |reference| {
+        let Some((name, default)) = env_reference(reference) else {
+            return Ok(None);
+        };
+        match (std::env::var(name), default) {
+            (Ok(value), _) => Ok(Some(value)),
+            (Err(_), Some(default)) => Ok(Some(default.to_string())),
+            (Err(_), None) => anyhow::bail!(
+                "environment variable {} is not set, set it or give a default with \
+                 ${{env.{}:-default}}",
+                name,
+                name
+            ),
+        }
+    })?;
+
+    let project = if content.contains("${project.") {
+        toml::from_str::<toml::Table>(&content)?
+            .remove("project")
+            .and_then(|project| project.as_table().cloned())
+            .unwrap_or_default()
+    } else {
+        toml::Table::new()
+    };
+    expand(&content, true, |reference| {
+        if !PROJECT_PROPERTIES.contains(&reference) {
+            anyhow::bail!(
+                "unknown property ${{{}}}, expected one of {} or env.VAR, or write $${{{}}} for \
+                 a literal ${{{}}}",
+                reference,
+                PROJECT_PROPERTIES.join(", "),
+                reference,
+                reference
+            );
+        }
+        let key = &reference["project.".len()..];
+        match project.get(key).and_then(|value| value.as_str()) {
+            Some(value) => Ok(Some(value.to_string())),
+            None => anyhow::bail!("${{{}}} is used but {} is not set", reference, reference),
+        }
+    })
+}
+
+/// Returns the environment variables the `${env.VAR}` references in the contents of a
+/// `fyg.toml` read, with their defaults, in the order they are first used.
+pub fn env_references(content: &str) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    let mut references: Vec<(String, Option<String>)> = Vec::new();
+    expand(content, false, |reference| {
+        if let Some((name, default)) = env_reference(reference)
+            && !references.iter().any(|(seen, _)| seen == name)
+        {
+            references.push((name.to_string(), default.map(str::to_string)));
+        }
+        Ok(None)
+    })?;
+    Ok(references)
+}
+
+/// Splits an `env.VAR:-default` reference into the variable's name and default, or returns
+/// `None` for any other reference.
+fn env_reference(reference: &str) -> Option<(&str, Option<&str>)> {
+    let var = reference.strip_prefix("env.")?;
+    Some(match var.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (var, None),
+    })
+}
+
+/// Replaces each `${reference}` in `content` with what `resolve` returns for it, leaving it be
+/// if that is `None`. Escaped `$${` references are skipped, and unescaped if `unescape` is set.
+fn expand(
+    content: &str,
+    unescape: bool,
+    mut resolve: impl FnMut(&str) -> anyhow::Result<Option<String>>,
+) -> anyhow::Result<String> {
+    let mut expanded = String::with_capacity(content.len());
+    let mut rest = content;
+    while let Some(start) = rest.find("${") {
+        let line = content[..content.len() - rest.len() + start].matches('\n').count() + 1;
+        if rest[..start].ends_with('$') {
+            let end = if unescape { start - 1 } else { start };
+            expanded.push_str(&rest[..end]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        let Some(len) = rest[start..].find('}') else {
+            anyhow::bail!("fyg.toml line {}: ${{ is never closed with }}", line);
+        };
+        let reference = &rest[start + 2..start + len];
+        let value = resolve(reference)
+            .map_err(|e| anyhow::anyhow!("fyg.toml line {}: {}", line, e))?;
+        expanded.push_str(&rest[..start]);
+        match value {
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(&rest[start..start + len + 1]),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// Whether `name` is non-empty and only contains letters, digits, `-` and `_`.
+fn is_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether `name` is a fully qualified Java or Kotlin class name, such as `kotlin.RequiresOptIn`.
+fn is_class_name(name: &str) -> bool {
+    let segments: Vec<&str> = name.split('.').collect();
+    segments.len() > 1
+        && segments.iter().all(|segment| {
+            let mut chars = segment.chars();
+            chars
+                .next()
+                .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+                && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        })
+}
+
+/// Whether `target` is a JVM bytecode version accepted by kotlinc, such as `1.8` or `17`.
+fn is_jvm_target(target: &str) -> bool {
+    target == "1.8" || target.parse::<u32>().is_ok_and(|v| v >= 9)
 }
\ No newline at end of file
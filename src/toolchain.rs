@@ -0,0 +1,362 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{cancel, config::UserConfig, http};
+
+/// Locates the `kotlinc` compiler.
+///
+/// The `FYG_KOTLINC` environment variable takes priority, followed by `kotlinc-path` in the
+/// [`UserConfig`], then `$KOTLIN_HOME/bin/kotlinc`, and finally whatever `kotlinc` is first on
+/// the `PATH`.
+pub fn kotlinc() -> Option<PathBuf> {
+    let configured = std::env::var_os("FYG_KOTLINC")
+        .map(PathBuf::from)
+        .or_else(|| UserConfig::current().kotlinc_path.clone());
+    if let Some(path) = configured {
+        return path.is_file().then_some(path);
+    }
+
+    if let Some(home) = std::env::var_os("KOTLIN_HOME") {
+        let bin = Path::new(&home).join("bin");
+        if let Some(path) = find_in_dir(&bin, "kotlinc") {
+            return Some(path);
+        }
+    }
+
+    find_executable("kotlinc")
+}
+
+/// Locates the `kotlinc-js` compiler, which ships alongside `kotlinc`.
+///
+/// `$KOTLIN_HOME/bin` is searched first, then the directory `kotlinc` was found in, and finally
+/// the `PATH`.
+pub fn kotlinc_js() -> Option<PathBuf> {
+    let kotlin_home = std::env::var_os("KOTLIN_HOME").map(|home| Path::new(&home).join("bin"));
+    let kotlinc_dir = kotlinc()
+        .and_then(|kotlinc| kotlinc.canonicalize().ok())
+        .and_then(|kotlinc| kotlinc.parent().map(Path::to_path_buf));
+
+    kotlin_home
+        .into_iter()
+        .chain(kotlinc_dir)
+        .find_map(|dir| find_in_dir(&dir, "kotlinc-js"))
+        .or_else(|| find_executable("kotlinc-js"))
+}
+
+/// Locates the compiler for Kotlin/Wasm targets.
+///
+/// A dedicated `kotlinc-wasm` is used if there is one, otherwise `kotlinc-js`, which compiles to
+/// WebAssembly when passed `-Xwasm`.
+pub fn kotlinc_wasm() -> Option<PathBuf> {
+    find_executable("kotlinc-wasm").or_else(kotlinc_js)
+}
+
+/// Locates the Kotlin/JS standard library shipped alongside the `kotlinc-js` compiler.
+pub fn kotlin_stdlib_js() -> Option<PathBuf> {
+    kotlin_klib("kotlin-stdlib-js.klib")
+}
+
+/// Locates the Kotlin/Wasm standard library for the given target, `js` or `wasi`.
+pub fn kotlin_stdlib_wasm(target: &str) -> Option<PathBuf> {
+    kotlin_klib(&format!("kotlin-stdlib-wasm-{}.klib", target))
+}
+
+fn kotlin_klib(name: &str) -> Option<PathBuf> {
+    let kotlinc_js = kotlinc_js()?.canonicalize().ok()?;
+    let home = kotlinc_js.parent()?.parent()?;
+    let klib = home.join("lib").join(name);
+    klib.exists().then_some(klib)
+}
+
+/// Locates a Kotlin/Native tool, such as `kotlinc-native` or `cinterop`.
+///
+/// `$KOTLIN_NATIVE_HOME/bin` is searched first, then the `PATH`.
+pub fn kotlin_native_tool(name: &str) -> Option<PathBuf> {
+    std::env::var_os("KOTLIN_NATIVE_HOME")
+        .and_then(|home| find_in_dir(&Path::new(&home).join("bin"), name))
+        .or_else(|| find_executable(name))
+}
+
+/// Locates `xcodebuild`, which comes with Xcode and is only available on macOS.
+pub fn xcodebuild() -> Option<PathBuf> {
+    find_executable("xcodebuild")
+}
+
+/// Locates `windres`, which compiles Windows resource files, for the Kotlin/Native `target`
+/// `mingw_x64` or `mingw_arm64`.
+///
+/// The MinGW-w64 `windres` for the target, such as `x86_64-w64-mingw32-windres`, is preferred
+/// over a plain `windres`, which is what MinGW installs on Windows itself.
+pub fn windres(target: &str) -> Option<PathBuf> {
+    let triplet = match target {
+        "mingw_arm64" => "aarch64-w64-mingw32",
+        _ => "x86_64-w64-mingw32",
+    };
+    find_executable(&format!("{}-windres", triplet)).or_else(|| find_executable("windres"))
+}
+
+/// Locates the `java` launcher, preferring the one in [`java_home`] over the `PATH`.
+pub fn java() -> Option<PathBuf> {
+    java_home()
+        .and_then(|home| java_in(&home))
+        .or_else(|| find_executable("java"))
+}
+
+/// Locates the `java` launcher of the JDK installed at `home`.
+pub fn java_in(home: &Path) -> Option<PathBuf> {
+    find_in_dir(&home.join("bin"), "java")
+}
+
+/// Locates the Java compiler, preferring the one in [`java_home`] over the `PATH`.
+pub fn javac() -> Option<PathBuf> {
+    java_home()
+        .and_then(|home| javac_in(&home))
+        .or_else(|| find_executable("javac"))
+}
+
+/// Locates the Java compiler of the JDK installed at `home`.
+pub fn javac_in(home: &Path) -> Option<PathBuf> {
+    find_in_dir(&home.join("bin"), "javac")
+}
+
+/// Locates the JDK's `jar` tool, preferring the one in [`java_home`] over the `PATH`.
+pub fn jar() -> Option<PathBuf> {
+    java_home()
+        .and_then(|home| jar_in(&home))
+        .or_else(|| find_executable("jar"))
+}
+
+/// Locates the `jar` tool of the JDK installed at `home`.
+pub fn jar_in(home: &Path) -> Option<PathBuf> {
+    find_in_dir(&home.join("bin"), "jar")
+}
+
+/// Returns `JAVA_HOME`, or `java-home` in the [`UserConfig`] if it isn't set.
+pub fn java_home() -> Option<PathBuf> {
+    std::env::var_os("JAVA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| UserConfig::current().java_home.clone())
+}
+
+/// Finds an installed JDK whose version is `version` or starts with it, so `17` matches
+/// `17.0.2`. Returns the JDK's home directory.
+///
+/// [`java_home`] is checked first, then the usual install locations on Linux and macOS, and
+/// finally JDKs installed with SDKMAN.
+pub fn jdk(version: &str) -> Option<PathBuf> {
+    let mut candidates: Vec<PathBuf> = java_home().into_iter().collect();
+
+    let mut roots = vec![
+        PathBuf::from("/usr/lib/jvm"),
+        PathBuf::from("/Library/Java/JavaVirtualMachines"),
+    ];
+    roots.extend(
+        home_dir()
+            .ok()
+            .map(|home| home.join(".sdkman").join("candidates").join("java")),
+    );
+    for root in roots {
+        let Ok(entries) = std::fs::read_dir(&root) else {
+            continue;
+        };
+        let mut homes: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        homes.sort();
+        for home in homes {
+            // macOS bundles keep the actual JDK under Contents/Home
+            let bundled = home.join("Contents").join("Home");
+            candidates.push(if bundled.is_dir() { bundled } else { home });
+        }
+    }
+
+    candidates.into_iter().find(|home| {
+        jdk_version(home).is_some_and(|installed| jdk_version_matches(&installed, version))
+    })
+}
+
+/// Reads the version of the JDK installed at `home` from its `release` file.
+pub fn jdk_version(home: &Path) -> Option<String> {
+    let release = std::fs::read_to_string(home.join("release")).ok()?;
+    release
+        .lines()
+        .find_map(|line| line.strip_prefix("JAVA_VERSION="))
+        .map(|version| version.trim().trim_matches('"').to_string())
+}
+
+fn jdk_version_matches(installed: &str, requested: &str) -> bool {
+    // Java 8 and earlier report themselves as 1.8.0_292 and so on
+    let installed = match installed.strip_prefix("1.") {
+        Some(legacy) if !requested.starts_with("1.") => legacy,
+        _ => installed,
+    };
+    installed == requested
+        || installed
+            .strip_prefix(requested)
+            .is_some_and(|rest| rest.starts_with(['.', '_', '+', '-']))
+}
+
+/// Where Kotlin compiler releases are downloaded from, unless `FYG_KOTLIN_MIRROR` is set.
+const KOTLIN_RELEASES: &str = "https://github.com/JetBrains/kotlin/releases/download";
+
+/// Returns the directory pinned compiler versions are installed in, `~/.fyg/toolchains`.
+pub fn toolchains_dir() -> anyhow::Result<PathBuf> {
+    Ok(fyg_home()?.join("toolchains"))
+}
+
+/// Locates the `kotlinc` of the given version in `~/.fyg/toolchains`, if it is installed.
+pub fn installed_kotlinc(version: &str) -> Option<PathBuf> {
+    let bin = toolchains_dir()
+        .ok()?
+        .join(format!("kotlin-{}", version))
+        .join("kotlinc")
+        .join("bin");
+    find_in_dir(&bin, "kotlinc")
+}
+
+/// Where [`install_kotlin`] downloads the given version of Kotlin from.
+pub(crate) fn kotlin_release_url(version: &str) -> String {
+    let mirror = std::env::var("FYG_KOTLIN_MIRROR").unwrap_or_else(|_| KOTLIN_RELEASES.to_string());
+    format!(
+        "{}/v{}/kotlin-compiler-{}.zip",
+        mirror.trim_end_matches('/'),
+        version,
+        version
+    )
+}
+
+/// Locates the `kotlinc` of the given version, downloading it into `~/.fyg/toolchains` first if
+/// it is not installed.
+///
+/// Releases are downloaded from GitHub, or from the mirror in `FYG_KOTLIN_MIRROR`, which must
+/// lay them out the same way: `<mirror>/v<version>/kotlin-compiler-<version>.zip`.
+pub fn install_kotlin(version: &str) -> anyhow::Result<PathBuf> {
+    if let Some(kotlinc) = installed_kotlinc(version) {
+        return Ok(kotlinc);
+    }
+
+    let unzip = find_executable("unzip").ok_or_else(|| {
+        anyhow::anyhow!(
+            "could not find unzip, which is needed to install Kotlin {}",
+            version
+        )
+    })?;
+
+    let url = kotlin_release_url(version);
+    let toolchains = toolchains_dir()?;
+    let archive = toolchains.join(format!("kotlin-compiler-{}.zip", version));
+    http::download(&url, &archive, None)
+        .map_err(|e| anyhow::anyhow!("failed to download Kotlin {}: {}", version, e))?;
+
+    // unpack next to the final location so a failed unzip never looks like an install
+    let dest = toolchains.join(format!("kotlin-{}", version));
+    let partial = toolchains.join(format!("kotlin-{}.part", version));
+    let _ = std::fs::remove_dir_all(&partial);
+    let output = cancel::output(
+        Command::new(unzip)
+            .arg("-q")
+            .arg(&archive)
+            .arg("-d")
+            .arg(&partial),
+    )?;
+    let _ = std::fs::remove_file(&archive);
+    if !output.status.success() {
+        let _ = std::fs::remove_dir_all(&partial);
+        anyhow::bail!(
+            "failed to unpack Kotlin {}: {}",
+            version,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    std::fs::rename(&partial, &dest)?;
+
+    installed_kotlinc(version).ok_or_else(|| {
+        anyhow::anyhow!(
+            "the Kotlin {} download did not contain kotlinc/bin/kotlinc",
+            version
+        )
+    })
+}
+
+/// Locates the Kotlin standard library shipped alongside the given `kotlinc` compiler.
+///
+/// Kotlin classes cannot run on a plain JVM without it, so it is needed on the runtime classpath.
+pub fn kotlin_stdlib(kotlinc: &Path) -> Option<PathBuf> {
+    let kotlinc = kotlinc.canonicalize().ok()?;
+    let home = kotlinc.parent()?.parent()?;
+    let stdlib = home.join("lib").join("kotlin-stdlib.jar");
+    stdlib.is_file().then_some(stdlib)
+}
+
+/// Locates the KAPT compiler plugin shipped alongside the given `kotlinc` compiler.
+pub fn kotlin_annotation_processing(kotlinc: &Path) -> Option<PathBuf> {
+    let kotlinc = kotlinc.canonicalize().ok()?;
+    let home = kotlinc.parent()?.parent()?;
+    let plugin = home.join("lib").join("kotlin-annotation-processing.jar");
+    plugin.is_file().then_some(plugin)
+}
+
+/// Returns fyg's per-user directory, `~/.fyg`.
+pub fn fyg_home() -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".fyg"))
+}
+
+/// Returns the default local Maven repository, `~/.m2/repository`.
+pub fn local_maven_repository() -> anyhow::Result<PathBuf> {
+    Ok(home_dir()?.join(".m2").join("repository"))
+}
+
+fn home_dir() -> anyhow::Result<PathBuf> {
+    let home = std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .ok_or_else(|| anyhow::anyhow!("could not determine the home directory"))?;
+    Ok(PathBuf::from(home))
+}
+
+/// Returns the directory downloaded artifacts are cached in, `cache-dir` in the [`UserConfig`]
+/// or `~/.fyg/cache`.
+pub fn cache_dir() -> anyhow::Result<PathBuf> {
+    #[cfg(test)]
+    if let Some(dir) = TEST_CACHE_DIR.with(|dir| dir.borrow().clone()) {
+        return Ok(dir);
+    }
+    match &UserConfig::current().cache_dir {
+        Some(dir) => Ok(dir.clone()),
+        None => Ok(fyg_home()?.join("cache")),
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_CACHE_DIR: std::cell::RefCell<Option<PathBuf>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Runs `f` with [`cache_dir`] returning `dir` on this thread, so tests that download don't
+/// fill the user's cache.
+#[cfg(test)]
+pub(crate) fn cache_dir_scope<T>(dir: &Path, f: impl FnOnce() -> T) -> T {
+    let previous = TEST_CACHE_DIR.with(|current| current.replace(Some(dir.to_path_buf())));
+    let result = f();
+    TEST_CACHE_DIR.with(|current| current.replace(previous));
+    result
+}
+
+/// Searches every directory on the `PATH` for an executable with the given name.
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path).find_map(|dir| find_in_dir(&dir, name))
+}
+
+fn find_in_dir(dir: &Path, name: &str) -> Option<PathBuf> {
+    let candidates: &[&str] = if cfg!(windows) {
+        &["exe", "bat", "cmd"]
+    } else {
+        &[""]
+    };
+
+    candidates.iter().find_map(|ext| {
+        let path = dir.join(name).with_extension(ext);
+        path.is_file().then_some(path)
+    })
+}
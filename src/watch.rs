@@ -0,0 +1,67 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+
+/// How long `fyg watch` waits for changes to settle before rebuilding, by default.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often the watch loop checks whether it has been asked to stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Watches every directory in `dirs` recursively, calling `on_change` with the changed paths
+/// each time a burst of file-system events is followed by `debounce` without any.
+///
+/// Returns once `stop` is set, letting any call to `on_change` in progress finish first.
+pub fn watch(
+    dirs: &[PathBuf],
+    debounce: Duration,
+    stop: &AtomicBool,
+    mut on_change: impl FnMut(&[PathBuf]),
+) -> anyhow::Result<()> {
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for dir in dirs {
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .map_err(|e| anyhow::anyhow!("failed to watch {}: {}", dir.display(), e))?;
+    }
+
+    while !stop.load(Ordering::SeqCst) {
+        let event = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let mut changed = Vec::new();
+        collect(event?, &mut changed);
+        // an editor saving a file often produces several events, so wait for them to settle
+        while let Ok(event) = rx.recv_timeout(debounce) {
+            collect(event?, &mut changed);
+        }
+
+        if !changed.is_empty() && !stop.load(Ordering::SeqCst) {
+            on_change(&changed);
+        }
+    }
+
+    Ok(())
+}
+
+fn collect(event: notify::Event, changed: &mut Vec<PathBuf>) {
+    if matches!(event.kind, EventKind::Access(_)) {
+        return;
+    }
+    for path in event.paths {
+        if !changed.contains(&path) {
+            changed.push(path);
+        }
+    }
+}
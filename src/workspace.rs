@@ -0,0 +1,224 @@
+use std::path::{Path, PathBuf};
+
+use crate::{config::DependencyValue, toml::FygToml};
+
+/// A workspace root and the member projects listed in its `[workspace]` section.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    pub root_dir: PathBuf,
+    pub root: FygToml,
+    pub members: Vec<Member>,
+}
+
+/// A single project inside a [`Workspace`].
+#[derive(Clone, Debug)]
+pub struct Member {
+    /// The directory containing the member's `fyg.toml`.
+    pub dir: PathBuf,
+    /// The path of the member's `fyg.toml`.
+    pub config_path: PathBuf,
+    pub fyg_toml: FygToml,
+}
+
+impl Workspace {
+    /// Loads the workspace whose root `fyg.toml` is at the given path, along with every member.
+    ///
+    /// Members inherit any `workspace = true` dependencies from `[workspace.dependencies]`.
+    pub fn load(root_config_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let root_config_path = root_config_path.as_ref();
+        let root = FygToml::from_file(root_config_path)?;
+        let root_dir = root_config_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+
+        let Some(workspace) = &root.workspace else {
+            anyhow::bail!("{} has no [workspace] section", root_config_path.display());
+        };
+
+        let mut members = Vec::new();
+        for member in &workspace.members {
+            let config_path = if member.ends_with(".toml") {
+                root_dir.join(member)
+            } else {
+                root_dir.join(member).join("fyg.toml")
+            };
+            let dir = config_path
+                .parent()
+                .unwrap_or(Path::new("."))
+                .to_path_buf();
+
+            let mut fyg_toml = FygToml::from_file(&config_path).map_err(|e| {
+                anyhow::anyhow!("failed to load member {}: {}", config_path.display(), e)
+            })?;
+            fyg_toml.inherit_workspace(&root)?;
+
+            members.push(Member {
+                dir,
+                config_path,
+                fyg_toml,
+            });
+        }
+
+        Ok(Self {
+            root_dir,
+            root,
+            members,
+        })
+    }
+
+    /// Searches the ancestors of `project_dir` for a workspace root `fyg.toml`.
+    pub fn find_root(project_dir: &Path) -> Option<PathBuf> {
+        let project_dir = project_dir.canonicalize().ok()?;
+        project_dir.ancestors().skip(1).find_map(|dir| {
+            let config_path = dir.join("fyg.toml");
+            let fyg_toml = FygToml::from_file(&config_path).ok()?;
+            fyg_toml.workspace.is_some().then_some(config_path)
+        })
+    }
+
+    /// Finds a member by its project name or by the path it is listed under in `members`.
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        let listed = self
+            .root
+            .workspace
+            .as_ref()
+            .map(|w| w.members.as_slice())
+            .unwrap_or_default();
+
+        self.members
+            .iter()
+            .zip(listed)
+            .find(|(member, path)| member.fyg_toml.project.name == name || *path == name)
+            .map(|(member, _)| member)
+    }
+
+    /// Returns the members in the order they must be built, so that every member comes after
+    /// the members it depends on through `path` dependencies.
+    ///
+    /// If `only` is given, just that member and the members it depends on are returned.
+    pub fn build_order(&self, only: Option<&Member>) -> anyhow::Result<Vec<&Member>> {
+        let graph = self.task_graph(only)?;
+
+        let mut order = Vec::new();
+        let mut done = vec![false; graph.members.len()];
+        while order.len() < graph.members.len() {
+            let next = (0..graph.members.len())
+                .find(|&i| !done[i] && graph.dependencies[i].iter().all(|&dep| done[dep]));
+            let Some(next) = next else {
+                anyhow::bail!("workspace members have a circular path dependency");
+            };
+            done[next] = true;
+            order.push(graph.members[next]);
+        }
+
+        Ok(order)
+    }
+
+    /// Returns the members to build and the members each of them depends on through `path`
+    /// dependencies.
+    ///
+    /// If `only` is given, just that member and the members it depends on are included.
+    pub fn task_graph(&self, only: Option<&Member>) -> anyhow::Result<TaskGraph<'_>> {
+        let dirs: Vec<PathBuf> = self
+            .members
+            .iter()
+            .map(|m| m.dir.canonicalize().unwrap_or_else(|_| m.dir.clone()))
+            .collect();
+
+        // edges[i] holds the indices of the members that member i depends on
+        let edges: Vec<Vec<usize>> = self
+            .members
+            .iter()
+            .map(|member| {
+                path_dependencies(&member.fyg_toml)
+                    .into_iter()
+                    .filter_map(|path| {
+                        let dir = member.dir.join(path);
+                        let dir = dir.canonicalize().unwrap_or(dir);
+                        dirs.iter().position(|d| *d == dir)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut wanted = vec![only.is_none(); self.members.len()];
+        if let Some(only) = only {
+            let start = self
+                .members
+                .iter()
+                .position(|m| m.config_path == only.config_path)
+                .ok_or_else(|| anyhow::anyhow!("{} is not a workspace member", only.dir.display()))?;
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                if !wanted[i] {
+                    wanted[i] = true;
+                    stack.extend(&edges[i]);
+                }
+            }
+        }
+
+        // renumber the wanted members, dropping edges to members that aren't built
+        let indices: Vec<usize> = (0..self.members.len()).filter(|&i| wanted[i]).collect();
+        Ok(TaskGraph {
+            members: indices.iter().map(|&i| &self.members[i]).collect(),
+            dependencies: indices
+                .iter()
+                .map(|&i| {
+                    edges[i]
+                        .iter()
+                        .filter_map(|dep| indices.iter().position(|j| j == dep))
+                        .collect()
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Workspace members to build, and which of them each one needs built first.
+#[derive(Clone, Debug)]
+pub struct TaskGraph<'a> {
+    pub members: Vec<&'a Member>,
+    /// `dependencies[i]` holds the indices in `members` of the members `members[i]` depends on.
+    pub dependencies: Vec<Vec<usize>>,
+}
+
+impl<'a> TaskGraph<'a> {
+    /// Groups the members into batches that can each be built in parallel, in the order the
+    /// batches must be built: every member's dependencies are in earlier batches.
+    pub fn batches(&self) -> anyhow::Result<Vec<Vec<&'a Member>>> {
+        let mut batches = Vec::new();
+        let mut done = vec![false; self.members.len()];
+        let mut remaining = self.members.len();
+        while remaining > 0 {
+            let batch: Vec<usize> = (0..self.members.len())
+                .filter(|&i| !done[i] && self.dependencies[i].iter().all(|&dep| done[dep]))
+                .collect();
+            if batch.is_empty() {
+                anyhow::bail!("workspace members have a circular path dependency");
+            }
+            for &i in &batch {
+                done[i] = true;
+            }
+            remaining -= batch.len();
+            batches.push(batch.into_iter().map(|i| self.members[i]).collect());
+        }
+        Ok(batches)
+    }
+}
+
+/// Returns the `path` of every local dependency in `fyg.toml`.
+fn path_dependencies(fyg_toml: &FygToml) -> Vec<&str> {
+    let Some(deps) = &fyg_toml.dependencies else {
+        return Vec::new();
+    };
+
+    deps.scopes()
+        .into_iter()
+        .flat_map(|(_, entries)| entries.into_iter().flatten())
+        .filter_map(|(_, value)| match value {
+            DependencyValue::Complex(dep) => dep.path.as_deref(),
+            DependencyValue::Version(_) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,165 @@
+use std::borrow::Cow;
+
+use quick_xml::{
+    Reader,
+    escape::{resolve_predefined_entity, unescape},
+    events::{BytesStart, Event},
+};
+
+/// An element of a parsed XML document.
+///
+/// Documents fyg reads, such as POMs, `maven-metadata.xml` and JUnit test reports, are parsed
+/// with `quick-xml` into a tree of these. Comments and processing instructions are skipped.
+/// Character references and the predefined entities are resolved, along with any entities the
+/// document declares in its `DOCTYPE`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<Element>,
+}
+
+impl Element {
+    /// Parses a document, returning its root element.
+    pub fn parse(xml: &str) -> anyhow::Result<Element> {
+        let mut reader = Reader::from_str(xml);
+        let mut entities = Vec::new();
+        let mut stack = vec![Element::default()];
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(start) => stack.push(element(&start, &entities)?),
+                Event::Empty(start) => {
+                    let element = element(&start, &entities)?;
+                    top(&mut stack).children.push(element);
+                }
+                Event::End(end) => {
+                    let element = stack.pop().filter(|_| !stack.is_empty()).ok_or_else(|| {
+                        anyhow::anyhow!(
+                            "unexpected closing tag </{}>",
+                            String::from_utf8_lossy(end.name().as_ref())
+                        )
+                    })?;
+                    top(&mut stack).children.push(finish(element));
+                }
+                Event::Text(text) => top(&mut stack).text.push_str(&text.decode()?),
+                Event::CData(cdata) => top(&mut stack).text.push_str(&cdata.decode()?),
+                Event::GeneralRef(reference) => {
+                    let text = match reference.resolve_char_ref()? {
+                        Some(c) => c.to_string(),
+                        None => {
+                            let name = reference.decode()?;
+                            resolve_entity(&name, &entities)
+                                .ok_or_else(|| anyhow::anyhow!("undeclared entity &{};", name))?
+                                .to_string()
+                        }
+                    };
+                    top(&mut stack).text.push_str(&text);
+                }
+                Event::DocType(doctype) => entities = declared_entities(&doctype.decode()?),
+                Event::Eof => break,
+                // the XML declaration, comments and processing instructions
+                _ => {}
+            }
+        }
+
+        if stack.len() != 1 {
+            anyhow::bail!("unclosed element <{}>", stack[stack.len() - 1].name);
+        }
+        stack
+            .pop()
+            .and_then(|document| document.children.into_iter().next())
+            .ok_or_else(|| anyhow::anyhow!("document has no root element"))
+    }
+
+    /// Returns the value of the attribute with the given name.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the first child element with the given name.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// Returns every child element with the given name.
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// Follows a path of child names, such as `["versioning", "release"]`.
+    pub fn find(&self, path: &[&str]) -> Option<&Element> {
+        path.iter().try_fold(self, |element, name| element.child(name))
+    }
+
+    /// Returns the text of the child element with the given name, if it is not empty.
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name)
+            .map(|c| c.text.as_str())
+            .filter(|text| !text.is_empty())
+    }
+}
+
+/// The element being parsed, which is the document itself outside the root element.
+fn top(stack: &mut [Element]) -> &mut Element {
+    stack.last_mut().expect("the document is never popped")
+}
+
+/// Starts the element for the tag `start`, resolving entities in its attribute values.
+fn element(start: &BytesStart, entities: &[(String, String)]) -> anyhow::Result<Element> {
+    let attributes = start
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute?;
+            let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+            let value = attribute.unescape_value_with(|name| resolve_entity(name, entities))?;
+            Ok((key, value.into_owned()))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Element {
+        name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+        attributes,
+        ..Default::default()
+    })
+}
+
+fn resolve_entity<'a>(name: &str, entities: &'a [(String, String)]) -> Option<&'a str> {
+    resolve_predefined_entity(name).or_else(|| {
+        entities
+            .iter()
+            .find(|(declared, _)| declared == name)
+            .map(|(_, value)| value.as_str())
+    })
+}
+
+/// Reads the internal entities declared in a `DOCTYPE`, such as `<!ENTITY copy "&#169;">`.
+///
+/// External entities, whose values would have to be fetched, and parameter entities, which only
+/// apply within the DTD, are left out.
+fn declared_entities(doctype: &str) -> Vec<(String, String)> {
+    doctype
+        .split("<!ENTITY")
+        .skip(1)
+        .filter_map(|declaration| {
+            let (name, rest) = declaration.trim_start().split_once(char::is_whitespace)?;
+            if name == "%" {
+                return None;
+            }
+            let rest = rest.trim_start();
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let len = rest[1..].find(quote)?;
+            let value = &rest[1..1 + len];
+            let value = unescape(value).unwrap_or(Cow::Borrowed(value));
+            Some((name.to_string(), value.into_owned()))
+        })
+        .collect()
+}
+
+fn finish(mut element: Element) -> Element {
+    element.text = element.text.trim().to_string();
+    element
+}
@@ -0,0 +1,25 @@
+package {{project.group}}
+
+import androidx.compose.foundation.layout.Column
+import androidx.compose.material3.Button
+import androidx.compose.material3.MaterialTheme
+import androidx.compose.material3.Text
+import androidx.compose.runtime.Composable
+import androidx.compose.runtime.getValue
+import androidx.compose.runtime.mutableStateOf
+import androidx.compose.runtime.remember
+import androidx.compose.runtime.setValue
+
+/** The UI shared by the desktop and Android apps. */
+@Composable
+fun App() {
+    var clicks by remember { mutableStateOf(0) }
+    MaterialTheme {
+        Column {
+            Text("Hello from {{project.name}}!")
+            Button(onClick = { clicks++ }) {
+                Text("Clicked $clicks times")
+            }
+        }
+    }
+}
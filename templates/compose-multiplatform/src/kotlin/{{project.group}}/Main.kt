@@ -0,0 +1,10 @@
+package {{project.group}}
+
+import androidx.compose.ui.window.Window
+import androidx.compose.ui.window.application
+
+fun main() = application {
+    Window(onCloseRequest = ::exitApplication, title = "{{project.name}}") {
+        App()
+    }
+}
@@ -0,0 +1,6 @@
+package {{project.group}}
+
+fun main(args: Array<String>) {
+    val name = args.firstOrNull() ?: "world"
+    println("Hello, $name, from {{project.name}}!")
+}
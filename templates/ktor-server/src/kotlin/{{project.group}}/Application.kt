@@ -0,0 +1,30 @@
+package {{project.group}}
+
+import io.ktor.serialization.jackson.jackson
+import io.ktor.server.application.call
+import io.ktor.server.application.install
+import io.ktor.server.engine.embeddedServer
+import io.ktor.server.netty.Netty
+import io.ktor.server.plugins.contentnegotiation.ContentNegotiation
+import io.ktor.server.response.respond
+import io.ktor.server.response.respondText
+import io.ktor.server.routing.get
+import io.ktor.server.routing.routing
+
+data class Status(val name: String, val version: String)
+
+fun main() {
+    embeddedServer(Netty, port = 8080) {
+        install(ContentNegotiation) {
+            jackson()
+        }
+        routing {
+            get("/") {
+                call.respondText("Hello from {{project.name}}!")
+            }
+            get("/status") {
+                call.respond(Status("{{project.name}}", "{{project.version}}"))
+            }
+        }
+    }.start(wait = true)
+}
@@ -0,0 +1,6 @@
+package {{project.group}}
+
+/** Greets people by name, the same way on every platform. */
+class Greeter(private val greeting: String = "Hello") {
+    fun greet(name: String): String = "$greeting, $name!"
+}
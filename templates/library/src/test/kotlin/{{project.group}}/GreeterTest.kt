@@ -0,0 +1,11 @@
+package {{project.group}}
+
+import org.junit.jupiter.api.Assertions.assertEquals
+import org.junit.jupiter.api.Test
+
+class GreeterTest {
+    @Test
+    fun greetsByName() {
+        assertEquals("Hello, Kotlin!", Greeter().greet("Kotlin"))
+    }
+}
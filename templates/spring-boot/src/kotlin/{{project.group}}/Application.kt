@@ -0,0 +1,20 @@
+package {{project.group}}
+
+import org.springframework.boot.autoconfigure.SpringBootApplication
+import org.springframework.boot.runApplication
+import org.springframework.web.bind.annotation.GetMapping
+import org.springframework.web.bind.annotation.RestController
+
+// Spring subclasses its configuration, so the classes it manages have to be open
+@SpringBootApplication
+open class Application
+
+@RestController
+open class GreetingController {
+    @GetMapping("/")
+    open fun greeting(): Map<String, String> = mapOf("message" to "Hello from {{project.name}}!")
+}
+
+fun main(args: Array<String>) {
+    runApplication<Application>(*args)
+}